@@ -1,7 +1,19 @@
+pub mod ad_campaign;
+pub mod book_catalogue;
+pub mod book_ledger;
 pub mod book_management;
+pub mod character_definition;
+pub mod copy_work;
 pub mod crypt;
+pub mod customer_registry;
+pub mod fatigue;
+pub mod financial_ledger;
 pub mod game_system;
+pub mod going_out;
 pub mod map_parser;
+pub mod path_search;
+pub mod reputation;
+pub mod tiled_loader;
 pub mod util;
 
 use game_system::WeekWorkSchedule;
@@ -12,9 +24,12 @@ use tdev::ProgramableKey;
 use crate::libsuzu::core::Clock;
 use crate::libsuzu::device as tdev;
 use crate::libsuzu::graphics::drawable::*;
+use crate::libsuzu::graphics::glyph;
+use crate::libsuzu::graphics::layout::{ScreenScalingMode, Viewport};
 use crate::libsuzu::graphics::object::sub_screen;
 use crate::libsuzu::graphics::object::sub_screen::SubScreen;
 use crate::libsuzu::graphics::object::tile_batch::*;
+use crate::libsuzu::graphics::object::rich_text::RichTextResource;
 use crate::libsuzu::graphics::object::{FontInformation, Transform};
 use crate::hash;
 use crate::libsuzu::numeric;
@@ -25,13 +40,15 @@ use ggez::input::keyboard::*;
 use ginput::mouse::MouseButton;
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::str::FromStr;
 
 use crate::{object::scenario_object::SuzunaStatusPageID, scene};
 use crate::{
     object::{
-        scenario_object::SuzunaAdAgencyType, task_object::tt_sub_component::BorrowingRecordBookData,
+        scenario_object::SuzunaAdAgencyType,
+        task_object::tt_sub_component::{BookConditionEvalReport, BorrowingRecordBookData},
     },
     parse_toml_file,
 };
@@ -103,6 +120,13 @@ impl PartialOrd for Version {
 pub const WINDOW_SIZE_X: i16 = 1366;
 pub const WINDOW_SIZE_Y: i16 = 768;
 
+///
+/// 全シーンが描画を行う仮想スクリーンの領域
+///
+pub fn virtual_screen_rect() -> numeric::Rect {
+    numeric::Rect::new(0.0, 0.0, WINDOW_SIZE_X as f32, WINDOW_SIZE_Y as f32)
+}
+
 pub struct InitialDisplay {
     texture: Vec<ggraphics::Image>,
     index: usize,
@@ -531,6 +555,20 @@ pub enum SoundID {
     Unknown,
 }
 
+impl FromStr for SoundID {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "SeTurnThePage" => Ok(Self::SeTurnThePage),
+            "SeCustomerBell" => Ok(Self::SeCustomerBell),
+            "SeMessage" => Ok(Self::SeMessage),
+            "ResultSE" => Ok(Self::ResultSE),
+            "FinalResultSE" => Ok(Self::FinalResultSE),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BookCondition {
     Good,
@@ -579,6 +617,27 @@ impl BookCondition {
         }
         .to_string()
     }
+
+    ///
+    /// 1段階傷んだ状態。悪はそれ以上傷まない
+    ///
+    pub fn degrade(&self) -> Self {
+        match self {
+            Self::Good => Self::Fair,
+            Self::Fair | Self::Bad => Self::Bad,
+        }
+    }
+
+    ///
+    /// 本の状態による、貸出料金の倍率
+    ///
+    pub fn fee_rate(&self) -> f32 {
+        match self {
+            Self::Good => 1.0,
+            Self::Fair => 0.8,
+            Self::Bad => 0.5,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -628,6 +687,29 @@ impl BookInformation {
         self.condition.clone()
     }
 
+    pub fn set_condition(&mut self, condition: BookCondition) {
+        self.condition = condition;
+    }
+
+    ///
+    /// chanceの確率で、本の状態を1段階傷める。傷んだ場合はtrueを返す
+    ///
+    pub fn wear(&mut self, chance: f32) -> bool {
+        if self.condition == BookCondition::Bad || rand::random::<f32>() >= chance {
+            return false;
+        }
+
+        self.condition = self.condition.degrade();
+        true
+    }
+
+    ///
+    /// 本の状態を反映した、貸出料金の基準額
+    ///
+    pub fn rental_price(&self) -> u32 {
+        (self.base_price as f32 * self.condition.fee_rate()) as u32
+    }
+
     pub fn get_name(&self) -> &str {
         &self.name
     }
@@ -672,6 +754,17 @@ impl RentalLimit {
             RentalLimit::Today => "本日",
         }
     }
+
+    ///
+    /// 1回の貸出で、本が1段階傷む確率。長く貸すほど傷みやすい
+    ///
+    pub fn wear_rate(&self) -> f32 {
+        match self {
+            RentalLimit::ShortTerm => 0.15,
+            RentalLimit::LongTerm => 0.3,
+            RentalLimit::Today => 0.05,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -814,6 +907,31 @@ impl GensoDate {
         }
     }
 
+    ///
+    /// 季も含めて数えた、日付の通し番号。add_dayと同じ月の日数で数える
+    ///
+    pub fn day_ordinal(&self) -> i64 {
+        static MONTH: [i32; 13] = [0, 31, 28, 31, 30, 30, 30, 31, 31, 30, 31, 30, 31];
+
+        let days_in_season: i32 = MONTH.iter().sum();
+        let days_before_month: i32 = MONTH.iter().take(self.month as usize).sum();
+        (self.season as i64 * days_in_season as i64) + days_before_month as i64 + self.day as i64
+    }
+
+    ///
+    /// selfからdate2までの日数。diff_dayと違い季をまたいでも数えられ、date2の方が前なら負になる
+    ///
+    pub fn signed_diff_day(&self, date2: &Self) -> i32 {
+        (date2.day_ordinal() - self.day_ordinal()) as i32
+    }
+
+    ///
+    /// selfを今日として、返却期限return_dateを過ぎた日数を返す。期限内であれば0
+    ///
+    pub fn overdue_days(&self, return_date: &GensoDate) -> i32 {
+        return_date.signed_diff_day(self).max(0)
+    }
+
     pub fn is_past(&self, date: &GensoDate) -> bool {
         match self.season.cmp(&date.season) {
             std::cmp::Ordering::Less => false,
@@ -850,6 +968,7 @@ impl GeneralScenarioID {
     pub fn from_str(s: &str) -> Self {
         match s {
             "NoEnoughMoney" => Self::NoEnoughMoney,
+            "NoEnoughHP" => Self::NoRnoughHP,
             _ => panic!("Invalid General Scenario String"),
         }
     }
@@ -933,6 +1052,14 @@ impl BookShelfInformation {
     pub fn contains_number(&self, inquire_number: u16) -> bool {
         self.billing_number_begin <= inquire_number && inquire_number <= self.billing_number_end
     }
+
+    pub fn get_begin_number(&self) -> u16 {
+        self.billing_number_begin
+    }
+
+    pub fn get_end_number(&self) -> u16 {
+        self.billing_number_end
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -1054,6 +1181,14 @@ impl DailyCustomerDist {
         }
     }
 
+    ///
+    /// 客足がcustomer_rate倍になったときの、客が来る間隔
+    ///
+    pub fn get_customer_dist(&self, day: &GensoDate, customer_rate: f32) -> Clock {
+        let default_dist = self.get_customer_dist_default(day) as f32;
+        ((default_dist / customer_rate.max(0.1)).round() as Clock).max(1)
+    }
+
     pub fn get_customer_dist_default(&self, day: &GensoDate) -> Clock {
         let offset = GensoDate::new(112, 7, 23).diff_day(day) % 7;
         match offset {
@@ -1076,6 +1211,8 @@ impl DailyCustomerDist {
 pub struct RawConfigFile {
     texture_paths: Vec<String>,
     font_paths: Vec<String>,
+    #[serde(default)]
+    fallback_font_paths: Vec<String>,
     customers_name: Vec<String>,
     books_information: Vec<BookInformation>,
     map_information: Vec<MapConstractData>,
@@ -1087,6 +1224,14 @@ pub struct RawConfigFile {
     ad_agency_cost_table: HashMap<String, u32>,
     ad_agency_gain_table: HashMap<String, u32>,
     daily_customer_dist_path: String,
+    #[serde(default)]
+    character_definition_path: Option<String>,
+    #[serde(default)]
+    book_catalogue_path: Option<String>,
+    #[serde(default)]
+    going_out_event_path: Option<String>,
+    #[serde(default)]
+    ad_campaign_path: Option<String>,
 }
 
 impl RawConfigFile {
@@ -1117,6 +1262,10 @@ pub struct GameResource {
     ad_info: AdCostTable,
     ad_agency_info: AdAgencyCostTable,
     daily_customer_dist: DailyCustomerDist,
+    character_definitions: character_definition::CharacterDefinitionTable,
+    book_catalogue: book_catalogue::BookCatalogue,
+    going_out_events: going_out::GoingOutEventTable,
+    ad_campaigns: ad_campaign::AdCampaignTable,
 }
 
 impl GameResource {
@@ -1143,9 +1292,18 @@ impl GameResource {
 
         for font_path in &src_file.font_paths {
             print!("Loading font {}...", font_path);
-            fonts.push(ggraphics::Font::new(ctx, font_path).unwrap());
+            fonts.push(glyph::load_font(ctx, font_path).unwrap());
+            println!(" done!");
+        }
+
+        // 文字を持っていないフォントの代わりに使うフォント。CJK用、記号用の順に並べる
+        let mut fallback_fonts = Vec::new();
+        for font_path in &src_file.fallback_font_paths {
+            print!("Loading fallback font {}...", font_path);
+            fallback_fonts.push(glyph::load_font(ctx, font_path).unwrap());
             println!(" done!");
         }
+        glyph::set_default_fallbacks(&fallback_fonts);
 
         for sb_data in &src_file.sprite_batch_information {
             print!("Loading font {}...", sb_data.path);
@@ -1173,6 +1331,26 @@ impl GameResource {
         let daily_customer_dist =
             DailyCustomerDist::from_toml_file(ctx, &src_file.daily_customer_dist_path);
 
+        let character_definitions = match src_file.character_definition_path.as_ref() {
+            Some(path) => character_definition::CharacterDefinitionTable::from_toml_file(ctx, path),
+            None => character_definition::CharacterDefinitionTable::new_empty(),
+        };
+
+        let book_catalogue = match src_file.book_catalogue_path.as_ref() {
+            Some(path) => book_catalogue::BookCatalogue::from_toml_file(ctx, path),
+            None => book_catalogue::BookCatalogue::new_empty(),
+        };
+
+        let going_out_events = match src_file.going_out_event_path.as_ref() {
+            Some(path) => going_out::GoingOutEventTable::from_toml_file(ctx, path),
+            None => going_out::GoingOutEventTable::new_empty(),
+        };
+
+        let ad_campaigns = match src_file.ad_campaign_path.as_ref() {
+            Some(path) => ad_campaign::AdCampaignTable::from_toml_file(ctx, path),
+            None => ad_campaign::AdCampaignTable::new_empty(),
+        };
+
         let mut bgm_table: HashMap<SoundID, Option<sound::SoundHandler>> = HashMap::new();
         bgm_table.insert(SoundID::Title, None);
         bgm_table.insert(SoundID::ScenarioBGM, None);
@@ -1199,6 +1377,10 @@ impl GameResource {
                 src_file.ad_agency_gain_table,
             ),
             daily_customer_dist: daily_customer_dist,
+            character_definitions: character_definitions,
+            book_catalogue: book_catalogue,
+            going_out_events: going_out_events,
+            ad_campaigns: ad_campaigns,
         }
     }
 
@@ -1241,11 +1423,43 @@ impl GameResource {
         None
     }
 
+    ///
+    /// candidatesから、目録の稀少さの重みで1冊選ぶ
+    ///
+    fn select_by_rarity<'a>(&self, candidates: &[&'a BookInformation]) -> &'a BookInformation {
+        let weights: Vec<f32> = candidates
+            .iter()
+            .map(|book| self.book_catalogue.appearance_weight_of(&book.name))
+            .collect();
+        let index = util::weighted_random_index(&weights)
+            .unwrap_or(rand::random::<usize>() % candidates.len());
+
+        candidates[index]
+    }
+
     pub fn book_random_select(&self) -> &BookInformation {
-        &self
+        let candidates: Vec<&BookInformation> = self.books_information.iter().collect();
+        self.select_by_rarity(&candidates)
+    }
+
+    ///
+    /// filterを満たす本から1冊選ぶ。満たす本がない場合は、全ての本から選ぶ
+    ///
+    pub fn book_random_select_where<F>(&self, filter: F) -> &BookInformation
+    where
+        F: Fn(&BookInformation) -> bool,
+    {
+        let candidates: Vec<&BookInformation> = self
             .books_information
-            .get(rand::random::<usize>() % self.books_information.len())
-            .unwrap()
+            .iter()
+            .filter(|book| filter(book))
+            .collect();
+
+        if candidates.is_empty() {
+            self.book_random_select()
+        } else {
+            self.select_by_rarity(&candidates)
+        }
     }
 
     pub fn search_book_with_title(&self, title: &str) -> Option<&BookInformation> {
@@ -1262,6 +1476,22 @@ impl GameResource {
         self.books_information.iter()
     }
 
+    pub fn get_character_definitions(&self) -> &character_definition::CharacterDefinitionTable {
+        &self.character_definitions
+    }
+
+    pub fn get_book_catalogue(&self) -> &book_catalogue::BookCatalogue {
+        &self.book_catalogue
+    }
+
+    pub fn get_going_out_events(&self) -> &going_out::GoingOutEventTable {
+        &self.going_out_events
+    }
+
+    pub fn get_ad_campaigns(&self) -> &ad_campaign::AdCampaignTable {
+        &self.ad_campaigns
+    }
+
     pub fn customer_random_select(&self) -> &str {
         &self
             .customers_name
@@ -1378,8 +1608,25 @@ impl GameResource {
         self.ad_agency_info.get_money_gain(ty)
     }
 
-    pub fn get_todays_customer_dist(&self, date: &GensoDate) -> Clock {
-        self.daily_customer_dist.get_customer_dist_default(date)
+    pub fn get_todays_customer_dist(&self, date: &GensoDate, customer_rate: f32) -> Clock {
+        self.daily_customer_dist
+            .get_customer_dist(date, customer_rate)
+    }
+}
+
+impl RichTextResource for GameResource {
+    fn get_font_by_name(&self, name: &str) -> Option<ggraphics::Font> {
+        FontID::from_str(name).ok().map(|id| self.get_font(id))
+    }
+
+    fn get_icon_by_name(
+        &mut self,
+        ctx: &mut ggez::Context,
+        name: &str,
+    ) -> Option<ggraphics::Image> {
+        TextureID::from_str(name)
+            .ok()
+            .map(|id| self.ref_texture(ctx, id))
     }
 }
 
@@ -1594,10 +1841,12 @@ impl GameMode {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TaskResult {
-    pub done_works: u32,                         // 総仕事数
-    pub not_shelved_books: Vec<BookInformation>, // 返却済, 未配架
-    pub borrowing_books: Vec<BookInformation>,   // 貸出中
-    pub total_money: i32,                        // 稼いだ金額
+    pub done_works: u32, // 総仕事数
+    // 返却済, 未配架。蔵書台帳ができる前のセーブデータを読み込むためだけに残している
+    #[serde(default)]
+    pub not_shelved_books: Vec<BookInformation>,
+    pub borrowing_books: Vec<BookInformation>, // 貸出中
+    pub total_money: i32,                      // 稼いだ金額
 }
 
 impl TaskResult {
@@ -1656,23 +1905,28 @@ impl SceneStack {
     }
 }
 
+///
+/// # 鈴奈庵の状態
+///
+/// ## reputation_record
+/// 評判が何によって変化したかの記録と、地区ごとの評判
+///
 #[derive(Clone, Serialize, Deserialize)]
 pub struct SuzunaAnStatus {
     pub jinyou_balance: f32,
     reputation: f32,
     pub kosuzu_hp: f32,
-}
-
-pub enum ReputationEvent {
-    DoneDeskTask,
+    #[serde(default)]
+    reputation_record: reputation::ReputationRecord,
 }
 
 impl SuzunaAnStatus {
     pub fn new() -> Self {
         SuzunaAnStatus {
             jinyou_balance: 0.0,
-            reputation: 50.0,
+            reputation: reputation::NEUTRAL_REPUTATION,
             kosuzu_hp: 100.0,
+            reputation_record: reputation::ReputationRecord::new(),
         }
     }
 
@@ -1680,9 +1934,43 @@ impl SuzunaAnStatus {
         self.reputation
     }
 
-    pub fn add_reputation(&mut self, diff: f32) {
-        let next = self.reputation + diff;
-        self.reputation = if next > 100.0 { 100.0 } else { next };
+    ///
+    /// eventによって評判を変化させる。実際に変化した量を返す
+    ///
+    pub fn apply_reputation_event(
+        &mut self,
+        event: reputation::ReputationEvent,
+        base_amount: f32,
+        district: Option<&str>,
+        date: GensoDate,
+    ) -> f32 {
+        self.reputation_record
+            .apply(&mut self.reputation, event, base_amount, district, date)
+    }
+
+    pub fn decay_reputation(&mut self, date: GensoDate) -> f32 {
+        self.reputation_record.decay(&mut self.reputation, date)
+    }
+
+    pub fn get_reputation_record(&self) -> &reputation::ReputationRecord {
+        &self.reputation_record
+    }
+
+    ///
+    /// 意欲を増減させる。0から上限の間に収め、実際に変化した量を返す
+    ///
+    pub fn add_kosuzu_hp(&mut self, diff: f32) -> f32 {
+        let next = (self.kosuzu_hp + diff)
+            .max(fatigue::MIN_KOSUZU_HP)
+            .min(fatigue::MAX_KOSUZU_HP);
+        let applied = next - self.kosuzu_hp;
+        self.kosuzu_hp = next;
+
+        applied
+    }
+
+    pub fn can_start_day_work(&self, work: &game_system::DayWorkType) -> bool {
+        work.can_start_with(self.kosuzu_hp)
     }
 }
 
@@ -1723,12 +2011,34 @@ impl BorrowingInformation {
         (self
             .borrowing
             .iter()
-            .map(|info| info.base_price)
+            .map(|info| info.rental_price())
             .fold(0, |sum, price| sum + price) as f32
             * self.rental_limit.fee_rate()) as i32
     }
 }
 
+///
+/// 延滞1日、1冊あたりの延滞料金
+///
+pub const LATE_FEE_PER_BOOK_DAY: i32 = 20;
+
+///
+/// 返却期限からこの日数を過ぎても返却されない貸出は、紛失したものとして扱う
+///
+pub const LOST_BOOK_OVERDUE_DAYS: i32 = 30;
+
+///
+/// 貸出1件分の本を紛失したときに下がる評判
+///
+pub const LOST_BOOK_REPUTATION_PENALTY: f32 = 3.0;
+
+///
+/// 返却期限return_dateの貸出を、todayに返却するときの延滞料金
+///
+pub fn calc_late_fee(return_date: &GensoDate, today: &GensoDate, book_count: usize) -> i32 {
+    today.overdue_days(return_date) * LATE_FEE_PER_BOOK_DAY * book_count as i32
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ReturnBookInformation {
     pub returning: Vec<BookInformation>,
@@ -1752,31 +2062,15 @@ impl ReturnBookInformation {
         }
     }
 
-    pub fn new_random(
-        game_data: &GameResource,
-        borrow_date: GensoDate,
-        return_date: GensoDate,
-    ) -> Self {
-        let borrowing_num = (rand::random::<u32>() % 5) + 1;
-        let mut borrow_books = Vec::new();
-
-        for _ in 0..borrowing_num {
-            borrow_books.push(game_data.book_random_select().clone());
-        }
-
-        Self::new(
-            borrow_books,
-            game_data.customer_random_select(),
-            borrow_date,
-            return_date,
-        )
-    }
-
     pub fn get_rental_limit(&self) -> RentalLimit {
         self.borrow_date
             .rental_limit_type(&self.return_date)
             .unwrap()
     }
+
+    pub fn calc_late_fee(&self, today: &GensoDate) -> i32 {
+        calc_late_fee(&self.return_date, today, self.returning.len())
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -1785,6 +2079,10 @@ pub struct SuzunaBookPool {
 }
 
 impl SuzunaBookPool {
+    pub fn iter(&self) -> std::slice::Iter<BookInformation> {
+        self.books.iter()
+    }
+
     pub fn new(game_data: &GameResource) -> Self {
         let mut books = Vec::new();
 
@@ -1806,11 +2104,16 @@ impl SuzunaBookPool {
         self.books.extend(book_info_vec);
     }
 
+    ///
+    /// 貸出の依頼を作る。本は、目録の借りた日の需要に、ジャンルを狙った広告の効果を掛けた重みで選ばれる
+    ///
     pub fn generate_borrowing_request(
         &mut self,
         customer_name: &str,
         borrow_date: GensoDate,
         rental_limit: RentalLimit,
+        catalogue: &book_catalogue::BookCatalogue,
+        ad_effects: &ad_campaign::AdEffectReport,
     ) -> BorrowingInformation {
         let mut borrowing_books: Vec<BookInformation> = Vec::new();
         for _ in 0..((rand::random::<u32>() % 5) + 1) {
@@ -1818,9 +2121,19 @@ impl SuzunaBookPool {
                 break;
             }
 
-            let book_info = self
+            let weights: Vec<f32> = self
                 .books
-                .swap_remove(rand::random::<usize>() % self.books.len());
+                .iter()
+                .map(|book| {
+                    catalogue.demand_of(&book.name, &borrow_date)
+                        * catalogue
+                            .get(&book.name)
+                            .map_or(1.0, |entry| ad_effects.genre_weight(&entry.genre))
+                })
+                .collect();
+            let index = util::weighted_random_index(&weights)
+                .unwrap_or(rand::random::<usize>() % self.books.len());
+            let book_info = self.books.swap_remove(index);
 
             if borrowing_books
                 .iter()
@@ -1973,6 +2286,126 @@ pub struct ScenarioSceneSaveData {
     pub page_showed: bool,
 }
 
+///
+/// # マップイベントの進行状況
+///
+/// ## flags
+/// シナリオやトリガーゾーンが立てるフラグ。トリガーゾーンの発動条件に使う
+///
+/// ## fired_events
+/// 一度だけ発動するトリガーゾーンのうち、発動済みのもののID
+///
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct MapEventRecord {
+    flags: HashMap<String, bool>,
+    fired_events: HashSet<String>,
+}
+
+impl MapEventRecord {
+    pub fn new() -> Self {
+        MapEventRecord::default()
+    }
+
+    ///
+    /// 一度も立てられていないフラグはfalseとして扱う
+    ///
+    pub fn get_flag(&self, name: &str) -> bool {
+        *self.flags.get(name).unwrap_or(&false)
+    }
+
+    pub fn set_flag(&mut self, name: &str, value: bool) {
+        self.flags.insert(name.to_string(), value);
+    }
+
+    pub fn is_fired(&self, event_id: &str) -> bool {
+        self.fired_events.contains(event_id)
+    }
+
+    pub fn mark_fired(&mut self, event_id: &str) {
+        self.fired_events.insert(event_id.to_string());
+    }
+}
+
+///
+/// # 延滞と紛失の記録
+///
+/// ## collected_late_fee
+/// これまでに受け取った延滞料金の合計
+///
+/// ## lost_books
+/// 返却されないまま紛失した本
+///
+/// ## unnotified_lost_loans
+/// 紛失したことを、まだ小鈴に知らせていない貸出
+///
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct OverdueRecord {
+    pub collected_late_fee: i32,
+    pub lost_books: Vec<BookInformation>,
+    pub lost_loan_count: u32,
+    #[serde(default)]
+    pub unnotified_lost_loans: Vec<ReturnBookInformation>,
+}
+
+impl OverdueRecord {
+    pub fn new() -> Self {
+        OverdueRecord::default()
+    }
+
+    pub fn add_late_fee(&mut self, late_fee: i32) {
+        self.collected_late_fee += late_fee;
+    }
+
+    pub fn add_lost_loan(&mut self, info: &ReturnBookInformation) {
+        self.lost_books.extend(info.returning.iter().cloned());
+        self.lost_loan_count += 1;
+        self.unnotified_lost_loans.push(info.clone());
+    }
+
+    ///
+    /// まだ知らせていない紛失した貸出を取り出す。取り出したものは知らせたことにする
+    ///
+    pub fn take_unnotified_lost_loans(&mut self) -> Vec<ReturnBookInformation> {
+        std::mem::replace(&mut self.unnotified_lost_loans, Vec::new())
+    }
+}
+
+///
+/// 1冊を修繕に出すのにかかる費用
+///
+pub const REPAIR_COST_PER_BOOK: i32 = 150;
+
+///
+/// 1冊を修繕に出すのに消費する小鈴の体力
+///
+pub const REPAIR_HP_COST_PER_BOOK: f32 = 2.0;
+
+///
+/// 状態が悪い本を1冊貸し出したときに下がる評判
+///
+pub const BAD_CONDITION_REPUTATION_PENALTY: f32 = 0.5;
+
+///
+/// # 本の修繕の記録
+///
+/// ## candidates
+/// 受付で状態が悪いと評価された本のID。修繕に出すまで覚えておく
+///
+/// ## in_repair
+/// 修繕中の本。次の日に状態が良になって返却箱に戻る
+///
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RepairRecord {
+    pub candidates: Vec<u64>,
+    pub in_repair: Vec<BookInformation>,
+}
+
+impl RepairRecord {
+    pub fn new() -> Self {
+        RepairRecord::default()
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct SavableData {
     pub suzuna_book_pool: SuzunaBookPool,
@@ -1987,6 +2420,26 @@ pub struct SavableData {
     pub game_mode: GameMode,
     pub run_tutorial: bool,
     pub scenario_save_data: Option<ScenarioSceneSaveData>,
+    #[serde(default)]
+    pub map_event_record: MapEventRecord,
+    #[serde(default)]
+    pub customer_registry: customer_registry::CustomerRegistry,
+    #[serde(default)]
+    pub overdue_record: OverdueRecord,
+    #[serde(default)]
+    pub book_ledger: book_ledger::BookLedger,
+    #[serde(default)]
+    pub repair_record: RepairRecord,
+    #[serde(default)]
+    pub purchase_record: book_management::PurchaseRecord,
+    #[serde(default)]
+    pub copy_work_record: copy_work::CopyWorkRecord,
+    #[serde(default)]
+    pub financial_ledger: financial_ledger::FinancialLedger,
+    #[serde(default)]
+    pub event_progress: game_system::EventProgressTable,
+    #[serde(default)]
+    pub ad_campaign_record: ad_campaign::AdCampaignRecord,
 }
 
 impl SavableData {
@@ -2018,7 +2471,7 @@ impl SavableData {
         let record_book_data =
             BorrowingRecordBookData::from_returning_request_pool(returning_request_pool);
 
-        SavableData {
+        let mut savable_data = SavableData {
             date: date.clone(),
             task_result: TaskResult::new(),
             suzunaan_status: SuzunaAnStatus::new(),
@@ -2031,7 +2484,37 @@ impl SavableData {
             game_mode: game_mode,
             run_tutorial: true,
 	    scenario_save_data: None,
-        }
+            map_event_record: MapEventRecord::new(),
+            customer_registry: customer_registry::CustomerRegistry::new(),
+            overdue_record: OverdueRecord::new(),
+            book_ledger: book_ledger::BookLedger::new(),
+            repair_record: RepairRecord::new(),
+            purchase_record: book_management::PurchaseRecord::new(),
+            copy_work_record: copy_work::CopyWorkRecord::new(),
+            financial_ledger: financial_ledger::FinancialLedger::new(),
+            event_progress: game_system::EventProgressTable::new(),
+            ad_campaign_record: ad_campaign::AdCampaignRecord::new(),
+        };
+
+        savable_data.seed_book_ledger();
+        savable_data
+    }
+
+    ///
+    /// 蔵書台帳にない本を、本のプールや貸出の記録などから登録する
+    /// 台帳ができる前のセーブデータも、これで台帳に移す
+    ///
+    fn seed_book_ledger(&mut self) {
+        // 返却箱の本は、台帳だけで管理する
+        let return_box = std::mem::replace(&mut self.task_result.not_shelved_books, Vec::new());
+        let loans = self.record_book_data.waiting_returning_requests();
+
+        self.book_ledger.seed(
+            return_box.iter(),
+            self.repair_record.in_repair.iter(),
+            &loans,
+            self.suzuna_book_pool.iter(),
+        );
     }
 
     pub fn save(&self, slot: u8) -> Result<(), Box<dyn std::error::Error>> {
@@ -2070,12 +2553,14 @@ impl SavableData {
 
         let content = crypt::decrypt_str(&buf);
 
-        let savable_data = serde_json::from_str(&content.unwrap());
+        let savable_data: Result<SavableData, _> = serde_json::from_str(&content.unwrap());
 
         if savable_data.is_err() {
             Err(())
         } else {
-            Ok(savable_data.unwrap())
+            let mut savable_data = savable_data.unwrap();
+            savable_data.seed_book_ledger();
+            Ok(savable_data)
         }
     }
 
@@ -2091,6 +2576,285 @@ impl SavableData {
         self.award_data = data.award_data;
         self.game_mode = data.game_mode;
         self.run_tutorial = data.run_tutorial;
+        self.map_event_record = data.map_event_record;
+        self.customer_registry = data.customer_registry;
+        self.overdue_record = data.overdue_record;
+        self.book_ledger = data.book_ledger;
+        self.repair_record = data.repair_record;
+        self.purchase_record = data.purchase_record;
+        self.copy_work_record = data.copy_work_record;
+        self.financial_ledger = data.financial_ledger;
+        self.event_progress = data.event_progress;
+        self.ad_campaign_record = data.ad_campaign_record;
+    }
+
+    ///
+    /// 今日の出来事として、評判を変化させる。実際に変化した量を返す
+    ///
+    pub fn apply_reputation_event(
+        &mut self,
+        event: reputation::ReputationEvent,
+        base_amount: f32,
+        district: Option<&str>,
+    ) -> f32 {
+        self.suzunaan_status
+            .apply_reputation_event(event, base_amount, district, self.date)
+    }
+
+    ///
+    /// 外出先での出来事の結果を反映し、済ませたことにする
+    /// 評判、意欲、所持金の実際の変化量を返す。所持金は0より少なくならない
+    ///
+    pub fn apply_going_out_event(
+        &mut self,
+        event: &going_out::GoingOutEventDefinition,
+    ) -> (f32, f32, i32) {
+        let outcome = &event.outcome;
+
+        let reputation_diff = self.apply_reputation_event(
+            reputation::ReputationEvent::GoingOut,
+            outcome.reputation,
+            None,
+        );
+        let hp_diff = self.suzunaan_status.add_kosuzu_hp(outcome.hp);
+        let money_diff = outcome.money.max(-self.task_result.total_money.max(0));
+        if money_diff != 0 {
+            self.record_transaction(
+                financial_ledger::TransactionCategory::GoingOutEvent,
+                money_diff,
+                &event.id,
+            );
+        }
+
+        for title in outcome.books.iter() {
+            self.event_progress.unlock_book(title);
+        }
+        for definition_id in outcome.customers.iter() {
+            self.event_progress.unlock_customer(definition_id);
+        }
+        if let Some(agency) = outcome.ad_agency {
+            self.event_progress.unlock_agency(agency);
+        }
+        self.event_progress.mark_done(&event.id);
+
+        (reputation_diff, hp_diff, money_diff)
+    }
+
+    ///
+    /// 所持金を増減させ、その内訳を帳簿に記録する
+    ///
+    pub fn record_transaction(
+        &mut self,
+        category: financial_ledger::TransactionCategory,
+        amount: i32,
+        reference: &str,
+    ) {
+        self.task_result.total_money += amount;
+        self.financial_ledger
+            .record(self.date, category, amount, reference);
+    }
+
+    ///
+    /// 本を発注し、仕入れ値を所持金から払う。所持金が足りない場合はfalse
+    ///
+    pub fn place_purchase_order(&mut self, offer: &book_management::SupplierOffer) -> bool {
+        if self.task_result.total_money < offer.price {
+            return false;
+        }
+
+        self.record_transaction(
+            financial_ledger::TransactionCategory::BookPurchase,
+            -offer.price,
+            &offer.book.name,
+        );
+        self.purchase_record
+            .push_order(book_management::PurchaseOrder::new(offer, self.date));
+
+        true
+    }
+
+    ///
+    /// まだ届いていないtitleの発注を取り消し、仕入れ値を返してもらう
+    ///
+    pub fn cancel_purchase_order(&mut self, title: &str) -> bool {
+        match self.purchase_record.remove_order(title) {
+            Some(order) => {
+                self.record_transaction(
+                    financial_ledger::TransactionCategory::PurchaseRefund,
+                    order.cost,
+                    &order.book.name,
+                );
+                true
+            }
+            None => false,
+        }
+    }
+
+    ///
+    /// 今日までに届いた本を受け取る
+    ///
+    pub fn receive_purchased_books(&mut self) -> Vec<BookInformation> {
+        self.purchase_record
+            .take_arrived(&self.date)
+            .iter()
+            .flat_map(|order| order.deliver())
+            .collect()
+    }
+
+    ///
+    /// customerから、bookの写本の依頼を受ける
+    ///
+    pub fn add_copy_request(&mut self, customer: &str, book: &BookInformation) {
+        self.copy_work_record
+            .push_request(copy_work::CopyRequest::new(customer, book, self.date));
+    }
+
+    ///
+    /// 写し終えた依頼を納品し、代金を受け取る。受け取った代金の合計を返す
+    ///
+    pub fn deliver_copy_works(&mut self) -> i32 {
+        let mut payment = 0;
+
+        for request in self.copy_work_record.take_finished() {
+            self.record_transaction(
+                financial_ledger::TransactionCategory::CopyPayment,
+                request.payment,
+                &request.book_title,
+            );
+            payment += request.payment;
+        }
+
+        payment
+    }
+
+    ///
+    /// 納期を過ぎた依頼を取り下げる。取り下げられた依頼1件ごとに評判が下がる
+    ///
+    pub fn expire_copy_requests(&mut self) -> Vec<copy_work::CopyRequest> {
+        let expired = self.copy_work_record.take_expired(&self.date);
+
+        for request in expired.iter() {
+            let district = self.customer_registry.district_of(&request.customer);
+            self.apply_reputation_event(
+                reputation::ReputationEvent::CopyExpired,
+                -copy_work::COPY_EXPIRED_REPUTATION_PENALTY,
+                district.as_deref(),
+            );
+        }
+
+        expired
+    }
+
+    ///
+    /// 受付での状態の評価が悪だった本を、修繕の候補にする
+    ///
+    pub fn add_repair_candidates(&mut self, report: &BookConditionEvalReport) {
+        for (book_info, eval) in report.iter() {
+            let unique_id = book_info.get_unique_id();
+            if *eval == BookCondition::Bad && !self.repair_record.candidates.contains(&unique_id) {
+                self.repair_record.candidates.push(unique_id);
+            }
+        }
+    }
+
+    ///
+    /// 返却箱にある修繕の候補を、お金と体力が足りる分だけ修繕に出す
+    /// carryingは配架のために持ち歩いている本で、修繕には出さない
+    /// 修繕に出した冊数を返す
+    ///
+    pub fn send_books_to_repair(&mut self, carrying: &[BookInformation]) -> usize {
+        let mut count = 0;
+
+        for unique_id in std::mem::replace(&mut self.repair_record.candidates, Vec::new()) {
+            if carrying
+                .iter()
+                .any(|info| info.get_unique_id() == unique_id)
+            {
+                self.repair_record.candidates.push(unique_id);
+                continue;
+            }
+
+            let book_info = match self.book_ledger.get(unique_id) {
+                Some(entry) if entry.location == book_ledger::BookLocation::ReturnBox => {
+                    entry.book.clone()
+                }
+                // 既に配架された本は、候補から外す
+                _ => continue,
+            };
+
+            if self.task_result.total_money < REPAIR_COST_PER_BOOK
+                || self.suzunaan_status.kosuzu_hp < REPAIR_HP_COST_PER_BOOK
+            {
+                self.repair_record.candidates.push(unique_id);
+                continue;
+            }
+
+            if let Err(e) = self
+                .book_ledger
+                .place(&book_info, book_ledger::BookLocation::InRepair)
+            {
+                eprintln!("Failed to send a book to repair -> {}", e);
+                continue;
+            }
+            self.record_transaction(
+                financial_ledger::TransactionCategory::RepairCost,
+                -REPAIR_COST_PER_BOOK,
+                &book_info.name,
+            );
+            self.repair_record.in_repair.push(book_info);
+
+            self.suzunaan_status.add_kosuzu_hp(-REPAIR_HP_COST_PER_BOOK);
+            count += 1;
+        }
+
+        count
+    }
+
+    ///
+    /// 修繕中の本の状態を良にして、返却箱に戻す
+    ///
+    pub fn finish_repairs(&mut self) {
+        for mut book_info in std::mem::replace(&mut self.repair_record.in_repair, Vec::new()) {
+            book_info.set_condition(BookCondition::Good);
+            if let Err(e) = self
+                .book_ledger
+                .place(&book_info, book_ledger::BookLocation::ReturnBox)
+            {
+                eprintln!(
+                    "Failed to return a repaired book to the return box -> {}",
+                    e
+                );
+            }
+        }
+    }
+
+    ///
+    /// 返却期限をLOST_BOOK_OVERDUE_DAYS日過ぎた貸出を紛失したものとし、評判を下げる
+    /// 紛失した貸出を返す。紛失した貸出は、次の開店時に知らせるために延滞の記録にも残る
+    ///
+    pub fn process_lost_loans(&mut self) -> Vec<ReturnBookInformation> {
+        let lost = self
+            .record_book_data
+            .mark_lost_loans(&self.date, LOST_BOOK_OVERDUE_DAYS);
+
+        for info in lost.iter() {
+            if let Err(e) = self
+                .book_ledger
+                .place_all(info.returning.iter(), book_ledger::BookLocation::Lost)
+            {
+                eprintln!("Failed to record lost books in the ledger -> {}", e);
+            }
+            self.overdue_record.add_lost_loan(info);
+            self.customer_registry.record_lost(info);
+            let district = self.customer_registry.district_of(&info.borrower);
+            self.apply_reputation_event(
+                reputation::ReputationEvent::BookLost,
+                -LOST_BOOK_REPUTATION_PENALTY,
+                district.as_deref(),
+            );
+        }
+
+        lost
     }
 
     pub fn change_ad_status(&mut self, ad_type: SuzunaAdType, status: bool) {
@@ -2101,6 +2865,46 @@ impl SavableData {
         self.agency_status.insert(ad_type, status);
     }
 
+    ///
+    /// 出す期間が終わった広告をやめ、効果がなくなった広告を記録から取り除く
+    /// 広告の記録がない頃のセーブデータで出していた広告は、今日から出し始めたことにする
+    ///
+    pub fn finish_ad_campaigns(&mut self, resource: &GameResource) {
+        let today = self.date;
+
+        let unrecorded: Vec<SuzunaAdType> = self
+            .ad_status
+            .iter()
+            .filter(|(ad_type, used)| **used && !self.ad_campaign_record.has_campaign(**ad_type))
+            .map(|(ad_type, _)| *ad_type)
+            .collect();
+
+        for ad_type in unrecorded {
+            self.ad_campaign_record
+                .start(today, &resource.get_ad_campaigns().get(ad_type));
+        }
+
+        let finished: Vec<SuzunaAdType> = self
+            .ad_status
+            .iter()
+            .filter(|(ad_type, used)| {
+                **used
+                    && self
+                        .ad_campaign_record
+                        .running_campaign(**ad_type, &today)
+                        .is_none()
+            })
+            .map(|(ad_type, _)| *ad_type)
+            .collect();
+
+        for ad_type in finished {
+            self.change_ad_status(ad_type, false);
+        }
+
+        self.ad_campaign_record
+            .remove_faded(&today, resource.get_ad_campaigns());
+    }
+
     pub fn get_ad_status(&self, ad_type: SuzunaAdType) -> bool {
         *self.ad_status.get(&ad_type).unwrap()
     }
@@ -2112,17 +2916,52 @@ impl SavableData {
     pub fn pay_ad_cost(&mut self, resource: &GameResource) -> i32 {
         let mut total_cost = 0;
 
-        for (ad_type, used) in self.ad_status.iter() {
-            if *used {
-                let cost = resource.get_default_ad_cost(*ad_type) as i32;
-                self.task_result.total_money -= cost;
-                total_cost += cost;
-            }
+        let used_ad_types: Vec<SuzunaAdType> = self
+            .ad_status
+            .iter()
+            .filter(|(_, used)| **used)
+            .map(|(ad_type, _)| *ad_type)
+            .collect();
+
+        for ad_type in used_ad_types {
+            let cost = resource.get_default_ad_cost(ad_type) as i32;
+            self.record_transaction(
+                financial_ledger::TransactionCategory::AdCost,
+                -cost,
+                ad_type.to_str(),
+            );
+            total_cost += cost;
         }
 
         total_cost
     }
 
+    ///
+    /// 契約中の広告代理店から、広告収入を受け取る
+    ///
+    pub fn receive_ad_agency_gain(&mut self, resource: &GameResource) -> i32 {
+        let mut total_gain = 0;
+
+        let contracted_agencies: Vec<SuzunaAdAgencyType> = self
+            .agency_status
+            .iter()
+            .filter(|(_, contracted)| **contracted)
+            .map(|(agency_type, _)| *agency_type)
+            .collect();
+
+        for agency_type in contracted_agencies {
+            let gain = resource.get_default_ad_agency_money_gain(&agency_type) as i32;
+            self.record_transaction(
+                financial_ledger::TransactionCategory::AdAgencyGain,
+                gain,
+                agency_type.to_str(),
+            );
+            total_gain += gain;
+        }
+
+        total_gain
+    }
+
     pub fn update_week_schedule(&mut self, sched: WeekWorkSchedule) {
         self.week_schedule = sched;
     }
@@ -2145,6 +2984,8 @@ pub struct ResultReportStringTable {
     pub shelving_is_done: String,
     pub condition_eval_mistakes: String,
     pub total_ad_cost: String,
+    pub satisfied_customers: String,
+    pub angry_customers: String,
 }
 
 #[derive(Clone, Debug)]
@@ -2154,6 +2995,8 @@ pub struct ResultReport {
     total_customers_waiting_time: Clock,
     condition_eval_mistakes: usize,
     total_ad_cost: i32,
+    satisfied_customers: usize,
+    angry_customers: usize,
 }
 
 impl ResultReport {
@@ -2164,9 +3007,30 @@ impl ResultReport {
             total_customers_waiting_time: 0,
             condition_eval_mistakes: 0,
             total_ad_cost: 0,
+            satisfied_customers: 0,
+            angry_customers: 0,
         }
     }
 
+    pub fn add_satisfied_customer(&mut self) {
+        self.satisfied_customers += 1;
+    }
+
+    ///
+    /// 待ちきれずに帰った客を記録する。評判への影響は、帰った時点で反映する
+    ///
+    pub fn add_angry_customer(&mut self) {
+        self.angry_customers += 1;
+    }
+
+    pub fn get_satisfied_customers(&self) -> usize {
+        self.satisfied_customers
+    }
+
+    pub fn get_angry_customers(&self) -> usize {
+        self.angry_customers
+    }
+
     pub fn add_new_book_id(&mut self, id: u64) {
         self.new_books_id.push(id);
     }
@@ -2243,22 +3107,22 @@ impl ResultReport {
         return "素人";
     }
 
+    ///
+    /// 1日の働きぶりによる評判の変化
+    /// 状態の評価の誤りは、評価したときにReputationEvent::ConditionMistakeで評判に反映済みなので含めない
+    ///
     pub fn generate_eval_result(&self) -> f32 {
         let missed_books_num = self.number_of_yet_shelved_and_new_books();
         let total_waiting_minute = self.total_customers_waiting_time / 60;
 
-        if missed_books_num == 0 && total_waiting_minute < 30 && self.condition_eval_mistakes == 0 {
+        if missed_books_num == 0 && total_waiting_minute < 30 {
             return 5.0;
         }
 
-        if missed_books_num == 0 && total_waiting_minute < 60 && self.condition_eval_mistakes == 0 {
+        if missed_books_num == 0 && total_waiting_minute < 60 {
             return 2.0;
         }
 
-        if missed_books_num <= 3 && total_waiting_minute < 120 && self.condition_eval_mistakes == 1 {
-            return 0.0;
-        }
-
         if missed_books_num <= 3 && total_waiting_minute < 120 {
             return -1.0;
         }
@@ -2283,6 +3147,10 @@ impl ResultReportStringTable {
                 result_report.condition_eval_mistakes as u64,
             ),
             total_ad_cost: number_to_jk::number_to_jk(result_report.total_ad_cost as u64),
+            satisfied_customers: number_to_jk::number_to_jk(
+                result_report.satisfied_customers as u64,
+            ),
+            angry_customers: number_to_jk::number_to_jk(result_report.angry_customers as u64),
         }
     }
 }
@@ -2295,6 +3163,8 @@ pub struct GameConfig {
     pause_when_inactive: bool,
     fullscreen_mode: bool,
     extra_unlocked: bool,
+    #[serde(default)]
+    screen_scaling_mode: ScreenScalingMode,
 }
 
 impl GameConfig {
@@ -2362,6 +3232,14 @@ impl GameConfig {
 	self.fullscreen_mode = flag;
     }
 
+    pub fn get_screen_scaling_mode(&self) -> ScreenScalingMode {
+        self.screen_scaling_mode
+    }
+
+    pub fn set_screen_scaling_mode(&mut self, mode: ScreenScalingMode) {
+        self.screen_scaling_mode = mode;
+    }
+
     pub fn save_config(&self) {
         let mut file = File::create("./game_config").expect("failed to create game config file.");
 
@@ -2526,6 +3404,16 @@ impl<'ctx> SuzuContext<'ctx> {
         self.savable_data.as_ref().expect("save data not found")
     }
 
+    ///
+    /// 小鈴の疲れに応じた文字送りの速さの割合。セーブデータがなければ等倍
+    ///
+    pub fn text_speed_rate(&self) -> f32 {
+        match self.savable_data.as_ref() {
+            Some(save_data) => fatigue::text_speed_rate(save_data.suzunaan_status.kosuzu_hp),
+            None => 1.0,
+        }
+    }
+
     pub fn ref_texture(&mut self, id: TextureID) -> ggraphics::Image {
         self.resource.ref_texture(self.context, id)
     }
@@ -2569,6 +3457,15 @@ impl<'ctx> SuzuContext<'ctx> {
             .pay_ad_cost(self.resource)
     }
 
+    ///
+    /// 1日分の広告収入を受け取り、広告費を払う。所持金の増減を返す
+    ///
+    pub fn settle_ad_money(&mut self) -> i32 {
+        let save_data = self.savable_data.as_mut().expect("save data not found");
+
+        save_data.receive_ad_agency_gain(self.resource) - save_data.pay_ad_cost(self.resource)
+    }
+
     pub fn holding_week_schedule_is_available(&self) -> bool {
         self.take_save_data()
             .week_schedule
@@ -2576,7 +3473,30 @@ impl<'ctx> SuzuContext<'ctx> {
     }
 
     pub fn go_next_day(&mut self) {
+        let today = self.take_save_data().date;
+        self.take_save_data_mut()
+            .suzunaan_status
+            .decay_reputation(today);
         self.take_save_data_mut().date.add_day(1);
+        // 紛失した貸出は延滞の記録に残り、次の開店時に通知で知らせる
+        self.take_save_data_mut().process_lost_loans();
+        self.take_save_data_mut().finish_repairs();
+        self.take_save_data_mut().expire_copy_requests();
+        self.savable_data
+            .as_mut()
+            .expect("save data not found")
+            .finish_ad_campaigns(self.resource);
+    }
+
+    ///
+    /// 今日の、広告ごとの効果
+    ///
+    pub fn todays_ad_effects(&self) -> ad_campaign::AdEffectReport {
+        let save_data = self.take_save_data();
+
+        save_data
+            .ad_campaign_record
+            .effects_on(&save_data.date, self.resource.get_ad_campaigns())
     }
 
     pub fn current_total_ad_cost(&self) -> i32 {
@@ -2598,23 +3518,45 @@ impl<'ctx> SuzuContext<'ctx> {
         return total_ad_cost as i32;
     }
 
+    ///
+    /// 今日の広告で上がる評判。出し始めとやめた後の広告は、効果の強さの分だけ上がる
+    ///
     pub fn current_total_ad_reputation_gain(&self) -> i32 {
-        let mut total_ad_reputation_gain = 0;
+        let total_ad_reputation_gain: f32 = self
+            .todays_ad_effects()
+            .iter()
+            .map(|effect| {
+                self.resource.get_default_ad_reputation_gain(effect.ad_type) as f32
+                    * effect.strength
+            })
+            .sum();
 
-        for ad_type in vec![
-            SuzunaAdType::AdPaper,
-            SuzunaAdType::Chindon,
-            SuzunaAdType::ShopNobori,
-            SuzunaAdType::TownNobori,
-            SuzunaAdType::NewsPaper,
-            SuzunaAdType::BunBunMaruPaper,
-        ] {
-            if *self.take_save_data().ad_status.get(&ad_type).unwrap() {
-                total_ad_reputation_gain += self.resource.get_default_ad_reputation_gain(ad_type);
-            }
-        }
+        total_ad_reputation_gain.round() as i32
+    }
 
-        return total_ad_reputation_gain as i32;
+    ///
+    /// 今日の広告による評判の変化を反映する。実際に変化した量を返す
+    ///
+    pub fn apply_ad_reputation(&mut self) -> f32 {
+        let gain = self.current_total_ad_reputation_gain() as f32;
+
+        self.take_save_data_mut().apply_reputation_event(
+            reputation::ReputationEvent::Advertisement,
+            gain,
+            None,
+        )
+    }
+
+    ///
+    /// 広告を頼める広告先か。評判が足りていて、外出先での出来事で頼めるようになっている必要がある
+    ///
+    pub fn ad_agency_is_available(&self, agency: &SuzunaAdAgencyType) -> bool {
+        let save_data = self.take_save_data();
+
+        self.resource.get_default_ad_agency_cost(agency) as f32
+            <= save_data.suzunaan_status.get_current_reputation()
+            && (!self.resource.get_going_out_events().locks_agency(agency)
+                || save_data.event_progress.agency_is_unlocked(agency))
     }
 
     pub fn current_total_ad_agency_money_gain(&self) -> i32 {
@@ -2652,8 +3594,20 @@ impl<'ctx> SuzuContext<'ctx> {
         }
     }
 
+    ///
+    /// 広告を出し始める、または打ち切る
+    ///
     pub fn change_ad_status(&mut self, ad_type: SuzunaAdType, status: bool) {
-        self.take_save_data_mut().change_ad_status(ad_type, status);
+        let profile = self.resource.get_ad_campaigns().get(ad_type);
+        let save_data = self.take_save_data_mut();
+        let today = save_data.date;
+
+        save_data.change_ad_status(ad_type, status);
+        if status {
+            save_data.ad_campaign_record.start(today, &profile);
+        } else {
+            save_data.ad_campaign_record.stop(ad_type, today);
+        }
     }
 
     pub fn change_ad_agency_status(&mut self, ad_type: SuzunaAdAgencyType, status: bool) {
@@ -2672,6 +3626,7 @@ pub enum TopScene {
     SaveScene(scene::save_scene::SaveScene),
     TitleScene(scene::title_scene::TitleScene),
     EndScene(scene::end_scene::EndScene),
+    CopyingScene(scene::copying_scene::CopyingScene),
     Null(scene::NullScene),
 }
 
@@ -2683,6 +3638,7 @@ impl TopScene {
             TopScene::SaveScene(scene) => scene,
             TopScene::TitleScene(scene) => scene,
             TopScene::EndScene(scene) => scene,
+            TopScene::CopyingScene(scene) => scene,
             TopScene::Null(scene) => scene,
         }
     }
@@ -2694,6 +3650,7 @@ impl TopScene {
             TopScene::SaveScene(scene) => scene,
             TopScene::TitleScene(scene) => scene,
             TopScene::EndScene(scene) => scene,
+            TopScene::CopyingScene(scene) => scene,
             TopScene::Null(scene) => scene,
         }
     }
@@ -2712,6 +3669,7 @@ struct SceneController {
     key_map: tdev::ProgramableGenericKey,
     global_clock: u64,
     root_screen: SubScreen,
+    viewport: Viewport,
     game_status: Option<SavableData>,
     game_config: GameConfig,
     redraw_request: scene::DrawRequest,
@@ -2722,21 +3680,22 @@ impl SceneController {
     pub fn new<'a>(ctx: &mut ggez::Context, game_data: &'a mut GameResource) -> SceneController {
         let window_size = ggraphics::drawable_size(ctx);
 
-        let mut root_screen = SubScreen::new(
+        let root_screen = SubScreen::new(
             ctx,
-            numeric::Rect::new(0.0, 0.0, 1366.0, 768.0),
+            virtual_screen_rect(),
             0,
             ggraphics::Color::from_rgba_u32(0),
         );
 
-        root_screen.fit_scale(
-            ctx,
-            numeric::Vector2f::new(window_size.0.round(), window_size.1.round()),
-        );
-
         let mut game_status = None;
         let mut game_config = GameConfig::new_from_toml(ctx, "/default_game_config.toml");
 
+        let viewport = Viewport::new(
+            numeric::Vector2f::new(WINDOW_SIZE_X as f32, WINDOW_SIZE_Y as f32),
+            numeric::Vector2f::new(window_size.0.round(), window_size.1.round()),
+            game_config.get_screen_scaling_mode(),
+        );
+
 	if game_config.is_fullscreen_mode_configed() {
 	    match ggraphics::set_fullscreen(ctx, ggez::conf::FullscreenType::Desktop) {
 		Ok(_) => (),
@@ -2764,18 +3723,85 @@ impl SceneController {
             permanent_save_data: &mut permanent_save_data,
         });
 
-        SceneController {
+        let mut scene_controller = SceneController {
             //current_scene: TopScene::ScenarioScene(current_scene),
             current_scene: TopScene::TitleScene(current_scene),
             scene_stack: SceneStack::new(),
             key_map: tdev::ProgramableGenericKey::new(),
             global_clock: 0,
             root_screen: root_screen,
+            viewport: viewport,
             game_status: game_status,
             game_config: game_config,
             redraw_request: scene::DrawRequest::Draw,
             permanent_save_data: permanent_save_data,
-        }
+        };
+
+        scene_controller.apply_viewport(ctx);
+        scene_controller
+    }
+
+    ///
+    /// ルートスクリーンを、レターボックスの黒帯も含めたウィンドウ全体に映る大きさで作り直す
+    /// 黒帯の部分にもアンカー配置したUIを描画できるようになる
+    ///
+    fn apply_viewport(&mut self, ctx: &mut ggez::Context) {
+        let area = self.viewport.visible_virtual_rect();
+        let origin = numeric::Point2f::new(area.x, area.y);
+
+        self.root_screen = SubScreen::new(
+            ctx,
+            numeric::Rect::new(0.0, 0.0, area.w.round(), area.h.round()),
+            0,
+            ggraphics::Color::from_rgba_u32(0),
+        );
+        self.root_screen.set_view_origin(origin);
+        self.root_screen.set_scale(self.viewport.get_scale());
+        self.root_screen
+            .set_position(self.viewport.virtual_to_window(origin));
+    }
+
+    ///
+    /// ウィンドウサイズの変更を仮想スクリーンに反映し、現在のシーンにUIの再配置を要求する
+    ///
+    fn resize_window(&mut self, ctx: &mut ggez::Context, game_data: &mut GameResource) {
+        let window_size = ggraphics::drawable_size(ctx);
+
+        self.viewport
+            .set_mode(self.game_config.get_screen_scaling_mode());
+        self.viewport
+            .resize(numeric::Vector2f::new(window_size.0.round(), window_size.1.round()));
+        self.apply_viewport(ctx);
+
+        self.notify_layout(ctx, game_data);
+    }
+
+    fn notify_layout(&mut self, ctx: &mut ggez::Context, game_data: &mut GameResource) {
+        let area = self.viewport.visible_virtual_rect();
+
+        self.current_scene.abs_mut().layout_event(
+            &mut SuzuContext {
+                context: ctx,
+                resource: game_data,
+                savable_data: &mut self.game_status,
+                config: &mut self.game_config,
+                process_utility: ProcessUtility {
+                    redraw_request: &mut self.redraw_request,
+                },
+                permanent_save_data: &mut self.permanent_save_data,
+            },
+            area,
+        );
+
+        self.redraw_request = scene::DrawRequest::Draw;
+    }
+
+    pub fn window_to_virtual(&self, point: numeric::Point2f) -> numeric::Point2f {
+        self.viewport.window_to_virtual(point)
+    }
+
+    pub fn window_to_virtual_vector(&self, v: numeric::Vector2f) -> numeric::Vector2f {
+        self.viewport.window_to_virtual_vector(v)
     }
 
     fn switch_scene_with_swap<'a>(
@@ -2829,6 +3855,13 @@ impl SceneController {
                             scene::scenario_scene::ScenarioSelect::DayBegin,
                         ))
                 }
+                TopScene::CopyingScene(_) => {
+                    self.current_scene =
+                        TopScene::ScenarioScene(scene::scenario_scene::ScenarioScene::new(
+                            &mut ctx,
+                            scene::scenario_scene::ScenarioSelect::DayBegin,
+                        ))
+                }
                 _ => (),
             },
             scene::SceneID::Title => {
@@ -2842,6 +3875,10 @@ impl SceneController {
             scene::SceneID::End => {
                 self.current_scene = TopScene::EndScene(scene::end_scene::EndScene::new(&mut ctx))
             }
+            scene::SceneID::Copying => {
+                self.current_scene =
+                    TopScene::CopyingScene(scene::copying_scene::CopyingScene::new(&mut ctx));
+            }
             scene::SceneID::Null => self.current_scene = TopScene::Null(scene::NullScene::new()),
             _ => (),
         }
@@ -2912,6 +3949,11 @@ impl SceneController {
     }
 
     fn run_post_process<'a>(&mut self, ctx: &mut ggez::Context, game_data: &'a mut GameResource) {
+        // 設定画面で拡大方法が変更された場合
+        if self.viewport.get_mode() != self.game_config.get_screen_scaling_mode() {
+            self.resize_window(ctx, game_data);
+        }
+
         let mut suzu_ctx = SuzuContext {
             context: ctx,
             resource: game_data,
@@ -2923,7 +3965,9 @@ impl SceneController {
             permanent_save_data: &mut self.permanent_save_data,
         };
 
-        match self.current_scene.abs_mut().post_process(&mut suzu_ctx) {
+        let transition = self.current_scene.abs_mut().post_process(&mut suzu_ctx);
+
+        match transition {
             scene::SceneTransition::Keep => (),
             scene::SceneTransition::Reset => (),
             scene::SceneTransition::SwapTransition => {
@@ -2948,6 +3992,13 @@ impl SceneController {
             }
         }
 
+        match transition {
+            scene::SceneTransition::SwapTransition
+            | scene::SceneTransition::StackingTransition
+            | scene::SceneTransition::PoppingTransition => self.notify_layout(ctx, game_data),
+            _ => (),
+        }
+
         // if self.global_clock % 120 == 0 {
         //     println!("fps: {}", ggez::timer::fps(ctx));
         // }
@@ -3080,7 +4131,10 @@ impl SceneController {
         x: f32,
         y: f32,
     ) {
-        let point = ggez::input::mouse::position(ctx);
+        let window_point = ggez::input::mouse::position(ctx);
+        let point = self
+            .viewport
+            .window_to_virtual(numeric::Point2f::new(window_point.x, window_point.y));
         self.current_scene.abs_mut().mouse_wheel_event(
             &mut SuzuContext {
                 context: ctx,
@@ -3092,7 +4146,7 @@ impl SceneController {
                 },
                 permanent_save_data: &mut self.permanent_save_data,
             },
-            numeric::Point2f::new(point.x, point.y),
+            point,
             x,
             y,
         );
@@ -3136,7 +4190,6 @@ pub struct State {
     fps: f64,
     scene_controller: SceneController,
     game_data: GameResource,
-    window_scale: numeric::Vector2f,
 }
 
 impl ggez::event::EventHandler for State {
@@ -3193,11 +4246,18 @@ impl ggez::event::EventHandler for State {
     }
 
     fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, dx: f32, dy: f32) {
+        let point = self
+            .scene_controller
+            .window_to_virtual(numeric::Point2f::new(x, y));
+        let offset = self
+            .scene_controller
+            .window_to_virtual_vector(numeric::Vector2f::new(dx, dy));
+
         self.scene_controller.mouse_motion_event(
             ctx,
             &mut self.game_data,
-            numeric::Point2f::new(x * self.window_scale.x, y * self.window_scale.y),
-            numeric::Vector2f::new(dx * self.window_scale.x, dy * self.window_scale.y),
+            point,
+            offset,
         );
     }
 
@@ -3208,11 +4268,15 @@ impl ggez::event::EventHandler for State {
         x: f32,
         y: f32,
     ) {
+        let point = self
+            .scene_controller
+            .window_to_virtual(numeric::Point2f::new(x, y));
+
         self.scene_controller.mouse_button_down_event(
             ctx,
             &mut self.game_data,
             button,
-            numeric::Point2f::new(x * self.window_scale.x, y * self.window_scale.y),
+            point,
         );
     }
 
@@ -3223,11 +4287,15 @@ impl ggez::event::EventHandler for State {
         x: f32,
         y: f32,
     ) {
+        let point = self
+            .scene_controller
+            .window_to_virtual(numeric::Point2f::new(x, y));
+
         self.scene_controller.mouse_button_up_event(
             ctx,
             &mut self.game_data,
             button,
-            numeric::Point2f::new(x * self.window_scale.x, y * self.window_scale.y),
+            point,
         );
     }
 
@@ -3247,12 +4315,8 @@ impl ggez::event::EventHandler for State {
 
     fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
 	println!("resize, {}, {}", width, height);
-	self.scene_controller.root_screen.set_scale(
-	    numeric::Vector2f::new(width / WINDOW_SIZE_X as f32, height / WINDOW_SIZE_Y as f32)
-	);
-	
-	let window_size = ggez::graphics::drawable_size(&ctx);
-	self.window_scale = numeric::Vector2f::new(WINDOW_SIZE_X as f32 / window_size.0, WINDOW_SIZE_Y as f32 / window_size.1);
+	self.scene_controller
+	    .resize_window(ctx, &mut self.game_data);
     }
 }
 
@@ -3270,13 +4334,13 @@ impl State {
 	let window_size = ggez::graphics::drawable_size(&ctx);
 
 	println!("window size -> ({}, {})", window_size.0, window_size.1);
-	
+
+
         let s = State {
             clock: 0,
             fps: 0.0,
             scene_controller: scene_controller,
             game_data: game_data,
-	    window_scale: numeric::Vector2f::new(WINDOW_SIZE_X as f32 / window_size.0, WINDOW_SIZE_Y as f32 / window_size.1),
         };
 
         Ok(s)
@@ -0,0 +1,508 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{util, GensoDate};
+use crate::object::scenario_object::SuzunaAdType;
+
+///
+/// 重ねて出した広告の効果は、強い順にこの割合ずつ目減りしていく
+///
+pub const STACKED_AD_EFFECT_RATE: f32 = 0.6;
+
+///
+/// 狙った地区の客や、ジャンルの本の選ばれやすさ。客足の増加の割合にこれを掛けて重みに足す
+///
+pub const TARGETED_AD_WEIGHT_RATE: f32 = 5.0;
+
+fn default_duration() -> i32 {
+    7
+}
+
+fn default_ramp_up_days() -> i32 {
+    2
+}
+
+fn default_fade_out_days() -> i32 {
+    3
+}
+
+fn default_customer_boost() -> f32 {
+    0.1
+}
+
+///
+/// # 広告1種類の出し方の定義
+///
+/// ## duration
+/// 1回の広告を出し続ける日数
+///
+/// ## ramp_up_days
+/// 出し始めてから、効果が最大になるまでの日数
+///
+/// ## fade_out_days
+/// 広告をやめてから、効果がなくなるまでの日数
+///
+/// ## customer_boost
+/// 効果が最大のときに増える客足の割合。0.2なら2割増し
+///
+/// ## district, genre
+/// 狙う客の地区と本のジャンル。Noneなら狙いを絞らない
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdCampaignProfile {
+    pub ad_type: SuzunaAdType,
+    #[serde(default = "default_duration")]
+    pub duration: i32,
+    #[serde(default = "default_ramp_up_days")]
+    pub ramp_up_days: i32,
+    #[serde(default = "default_fade_out_days")]
+    pub fade_out_days: i32,
+    #[serde(default = "default_customer_boost")]
+    pub customer_boost: f32,
+    #[serde(default)]
+    pub district: Option<String>,
+    #[serde(default)]
+    pub genre: Option<String>,
+}
+
+impl AdCampaignProfile {
+    pub fn default_of(ad_type: SuzunaAdType) -> Self {
+        AdCampaignProfile {
+            ad_type: ad_type,
+            duration: default_duration(),
+            ramp_up_days: default_ramp_up_days(),
+            fade_out_days: default_fade_out_days(),
+            customer_boost: default_customer_boost(),
+            district: None,
+            genre: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawAdCampaignFile {
+    campaign: Vec<AdCampaignProfile>,
+}
+
+///
+/// # 広告の出し方の一覧
+///
+/// 定義がない広告は、AdCampaignProfile::default_ofの出し方で扱う
+///
+pub struct AdCampaignTable {
+    profiles: HashMap<SuzunaAdType, AdCampaignProfile>,
+}
+
+impl AdCampaignTable {
+    pub fn new_empty() -> Self {
+        AdCampaignTable {
+            profiles: HashMap::new(),
+        }
+    }
+
+    pub fn from_toml_file(ctx: &mut ggez::Context, path: &str) -> Self {
+        let s = util::read_from_resources_as_string(ctx, path);
+
+        let raw_data: Result<RawAdCampaignFile, toml::de::Error> = toml::from_str(&s);
+        let raw_data = match raw_data {
+            Ok(p) => p,
+            Err(e) => panic!("Failed to parse ad campaigns {}: {}", path, e),
+        };
+
+        let mut profiles = HashMap::new();
+        for profile in raw_data.campaign {
+            profiles.insert(profile.ad_type, profile);
+        }
+
+        AdCampaignTable { profiles: profiles }
+    }
+
+    pub fn get(&self, ad_type: SuzunaAdType) -> AdCampaignProfile {
+        self.profiles
+            .get(&ad_type)
+            .cloned()
+            .unwrap_or_else(|| AdCampaignProfile::default_of(ad_type))
+    }
+}
+
+///
+/// # 1回分の広告
+///
+/// ## start, end
+/// 広告を出した最初の日と最後の日（両端を含む）
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdCampaign {
+    pub ad_type: SuzunaAdType,
+    pub start: GensoDate,
+    pub end: GensoDate,
+}
+
+impl AdCampaign {
+    pub fn is_running(&self, date: &GensoDate) -> bool {
+        !self.start.is_past(date) && !date.is_past(&self.end)
+    }
+
+    ///
+    /// dateにおける効果の強さ。0.0から1.0で、出し始めは徐々に強まり、やめた後は徐々に弱まる
+    ///
+    pub fn strength_at(&self, profile: &AdCampaignProfile, date: &GensoDate) -> f32 {
+        if self.start.is_past(date) {
+            return 0.0;
+        }
+
+        let ramp_up = |last_day: &GensoDate| {
+            ((self.start.signed_diff_day(last_day) + 1) as f32 / profile.ramp_up_days.max(1) as f32)
+                .max(0.0)
+                .min(1.0)
+        };
+
+        if !date.is_past(&self.end) {
+            ramp_up(date)
+        } else {
+            let faded = (self.end.signed_diff_day(date) as f32
+                / (profile.fade_out_days.max(0) + 1) as f32)
+                .max(0.0)
+                .min(1.0);
+            ramp_up(&self.end) * (1.0 - faded)
+        }
+    }
+}
+
+///
+/// # 広告1種類の、ある日の効果
+///
+/// ## strength
+/// 出し始めとやめた後を考えた、効果の強さ
+///
+/// ## customer_boost
+/// 重ねて出した広告で目減りした後の、客足の増加の割合
+///
+/// ## remaining_days
+/// 広告を出し続ける残りの日数。やめた後の広告ではNone
+///
+#[derive(Debug, Clone)]
+pub struct AdEffect {
+    pub ad_type: SuzunaAdType,
+    pub strength: f32,
+    pub customer_boost: f32,
+    pub remaining_days: Option<i32>,
+    pub district: Option<String>,
+    pub genre: Option<String>,
+}
+
+///
+/// # ある日の、広告ごとの効果の報告
+///
+/// 効果の大きい順に並ぶ
+///
+pub struct AdEffectReport {
+    effects: Vec<AdEffect>,
+}
+
+impl AdEffectReport {
+    pub fn iter(&self) -> std::slice::Iter<AdEffect> {
+        self.effects.iter()
+    }
+
+    pub fn get(&self, ad_type: SuzunaAdType) -> Option<&AdEffect> {
+        self.effects.iter().find(|effect| effect.ad_type == ad_type)
+    }
+
+    ///
+    /// 客足の倍率。広告を出していなければ1.0
+    ///
+    pub fn customer_rate(&self) -> f32 {
+        1.0 + self
+            .effects
+            .iter()
+            .map(|effect| effect.customer_boost)
+            .sum::<f32>()
+    }
+
+    ///
+    /// districtに住む客の、来店しやすさの倍率
+    ///
+    pub fn district_weight(&self, district: Option<&str>) -> f32 {
+        1.0 + self
+            .effects
+            .iter()
+            .filter(|effect| effect.district.is_some() && effect.district.as_deref() == district)
+            .map(|effect| effect.customer_boost * TARGETED_AD_WEIGHT_RATE)
+            .sum::<f32>()
+    }
+
+    ///
+    /// genreの本の、借りられやすさの倍率
+    ///
+    pub fn genre_weight(&self, genre: &str) -> f32 {
+        1.0 + self
+            .effects
+            .iter()
+            .filter(|effect| effect.genre.as_deref() == Some(genre))
+            .map(|effect| effect.customer_boost * TARGETED_AD_WEIGHT_RATE)
+            .sum::<f32>()
+    }
+}
+
+///
+/// # 出している広告と、やめた後も効果が残っている広告の記録
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AdCampaignRecord {
+    campaigns: Vec<AdCampaign>,
+}
+
+impl AdCampaignRecord {
+    pub fn new() -> Self {
+        AdCampaignRecord::default()
+    }
+
+    pub fn running_campaign(&self, ad_type: SuzunaAdType, date: &GensoDate) -> Option<&AdCampaign> {
+        self.campaigns
+            .iter()
+            .find(|campaign| campaign.ad_type == ad_type && campaign.is_running(date))
+    }
+
+    ///
+    /// ad_typeの広告を、出しているか、効果が残っているものとして記録しているか
+    ///
+    pub fn has_campaign(&self, ad_type: SuzunaAdType) -> bool {
+        self.campaigns
+            .iter()
+            .any(|campaign| campaign.ad_type == ad_type)
+    }
+
+    ///
+    /// todayから、profileの日数だけ広告を出す。既に出していれば何もしない
+    ///
+    pub fn start(&mut self, today: GensoDate, profile: &AdCampaignProfile) {
+        if self.running_campaign(profile.ad_type, &today).is_some() {
+            return;
+        }
+
+        // 同じ広告の余韻は、出し直した広告に含まれる
+        self.campaigns
+            .retain(|campaign| campaign.ad_type != profile.ad_type);
+
+        self.campaigns.push(AdCampaign {
+            ad_type: profile.ad_type,
+            start: today,
+            end: today.add_day_chain(profile.duration.max(1) - 1),
+        });
+    }
+
+    ///
+    /// 広告をtodayで打ち切る。今日出し始めた広告は、なかったことになる
+    ///
+    pub fn stop(&mut self, ad_type: SuzunaAdType, today: GensoDate) {
+        self.campaigns.retain(|campaign| {
+            !(campaign.ad_type == ad_type && campaign.is_running(&today) && campaign.start == today)
+        });
+
+        for campaign in self.campaigns.iter_mut() {
+            if campaign.ad_type == ad_type && campaign.is_running(&today) {
+                campaign.end = today;
+            }
+        }
+    }
+
+    ///
+    /// 効果がなくなった広告を記録から取り除く
+    ///
+    pub fn remove_faded(&mut self, date: &GensoDate, table: &AdCampaignTable) {
+        self.campaigns.retain(|campaign| {
+            campaign.is_running(date)
+                || campaign.strength_at(&table.get(campaign.ad_type), date) > 0.0
+        });
+    }
+
+    ///
+    /// dateにおける広告ごとの効果。重ねた広告は、効果の大きい順に目減りする
+    ///
+    pub fn effects_on(&self, date: &GensoDate, table: &AdCampaignTable) -> AdEffectReport {
+        let mut effects: Vec<AdEffect> = self
+            .campaigns
+            .iter()
+            .filter_map(|campaign| {
+                let profile = table.get(campaign.ad_type);
+                let strength = campaign.strength_at(&profile, date);
+                if strength <= 0.0 {
+                    return None;
+                }
+
+                Some(AdEffect {
+                    ad_type: campaign.ad_type,
+                    strength: strength,
+                    customer_boost: profile.customer_boost * strength,
+                    remaining_days: if campaign.is_running(date) {
+                        Some(date.signed_diff_day(&campaign.end) + 1)
+                    } else {
+                        None
+                    },
+                    district: profile.district,
+                    genre: profile.genre,
+                })
+            })
+            .collect();
+
+        effects.sort_by(|a, b| b.customer_boost.total_cmp(&a.customer_boost));
+
+        let mut rate = 1.0;
+        for effect in effects.iter_mut() {
+            effect.customer_boost *= rate;
+            rate *= STACKED_AD_EFFECT_RATE;
+        }
+
+        AdEffectReport { effects: effects }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(ramp_up_days: i32, fade_out_days: i32) -> AdCampaignProfile {
+        AdCampaignProfile {
+            ramp_up_days: ramp_up_days,
+            fade_out_days: fade_out_days,
+            customer_boost: 0.2,
+            ..AdCampaignProfile::default_of(SuzunaAdType::AdPaper)
+        }
+    }
+
+    fn campaign(start: GensoDate, end: GensoDate) -> AdCampaign {
+        AdCampaign {
+            ad_type: SuzunaAdType::AdPaper,
+            start: start,
+            end: end,
+        }
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn strength_ramps_up() {
+        let start = GensoDate::new(1, 4, 1);
+        let campaign = campaign(start, start.add_day_chain(6));
+        let profile = profile(2, 3);
+
+        assert_close(
+            campaign.strength_at(&profile, &GensoDate::new(1, 3, 31)),
+            0.0,
+        );
+        assert_close(campaign.strength_at(&profile, &start), 0.5);
+        assert_close(campaign.strength_at(&profile, &start.add_day_chain(1)), 1.0);
+        assert_close(campaign.strength_at(&profile, &start.add_day_chain(6)), 1.0);
+    }
+
+    #[test]
+    fn strength_fades_out() {
+        let start = GensoDate::new(1, 4, 1);
+        let end = start.add_day_chain(6);
+        let campaign = campaign(start, end);
+        let profile = profile(2, 3);
+
+        assert_close(campaign.strength_at(&profile, &end.add_day_chain(1)), 0.75);
+        assert_close(campaign.strength_at(&profile, &end.add_day_chain(2)), 0.5);
+        assert_close(campaign.strength_at(&profile, &end.add_day_chain(3)), 0.25);
+        assert_close(campaign.strength_at(&profile, &end.add_day_chain(4)), 0.0);
+        assert_close(campaign.strength_at(&profile, &end.add_day_chain(30)), 0.0);
+    }
+
+    #[test]
+    fn strength_fades_out_across_seasons() {
+        let end = GensoDate::new(1, 12, 31);
+        let campaign = campaign(GensoDate::new(1, 12, 25), end);
+        let profile = profile(2, 3);
+
+        let next_season = end.add_day_chain(1);
+        assert_eq!(next_season.season, 2);
+        assert_close(campaign.strength_at(&profile, &next_season), 0.75);
+        assert_close(campaign.strength_at(&profile, &end.add_day_chain(4)), 0.0);
+    }
+
+    #[test]
+    fn stopped_on_first_day_keeps_partial_strength() {
+        let start = GensoDate::new(1, 4, 1);
+        let campaign = campaign(start, start);
+        let profile = profile(2, 1);
+
+        assert_close(
+            campaign.strength_at(&profile, &start.add_day_chain(1)),
+            0.25,
+        );
+    }
+
+    #[test]
+    fn stacked_effects_diminish() {
+        let today = GensoDate::new(1, 4, 10);
+        let mut table = AdCampaignTable::new_empty();
+        for (ad_type, boost) in [
+            (SuzunaAdType::AdPaper, 0.3),
+            (SuzunaAdType::Chindon, 0.2),
+            (SuzunaAdType::NewsPaper, 0.1),
+        ]
+        .iter()
+        {
+            table.profiles.insert(
+                *ad_type,
+                AdCampaignProfile {
+                    ramp_up_days: 1,
+                    customer_boost: *boost,
+                    ..AdCampaignProfile::default_of(*ad_type)
+                },
+            );
+        }
+
+        let mut record = AdCampaignRecord::new();
+        for ad_type in [
+            SuzunaAdType::NewsPaper,
+            SuzunaAdType::AdPaper,
+            SuzunaAdType::Chindon,
+        ]
+        .iter()
+        {
+            record.start(today, &table.get(*ad_type));
+        }
+
+        let report = record.effects_on(&today, &table);
+        let boosts: Vec<f32> = report.iter().map(|effect| effect.customer_boost).collect();
+        assert_eq!(boosts.len(), 3);
+        assert_close(boosts[0], 0.3);
+        assert_close(boosts[1], 0.2 * STACKED_AD_EFFECT_RATE);
+        assert_close(
+            boosts[2],
+            0.1 * STACKED_AD_EFFECT_RATE * STACKED_AD_EFFECT_RATE,
+        );
+        assert_close(report.customer_rate(), 1.0 + boosts.iter().sum::<f32>());
+        assert_eq!(
+            report.get(SuzunaAdType::AdPaper).unwrap().remaining_days,
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn remove_faded_keeps_lingering_campaigns() {
+        let table = AdCampaignTable::new_empty();
+        let profile = table.get(SuzunaAdType::AdPaper);
+        let start = GensoDate::new(1, 12, 25);
+        let end = start.add_day_chain(profile.duration - 1);
+
+        let mut record = AdCampaignRecord::new();
+        record.start(start, &profile);
+
+        let lingering = end.add_day_chain(profile.fade_out_days);
+        record.remove_faded(&lingering, &table);
+        assert!(record.has_campaign(SuzunaAdType::AdPaper));
+        assert!(record
+            .running_campaign(SuzunaAdType::AdPaper, &lingering)
+            .is_none());
+
+        record.remove_faded(&end.add_day_chain(profile.fade_out_days + 1), &table);
+        assert!(!record.has_campaign(SuzunaAdType::AdPaper));
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::core::{util, GensoDate};
+
+///
+/// # 本の稀少さ
+///
+/// 稀少な本ほど、新しく入ってくることが少ない
+///
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BookRarity {
+    Common,
+    Uncommon,
+    Rare,
+}
+
+impl BookRarity {
+    ///
+    /// 本を無作為に選ぶときの重み
+    ///
+    pub fn appearance_weight(&self) -> f32 {
+        match self {
+            BookRarity::Common => 1.0,
+            BookRarity::Uncommon => 0.5,
+            BookRarity::Rare => 0.2,
+        }
+    }
+
+    pub fn to_str(&self) -> &str {
+        match self {
+            BookRarity::Common => "並",
+            BookRarity::Uncommon => "稀",
+            BookRarity::Rare => "珍",
+        }
+    }
+
+    ///
+    /// 仕入れ値の、貸出料金の基準額に対する倍率
+    ///
+    pub fn purchase_price_rate(&self) -> f32 {
+        match self {
+            BookRarity::Common => 5.0,
+            BookRarity::Uncommon => 8.0,
+            BookRarity::Rare => 15.0,
+        }
+    }
+
+    ///
+    /// 発注してから届くまでの日数
+    ///
+    pub fn lead_days(&self) -> i32 {
+        match self {
+            BookRarity::Common => 1,
+            BookRarity::Uncommon => 3,
+            BookRarity::Rare => 7,
+        }
+    }
+}
+
+fn default_rarity() -> BookRarity {
+    BookRarity::Common
+}
+
+fn default_demand() -> f32 {
+    1.0
+}
+
+fn default_peak_month() -> u8 {
+    1
+}
+
+fn default_spread() -> u8 {
+    2
+}
+
+///
+/// # 季節による需要の変化
+///
+/// peak_monthに需要がpeakになり、前後spreadヶ月かけてbaseに戻る
+///
+/// ## base
+/// 流行りの時期以外の需要
+///
+/// ## peak
+/// 流行りの時期の需要
+///
+/// ## peak_month
+/// 最も需要が高くなる月
+///
+/// ## spread
+/// 需要の高まりが続く月数
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct DemandCurve {
+    #[serde(default = "default_demand")]
+    pub base: f32,
+    #[serde(default = "default_demand")]
+    pub peak: f32,
+    #[serde(default = "default_peak_month")]
+    pub peak_month: u8,
+    #[serde(default = "default_spread")]
+    pub spread: u8,
+}
+
+impl Default for DemandCurve {
+    fn default() -> Self {
+        DemandCurve {
+            base: default_demand(),
+            peak: default_demand(),
+            peak_month: default_peak_month(),
+            spread: default_spread(),
+        }
+    }
+}
+
+impl DemandCurve {
+    pub fn demand_at(&self, date: &GensoDate) -> f32 {
+        // 12月と1月は隣り合う
+        let distance = (date.month as i32 - self.peak_month as i32).abs();
+        let distance = distance.min(12 - distance);
+
+        if distance >= self.spread as i32 {
+            return self.base.max(0.0);
+        }
+
+        let ratio = 1.0 - (distance as f32 / self.spread as f32);
+        (self.base + ((self.peak - self.base) * ratio)).max(0.0)
+    }
+}
+
+///
+/// 需要を、高・中・低の三段階で表す
+///
+pub fn demand_level_str(demand: f32) -> &'static str {
+    if demand >= 1.5 {
+        "高"
+    } else if demand >= 0.8 {
+        "中"
+    } else {
+        "低"
+    }
+}
+
+///
+/// # 目録に載っている本1種類の情報
+///
+/// titleでBookInformationのnameと対応する
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct CatalogueEntry {
+    pub title: String,
+    pub genre: String,
+    pub author: String,
+    #[serde(default)]
+    pub era: String,
+    #[serde(default = "default_rarity")]
+    pub rarity: BookRarity,
+    #[serde(default)]
+    pub demand: DemandCurve,
+}
+
+impl CatalogueEntry {
+    pub fn demand_at(&self, date: &GensoDate) -> f32 {
+        self.demand.demand_at(date)
+    }
+}
+
+#[derive(Deserialize)]
+struct RawBookCatalogueFile {
+    book: Vec<CatalogueEntry>,
+}
+
+///
+/// # 鈴奈庵の蔵書目録
+///
+/// 目録にない本は、並の稀少さで需要1.0として扱う
+///
+pub struct BookCatalogue {
+    entries: HashMap<String, CatalogueEntry>,
+}
+
+impl BookCatalogue {
+    pub fn new_empty() -> Self {
+        BookCatalogue {
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn from_toml_file(ctx: &mut ggez::Context, path: &str) -> Self {
+        let s = util::read_from_resources_as_string(ctx, path);
+
+        let raw_data: Result<RawBookCatalogueFile, toml::de::Error> = toml::from_str(&s);
+        let raw_data = match raw_data {
+            Ok(p) => p,
+            Err(e) => panic!("Failed to parse book catalogue {}: {}", path, e),
+        };
+
+        let mut entries = HashMap::new();
+        for entry in raw_data.book {
+            entries.insert(entry.title.clone(), entry);
+        }
+
+        BookCatalogue { entries: entries }
+    }
+
+    pub fn get(&self, title: &str) -> Option<&CatalogueEntry> {
+        self.entries.get(title)
+    }
+
+    pub fn demand_of(&self, title: &str, date: &GensoDate) -> f32 {
+        self.entries
+            .get(title)
+            .map_or(default_demand(), |entry| entry.demand_at(date))
+    }
+
+    pub fn rarity_of(&self, title: &str) -> BookRarity {
+        self.entries
+            .get(title)
+            .map_or(default_rarity(), |entry| entry.rarity)
+    }
+
+    pub fn appearance_weight_of(&self, title: &str) -> f32 {
+        self.rarity_of(title).appearance_weight()
+    }
+
+    ///
+    /// 目録の本を、dateの需要が高い順に返す
+    ///
+    pub fn entries_by_demand(&self, date: &GensoDate) -> Vec<(&CatalogueEntry, f32)> {
+        let mut entries: Vec<(&CatalogueEntry, f32)> = self
+            .entries
+            .values()
+            .map(|entry| (entry, entry.demand_at(date)))
+            .collect();
+        entries.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.0.title.cmp(&b.0.title))
+        });
+
+        entries
+    }
+}
@@ -0,0 +1,483 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{BookInformation, BookShelfInformation, GensoDate, ReturnBookInformation};
+
+///
+/// # 1冊ごとの本の所在
+///
+/// ## OnShelf
+/// 請求番号がbeginからendまでの本棚に配架されている
+///
+/// ## ReturnBox
+/// 返却箱の中、または小鈴が配架のために持ち歩いている
+///
+/// ## OnDesk
+/// 受付の机の上にある
+///
+/// ## Lent
+/// borrowerに貸し出されている
+///
+/// ## InRepair
+/// 修繕中
+///
+/// ## Lost
+/// 紛失した
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BookLocation {
+    OnShelf {
+        begin: u16,
+        end: u16,
+    },
+    ReturnBox,
+    OnDesk,
+    Lent {
+        borrower: String,
+        return_date: GensoDate,
+    },
+    InRepair,
+    Lost,
+}
+
+impl BookLocation {
+    pub fn on_shelf(shelf_info: &BookShelfInformation) -> Self {
+        BookLocation::OnShelf {
+            begin: shelf_info.get_begin_number(),
+            end: shelf_info.get_end_number(),
+        }
+    }
+
+    pub fn is_on_shelf(&self) -> bool {
+        match self {
+            BookLocation::OnShelf { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_lent(&self) -> bool {
+        match self {
+            BookLocation::Lent { .. } => true,
+            _ => false,
+        }
+    }
+
+    ///
+    /// selfからnextへ本を動かせるか
+    ///
+    pub fn can_move_to(&self, next: &BookLocation) -> bool {
+        match (self, next) {
+            (BookLocation::OnShelf { .. }, BookLocation::OnShelf { .. })
+            | (BookLocation::OnShelf { .. }, BookLocation::OnDesk)
+            | (BookLocation::OnShelf { .. }, BookLocation::InRepair)
+            | (BookLocation::OnShelf { .. }, BookLocation::Lost) => true,
+            (BookLocation::ReturnBox, BookLocation::OnShelf { .. })
+            | (BookLocation::ReturnBox, BookLocation::OnDesk)
+            | (BookLocation::ReturnBox, BookLocation::InRepair)
+            | (BookLocation::ReturnBox, BookLocation::Lost) => true,
+            (BookLocation::OnDesk, BookLocation::OnShelf { .. })
+            | (BookLocation::OnDesk, BookLocation::ReturnBox)
+            | (BookLocation::OnDesk, BookLocation::Lent { .. }) => true,
+            (BookLocation::Lent { .. }, BookLocation::OnDesk)
+            | (BookLocation::Lent { .. }, BookLocation::ReturnBox)
+            | (BookLocation::Lent { .. }, BookLocation::Lost) => true,
+            (BookLocation::InRepair, BookLocation::OnShelf { .. })
+            | (BookLocation::InRepair, BookLocation::ReturnBox)
+            | (BookLocation::InRepair, BookLocation::Lost) => true,
+            // 見つかった本は、返却箱に戻す
+            (BookLocation::Lost, BookLocation::ReturnBox) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LedgerError {
+    UnknownBook(u64),
+    AlreadyRegistered(u64),
+    InvalidTransition {
+        unique_id: u64,
+        from: BookLocation,
+        to: BookLocation,
+    },
+}
+
+impl std::fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LedgerError::UnknownBook(id) => write!(f, "book {} is not in the ledger", id),
+            LedgerError::AlreadyRegistered(id) => {
+                write!(f, "book {} is already in the ledger", id)
+            }
+            LedgerError::InvalidTransition {
+                unique_id,
+                from,
+                to,
+            } => write!(
+                f,
+                "book {} cannot move from {:?} to {:?}",
+                unique_id, from, to
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookLedgerEntry {
+    pub book: BookInformation,
+    pub location: BookLocation,
+}
+
+///
+/// # 蔵書台帳
+///
+/// 鈴奈庵の本1冊ごとに、unique_idをキーとして所在を記録する
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BookLedger {
+    entries: HashMap<u64, BookLedgerEntry>,
+}
+
+impl BookLedger {
+    pub fn new() -> Self {
+        BookLedger::default()
+    }
+
+    pub fn register(
+        &mut self,
+        book: &BookInformation,
+        location: BookLocation,
+    ) -> Result<(), LedgerError> {
+        let unique_id = book.get_unique_id();
+        if self.entries.contains_key(&unique_id) {
+            return Err(LedgerError::AlreadyRegistered(unique_id));
+        }
+
+        self.entries.insert(
+            unique_id,
+            BookLedgerEntry {
+                book: book.clone(),
+                location: location,
+            },
+        );
+
+        Ok(())
+    }
+
+    pub fn move_to(&mut self, unique_id: u64, location: BookLocation) -> Result<(), LedgerError> {
+        let entry = match self.entries.get_mut(&unique_id) {
+            Some(entry) => entry,
+            None => return Err(LedgerError::UnknownBook(unique_id)),
+        };
+
+        if !entry.location.can_move_to(&location) {
+            return Err(LedgerError::InvalidTransition {
+                unique_id: unique_id,
+                from: entry.location.clone(),
+                to: location,
+            });
+        }
+
+        entry.location = location;
+        Ok(())
+    }
+
+    ///
+    /// 台帳にない本は登録し、ある本は動かす。本の状態などの情報も、bookの内容に更新する
+    ///
+    pub fn place(
+        &mut self,
+        book: &BookInformation,
+        location: BookLocation,
+    ) -> Result<(), LedgerError> {
+        let unique_id = book.get_unique_id();
+        if self.entries.contains_key(&unique_id) {
+            self.move_to(unique_id, location)?;
+        } else {
+            self.register(book, location)?;
+        }
+
+        self.entries.get_mut(&unique_id).unwrap().book = book.clone();
+        Ok(())
+    }
+
+    ///
+    /// 全ての本をplaceする。動かせない本があっても残りの本は動かし、最初のエラーを返す
+    ///
+    pub fn place_all<'a, I>(&mut self, books: I, location: BookLocation) -> Result<(), LedgerError>
+    where
+        I: IntoIterator<Item = &'a BookInformation>,
+    {
+        let mut result = Ok(());
+        for book in books {
+            if let Err(e) = self.place(book, location.clone()) {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+
+        result
+    }
+
+    ///
+    /// 台帳にない本だけを登録する。台帳ができる前のセーブデータを読み込んだ時に使う
+    ///
+    pub fn register_if_missing(&mut self, book: &BookInformation, location: BookLocation) {
+        if !self.entries.contains_key(&book.get_unique_id()) {
+            // 台帳にないことを確かめたので、登録は失敗しない
+            let _ = self.register(book, location);
+        }
+    }
+
+    ///
+    /// 台帳ができる前の記録から、台帳にない本を登録する
+    /// 同じ本が複数の記録にある場合は、返却箱、修繕中、貸出中、本棚の順に優先する
+    ///
+    pub fn seed<'a, R, P, S>(
+        &mut self,
+        return_box: R,
+        in_repair: P,
+        loans: &[ReturnBookInformation],
+        on_shelf: S,
+    ) where
+        R: IntoIterator<Item = &'a BookInformation>,
+        P: IntoIterator<Item = &'a BookInformation>,
+        S: IntoIterator<Item = &'a BookInformation>,
+    {
+        for book in return_box {
+            self.register_if_missing(book, BookLocation::ReturnBox);
+        }
+
+        for book in in_repair {
+            self.register_if_missing(book, BookLocation::InRepair);
+        }
+
+        for loan in loans.iter() {
+            for book in loan.returning.iter() {
+                self.register_if_missing(
+                    book,
+                    BookLocation::Lent {
+                        borrower: loan.borrower.clone(),
+                        return_date: loan.return_date,
+                    },
+                );
+            }
+        }
+
+        // 本のプールの本は、どの本棚にあるかを区別しない
+        for book in on_shelf {
+            self.register_if_missing(
+                book,
+                BookLocation::on_shelf(&BookShelfInformation::new(0, u16::MAX)),
+            );
+        }
+    }
+
+    pub fn get(&self, unique_id: u64) -> Option<&BookLedgerEntry> {
+        self.entries.get(&unique_id)
+    }
+
+    pub fn location_of(&self, unique_id: u64) -> Option<&BookLocation> {
+        self.entries.get(&unique_id).map(|entry| &entry.location)
+    }
+
+    ///
+    /// 所在がfilterを満たす本を、請求番号順に返す
+    ///
+    pub fn books_where<F>(&self, filter: F) -> Vec<&BookInformation>
+    where
+        F: Fn(&BookLocation) -> bool,
+    {
+        let mut books: Vec<&BookInformation> = self
+            .entries
+            .values()
+            .filter(|entry| filter(&entry.location))
+            .map(|entry| &entry.book)
+            .collect();
+        books.sort_by(|a, b| {
+            a.billing_number
+                .cmp(&b.billing_number)
+                .then(a.get_unique_id().cmp(&b.get_unique_id()))
+        });
+
+        books
+    }
+
+    pub fn count_where<F>(&self, filter: F) -> usize
+    where
+        F: Fn(&BookLocation) -> bool,
+    {
+        self.entries
+            .values()
+            .filter(|entry| filter(&entry.location))
+            .count()
+    }
+
+    pub fn return_box_books(&self) -> Vec<&BookInformation> {
+        self.books_where(|location| *location == BookLocation::ReturnBox)
+    }
+
+    pub fn lent_books_count(&self) -> usize {
+        self.count_where(|location| location.is_lent())
+    }
+
+    pub fn return_box_books_count(&self) -> usize {
+        self.count_where(|location| *location == BookLocation::ReturnBox)
+    }
+
+    pub fn books_lent_to(&self, borrower_name: &str) -> Vec<&BookInformation> {
+        self.books_where(|location| match location {
+            BookLocation::Lent { borrower, .. } => borrower == borrower_name,
+            _ => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(name: &str) -> BookInformation {
+        BookInformation::new(name.to_string(), 100, "中".to_string(), 1, 100)
+    }
+
+    fn lent_to(borrower: &str) -> BookLocation {
+        BookLocation::Lent {
+            borrower: borrower.to_string(),
+            return_date: GensoDate::new(1, 4, 8),
+        }
+    }
+
+    fn shelf() -> BookLocation {
+        BookLocation::OnShelf { begin: 0, end: 10 }
+    }
+
+    #[test]
+    fn allowed_transitions() {
+        assert!(shelf().can_move_to(&BookLocation::OnDesk));
+        assert!(BookLocation::OnDesk.can_move_to(&lent_to("阿求")));
+        assert!(lent_to("阿求").can_move_to(&BookLocation::ReturnBox));
+        assert!(BookLocation::ReturnBox.can_move_to(&shelf()));
+        assert!(BookLocation::ReturnBox.can_move_to(&BookLocation::InRepair));
+        assert!(BookLocation::InRepair.can_move_to(&shelf()));
+        assert!(lent_to("阿求").can_move_to(&BookLocation::Lost));
+        assert!(BookLocation::Lost.can_move_to(&BookLocation::ReturnBox));
+    }
+
+    #[test]
+    fn rejected_transitions() {
+        assert!(!shelf().can_move_to(&lent_to("阿求")));
+        assert!(!BookLocation::ReturnBox.can_move_to(&lent_to("阿求")));
+        assert!(!lent_to("阿求").can_move_to(&shelf()));
+        assert!(!lent_to("阿求").can_move_to(&lent_to("魔理沙")));
+        assert!(!BookLocation::InRepair.can_move_to(&BookLocation::OnDesk));
+        assert!(!BookLocation::Lost.can_move_to(&shelf()));
+    }
+
+    #[test]
+    fn place_registers_and_moves() {
+        let mut ledger = BookLedger::new();
+        let book = book("竹取物語");
+
+        ledger.place(&book, shelf()).unwrap();
+        ledger.place(&book, BookLocation::OnDesk).unwrap();
+        ledger.place(&book, lent_to("阿求")).unwrap();
+
+        assert_eq!(
+            ledger.location_of(book.get_unique_id()),
+            Some(&lent_to("阿求"))
+        );
+        assert_eq!(ledger.books_lent_to("阿求").len(), 1);
+    }
+
+    #[test]
+    fn invalid_move_keeps_location() {
+        let mut ledger = BookLedger::new();
+        let book = book("竹取物語");
+        ledger.register(&book, shelf()).unwrap();
+
+        let result = ledger.move_to(book.get_unique_id(), lent_to("阿求"));
+
+        assert!(matches!(result, Err(LedgerError::InvalidTransition { .. })));
+        assert_eq!(ledger.location_of(book.get_unique_id()), Some(&shelf()));
+        assert_eq!(
+            ledger.move_to(0, BookLocation::OnDesk),
+            Err(LedgerError::UnknownBook(0))
+        );
+        assert_eq!(
+            ledger.register(&book, shelf()),
+            Err(LedgerError::AlreadyRegistered(book.get_unique_id()))
+        );
+    }
+
+    #[test]
+    fn place_all_moves_the_rest_and_returns_the_first_error() {
+        let mut ledger = BookLedger::new();
+        let (a, b) = (book("竹取物語"), book("伊勢物語"));
+        ledger.register(&a, shelf()).unwrap();
+        ledger.register(&b, BookLocation::OnDesk).unwrap();
+
+        let result = ledger.place_all(vec![&a, &b], lent_to("阿求"));
+
+        assert!(result.is_err());
+        assert_eq!(ledger.location_of(a.get_unique_id()), Some(&shelf()));
+        assert_eq!(
+            ledger.location_of(b.get_unique_id()),
+            Some(&lent_to("阿求"))
+        );
+    }
+
+    #[test]
+    fn seed_migrates_old_records() {
+        let returned = book("竹取物語");
+        let repairing = book("伊勢物語");
+        let lent = book("源氏物語");
+        let shelved = book("枕草子");
+        let loan = ReturnBookInformation::new(
+            vec![lent.clone()],
+            "阿求",
+            GensoDate::new(1, 4, 1),
+            GensoDate::new(1, 4, 8),
+        );
+
+        let mut ledger = BookLedger::new();
+        // 本のプールにも残っている本は、返却箱などの記録を優先する
+        let pool = vec![returned.clone(), repairing.clone(), shelved.clone()];
+        ledger.seed(vec![&returned], vec![&repairing], &[loan], pool.iter());
+
+        assert_eq!(
+            ledger.location_of(returned.get_unique_id()),
+            Some(&BookLocation::ReturnBox)
+        );
+        assert_eq!(
+            ledger.location_of(repairing.get_unique_id()),
+            Some(&BookLocation::InRepair)
+        );
+        assert_eq!(
+            ledger.location_of(lent.get_unique_id()),
+            Some(&lent_to("阿求"))
+        );
+        assert!(ledger
+            .location_of(shelved.get_unique_id())
+            .unwrap()
+            .is_on_shelf());
+        assert_eq!(ledger.return_box_books_count(), 1);
+        assert_eq!(ledger.lent_books_count(), 1);
+    }
+
+    #[test]
+    fn seed_keeps_existing_entries() {
+        let lent = book("源氏物語");
+        let mut ledger = BookLedger::new();
+        ledger.register(&lent, lent_to("阿求")).unwrap();
+
+        ledger.seed(vec![&lent], Vec::new(), &[], Vec::new());
+
+        assert_eq!(
+            ledger.location_of(lent.get_unique_id()),
+            Some(&lent_to("阿求"))
+        );
+    }
+}
@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 
+use serde::{Deserialize, Serialize};
+
 use crate::parse_toml_file;
 
+use super::game_system::EventProgressTable;
 use super::{GameResource, GensoDate};
 use super::{BookInformation, SuzuContext};
 
@@ -37,6 +40,13 @@ impl DayNewBooks {
         }
     }
 
+    ///
+    /// 届いた本など、既にある本から作る
+    ///
+    pub fn from_books(books: Vec<BookInformation>) -> Self {
+        DayNewBooks { new_books: books }
+    }
+
     pub fn get_new_books(&self) -> Vec<BookInformation> {
         self.new_books.clone()
     }
@@ -93,3 +103,177 @@ impl NewBookSchedule {
         self.new_book_schedule.get(date)
     }
 }
+
+///
+/// 1回の発注で届く冊数
+///
+pub const PURCHASE_UNIT: usize = 2;
+
+///
+/// # 仕入れ先が扱う本1種類
+///
+/// ## book
+/// 届く本の元になる情報。届くときに、1冊ごとに新しいIDと状態が付く
+///
+/// ## price
+/// PURCHASE_UNIT冊の仕入れ値
+///
+/// ## lead_days
+/// 発注してから届くまでの日数
+///
+#[derive(Debug, Clone)]
+pub struct SupplierOffer {
+    pub book: BookInformation,
+    pub price: i32,
+    pub lead_days: i32,
+}
+
+///
+/// # 仕入れ先の品揃え
+///
+/// 仕入れ値と届くまでの日数は、目録の稀少さで決まる
+///
+pub struct SupplierCatalogue {
+    offers: Vec<SupplierOffer>,
+}
+
+impl SupplierCatalogue {
+    pub fn from_resource(resource: &GameResource, progress: &EventProgressTable) -> Self {
+        let catalogue = resource.get_book_catalogue();
+        let going_out_events = resource.get_going_out_events();
+
+        let offers = resource
+            .iter_available_books()
+            // 外出先での出来事で仕入れられるようになる本は、それまで品揃えに並ばない
+            .filter(|book| {
+                !going_out_events.locks_book(&book.name) || progress.book_is_unlocked(&book.name)
+            })
+            .map(|book| {
+                let rarity = catalogue.rarity_of(&book.name);
+                SupplierOffer {
+                    book: book.clone(),
+                    price: (book.base_price as f32
+                        * rarity.purchase_price_rate()
+                        * PURCHASE_UNIT as f32) as i32,
+                    lead_days: rarity.lead_days(),
+                }
+            })
+            .collect();
+
+        SupplierCatalogue { offers: offers }
+    }
+
+    pub fn find(&self, title: &str) -> Option<&SupplierOffer> {
+        self.offers.iter().find(|offer| offer.book.name == title)
+    }
+
+    ///
+    /// 品揃えを、dateの需要が高い順に返す
+    ///
+    pub fn offers_by_demand(
+        &self,
+        resource: &GameResource,
+        date: &GensoDate,
+    ) -> Vec<&SupplierOffer> {
+        let catalogue = resource.get_book_catalogue();
+
+        let mut offers: Vec<&SupplierOffer> = self.offers.iter().collect();
+        offers.sort_by(|a, b| {
+            catalogue
+                .demand_of(&b.book.name, date)
+                .partial_cmp(&catalogue.demand_of(&a.book.name, date))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.book.name.cmp(&b.book.name))
+        });
+
+        offers
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurchaseOrder {
+    pub book: BookInformation,
+    pub count: usize,
+    pub cost: i32,
+    pub order_date: GensoDate,
+    pub arrival_date: GensoDate,
+}
+
+impl PurchaseOrder {
+    pub fn new(offer: &SupplierOffer, order_date: GensoDate) -> Self {
+        PurchaseOrder {
+            book: offer.book.clone(),
+            count: PURCHASE_UNIT,
+            cost: offer.price,
+            order_date: order_date,
+            arrival_date: order_date.add_day_chain(offer.lead_days),
+        }
+    }
+
+    pub fn is_arrived(&self, today: &GensoDate) -> bool {
+        !self.arrival_date.is_past(today)
+    }
+
+    ///
+    /// 届いた本を、1冊ずつ新しいIDと状態で作る
+    ///
+    pub fn deliver(&self) -> Vec<BookInformation> {
+        (0..self.count)
+            .map(|_| self.book.clone_with_new_id_condition())
+            .collect()
+    }
+}
+
+///
+/// # まだ届いていない発注の記録
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PurchaseRecord {
+    orders: Vec<PurchaseOrder>,
+}
+
+impl PurchaseRecord {
+    pub fn new() -> Self {
+        PurchaseRecord::default()
+    }
+
+    pub fn push_order(&mut self, order: PurchaseOrder) {
+        self.orders.push(order);
+    }
+
+    pub fn has_pending_order(&self, title: &str) -> bool {
+        self.orders.iter().any(|order| order.book.name == title)
+    }
+
+    pub fn pending_orders_count(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<PurchaseOrder> {
+        self.orders.iter()
+    }
+
+    ///
+    /// titleの発注を取り消して返す
+    ///
+    pub fn remove_order(&mut self, title: &str) -> Option<PurchaseOrder> {
+        let index = self
+            .orders
+            .iter()
+            .position(|order| order.book.name == title)?;
+        Some(self.orders.remove(index))
+    }
+
+    ///
+    /// todayまでに届いた発注を取り出す
+    ///
+    pub fn take_arrived(&mut self, today: &GensoDate) -> Vec<PurchaseOrder> {
+        let (arrived, pending): (Vec<PurchaseOrder>, Vec<PurchaseOrder>) =
+            std::mem::replace(&mut self.orders, Vec::new())
+                .into_iter()
+                .partition(|order| order.is_arrived(today));
+        self.orders = pending;
+
+        arrived
+    }
+}
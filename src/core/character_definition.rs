@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::core::util;
+use crate::core::TextureID;
+use crate::libsuzu::core::Clock;
+use crate::libsuzu::numeric;
+
+fn default_spawn_weight() -> u32 {
+    1
+}
+
+fn default_frame_speed() -> Clock {
+    15
+}
+
+fn default_scale() -> f32 {
+    1.5
+}
+
+fn default_walk_speed() -> f32 {
+    6.0
+}
+
+fn default_initial_direction() -> String {
+    "stop_down".to_string()
+}
+
+fn default_collision() -> CollisionCropData {
+    CollisionCropData {
+        x: 0.02,
+        y: 0.6,
+        w: 0.98,
+        h: 1.0,
+    }
+}
+
+///
+/// # テクスチャに対する衝突判定の範囲の割合
+///
+/// テクスチャの大きさを1.0とした、左上と右下の位置
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct CollisionCropData {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl CollisionCropData {
+    pub fn to_rect(&self) -> numeric::Rect {
+        numeric::Rect::new(self.x, self.y, self.w, self.h)
+    }
+}
+
+///
+/// # 客1人ごとの性格
+///
+/// ## patience
+/// 会計の列で待てる時間
+///
+/// ## browsing_time
+/// 本棚の前で本を眺めている時間
+///
+/// ## preferred_sizes
+/// 借りたがる本の大きさ。空の場合は、どの本でも借りる
+///
+/// ## preferred_genres
+/// 借りたがる本のジャンル。空の場合は、どのジャンルでも借りる
+///
+/// ## borrow_rate
+/// 会計で、返却ではなく貸出を頼む確率
+///
+/// ## reputation_sensitivity
+/// 待たされたり、満足したりしたときに、評判に与える影響の倍率
+///
+/// ## carelessness
+/// 借りた本の傷めやすさ。貸出期限による傷む確率に掛ける
+///
+#[derive(Debug, Clone)]
+pub struct CustomerPersonality {
+    pub patience: Clock,
+    pub browsing_time: Clock,
+    pub preferred_sizes: Vec<String>,
+    pub preferred_genres: Vec<String>,
+    pub borrow_rate: f32,
+    pub reputation_sensitivity: f32,
+    pub carelessness: f32,
+}
+
+impl CustomerPersonality {
+    ///
+    /// 性格の定義がない客の、標準的な性格
+    ///
+    pub fn new_default() -> Self {
+        CustomerPersonality {
+            patience: 1200,
+            browsing_time: 100,
+            preferred_sizes: Vec::new(),
+            preferred_genres: Vec::new(),
+            borrow_rate: 0.5,
+            reputation_sensitivity: 1.0,
+            carelessness: 1.0,
+        }
+    }
+
+    ///
+    /// 評判を考慮した、列で待てる時間
+    /// 評判が高い店では長く、低い店では短く待つ。reputation_sensitivityが大きいほど影響を受ける
+    ///
+    pub fn patience_with_reputation(&self, reputation: f32) -> Clock {
+        let rate = 1.0 + ((reputation - 50.0) / 100.0) * self.reputation_sensitivity;
+        (self.patience as f32 * rate.max(0.2)) as Clock
+    }
+
+    pub fn prefers_size(&self, size: &str) -> bool {
+        self.preferred_sizes.is_empty() || self.preferred_sizes.iter().any(|s| s == size)
+    }
+
+    ///
+    /// genreの本を好むか。目録にない本のようにジャンルが分からなければNoneを渡す
+    ///
+    pub fn prefers_genre(&self, genre: Option<&str>) -> bool {
+        self.preferred_genres.is_empty()
+            || genre.map_or(false, |genre| {
+                self.preferred_genres.iter().any(|g| g == genre)
+            })
+    }
+}
+
+fn random_range(range: &[f32; 2]) -> f32 {
+    range[0] + (rand::random::<f32>() * (range[1] - range[0]))
+}
+
+fn default_patience_range() -> [f32; 2] {
+    [1200.0, 1200.0]
+}
+
+fn default_browsing_time_range() -> [f32; 2] {
+    [100.0, 100.0]
+}
+
+fn default_borrow_rate_range() -> [f32; 2] {
+    [0.5, 0.5]
+}
+
+fn default_reputation_sensitivity_range() -> [f32; 2] {
+    [1.0, 1.0]
+}
+
+fn default_carelessness_range() -> [f32; 2] {
+    [1.0, 1.0]
+}
+
+///
+/// # 客の性格の定義
+///
+/// 各値は[最小, 最大]の範囲で書き、客を生成するたびにその範囲から選ぶ
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct PersonalityDefinition {
+    #[serde(default = "default_patience_range")]
+    pub patience: [f32; 2],
+    #[serde(default = "default_browsing_time_range")]
+    pub browsing_time: [f32; 2],
+    #[serde(default)]
+    pub preferred_sizes: Vec<String>,
+    #[serde(default)]
+    pub preferred_genres: Vec<String>,
+    #[serde(default = "default_borrow_rate_range")]
+    pub borrow_rate: [f32; 2],
+    #[serde(default = "default_reputation_sensitivity_range")]
+    pub reputation_sensitivity: [f32; 2],
+    #[serde(default = "default_carelessness_range")]
+    pub carelessness: [f32; 2],
+}
+
+impl PersonalityDefinition {
+    pub fn generate(&self) -> CustomerPersonality {
+        CustomerPersonality {
+            patience: random_range(&self.patience).max(0.0) as Clock,
+            browsing_time: random_range(&self.browsing_time).max(0.0) as Clock,
+            preferred_sizes: self.preferred_sizes.clone(),
+            preferred_genres: self.preferred_genres.clone(),
+            borrow_rate: random_range(&self.borrow_rate).max(0.0).min(1.0),
+            reputation_sensitivity: random_range(&self.reputation_sensitivity).max(0.0),
+            carelessness: random_range(&self.carelessness).max(0.0),
+        }
+    }
+}
+
+///
+/// # キャラクターの種類の定義
+///
+/// ## animation
+/// move_down, stop_leftのような向きの名前から、アニメーションに使うTextureIDの名前の列
+///
+/// ## customer
+/// 客として店に来るキャラクターか
+///
+/// ## spawn_weight
+/// 客として現れるときの重み。大きいほど現れやすい
+///
+/// ## pixel_art
+/// ドット絵として、拡大時に補間しない
+///
+/// ## walk_speed
+/// 歩く速さの上限
+///
+/// ## name_pool
+/// 客の名前の候補。空の場合は、設定ファイルのcustomers_nameから選ぶ
+///
+/// ## personality
+/// 客の性格の範囲。ない場合は標準的な性格になる
+///
+/// ## district
+/// 客が住んでいる地区。地区ごとの評判に使う
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct CharacterDefinition {
+    pub id: String,
+    pub animation: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub customer: bool,
+    #[serde(default = "default_spawn_weight")]
+    pub spawn_weight: u32,
+    #[serde(default = "default_frame_speed")]
+    pub frame_speed: Clock,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub pixel_art: bool,
+    #[serde(default = "default_collision")]
+    pub collision: CollisionCropData,
+    #[serde(default = "default_walk_speed")]
+    pub walk_speed: f32,
+    #[serde(default = "default_initial_direction")]
+    pub initial_direction: String,
+    #[serde(default)]
+    pub name_pool: Vec<String>,
+    #[serde(default)]
+    pub personality: Option<PersonalityDefinition>,
+    #[serde(default)]
+    pub district: Option<String>,
+}
+
+impl CharacterDefinition {
+    ///
+    /// 生成するときに困らない定義か調べる。誤りがあれば、その内容を返す
+    /// アニメーションが1つ以上あり、どのコマもTextureIDの名前で、空のアニメーションがないこと
+    ///
+    pub fn validate(&self) -> Result<(), String> {
+        if self.animation.is_empty() {
+            return Err("no animation".to_string());
+        }
+
+        for (name, frames) in self.animation.iter() {
+            if frames.is_empty() {
+                return Err(format!("animation {} has no frames", name));
+            }
+
+            if let Some(frame) = frames
+                .iter()
+                .find(|frame| TextureID::from_str(frame).is_err())
+            {
+                return Err(format!("unknown texture {} in animation {}", frame, name));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn random_name(&self) -> Option<&str> {
+        if self.name_pool.is_empty() {
+            None
+        } else {
+            Some(&self.name_pool[rand::random::<usize>() % self.name_pool.len()])
+        }
+    }
+
+    pub fn generate_personality(&self) -> CustomerPersonality {
+        match self.personality.as_ref() {
+            Some(personality) => personality.generate(),
+            None => CustomerPersonality::new_default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawCharacterDefinitionFile {
+    character: Vec<CharacterDefinition>,
+}
+
+///
+/// # キャラクターの種類の定義の一覧
+///
+pub struct CharacterDefinitionTable {
+    definitions: HashMap<String, CharacterDefinition>,
+}
+
+impl CharacterDefinitionTable {
+    pub fn new_empty() -> Self {
+        CharacterDefinitionTable {
+            definitions: HashMap::new(),
+        }
+    }
+
+    pub fn from_toml_file(ctx: &mut ggez::Context, path: &str) -> Self {
+        let s = util::read_from_resources_as_string(ctx, path);
+
+        let raw_data: Result<RawCharacterDefinitionFile, toml::de::Error> = toml::from_str(&s);
+        let raw_data = match raw_data {
+            Ok(p) => p,
+            Err(e) => panic!("Failed to parse character definition {}: {}", path, e),
+        };
+
+        // 定義の誤りは、客が現れたときではなく読み込んだときに見つける
+        let mut definitions = HashMap::new();
+        for definition in raw_data.character {
+            if let Err(e) = definition.validate() {
+                panic!(
+                    "Invalid character definition {} in {}: {}",
+                    definition.id, path, e
+                );
+            }
+            definitions.insert(definition.id.clone(), definition);
+        }
+
+        CharacterDefinitionTable {
+            definitions: definitions,
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&CharacterDefinition> {
+        self.definitions.get(id)
+    }
+
+    ///
+    /// 客のキャラクターを、spawn_weightにrateを掛けた重みで1つ選ぶ
+    ///
+    pub fn random_customer<F>(&self, rate: F) -> Option<&CharacterDefinition>
+    where
+        F: Fn(&CharacterDefinition) -> f32,
+    {
+        let customers: Vec<&CharacterDefinition> = self
+            .definitions
+            .values()
+            .filter(|definition| definition.customer)
+            .collect();
+        let weights: Vec<f32> = customers
+            .iter()
+            .map(|definition| definition.spawn_weight as f32 * rate(definition))
+            .collect();
+
+        util::weighted_random_index(&weights).map(|index| customers[index])
+    }
+}
@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{BookInformation, GensoDate};
+
+///
+/// 来店した客が、写本を依頼する確率
+///
+pub const COPY_REQUEST_RATE: f32 = 0.1;
+
+///
+/// 依頼を受けてから、納期までの日数
+///
+pub const COPY_DEADLINE_DAYS: i32 = 14;
+
+pub const COPY_PAYMENT_PER_PAGE: i32 = 15;
+
+///
+/// 納期に間に合わなかった依頼1件ごとに下がる評判
+///
+pub const COPY_EXPIRED_REPUTATION_PENALTY: f32 = 5.0;
+
+///
+/// 版木の上を、これだけなぞると1頁写したことになる
+///
+pub const COPY_DRAG_DISTANCE_PER_PAGE: f32 = 600.0;
+
+///
+/// 写本の作業ができる時間
+///
+pub const COPY_WORK_TIME_LIMIT: u64 = 3600;
+
+///
+/// # 写本の依頼
+///
+/// ## copied_pages
+/// 写し終えた頁数。pagesに達したら納品できる
+///
+/// ## payment
+/// 納品したときに受け取る代金
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CopyRequest {
+    pub customer: String,
+    pub book_title: String,
+    pub pages: usize,
+    pub copied_pages: usize,
+    pub request_date: GensoDate,
+    pub deadline: GensoDate,
+    pub payment: i32,
+}
+
+impl CopyRequest {
+    pub fn new(customer: &str, book: &BookInformation, request_date: GensoDate) -> Self {
+        CopyRequest {
+            customer: customer.to_string(),
+            book_title: book.name.clone(),
+            pages: book.pages,
+            copied_pages: 0,
+            request_date: request_date,
+            deadline: request_date.add_day_chain(COPY_DEADLINE_DAYS),
+            payment: book.pages as i32 * COPY_PAYMENT_PER_PAGE,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.copied_pages >= self.pages
+    }
+
+    pub fn remaining_pages(&self) -> usize {
+        self.pages.saturating_sub(self.copied_pages)
+    }
+
+    ///
+    /// 写した頁を進める。実際に進んだ頁数を返す
+    ///
+    pub fn add_copied_pages(&mut self, pages: usize) -> usize {
+        let added = pages.min(self.remaining_pages());
+        self.copied_pages += added;
+        added
+    }
+
+    pub fn is_expired(&self, today: &GensoDate) -> bool {
+        today.is_past(&self.deadline)
+    }
+}
+
+///
+/// # 写本の仕事の記録
+///
+/// ## requests
+/// まだ納品していない依頼
+///
+/// ## delivered_count
+/// これまでに納品した依頼の数
+///
+/// ## expired_count
+/// 納期に間に合わなかった依頼の数
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CopyWorkRecord {
+    requests: Vec<CopyRequest>,
+    pub delivered_count: u32,
+    pub expired_count: u32,
+}
+
+impl CopyWorkRecord {
+    pub fn new() -> Self {
+        CopyWorkRecord::default()
+    }
+
+    pub fn push_request(&mut self, request: CopyRequest) {
+        self.requests.push(request);
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<CopyRequest> {
+        self.requests.iter()
+    }
+
+    ///
+    /// 納期が最も近い、写し終えていない依頼
+    ///
+    pub fn current_request(&self) -> Option<&CopyRequest> {
+        self.requests
+            .iter()
+            .filter(|request| !request.is_finished())
+            .min_by(|a, b| {
+                if a.deadline.is_past(&b.deadline) {
+                    std::cmp::Ordering::Greater
+                } else if b.deadline.is_past(&a.deadline) {
+                    std::cmp::Ordering::Less
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+    }
+
+    pub fn current_request_mut(&mut self) -> Option<&mut CopyRequest> {
+        let current = self.current_request()?;
+        let (title, request_date) = (current.book_title.clone(), current.request_date);
+
+        self.requests.iter_mut().find(|request| {
+            !request.is_finished()
+                && request.book_title == title
+                && request.request_date == request_date
+        })
+    }
+
+    ///
+    /// 納期が近い依頼から順に、pages頁写し進める。実際に進んだ頁数を返す
+    ///
+    pub fn copy_pages(&mut self, pages: usize) -> usize {
+        let mut rest = pages;
+
+        while rest > 0 {
+            match self.current_request_mut() {
+                Some(request) => rest -= request.add_copied_pages(rest),
+                None => break,
+            }
+        }
+
+        pages - rest
+    }
+
+    ///
+    /// 写し終えた依頼を取り出す
+    ///
+    pub fn take_finished(&mut self) -> Vec<CopyRequest> {
+        let (finished, pending): (Vec<CopyRequest>, Vec<CopyRequest>) =
+            std::mem::replace(&mut self.requests, Vec::new())
+                .into_iter()
+                .partition(|request| request.is_finished());
+        self.requests = pending;
+        self.delivered_count += finished.len() as u32;
+
+        finished
+    }
+
+    ///
+    /// todayの時点で納期を過ぎた依頼を取り出す
+    ///
+    pub fn take_expired(&mut self, today: &GensoDate) -> Vec<CopyRequest> {
+        let (expired, pending): (Vec<CopyRequest>, Vec<CopyRequest>) =
+            std::mem::replace(&mut self.requests, Vec::new())
+                .into_iter()
+                .partition(|request| request.is_expired(today));
+        self.requests = pending;
+        self.expired_count += expired.len() as u32;
+
+        expired
+    }
+}
@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{BorrowingInformation, GensoDate, ReturnBookInformation};
+
+///
+/// この回数以上来店した客を、常連として扱う
+///
+pub const REGULAR_VISIT_COUNT: u32 = 3;
+
+fn default_carelessness() -> f32 {
+    1.0
+}
+
+///
+/// # 1回分の貸出の記録
+///
+/// ## returned_date
+/// 実際に返却された日。まだ返却されていなければNone
+///
+/// ## lost
+/// 返却されないまま紛失したか
+///
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BorrowingHistoryEntry {
+    pub titles: Vec<String>,
+    pub borrow_date: GensoDate,
+    pub return_date: GensoDate,
+    pub returned_date: Option<GensoDate>,
+    #[serde(default)]
+    pub lost: bool,
+}
+
+impl BorrowingHistoryEntry {
+    pub fn is_returned(&self) -> bool {
+        self.returned_date.is_some()
+    }
+
+    ///
+    /// infoの貸出の記録か。借り手ごとに記録しているので、借りた日と本で見分ける
+    ///
+    pub fn is_loan_of(&self, info: &ReturnBookInformation) -> bool {
+        if self.borrow_date != info.borrow_date || self.titles.len() != info.returning.len() {
+            return false;
+        }
+
+        let mut titles: Vec<&str> = self.titles.iter().map(|title| title.as_str()).collect();
+        let mut returning: Vec<&str> = info
+            .returning
+            .iter()
+            .map(|book| book.name.as_str())
+            .collect();
+        titles.sort();
+        returning.sort();
+
+        titles == returning
+    }
+
+    ///
+    /// 返却期限を過ぎて返却されたか
+    ///
+    pub fn is_overdue_returned(&self) -> bool {
+        match self.returned_date.as_ref() {
+            Some(returned_date) => returned_date.is_past(&self.return_date),
+            None => false,
+        }
+    }
+}
+
+///
+/// # 鈴奈庵に来たことのある客の情報
+///
+/// ## definition_id
+/// 客の見た目に使ったキャラクター定義のid。次に来たときも同じ見た目にする
+///
+/// ## title_counts
+/// 本の題名ごとの、借りた回数
+///
+/// ## carelessness
+/// 前回来たときの、本の傷めやすさ
+///
+/// ## district
+/// 客が住んでいる地区。分からなければNone
+///
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegularCustomer {
+    pub name: String,
+    pub definition_id: Option<String>,
+    pub first_visit: GensoDate,
+    pub last_visit: GensoDate,
+    pub visit_count: u32,
+    pub history: Vec<BorrowingHistoryEntry>,
+    pub overdue_count: u32,
+    title_counts: HashMap<String, u32>,
+    #[serde(default = "default_carelessness")]
+    pub carelessness: f32,
+    #[serde(default)]
+    pub district: Option<String>,
+}
+
+impl RegularCustomer {
+    pub fn new(name: &str, definition_id: Option<String>, date: GensoDate) -> Self {
+        RegularCustomer {
+            name: name.to_string(),
+            definition_id: definition_id,
+            first_visit: date,
+            last_visit: date,
+            visit_count: 0,
+            history: Vec::new(),
+            overdue_count: 0,
+            title_counts: HashMap::new(),
+            carelessness: default_carelessness(),
+            district: None,
+        }
+    }
+
+    pub fn is_regular(&self) -> bool {
+        self.visit_count >= REGULAR_VISIT_COUNT
+    }
+
+    ///
+    /// よく借りる本の題名を、借りた回数の多い順にmax_len個まで返す
+    ///
+    pub fn favourite_titles(&self, max_len: usize) -> Vec<&str> {
+        let mut titles: Vec<(&String, &u32)> = self.title_counts.iter().collect();
+        titles.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        titles
+            .into_iter()
+            .take(max_len)
+            .map(|(title, _)| title.as_str())
+            .collect()
+    }
+
+    pub fn unreturned_entries(&self) -> impl Iterator<Item = &BorrowingHistoryEntry> {
+        self.history
+            .iter()
+            .filter(|entry| !entry.is_returned() && !entry.lost)
+    }
+
+    pub fn total_borrowed_books(&self) -> usize {
+        self.history.iter().map(|entry| entry.titles.len()).sum()
+    }
+}
+
+///
+/// # 客の名簿
+///
+/// セーブデータに保存され、日をまたいでも客の貸出の履歴を覚えておく
+///
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CustomerRegistry {
+    customers: HashMap<String, RegularCustomer>,
+}
+
+impl CustomerRegistry {
+    pub fn new() -> Self {
+        CustomerRegistry::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RegularCustomer> {
+        self.customers.get(name)
+    }
+
+    fn entry(&mut self, name: &str, date: GensoDate) -> &mut RegularCustomer {
+        self.customers
+            .entry(name.to_string())
+            .or_insert_with(|| RegularCustomer::new(name, None, date))
+    }
+
+    ///
+    /// 来店を記録する。初めての客であれば名簿に加える
+    ///
+    pub fn record_visit(
+        &mut self,
+        name: &str,
+        definition_id: Option<&str>,
+        district: Option<&str>,
+        carelessness: f32,
+        date: GensoDate,
+    ) {
+        let customer = self.entry(name, date);
+
+        customer.visit_count += 1;
+        customer.last_visit = date;
+        customer.carelessness = carelessness;
+        if customer.definition_id.is_none() {
+            customer.definition_id = definition_id.map(|id| id.to_string());
+        }
+        if customer.district.is_none() {
+            customer.district = district.map(|district| district.to_string());
+        }
+    }
+
+    pub fn district_of(&self, name: &str) -> Option<String> {
+        self.customers
+            .get(name)
+            .and_then(|customer| customer.district.clone())
+    }
+
+    pub fn record_borrowing(&mut self, info: &BorrowingInformation) {
+        let customer = self.entry(&info.borrower, info.borrow_date);
+
+        for book in info.borrowing.iter() {
+            *customer.title_counts.entry(book.name.clone()).or_insert(0) += 1;
+        }
+
+        customer.history.push(BorrowingHistoryEntry {
+            titles: info
+                .borrowing
+                .iter()
+                .map(|book| book.name.clone())
+                .collect(),
+            borrow_date: info.borrow_date,
+            return_date: info.return_date,
+            returned_date: None,
+            lost: false,
+        });
+    }
+
+    ///
+    /// 返却を記録する。借り手と借りた日と本が同じ貸出の記録を返却済みにし、
+    /// 期限を過ぎていれば延滞を数える
+    ///
+    pub fn record_returning(&mut self, info: &ReturnBookInformation, today: GensoDate) {
+        let customer = self.entry(&info.borrower, info.borrow_date);
+
+        let maybe_entry = customer
+            .history
+            .iter_mut()
+            .find(|entry| !entry.is_returned() && entry.is_loan_of(info));
+
+        match maybe_entry {
+            Some(entry) => entry.returned_date = Some(today),
+            None => {
+                // 名簿ができる前の貸出は、返却時に記録する
+                customer.history.push(BorrowingHistoryEntry {
+                    titles: info
+                        .returning
+                        .iter()
+                        .map(|book| book.name.clone())
+                        .collect(),
+                    borrow_date: info.borrow_date,
+                    return_date: info.return_date,
+                    returned_date: Some(today),
+                    lost: false,
+                });
+            }
+        }
+
+        if today.is_past(&info.return_date) {
+            customer.overdue_count += 1;
+        }
+    }
+
+    ///
+    /// 貸出の紛失を記録する。紛失も延滞として数える
+    ///
+    pub fn record_lost(&mut self, info: &ReturnBookInformation) {
+        let customer = self.entry(&info.borrower, info.borrow_date);
+
+        if let Some(entry) = customer
+            .history
+            .iter_mut()
+            .find(|entry| !entry.is_returned() && entry.is_loan_of(info))
+        {
+            entry.lost = true;
+        }
+
+        customer.overdue_count += 1;
+    }
+
+    ///
+    /// 客の本の傷めやすさ。名簿にない客は標準の1.0
+    ///
+    pub fn carelessness_of(&self, name: &str) -> f32 {
+        self.customers
+            .get(name)
+            .map_or(default_carelessness(), |customer| customer.carelessness)
+    }
+
+    ///
+    /// 常連の客の名前を返す
+    ///
+    pub fn regular_names(&self) -> Vec<&str> {
+        self.customers
+            .values()
+            .filter(|customer| customer.is_regular())
+            .map(|customer| customer.name.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{BookInformation, RentalLimit};
+    use crate::object::task_object::tt_sub_component::{
+        BorrowingRecordBookData, BorrowingRecordBookPageData,
+    };
+
+    fn book(name: &str) -> BookInformation {
+        BookInformation::new(name.to_string(), 100, "中".to_string(), 1, 100)
+    }
+
+    fn loan(borrower: &str, titles: &[&str], borrow_date: GensoDate) -> ReturnBookInformation {
+        ReturnBookInformation::new(
+            titles.iter().map(|title| book(title)).collect(),
+            borrower,
+            borrow_date,
+            borrow_date.add_day_chain(7),
+        )
+    }
+
+    fn record_book(loans: &[ReturnBookInformation]) -> BorrowingRecordBookData {
+        BorrowingRecordBookData {
+            pages_data: loans
+                .iter()
+                .map(|loan| BorrowingRecordBookPageData::from(loan))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn picks_the_oldest_loan_of_the_customer() {
+        let newer = loan("阿求", &["枕草子"], GensoDate::new(1, 4, 10));
+        let older = loan("阿求", &["竹取物語"], GensoDate::new(1, 4, 3));
+        let other = loan("魔理沙", &["伊勢物語"], GensoDate::new(1, 4, 1));
+        let data = record_book(&[newer, older.clone(), other]);
+
+        assert_eq!(data.pick_returning_request_of("阿求"), Some(older));
+    }
+
+    #[test]
+    fn never_picks_another_customers_loan() {
+        let data = record_book(&[loan("魔理沙", &["伊勢物語"], GensoDate::new(1, 4, 1))]);
+
+        assert_eq!(data.pick_returning_request_of("阿求"), None);
+    }
+
+    #[test]
+    fn picks_across_seasons_by_date() {
+        let newer = loan("阿求", &["枕草子"], GensoDate::new(2, 1, 2));
+        let older = loan("阿求", &["竹取物語"], GensoDate::new(1, 12, 20));
+        let data = record_book(&[newer, older.clone()]);
+
+        assert_eq!(data.pick_returning_request_of("阿求"), Some(older));
+    }
+
+    #[test]
+    fn loan_matches_by_date_and_titles() {
+        let mut registry = CustomerRegistry::new();
+        let today = GensoDate::new(1, 4, 3);
+        registry.record_borrowing(&BorrowingInformation::new(
+            vec![book("竹取物語"), book("枕草子")],
+            "阿求",
+            today,
+            RentalLimit::ShortTerm,
+        ));
+        registry.record_borrowing(&BorrowingInformation::new(
+            vec![book("竹取物語")],
+            "阿求",
+            today,
+            RentalLimit::ShortTerm,
+        ));
+
+        // 題名の並びが違っても、同じ貸出として返却する
+        registry.record_returning(&loan("阿求", &["枕草子", "竹取物語"], today), today);
+
+        let history = &registry.get("阿求").unwrap().history;
+        assert_eq!(history.len(), 2);
+        assert!(history[0].is_returned());
+        assert!(!history[1].is_returned());
+        assert_eq!(registry.get("阿求").unwrap().overdue_count, 0);
+    }
+
+    #[test]
+    fn overdue_and_lost_loans_are_counted() {
+        let mut registry = CustomerRegistry::new();
+        let borrow_date = GensoDate::new(1, 4, 3);
+        let returned = loan("阿求", &["竹取物語"], borrow_date);
+        let lost = loan("阿求", &["枕草子"], borrow_date.add_day_chain(1));
+
+        registry.record_returning(&returned, borrow_date.add_day_chain(10));
+        registry.record_lost(&lost);
+
+        let customer = registry.get("阿求").unwrap();
+        assert!(customer.history[0].is_overdue_returned());
+        assert_eq!(customer.overdue_count, 2);
+        assert!(registry.get("魔理沙").is_none());
+    }
+}
@@ -0,0 +1,51 @@
+pub const MAX_KOSUZU_HP: f32 = 100.0;
+pub const MIN_KOSUZU_HP: f32 = 0.0;
+
+///
+/// 意欲がこれを下回ると、疲れが動きや判断に表れ始める
+///
+pub const TIRED_HP_THRESHOLD: f32 = 40.0;
+
+///
+/// 受付の仕事を1件こなすごとに減る意欲
+///
+pub const DESK_TASK_HP_COST: f32 = 1.5;
+
+///
+/// 本を1冊配架するごとに減る意欲
+///
+pub const SHELVING_HP_COST: f32 = 0.3;
+
+///
+/// 意欲が0のときの、歩く速さと文字送りの速さの割合
+///
+const MIN_MOVE_SPEED_RATE: f32 = 0.6;
+const MIN_TEXT_SPEED_RATE: f32 = 0.5;
+
+///
+/// 意欲が0のときに、正しく評価した本の状態を見誤る確率
+///
+const MAX_FATIGUE_MISTAKE_RATE: f32 = 0.3;
+
+///
+/// 疲れの度合い。意欲がTIRED_HP_THRESHOLD以上なら0.0、0なら1.0
+///
+pub fn tiredness(hp: f32) -> f32 {
+    if hp >= TIRED_HP_THRESHOLD {
+        0.0
+    } else {
+        ((TIRED_HP_THRESHOLD - hp) / TIRED_HP_THRESHOLD).min(1.0)
+    }
+}
+
+pub fn move_speed_rate(hp: f32) -> f32 {
+    1.0 - (1.0 - MIN_MOVE_SPEED_RATE) * tiredness(hp)
+}
+
+pub fn text_speed_rate(hp: f32) -> f32 {
+    1.0 - (1.0 - MIN_TEXT_SPEED_RATE) * tiredness(hp)
+}
+
+pub fn mistake_rate(hp: f32) -> f32 {
+    MAX_FATIGUE_MISTAKE_RATE * tiredness(hp)
+}
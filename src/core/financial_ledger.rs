@@ -0,0 +1,380 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::GensoDate;
+
+///
+/// # お金の出入りの種類
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransactionCategory {
+    RentalFee,
+    LateFee,
+    CopyPayment,
+    AdAgencyGain,
+    PurchaseRefund,
+    GoingOutEvent,
+    AdCost,
+    GoingOutCost,
+    BookPurchase,
+    RepairCost,
+}
+
+impl TransactionCategory {
+    ///
+    /// 収支報告に並べる順番
+    ///
+    pub const ALL: [TransactionCategory; 10] = [
+        TransactionCategory::RentalFee,
+        TransactionCategory::LateFee,
+        TransactionCategory::CopyPayment,
+        TransactionCategory::AdAgencyGain,
+        TransactionCategory::PurchaseRefund,
+        TransactionCategory::GoingOutEvent,
+        TransactionCategory::AdCost,
+        TransactionCategory::GoingOutCost,
+        TransactionCategory::BookPurchase,
+        TransactionCategory::RepairCost,
+    ];
+
+    pub fn to_str(&self) -> &str {
+        match self {
+            TransactionCategory::RentalFee => "貸出料",
+            TransactionCategory::LateFee => "延滞料",
+            TransactionCategory::CopyPayment => "写本代",
+            TransactionCategory::AdAgencyGain => "広告収入",
+            TransactionCategory::PurchaseRefund => "発注取消",
+            TransactionCategory::GoingOutEvent => "外出先",
+            TransactionCategory::AdCost => "広告費",
+            TransactionCategory::GoingOutCost => "外出費",
+            TransactionCategory::BookPurchase => "仕入れ",
+            TransactionCategory::RepairCost => "修繕費",
+        }
+    }
+
+    ///
+    /// CSVに書き出すときの名前
+    ///
+    pub fn to_key(&self) -> &str {
+        match self {
+            TransactionCategory::RentalFee => "rental-fee",
+            TransactionCategory::LateFee => "late-fee",
+            TransactionCategory::CopyPayment => "copy-payment",
+            TransactionCategory::AdAgencyGain => "ad-agency-gain",
+            TransactionCategory::PurchaseRefund => "purchase-refund",
+            TransactionCategory::GoingOutEvent => "going-out-event",
+            TransactionCategory::AdCost => "ad-cost",
+            TransactionCategory::GoingOutCost => "going-out-cost",
+            TransactionCategory::BookPurchase => "book-purchase",
+            TransactionCategory::RepairCost => "repair-cost",
+        }
+    }
+}
+
+///
+/// # お金の出入り1件
+///
+/// ## amount
+/// 収入なら正、支出なら負
+///
+/// ## reference
+/// 相手の名前や本の題名など、何のお金か分かるもの
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transaction {
+    pub date: GensoDate,
+    pub category: TransactionCategory,
+    pub amount: i32,
+    pub reference: String,
+}
+
+///
+/// # 期間中の収支報告
+///
+/// ## totals
+/// 種類ごとの合計。出入りがなかった種類は含まない
+///
+pub struct IncomeStatement {
+    pub first_day: GensoDate,
+    pub last_day: GensoDate,
+    totals: Vec<(TransactionCategory, i32)>,
+}
+
+impl IncomeStatement {
+    pub fn iter(&self) -> std::slice::Iter<(TransactionCategory, i32)> {
+        self.totals.iter()
+    }
+
+    pub fn income(&self) -> i32 {
+        self.totals
+            .iter()
+            .map(|(_, amount)| *amount)
+            .filter(|amount| *amount > 0)
+            .sum()
+    }
+
+    pub fn expense(&self) -> i32 {
+        -self
+            .totals
+            .iter()
+            .map(|(_, amount)| *amount)
+            .filter(|amount| *amount < 0)
+            .sum::<i32>()
+    }
+
+    pub fn net(&self) -> i32 {
+        self.totals.iter().map(|(_, amount)| *amount).sum()
+    }
+}
+
+///
+/// # 鈴奈庵の帳簿
+///
+/// 所持金の増減は、すべてここに記録される
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FinancialLedger {
+    transactions: Vec<Transaction>,
+}
+
+impl FinancialLedger {
+    pub fn new() -> Self {
+        FinancialLedger::default()
+    }
+
+    pub fn record(
+        &mut self,
+        date: GensoDate,
+        category: TransactionCategory,
+        amount: i32,
+        reference: &str,
+    ) {
+        if amount == 0 {
+            return;
+        }
+
+        self.transactions.push(Transaction {
+            date: date,
+            category: category,
+            amount: amount,
+            reference: reference.to_string(),
+        });
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<Transaction> {
+        self.transactions.iter()
+    }
+
+    ///
+    /// first_dayからlast_dayまで（両端を含む）の収支報告
+    ///
+    pub fn statement_between(&self, first_day: GensoDate, last_day: GensoDate) -> IncomeStatement {
+        let in_range: Vec<&Transaction> = self
+            .transactions
+            .iter()
+            .filter(|t| !first_day.is_past(&t.date) && !t.date.is_past(&last_day))
+            .collect();
+
+        let totals = TransactionCategory::ALL
+            .iter()
+            .filter_map(|category| {
+                let matched: Vec<i32> = in_range
+                    .iter()
+                    .filter(|t| t.category == *category)
+                    .map(|t| t.amount)
+                    .collect();
+
+                if matched.is_empty() {
+                    None
+                } else {
+                    Some((*category, matched.iter().sum()))
+                }
+            })
+            .collect();
+
+        IncomeStatement {
+            first_day: first_day,
+            last_day: last_day,
+            totals: totals,
+        }
+    }
+
+    pub fn daily_statement(&self, date: GensoDate) -> IncomeStatement {
+        self.statement_between(date, date)
+    }
+
+    ///
+    /// first_dayから始まる1週間の収支報告
+    ///
+    pub fn weekly_statement(&self, first_day: GensoDate) -> IncomeStatement {
+        self.statement_between(first_day, first_day.add_day_chain(6))
+    }
+
+    ///
+    /// first_dayから始まるdays日間の、日ごとの収支
+    ///
+    pub fn daily_net_history(&self, first_day: GensoDate, days: usize) -> Vec<(GensoDate, i32)> {
+        (0..days)
+            .map(|diff| {
+                let date = first_day.add_day_chain(diff as i32);
+                (date, self.daily_statement(date).net())
+            })
+            .collect()
+    }
+
+    ///
+    /// 帳簿をCSVとして書き出す
+    ///
+    pub fn export_csv<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut file = File::create(path)?;
+
+        writeln!(file, "season,month,day,category,amount,reference")?;
+        for t in self.transactions.iter() {
+            writeln!(
+                file,
+                "{},{},{},{},{},\"{}\"",
+                t.date.season,
+                t.date.month,
+                t.date.day,
+                t.category.to_key(),
+                t.amount,
+                t.reference.replace('"', "\"\"")
+            )?;
+        }
+        file.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_of(statement: &IncomeStatement, category: TransactionCategory) -> Option<i32> {
+        statement
+            .iter()
+            .find(|(c, _)| *c == category)
+            .map(|(_, amount)| *amount)
+    }
+
+    fn sample_ledger(first_day: GensoDate) -> FinancialLedger {
+        let mut ledger = FinancialLedger::new();
+        ledger.record(first_day, TransactionCategory::RentalFee, 300, "霊夢");
+        ledger.record(first_day, TransactionCategory::RentalFee, 200, "魔理沙");
+        ledger.record(first_day, TransactionCategory::AdCost, -150, "チラシ");
+        ledger.record(
+            first_day.add_day_chain(1),
+            TransactionCategory::LateFee,
+            80,
+            "魔理沙",
+        );
+        ledger.record(
+            first_day.add_day_chain(7),
+            TransactionCategory::BookPurchase,
+            -500,
+            "古事記",
+        );
+        ledger
+    }
+
+    #[test]
+    fn zero_amount_is_not_recorded() {
+        let mut ledger = FinancialLedger::new();
+        ledger.record(
+            GensoDate::new(1, 4, 1),
+            TransactionCategory::RentalFee,
+            0,
+            "",
+        );
+
+        assert_eq!(ledger.iter().count(), 0);
+    }
+
+    #[test]
+    fn daily_statement_totals_each_category() {
+        let first_day = GensoDate::new(1, 4, 1);
+        let statement = sample_ledger(first_day).daily_statement(first_day);
+
+        assert_eq!(
+            total_of(&statement, TransactionCategory::RentalFee),
+            Some(500)
+        );
+        assert_eq!(
+            total_of(&statement, TransactionCategory::AdCost),
+            Some(-150)
+        );
+        assert_eq!(total_of(&statement, TransactionCategory::LateFee), None);
+        assert_eq!(statement.income(), 500);
+        assert_eq!(statement.expense(), 150);
+        assert_eq!(statement.net(), 350);
+    }
+
+    #[test]
+    fn statement_follows_category_order() {
+        let first_day = GensoDate::new(1, 4, 1);
+        let statement = sample_ledger(first_day).weekly_statement(first_day);
+
+        let categories: Vec<TransactionCategory> = statement.iter().map(|(c, _)| *c).collect();
+        assert_eq!(
+            categories,
+            vec![
+                TransactionCategory::RentalFee,
+                TransactionCategory::LateFee,
+                TransactionCategory::AdCost,
+            ]
+        );
+    }
+
+    #[test]
+    fn weekly_statement_covers_seven_days() {
+        let first_day = GensoDate::new(1, 4, 1);
+        let ledger = sample_ledger(first_day);
+
+        let statement = ledger.weekly_statement(first_day);
+        assert_eq!(statement.income(), 580);
+        assert_eq!(statement.expense(), 150);
+        assert_eq!(
+            total_of(&statement, TransactionCategory::BookPurchase),
+            None
+        );
+
+        let next_week = ledger.weekly_statement(first_day.add_day_chain(7));
+        assert_eq!(next_week.net(), -500);
+    }
+
+    #[test]
+    fn daily_net_history_includes_empty_days() {
+        let first_day = GensoDate::new(1, 4, 1);
+        let history = sample_ledger(first_day).daily_net_history(first_day, 3);
+
+        let nets: Vec<i32> = history.iter().map(|(_, net)| *net).collect();
+        assert_eq!(nets, vec![350, 80, 0]);
+        assert_eq!(history[2].0, first_day.add_day_chain(2));
+    }
+
+    #[test]
+    fn export_csv_escapes_reference() {
+        let mut ledger = FinancialLedger::new();
+        ledger.record(
+            GensoDate::new(1, 4, 1),
+            TransactionCategory::RentalFee,
+            300,
+            "\"東方\",求聞史紀",
+        );
+
+        let path = std::env::temp_dir().join("suzu_financial_ledger_test.csv");
+        ledger.export_csv(&path).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "season,month,day,category,amount,reference");
+        assert_eq!(lines[1], "1,4,1,rental-fee,300,\"\"\"東方\"\",求聞史紀\"");
+        assert_eq!(lines.len(), 2);
+    }
+}
@@ -1,12 +1,33 @@
 use super::*;
 
-#[derive(Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum GoingOutEvent {
     AkyuTei,
     Dangoya,
     Terakoya,
 }
 
+impl GoingOutEvent {
+    pub fn to_string_jp(&self) -> &str {
+        match self {
+            GoingOutEvent::AkyuTei => "稗田邸",
+            GoingOutEvent::Dangoya => "団子屋",
+            GoingOutEvent::Terakoya => "寺子屋",
+        }
+    }
+
+    ///
+    /// 外出先ごとの意欲の変化
+    ///
+    pub fn hp_diff(&self) -> f32 {
+        match self {
+            GoingOutEvent::AkyuTei => 30.0,
+            GoingOutEvent::Dangoya => 40.0,
+            GoingOutEvent::Terakoya => 15.0,
+        }
+    }
+}
+
 pub const GOING_OUT_MONEY_COST: i64 = 400;
 pub const TAKING_REST_REPUTATION_COST: i64 = 2;
 
@@ -15,24 +36,117 @@ pub enum DayWorkType {
     ShopWork,
     GoingOut(GoingOutEvent),
     TakingRest,
+    Copying,
 }
 
 impl DayWorkType {
     pub fn to_string_jp(&self) -> String {
         match self {
             DayWorkType::ShopWork => "店番",
-            DayWorkType::GoingOut(dest) => match dest {
-                GoingOutEvent::AkyuTei => "外出",
-                GoingOutEvent::Dangoya => "外出",
-                GoingOutEvent::Terakoya => "外出",
-            },
+            DayWorkType::GoingOut(dest) => dest.to_string_jp(),
             DayWorkType::TakingRest => "休憩",
+            DayWorkType::Copying => "写本",
         }
         .to_string()
     }
+
+    ///
+    /// 1日の予定を始めるときの意欲の変化。負なら消費、正なら回復
+    ///
+    pub fn hp_diff(&self) -> f32 {
+        match self {
+            DayWorkType::ShopWork => -20.0,
+            DayWorkType::GoingOut(dest) => dest.hp_diff(),
+            DayWorkType::TakingRest => 20.0,
+            DayWorkType::Copying => -15.0,
+        }
+    }
+
+    ///
+    /// 意欲がhpのときに、この予定をこなせるか。消費した後の意欲が0を下回る予定はこなせない
+    ///
+    pub fn can_start_with(&self, hp: f32) -> bool {
+        hp + self.hp_diff() >= fatigue::MIN_KOSUZU_HP
+    }
+}
+
+///
+/// # 外出先での出来事の進み具合
+///
+/// ## done_events
+/// 済ませた出来事のid。済ませた順に並ぶ
+///
+/// ## unlocked_books
+/// 出来事によって、仕入れられるようになった本の題名
+///
+/// ## unlocked_customers
+/// 出来事によって知り合い、店に来るようになった客のキャラクター定義のid
+///
+/// ## unlocked_agencies
+/// 出来事によって、広告を頼めるようになった広告先
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventProgressTable {
+    done_events: Vec<String>,
+    unlocked_books: HashSet<String>,
+    unlocked_customers: Vec<String>,
+    unlocked_agencies: HashSet<SuzunaAdAgencyType>,
 }
 
-pub struct EventProgressTable {}
+impl EventProgressTable {
+    pub fn new() -> Self {
+        EventProgressTable::default()
+    }
+
+    pub fn is_done(&self, event_id: &str) -> bool {
+        self.done_events.iter().any(|id| id == event_id)
+    }
+
+    pub fn mark_done(&mut self, event_id: &str) {
+        if !self.is_done(event_id) {
+            self.done_events.push(event_id.to_string());
+        }
+    }
+
+    pub fn done_count(&self) -> usize {
+        self.done_events.len()
+    }
+
+    pub fn unlock_book(&mut self, title: &str) {
+        self.unlocked_books.insert(title.to_string());
+    }
+
+    pub fn book_is_unlocked(&self, title: &str) -> bool {
+        self.unlocked_books.contains(title)
+    }
+
+    pub fn unlock_customer(&mut self, definition_id: &str) {
+        if !self.customer_is_unlocked(definition_id) {
+            self.unlocked_customers.push(definition_id.to_string());
+        }
+    }
+
+    pub fn customer_is_unlocked(&self, definition_id: &str) -> bool {
+        self.unlocked_customers.iter().any(|id| id == definition_id)
+    }
+
+    pub fn random_unlocked_customer(&self) -> Option<&str> {
+        if self.unlocked_customers.is_empty() {
+            None
+        } else {
+            let index = rand::random::<usize>() % self.unlocked_customers.len();
+            Some(&self.unlocked_customers[index])
+        }
+    }
+
+    pub fn unlock_agency(&mut self, agency: SuzunaAdAgencyType) {
+        self.unlocked_agencies.insert(agency);
+    }
+
+    pub fn agency_is_unlocked(&self, agency: &SuzunaAdAgencyType) -> bool {
+        self.unlocked_agencies.contains(agency)
+    }
+}
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct WeekWorkSchedule {
@@ -0,0 +1,146 @@
+use serde::Deserialize;
+
+use crate::core::game_system::{EventProgressTable, GoingOutEvent};
+use crate::core::util;
+use crate::object::scenario_object::SuzunaAdAgencyType;
+
+///
+/// 来店する客が、外出先で知り合った客である確率
+///
+pub const ACQUAINTANCE_VISIT_RATE: f32 = 0.15;
+
+///
+/// # 外出先での出来事の結果
+///
+/// ## reputation, hp, money
+/// 評判、意欲、所持金の変化
+///
+/// ## books
+/// 仕入れられるようになる本の題名
+///
+/// ## customers
+/// 知り合い、店に来るようになる客のキャラクター定義のid
+///
+/// ## ad_agency
+/// 広告を頼めるようになる広告先
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GoingOutOutcome {
+    #[serde(default)]
+    pub reputation: f32,
+    #[serde(default)]
+    pub hp: f32,
+    #[serde(default)]
+    pub money: i32,
+    #[serde(default)]
+    pub books: Vec<String>,
+    #[serde(default)]
+    pub customers: Vec<String>,
+    #[serde(default)]
+    pub ad_agency: Option<SuzunaAdAgencyType>,
+}
+
+///
+/// # 外出先での出来事1つの定義
+///
+/// ## scenario
+/// 出来事のシナリオファイルのパス。最後にFinishGoingOutを呼ぶ
+///
+/// ## requires
+/// この出来事の前に、済ませておく必要がある出来事のid
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct GoingOutEventDefinition {
+    pub id: String,
+    pub destination: GoingOutEvent,
+    pub scenario: String,
+    #[serde(default)]
+    pub requires: Vec<String>,
+    #[serde(default)]
+    pub outcome: GoingOutOutcome,
+}
+
+impl GoingOutEventDefinition {
+    pub fn is_available(&self, progress: &EventProgressTable) -> bool {
+        !progress.is_done(&self.id) && self.requires.iter().all(|id| progress.is_done(id))
+    }
+}
+
+#[derive(Deserialize)]
+struct RawGoingOutEventFile {
+    event: Vec<GoingOutEventDefinition>,
+}
+
+///
+/// # 外出先での出来事の一覧
+///
+/// 外出先ごとに、ファイルに書かれた順で出来事が進む
+///
+pub struct GoingOutEventTable {
+    events: Vec<GoingOutEventDefinition>,
+}
+
+impl GoingOutEventTable {
+    pub fn new_empty() -> Self {
+        GoingOutEventTable { events: Vec::new() }
+    }
+
+    pub fn from_toml_file(ctx: &mut ggez::Context, path: &str) -> Self {
+        let s = util::read_from_resources_as_string(ctx, path);
+
+        let raw_data: Result<RawGoingOutEventFile, toml::de::Error> = toml::from_str(&s);
+        let raw_data = match raw_data {
+            Ok(p) => p,
+            Err(e) => panic!("Failed to parse going out events {}: {}", path, e),
+        };
+
+        GoingOutEventTable {
+            events: raw_data.event,
+        }
+    }
+
+    pub fn get(&self, event_id: &str) -> Option<&GoingOutEventDefinition> {
+        self.events.iter().find(|event| event.id == event_id)
+    }
+
+    ///
+    /// destinationで次に起きる出来事。起きる出来事がなければNone
+    ///
+    pub fn next_event(
+        &self,
+        destination: GoingOutEvent,
+        progress: &EventProgressTable,
+    ) -> Option<&GoingOutEventDefinition> {
+        self.events
+            .iter()
+            .filter(|event| event.destination == destination)
+            .find(|event| event.is_available(progress))
+    }
+
+    ///
+    /// 出来事で仕入れられるようになる本か。そういう本は、出来事を済ませるまで仕入れられない
+    ///
+    pub fn locks_book(&self, title: &str) -> bool {
+        self.events
+            .iter()
+            .any(|event| event.outcome.books.iter().any(|book| book == title))
+    }
+
+    ///
+    /// 出来事で知り合う客のキャラクター定義か。そういう客は、出来事を済ませるまで店に来ない
+    ///
+    pub fn locks_customer(&self, definition_id: &str) -> bool {
+        self.events
+            .iter()
+            .any(|event| event.outcome.customers.iter().any(|id| id == definition_id))
+    }
+
+    ///
+    /// 出来事で頼めるようになる広告先か。そういう広告先は、出来事を済ませるまで頼めない
+    ///
+    pub fn locks_agency(&self, agency: &SuzunaAdAgencyType) -> bool {
+        self.events
+            .iter()
+            .any(|event| event.outcome.ad_agency.as_ref() == Some(agency))
+    }
+}
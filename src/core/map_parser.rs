@@ -1,9 +1,7 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
-use pathfinding::prelude::dijkstra;
-
 use ggez::graphics as ggraphics;
 use tiled;
 use crate::libsuzu::numeric;
@@ -16,9 +14,63 @@ use crate::{mintp, mintv};
 
 use collision::prelude::*;
 
+use crate::core::path_search::*;
+use crate::core::tiled_loader::*;
 use crate::object::collision::*;
 use crate::object::map_object::MapObject;
 
+///
+/// タイルアニメーションの時間を、Clockに換算するための1秒あたりのClock
+///
+const CLOCK_PER_SECOND: u32 = 60;
+
+///
+/// # Tiledで設定されたタイルアニメーション
+///
+/// ## frames
+/// 表示するタイルセット内のIDと、表示し続けるClockの組
+///
+struct TileAnimation {
+    frames: Vec<(u32, Clock)>,
+    total: Clock,
+}
+
+impl TileAnimation {
+    fn new(frames: &[tiled::Frame]) -> Self {
+        let frames: Vec<(u32, Clock)> = frames
+            .iter()
+            .map(|frame| {
+                (
+                    frame.tile_id,
+                    ((frame.duration * CLOCK_PER_SECOND) / 1000).max(1) as Clock,
+                )
+            })
+            .collect();
+        let total = frames.iter().map(|(_, duration)| duration).sum();
+
+        TileAnimation {
+            frames: frames,
+            total: total,
+        }
+    }
+
+    ///
+    /// 時刻tに表示する、タイルセット内のID
+    ///
+    fn frame_at(&self, t: Clock) -> u32 {
+        let mut rest = t % self.total;
+
+        for (id, duration) in self.frames.iter() {
+            if rest < *duration {
+                return *id;
+            }
+            rest -= duration;
+        }
+
+        self.frames.last().unwrap().0
+    }
+}
+
 ///
 /// # TileSetの情報を保持している構造体
 /// ## フィールド
@@ -34,21 +86,56 @@ use crate::object::map_object::MapObject;
 /// ### first_gid
 /// このtilesetのfirst_gidを格納している
 ///
+/// ### move_costs
+/// タイルのプロパティmove_costで指定された、経路探索で使う移動コストの倍率
+///
+/// ### tile_properties
+/// タイルごとのカスタムプロパティ。キーはタイルセット内のID
+///
+/// ### animations
+/// アニメーションするタイル。キーはタイルセット内のID
+///
 pub struct TileSet {
     tile_size: numeric::Vector2u,
     tile_size_ratio: numeric::Vector2f,
     tile_count: numeric::Vector2u,
     first_gid: u32,
     collision_info: HashMap<u32, Vec<CollisionType>>,
+    move_costs: HashMap<u32, u32>,
+    tile_properties: HashMap<u32, TiledProperties>,
+    animations: HashMap<u32, TileAnimation>,
 }
 
 impl TileSet {
-    fn new(ctx: &mut ggez::Context, tileset: &tiled::Tileset) -> (TileSet, ggraphics::Image) {
+    fn new(
+        ctx: &mut ggez::Context,
+        tileset: &tiled::Tileset,
+        image_path: &str,
+    ) -> (TileSet, ggraphics::Image) {
         // tilesetが使用する画像を読み込む
         let tiled_image = tileset.images.get(0).unwrap();
         let mut collision_info: HashMap<u32, Vec<CollisionType>> = HashMap::new();
+        let mut move_costs: HashMap<u32, u32> = HashMap::new();
+        let mut tile_properties: HashMap<u32, TiledProperties> = HashMap::new();
+        let mut animations: HashMap<u32, TileAnimation> = HashMap::new();
 
         for tile in &tileset.tiles {
+            if let Some(frames) = &tile.animation {
+                if !frames.is_empty() {
+                    animations.insert(tile.id, TileAnimation::new(frames));
+                }
+            }
+
+            let properties = TiledProperties::new(tile.properties.clone());
+
+            if let Some(cost) = properties.get_int("move_cost") {
+                move_costs.insert(tile.id, cost.max(1) as u32);
+            }
+
+            if properties.iter().next().is_some() {
+                tile_properties.insert(tile.id, properties);
+            }
+
             if let Some(group) = &tile.objectgroup {
                 for object in &group.objects {
                     let c = match &object.shape {
@@ -83,7 +170,10 @@ impl TileSet {
         );
 
         // 使用する画像のパスから、ggezの画像を生成
-        let image = ggraphics::Image::new(ctx, format!("/{}", tiled_image.source)).unwrap();
+        let image = match ggraphics::Image::new(ctx, image_path) {
+            Ok(image) => image,
+            Err(e) => panic!("Failed to load tileset image {} -> {:?}", image_path, e),
+        };
 
         // 新しいTileSetと読み込んだ画像データを返す
         (
@@ -96,6 +186,9 @@ impl TileSet {
                 tile_count: tile_count,
                 first_gid: tileset.first_gid,
                 collision_info: collision_info,
+                move_costs: move_costs,
+                tile_properties: tile_properties,
+                animations: animations,
             },
             image,
         )
@@ -200,6 +293,31 @@ impl TileSet {
         self.contains_gid(gid) && self.collision_info.contains_key(&gid)
     }
 
+    ///
+    /// gidが指すタイルの移動コストの倍率。指定が無ければNone
+    ///
+    fn move_cost(&self, gid: u32) -> Option<u32> {
+        self.move_costs.get(&(gid - self.first_gid)).copied()
+    }
+
+    fn is_animated_tile(&self, gid: u32) -> bool {
+        self.animations.contains_key(&(gid - self.first_gid))
+    }
+
+    ///
+    /// 時刻tにgidの位置に表示するタイルのgid。アニメーションしないタイルはそのまま
+    ///
+    fn animated_gid(&self, gid: u32, t: Clock) -> u32 {
+        match self.animations.get(&(gid - self.first_gid)) {
+            Some(animation) => self.first_gid + animation.frame_at(t),
+            None => gid,
+        }
+    }
+
+    fn tile_properties(&self, gid: u32) -> Option<&TiledProperties> {
+        self.tile_properties.get(&(gid - self.first_gid))
+    }
+
     /// gidから、Tilesetのクロップ範囲を計算して返す
     fn gid_to_crop(&self, gid: u32) -> numeric::Rect {
         let location = self.gid_to_location_index(gid);
@@ -225,123 +343,67 @@ impl TileSet {
     }
 }
 
-#[derive(Clone, Copy)]
-pub struct CollisionMapNode {
-    pub done: bool,
-    pub cost: i32,
-}
-
-pub struct CollisionMap {
-    map: Vec<Vec<CollisionMapNode>>,
-    size: numeric::Vector2u,
-    collision_objects: Vec<numeric::Vector2u>,
-}
-
-impl CollisionMap {
-    pub fn new(size: numeric::Vector2u, collision_objects: Vec<numeric::Vector2u>) -> Self {
-        CollisionMap {
-            map: vec![
-                vec![
-                    CollisionMapNode {
-                        done: false,
-                        cost: -1
-                    };
-                    size.y as usize
-                ];
-                size.x as usize
-            ],
-            size: size,
-            collision_objects: collision_objects,
-        }
-    }
-
-    pub fn not_contains(&self, position: numeric::Point2i) -> bool {
-        position.x < 0
-            && position.x >= self.size.x as i32
-            && position.y < 0
-            && position.y >= self.size.y as i32
-    }
-
-    fn is_movable_position(&self, x: i32, y: i32) -> bool {
-        !self.not_contains(numeric::Point2i::new(x, y))
-            && !self
-                .collision_objects
-                .contains(&numeric::Vector2u::new(x as u32, y as u32))
-    }
-
-    pub fn get(&self, position: numeric::Point2i) -> Option<&CollisionMapNode> {
-        if let Some(row) = self.map.get(position.x as usize).as_ref() {
-            row.get(position.y as usize).as_ref();
-        }
-
-        None
-    }
-
-    pub fn find_path(
-        &self,
-        start: numeric::Point2i,
-        goal: numeric::Point2i,
-    ) -> Option<Vec<numeric::Point2i>> {
-        let result = dijkstra(
-            &start,
-            |point| self.successors(point),
-            |point| point.eq(&goal),
-        );
-
-        if let Some((path, _)) = result {
-            Some(path)
-        } else {
-            None
-        }
-    }
-
-    fn successors(&self, point: &numeric::Point2i) -> Vec<(numeric::Point2i, usize)> {
-        let mut successors_list = Vec::new();
-
-        let cand_point = numeric::Point2i::new(point.x as i32, point.y as i32 - 1);
-        if self.is_movable_position(cand_point.x, cand_point.y) {
-            successors_list.push(cand_point);
-        }
-
-        let cand_point = numeric::Point2i::new(point.x as i32, point.y as i32 + 1);
-        if self.is_movable_position(cand_point.x, cand_point.y) {
-            successors_list.push(cand_point);
-        }
-
-        let cand_point = numeric::Point2i::new(point.x as i32 - 1, point.y as i32);
-        if self.is_movable_position(cand_point.x, cand_point.y) {
-            successors_list.push(cand_point);
-        }
-
-        let cand_point = numeric::Point2i::new(point.x as i32 + 1, point.y as i32);
-        if self.is_movable_position(cand_point.x, cand_point.y) {
-            successors_list.push(cand_point);
-        }
-
-        successors_list.into_iter().map(|p| (p, 1)).collect()
-    }
+///
+/// # タイルレイヤー1枚分の描画の状態
+///
+/// ## batchs
+/// タイルセットのfirst_gidと、そのタイルセットを描画するSpriteBatchの組
+///
+/// ## parallax
+/// カメラの移動量に掛ける倍率。1.0より小さいと遠くにあるように見える
+///
+/// ## animated_gids
+/// レイヤー内に置かれている、アニメーションするタイルのgid
+///
+/// ## dirty
+/// SpriteBatchを作り直す必要があるか
+///
+struct LayerRenderState {
+    batchs: Vec<(u32, ggraphics::spritebatch::SpriteBatch)>,
+    parallax: numeric::Vector2f,
+    animated_gids: HashSet<u32>,
+    dirty: bool,
 }
 
 ///
 /// # マップエディタで作ったマップを表示するための構造体
 /// ## フィールド
-/// ### tile_map
-/// 読み込んだときの生データ
+/// ### layers
+/// タイルレイヤー。無限マップのチャンクは1枚にまとめられている
+///
+/// ### objects
+/// オブジェクトレイヤーに置かれたオブジェクト
+///
+/// ### origin
+/// タイル座標(0, 0)に対応する、Tiled上のタイル座標
 ///
 /// ### tilesets
 /// 使用するタイルセットの情報
 ///
-/// ### tilesets_batchs
-/// タイルセットを描画するためのSpriteBatchたち
+/// ### layer_states
+/// レイヤーごとの、タイルセットを描画するためのSpriteBatchたち
+///
+/// ### animation_frames
+/// アニメーションするタイルのgidと、現在表示しているタイルのgid
 ///
 /// ### drwob_essential
 /// 描画を行うときの情報
 ///
+/// ### dynamic_obstacles
+/// 経路探索で一時的に避けるタイル。他のキャラクターの位置など
+///
 pub struct StageObjectMap {
-    tile_map: tiled::Map,
+    layers: Vec<TileLayer>,
+    objects: Vec<MapObjectData>,
+    properties: TiledProperties,
+    tile_size: numeric::Vector2u,
+    map_size: numeric::Vector2u,
+    origin: numeric::Point2i,
     tilesets: Vec<TileSet>,
-    tilesets_batchs: HashMap<u32, ggraphics::spritebatch::SpriteBatch>,
+    layer_states: Vec<LayerRenderState>,
+    animation_frames: HashMap<u32, u32>,
     collision_map: Option<CollisionMap>,
+    dynamic_obstacles: HashSet<numeric::Point2i>,
     camera: Rc<RefCell<numeric::Rect>>,
     scale: numeric::Vector2f,
     redraw_request: bool,
@@ -357,35 +419,79 @@ impl StageObjectMap {
         canvas_rect: numeric::Rect,
         scale: numeric::Vector2f,
     ) -> StageObjectMap {
-        // マップ情報を読み込む
-        println!("FIXME: map_parse.rs StageObjectMap::new");
-        //let file = ggez::filesystem::open(ctx, path).unwrap();
-        //let tile_map = tiled::parse(file).unwrap();
-        let tile_map = tiled::parse_file(std::path::Path::new(path)).unwrap();
-
-        // タイルセットを読み込み、それと同時にタイルセットの画像からSpriteBatchを生成する
-        let mut batchs = HashMap::new();
+        // マップ情報をリソースから読み込む
+        let tile_map = TiledMapData::load(ctx, path);
+
+        // タイルセットを読み込む
+        let mut images = Vec::new();
         let tilesets: Vec<TileSet> = tile_map
             .tilesets
             .iter()
             .map(|ts| {
-                let (ts, image) = TileSet::new(ctx, &ts);
-                let mut batch = ggraphics::spritebatch::SpriteBatch::new(image);
-                batch.set_filter(ggraphics::FilterMode::Nearest);
-
-                batchs.insert(ts.first_gid, batch);
+                let image_path = match ts.images.get(0) {
+                    Some(image) => tile_map.tileset_image_path(ts, &image.source),
+                    None => panic!("Tileset {} has no image", ts.name),
+                };
+                let (ts, image) = TileSet::new(ctx, &ts, &image_path);
+                images.push((ts.first_gid, image));
                 ts
             })
             .collect();
 
+        // レイヤーごとに、タイルセットの画像からSpriteBatchを生成する
+        let layer_states: Vec<LayerRenderState> = tile_map
+            .layers
+            .iter()
+            .map(|layer| {
+                let batchs = images
+                    .iter()
+                    .map(|(first_gid, image)| {
+                        let mut batch = ggraphics::spritebatch::SpriteBatch::new(image.clone());
+                        batch.set_filter(ggraphics::FilterMode::Nearest);
+                        (*first_gid, batch)
+                    })
+                    .collect();
+
+                let animated_gids = layer
+                    .tiles
+                    .iter()
+                    .flatten()
+                    .map(|tile| tile.gid)
+                    .filter(|gid| {
+                        *gid != 0
+                            && tilesets
+                                .iter()
+                                .any(|ts| ts.contains_gid(*gid) && ts.is_animated_tile(*gid))
+                    })
+                    .collect();
+
+                LayerRenderState {
+                    batchs: batchs,
+                    parallax: numeric::Vector2f::new(
+                        layer.properties.get_float("parallax_x").unwrap_or(1.0),
+                        layer.properties.get_float("parallax_y").unwrap_or(1.0),
+                    ),
+                    animated_gids: animated_gids,
+                    dirty: true,
+                }
+            })
+            .collect();
+
         let mut canvas = SubScreen::new(ctx, canvas_rect, 0, ggraphics::Color::from_rgba_u32(0));
         canvas.set_filter(ggraphics::FilterMode::Nearest);
 
         StageObjectMap {
-            tile_map: tile_map,
+            layers: tile_map.layers,
+            objects: tile_map.objects,
+            properties: tile_map.properties,
+            tile_size: tile_map.tile_size,
+            map_size: tile_map.size,
+            origin: tile_map.origin,
             tilesets: tilesets,
-            tilesets_batchs: batchs,
+            layer_states: layer_states,
+            animation_frames: HashMap::new(),
             collision_map: None,
+            dynamic_obstacles: HashSet::new(),
             camera: camera,
             scale: scale,
             canvas: canvas,
@@ -396,13 +502,38 @@ impl StageObjectMap {
 
     /// 引数で受け取ったタイルの情報から、そのタイルがカメラに写るか調べるメソッド
     fn tile_is_inside_of_camera(&self, dest: numeric::Point2f, size: numeric::Vector2u) -> bool {
+        self.tile_is_inside_of_rect(&self.camera.borrow(), dest, size)
+    }
+
+    fn tile_is_inside_of_rect(
+        &self,
+        camera: &numeric::Rect,
+        dest: numeric::Point2f,
+        size: numeric::Vector2u,
+    ) -> bool {
         let rect = numeric::Rect::new(
             dest.x,
             dest.y,
             dest.x + (size.x as f32 * self.scale.x),
             dest.y + (size.y as f32 * self.scale.y),
         );
-        self.camera.borrow().overlaps(&rect)
+        camera.overlaps(&rect)
+    }
+
+    ///
+    /// レイヤーの視差とずれを反映した、レイヤー上でのカメラの位置
+    ///
+    fn layer_camera(&self, index: usize) -> numeric::Rect {
+        let camera = self.camera.borrow();
+        let layer = &self.layers[index];
+        let parallax = self.layer_states[index].parallax;
+
+        numeric::Rect::new(
+            (camera.x * parallax.x) - (layer.offset.x * self.scale.x),
+            (camera.y * parallax.y) - (layer.offset.y * self.scale.y),
+            camera.w,
+            camera.h,
+        )
     }
 
     /// タイルが配置されるであろう座標を計算するメソッド
@@ -426,6 +557,36 @@ impl StageObjectMap {
         self.update_batch_request = true;
     }
 
+    ///
+    /// アニメーションするタイルの表示を時刻tのものに更新する
+    /// 表示が変わったタイルを含むレイヤーだけ、SpriteBatchを作り直す
+    ///
+    fn update_tile_animation(&mut self, t: Clock) {
+        let mut changed = HashSet::new();
+
+        for tileset in self.tilesets.iter() {
+            for local_id in tileset.animations.keys() {
+                let gid = tileset.first_gid + local_id;
+                let frame = tileset.animated_gid(gid, t);
+
+                if self.animation_frames.insert(gid, frame) != Some(frame) {
+                    changed.insert(gid);
+                }
+            }
+        }
+
+        if changed.is_empty() {
+            return;
+        }
+
+        for state in self.layer_states.iter_mut() {
+            if state.animated_gids.iter().any(|gid| changed.contains(gid)) {
+                state.dirty = true;
+                self.redraw_request = true;
+            }
+        }
+    }
+
     /// ある座標が、カメラに写ったときの座標を返すメソッド
     fn camera_relative_position(&self, p: numeric::Point2f) -> numeric::Point2f {
         numeric::Point2f::new(p.x - self.camera.borrow().x, p.y - self.camera.borrow().y)
@@ -437,7 +598,7 @@ impl StageObjectMap {
         chara: &MapObject,
     ) -> CollisionInformation {
         // 全てのレイヤーで衝突検査
-        for layer in self.tile_map.layers.iter() {
+        for layer in self.layers.iter() {
             if !layer.visible {
                 // レイヤーが非表示設定になっていれば、無視
                 continue;
@@ -459,10 +620,7 @@ impl StageObjectMap {
                         continue;
                     }
 
-                    let tiles = match &layer.tiles {
-                        tiled::LayerData::Finite(tiles) => tiles,
-                        _ => panic!(""),
-                    };
+                    let tiles = &layer.tiles;
                     let tile = match tiles.get(y as usize) {
                         Some(row) => match row.get(x as usize) {
                             Some(tile) => tile,
@@ -511,7 +669,7 @@ impl StageObjectMap {
     }
 
     pub fn get_tile_size(&self) -> numeric::Point2u {
-        numeric::Point2u::new(self.tile_map.tile_width, self.tile_map.tile_height)
+        numeric::Point2u::new(self.tile_size.x, self.tile_size.y)
     }
 
     pub fn get_tile_scale(&self) -> numeric::Vector2f {
@@ -520,8 +678,8 @@ impl StageObjectMap {
 
     pub fn get_tile_drawing_size(&self) -> numeric::Vector2f {
         numeric::Vector2f::new(
-            (self.tile_map.tile_width as f32) * self.scale.x,
-            (self.tile_map.tile_height as f32) * self.scale.y,
+            (self.tile_size.x as f32) * self.scale.x,
+            (self.tile_size.y as f32) * self.scale.y,
         )
     }
 
@@ -533,13 +691,6 @@ impl StageObjectMap {
         )
     }
 
-    /// 全てのsprite batch処理をクリアするメソッド
-    fn clear_all_batchs(&mut self) {
-        for (_, batch) in &mut self.tilesets_batchs {
-            batch.clear();
-        }
-    }
-
     /// gidから、どのタイルセットを利用するかを決定するメソッド
     fn get_tileset_by_gid(&self, gid: u32) -> Option<&TileSet> {
         for tileset in &self.tilesets {
@@ -565,28 +716,30 @@ impl StageObjectMap {
 
     /// sprite batch処理を実際に行うメソッド
     fn update_sprite_batch(&mut self) {
-        if !self.update_batch_request {
-            return;
+        if self.update_batch_request {
+            // カメラが動いた場合は、全てのレイヤーを作り直す
+            self.update_batch_request = false;
+            for state in self.layer_states.iter_mut() {
+                state.dirty = true;
+            }
         }
 
-        self.update_batch_request = false;
-
-        // batch処理を全てクリア
-        self.clear_all_batchs();
-
-        // 全てのレイヤーで描画を実行
-        for layer in self.tile_map.layers.iter() {
-            if !layer.visible {
-                // レイヤーが非表示設定になっていれば、描画は行わない
-                continue;
+        for index in 0..self.layers.len() {
+            if self.layer_states[index].dirty {
+                self.rebuild_layer_batch(index);
             }
+        }
+    }
+
+    /// レイヤー1枚分のsprite batchを作り直すメソッド
+    fn rebuild_layer_batch(&mut self, index: usize) {
+        let camera = self.layer_camera(index);
+        let mut params: Vec<(u32, ggraphics::DrawParam)> = Vec::new();
 
-            let tiles = match &layer.tiles {
-                tiled::LayerData::Finite(tiles) => tiles,
-                _ => panic!(""),
-            };
+        let layer = &self.layers[index];
+        if layer.visible {
             // 二次元のマップデータを全てbatch処理に掛ける
-            for (y, row) in tiles.iter().enumerate() {
+            for (y, row) in layer.tiles.iter().enumerate() {
                 for (x, &tile) in row.iter().enumerate() {
                     let gid = tile.gid;
                     // gidが0のときは、何も配置されていない状態を表すので、描画は行わない
@@ -601,40 +754,50 @@ impl StageObjectMap {
                         Self::calc_tile_dest_point(x as u32, y as u32, tile_size, self.scale);
 
                     // カメラに入っていないマップチップは描画しない
-                    if !self.tile_is_inside_of_camera(dest_pos, tile_size) {
+                    if !self.tile_is_inside_of_rect(&camera, dest_pos, tile_size) {
                         continue;
                     }
 
-                    let crop = tileset.gid_to_crop(gid); // クロップする部分をgidから計算
-                    let first_gid = tileset.get_first_gid(); // batch処理を行うタイルセットのfirst_gidを取得
-
-                    let batch = self.tilesets_batchs.get_mut(&first_gid).unwrap(); // sprite batchをfirst_gidから取得
+                    // アニメーションするタイルは、現在のフレームのタイルを描画する
+                    let frame_gid = *self.animation_frames.get(&gid).unwrap_or(&gid);
+                    let crop = tileset.gid_to_crop(frame_gid); // クロップする部分をgidから計算
 
                     let draw_param = ggraphics::DrawParam::default()
                         .src(numeric::Rect::new(crop.x, crop.y, crop.w, crop.h))
                         .scale(mintv!(self.scale))
                         .dest(mintp!(dest_pos));
-                    // batch処理を追加
-                    batch.add(draw_param);
+
+                    // batch処理を行うタイルセットのfirst_gidと共に記録
+                    params.push((tileset.get_first_gid(), draw_param));
                 }
             }
         }
+
+        let state = &mut self.layer_states[index];
+        for (_, batch) in state.batchs.iter_mut() {
+            batch.clear();
+        }
+
+        for (first_gid, param) in params {
+            if let Some((_, batch)) = state.batchs.iter_mut().find(|(id, _)| *id == first_gid) {
+                batch.add(param);
+            }
+        }
+
+        state.dirty = false;
     }
 
     fn search_collision_locations(&self) -> Vec<numeric::Vector2u> {
         let mut collision_locations = Vec::new();
 
-        for layer in self.tile_map.layers.iter() {
+        for layer in self.layers.iter() {
             println!("name -> {}", layer.name);
             if !layer.visible {
                 // レイヤーが非表示設定になっていれば、衝突オブジェクトの検索を行わない
                 continue;
             }
 
-            let tiles = match &layer.tiles {
-                tiled::LayerData::Finite(tiles) => tiles,
-                _ => panic!(""),
-            };
+            let tiles = &layer.tiles;
             // 二次元のマップデータを全てbatch処理に掛ける
             for (y, row) in tiles.iter().enumerate() {
                 for (x, &tile) in row.iter().enumerate() {
@@ -655,13 +818,103 @@ impl StageObjectMap {
         collision_locations
     }
 
+    ///
+    /// 移動コストが指定されたタイルの位置とコストを探す
+    /// 複数のレイヤーで指定されている場合は、大きい方を使う
+    ///
+    fn search_move_costs(&self) -> HashMap<numeric::Vector2u, u32> {
+        let mut move_costs = HashMap::new();
+
+        for layer in self.layers.iter() {
+            if !layer.visible {
+                continue;
+            }
+
+            let tiles = &layer.tiles;
+
+            for (y, row) in tiles.iter().enumerate() {
+                for (x, &tile) in row.iter().enumerate() {
+                    if tile.gid == 0 {
+                        continue;
+                    }
+
+                    let cost = match self
+                        .get_tileset_by_gid(tile.gid)
+                        .and_then(|tileset| tileset.move_cost(tile.gid))
+                    {
+                        Some(cost) => cost,
+                        None => continue,
+                    };
+
+                    let entry = move_costs
+                        .entry(numeric::Vector2u::new(x as u32, y as u32))
+                        .or_insert(cost);
+                    *entry = (*entry).max(cost);
+                }
+            }
+        }
+
+        move_costs
+    }
+
+    ///
+    /// マップのプロパティdiagonal_moveから、斜め移動の扱いを決める
+    /// 指定が無ければ縦横移動のみ
+    ///
+    fn diagonal_mode_property(&self) -> DiagonalMode {
+        if let Some(s) = self.properties.get_str("diagonal_move") {
+            return DiagonalMode::from_str(s).unwrap_or(DiagonalMode::Never);
+        }
+
+        match self.properties.get_bool("diagonal_move") {
+            Some(true) => DiagonalMode::NoCornerCutting,
+            _ => DiagonalMode::Never,
+        }
+    }
+
+    ///
+    /// マップのプロパティcamera_zoomから、カメラの拡大率を決める
+    /// 縮小すると視界が描画先よりも大きくなるため、1.0未満は1.0として扱う
+    ///
+    pub fn camera_zoom_property(&self) -> Option<f32> {
+        self.properties
+            .get_float("camera_zoom")
+            .map(|zoom| zoom.max(1.0))
+    }
+
     pub fn build_collision_map(&mut self) {
         let collision_points = self.search_collision_locations();
 
-        self.collision_map = Some(CollisionMap::new(
-            numeric::Vector2u::new(self.tile_map.width, self.tile_map.height),
-            collision_points.clone(),
-        ));
+        let mut collision_map = CollisionMap::new(
+            self.map_size,
+            collision_points,
+        );
+
+        for (position, cost) in self.search_move_costs() {
+            collision_map.set_cost(position, cost);
+        }
+
+        collision_map.set_diagonal_mode(self.diagonal_mode_property());
+
+        self.collision_map = Some(collision_map);
+    }
+
+    ///
+    /// 経路探索で一時的に避けるタイルを設定する
+    /// 毎フレーム、他のキャラクターのタイル位置を渡して更新する
+    ///
+    pub fn set_dynamic_obstacles<I>(&mut self, obstacles: I)
+    where
+        I: IntoIterator<Item = numeric::Vector2u>,
+    {
+        self.dynamic_obstacles = obstacles
+            .into_iter()
+            .map(|p| numeric::Point2i::new(p.x as i32, p.y as i32))
+            .collect();
+    }
+
+    pub fn clear_dynamic_obstacles(&mut self) {
+        self.dynamic_obstacles.clear();
     }
 
     pub fn map_position_to_tile_position(
@@ -694,10 +947,15 @@ impl StageObjectMap {
         goal: numeric::Vector2u,
     ) -> Option<Vec<numeric::Vector2u>> {
         if let Some(collision_map) = self.collision_map.as_ref() {
-            if let Some(path) = collision_map.find_path(
-                numeric::Point2i::new(start.x as i32, start.y as i32),
-                numeric::Point2i::new(goal.x as i32, goal.y as i32),
-            ) {
+            let start = numeric::Point2i::new(start.x as i32, start.y as i32);
+            let goal = numeric::Point2i::new(goal.x as i32, goal.y as i32);
+
+            // 他のキャラクターを避けるルートが無ければ、すり抜けるルートで妥協する
+            let maybe_path = collision_map
+                .find_path_avoiding(start, goal, &self.dynamic_obstacles)
+                .or_else(|| collision_map.find_path(start, goal));
+
+            if let Some(path) = maybe_path {
                 Some(
                     path.iter()
                         .map(|p| numeric::Vector2u::new(p.x as u32, p.y as u32))
@@ -715,10 +973,168 @@ impl StageObjectMap {
         let tile_size = self.get_tile_drawing_size();
 
         numeric::Vector2f::new(
-            self.tile_map.width as f32 * tile_size.x,
-            self.tile_map.height as f32 * tile_size.y,
+            self.map_size.x as f32 * tile_size.x,
+            self.map_size.y as f32 * tile_size.y,
         )
     }
+
+    ///
+    /// タイル座標(0, 0)に対応する、Tiled上のタイル座標
+    /// 無限マップでは負になることがある
+    ///
+    pub fn get_origin(&self) -> numeric::Point2i {
+        self.origin
+    }
+
+    ///
+    /// マップ全体のカスタムプロパティ
+    ///
+    pub fn get_properties(&self) -> &TiledProperties {
+        &self.properties
+    }
+
+    pub fn get_layer_properties(&self, layer_name: &str) -> Option<&TiledProperties> {
+        self.layers
+            .iter()
+            .find(|layer| layer.name == layer_name)
+            .map(|layer| &layer.properties)
+    }
+
+    ///
+    /// positionのタイルに付けられたカスタムプロパティを、上のレイヤーから順に返す
+    ///
+    pub fn get_tile_properties(&self, position: numeric::Vector2u) -> Vec<&TiledProperties> {
+        self.layers
+            .iter()
+            .rev()
+            .filter_map(|layer| {
+                let gid = layer.get(position.x, position.y)?.gid;
+                if gid == 0 {
+                    return None;
+                }
+                self.get_tileset_by_gid(gid)?.tile_properties(gid)
+            })
+            .collect()
+    }
+
+    pub fn get_objects(&self) -> &[MapObjectData] {
+        &self.objects
+    }
+
+    ///
+    /// Tiledのtypeがobject_typeのオブジェクトを返す
+    ///
+    pub fn objects_of_type<'a>(
+        &'a self,
+        object_type: &'a str,
+    ) -> impl Iterator<Item = &'a MapObjectData> + 'a {
+        self.objects
+            .iter()
+            .filter(move |object| object.object_type == object_type)
+    }
+
+    ///
+    /// オブジェクトの領域を、拡大後のマップ座標で返す
+    ///
+    pub fn object_map_area(&self, object: &MapObjectData) -> numeric::Rect {
+        numeric::Rect::new(
+            object.area.x * self.scale.x,
+            object.area.y * self.scale.y,
+            object.area.w * self.scale.x,
+            object.area.h * self.scale.y,
+        )
+    }
+
+    ///
+    /// オブジェクトが覆っているタイルの一覧を返す。点オブジェクトの場合は1マス
+    ///
+    pub fn object_tiles(&self, object: &MapObjectData) -> Vec<numeric::Point2i> {
+        let tw = self.tile_size.x as f32;
+        let th = self.tile_size.y as f32;
+
+        let left = (object.area.x / tw).floor() as i32;
+        let top = (object.area.y / th).floor() as i32;
+        let right = ((object.area.x + object.area.w) / tw).ceil().max(left as f32 + 1.0) as i32;
+        let bottom = ((object.area.y + object.area.h) / th).ceil().max(top as f32 + 1.0) as i32;
+
+        let mut tiles = Vec::new();
+        for y in top..bottom {
+            for x in left..right {
+                tiles.push(numeric::Point2i::new(x, y));
+            }
+        }
+
+        tiles
+    }
+
+    ///
+    /// typeが"spawn"で、名前がnameのオブジェクトの位置を、拡大後のマップ座標で返す
+    ///
+    pub fn find_spawn_point(&self, name: &str) -> Option<numeric::Point2f> {
+        let object = self
+            .objects_of_type("spawn")
+            .find(|object| object.name == name)?;
+        let area = self.object_map_area(object);
+
+        Some(numeric::Point2f::new(area.x, area.y))
+    }
+
+    ///
+    /// typeが"customer-dest"のオブジェクトから、客の行き先の候補となるタイルを集める
+    ///
+    pub fn customer_dest_candidates(&self) -> Vec<numeric::Vector2u> {
+        self.objects_of_type("customer-dest")
+            .flat_map(|object| self.object_tiles(object))
+            .filter(|tile| tile.x >= 0 && tile.y >= 0)
+            .map(|tile| numeric::Vector2u::new(tile.x as u32, tile.y as u32))
+            .collect()
+    }
+
+    ///
+    /// オブジェクトの領域を、タイル単位の座標で返す
+    ///
+    pub fn object_tile_area(&self, object: &MapObjectData) -> numeric::Rect {
+        let tw = self.tile_size.x as f32;
+        let th = self.tile_size.y as f32;
+
+        numeric::Rect::new(
+            object.area.x / tw,
+            object.area.y / th,
+            object.area.w / tw,
+            object.area.h / th,
+        )
+    }
+
+    ///
+    /// 多角形オブジェクトの頂点を、タイル単位の座標で返す。多角形でなければ空
+    ///
+    pub fn object_tile_polygon(&self, object: &MapObjectData) -> Vec<numeric::Point2f> {
+        let tw = self.tile_size.x as f32;
+        let th = self.tile_size.y as f32;
+
+        object
+            .points
+            .iter()
+            .map(|p| numeric::Point2f::new(p.x / tw, p.y / th))
+            .collect()
+    }
+
+    ///
+    /// typeが"trigger"のオブジェクトを返す。トリガーゾーンの定義に使う
+    ///
+    pub fn trigger_objects(&self) -> impl Iterator<Item = &MapObjectData> {
+        self.objects_of_type("trigger")
+    }
+
+    ///
+    /// typeが"event"のオブジェクトを、覆っているタイルとプロパティの組にして返す
+    /// プロパティの書式は、イベントマップのtomlファイルのevent-panelと同じ
+    ///
+    pub fn event_regions(&self) -> Vec<(Vec<numeric::Point2i>, &TiledProperties)> {
+        self.objects_of_type("event")
+            .map(|object| (self.object_tiles(object), &object.properties))
+            .collect()
+    }
 }
 
 impl Drawable for StageObjectMap {
@@ -727,15 +1143,24 @@ impl Drawable for StageObjectMap {
             if self.redraw_request {
                 sub_screen::stack_screen(ctx, &self.canvas);
 
-                // 全てのsprite batchを描画
-                for (_, batch) in &self.tilesets_batchs {
-                    let draw_param =
-                        ggraphics::DrawParam::default().dest(mintp!(numeric::Point2f::new(
-                            -self.camera.borrow().x.round(),
-                            -self.camera.borrow().y.round(),
-                        )));
+                // 全てのレイヤーのsprite batchを、下のレイヤーから描画
+                for index in 0..self.layers.len() {
+                    let layer = &self.layers[index];
+                    if !layer.visible {
+                        continue;
+                    }
 
-                    ggraphics::draw(ctx, batch, draw_param)?;
+                    let camera = self.layer_camera(index);
+                    let draw_param = ggraphics::DrawParam::default()
+                        .dest(mintp!(numeric::Point2f::new(
+                            -camera.x.round(),
+                            -camera.y.round(),
+                        )))
+                        .color(ggraphics::Color::new(1.0, 1.0, 1.0, layer.opacity));
+
+                    for (_, batch) in self.layer_states[index].batchs.iter() {
+                        ggraphics::draw(ctx, batch, draw_param)?;
+                    }
                 }
 
                 sub_screen::pop_screen(ctx);
@@ -771,11 +1196,8 @@ impl Drawable for StageObjectMap {
 impl Node2D for StageObjectMap {}
 
 impl Updatable for StageObjectMap {
-    fn update(&mut self, _ctx: &mut ggez::Context, _t: Clock) {
+    fn update(&mut self, _ctx: &mut ggez::Context, t: Clock) {
+        self.update_tile_animation(t);
         self.update_sprite_batch();
     }
 }
-
-pub fn map_to_display(map_pos: &numeric::Point2f, camera: &numeric::Rect) -> numeric::Point2f {
-    numeric::Point2f::new(map_pos.x - camera.x, map_pos.y - camera.y)
-}
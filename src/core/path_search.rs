@@ -0,0 +1,478 @@
+use std::collections::{HashMap, HashSet};
+
+use pathfinding::prelude::astar;
+
+use crate::libsuzu::numeric;
+
+///
+/// 縦横に1マス進むときの基本コスト
+/// 斜め移動(√2倍)を整数で扱うため、10倍した値を使う
+///
+pub const STRAIGHT_MOVE_COST: u32 = 10;
+
+///
+/// 斜めに1マス進むときの基本コスト
+///
+pub const DIAGONAL_MOVE_COST: u32 = 14;
+
+///
+/// # A*で使う推定コストの計算方法
+///
+/// ## Manhattan
+/// 縦横移動のみの場合に使う
+///
+/// ## Octile
+/// 斜め移動を許す場合に使う
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Heuristic {
+    Manhattan,
+    Octile,
+}
+
+impl Heuristic {
+    fn estimate(&self, from: numeric::Point2i, to: numeric::Point2i) -> u32 {
+        let dx = (from.x - to.x).abs() as u32;
+        let dy = (from.y - to.y).abs() as u32;
+
+        match self {
+            Heuristic::Manhattan => (dx + dy) * STRAIGHT_MOVE_COST,
+            Heuristic::Octile => {
+                let (long, short) = if dx > dy { (dx, dy) } else { (dy, dx) };
+                (long - short) * STRAIGHT_MOVE_COST + short * DIAGONAL_MOVE_COST
+            }
+        }
+    }
+}
+
+///
+/// # 斜め移動の扱い
+///
+/// ## Never
+/// 斜めには移動しない
+///
+/// ## NoCornerCutting
+/// 斜めに隣接するマスへは、間にある縦横の2マスがどちらも通れる場合のみ移動する
+///
+/// ## Always
+/// 斜めに隣接するマスが通れれば、角をかすめてでも移動する
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiagonalMode {
+    Never,
+    NoCornerCutting,
+    Always,
+}
+
+impl DiagonalMode {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "never" => Some(DiagonalMode::Never),
+            "no_corner_cutting" => Some(DiagonalMode::NoCornerCutting),
+            "always" => Some(DiagonalMode::Always),
+            _ => None,
+        }
+    }
+
+    ///
+    /// 斜め移動の設定に合った推定コストの計算方法
+    ///
+    pub fn heuristic(&self) -> Heuristic {
+        match self {
+            DiagonalMode::Never => Heuristic::Manhattan,
+            _ => Heuristic::Octile,
+        }
+    }
+}
+
+///
+/// # 1マス分の経路探索の情報
+///
+/// ## blocked
+/// 衝突判定があり、通れないマスか
+///
+/// ## cost
+/// このマスに入るときのコストの倍率。通常の床は1
+///
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionMapNode {
+    pub blocked: bool,
+    pub cost: u32,
+}
+
+impl CollisionMapNode {
+    pub fn new(blocked: bool, cost: u32) -> Self {
+        CollisionMapNode {
+            blocked: blocked,
+            cost: cost,
+        }
+    }
+}
+
+///
+/// # 経路探索用のマップ
+///
+/// ## map
+/// マスの情報。y * size.x + xの位置に格納する
+///
+/// ## diagonal
+/// 斜め移動の扱い
+///
+pub struct CollisionMap {
+    map: Vec<CollisionMapNode>,
+    size: numeric::Vector2u,
+    diagonal: DiagonalMode,
+}
+
+impl CollisionMap {
+    pub fn new(size: numeric::Vector2u, collision_objects: Vec<numeric::Vector2u>) -> Self {
+        let mut collision_map = CollisionMap {
+            map: vec![CollisionMapNode::new(false, 1); (size.x * size.y) as usize],
+            size: size,
+            diagonal: DiagonalMode::Never,
+        };
+
+        for position in collision_objects {
+            collision_map.set_blocked(position, true);
+        }
+
+        collision_map
+    }
+
+    fn index(&self, position: numeric::Point2i) -> Option<usize> {
+        if self.not_contains(position) {
+            None
+        } else {
+            Some((position.y as u32 * self.size.x + position.x as u32) as usize)
+        }
+    }
+
+    pub fn not_contains(&self, position: numeric::Point2i) -> bool {
+        position.x < 0
+            || position.x >= self.size.x as i32
+            || position.y < 0
+            || position.y >= self.size.y as i32
+    }
+
+    pub fn get(&self, position: numeric::Point2i) -> Option<&CollisionMapNode> {
+        let index = self.index(position)?;
+        self.map.get(index)
+    }
+
+    pub fn set_blocked(&mut self, position: numeric::Vector2u, blocked: bool) {
+        if let Some(index) = self.index(numeric::Point2i::new(position.x as i32, position.y as i32))
+        {
+            self.map[index].blocked = blocked;
+        }
+    }
+
+    ///
+    /// マスに入るときのコストの倍率を設定する。0は1として扱う
+    ///
+    pub fn set_cost(&mut self, position: numeric::Vector2u, cost: u32) {
+        if let Some(index) = self.index(numeric::Point2i::new(position.x as i32, position.y as i32))
+        {
+            self.map[index].cost = cost.max(1);
+        }
+    }
+
+    pub fn set_diagonal_mode(&mut self, diagonal: DiagonalMode) {
+        self.diagonal = diagonal;
+    }
+
+    pub fn get_diagonal_mode(&self) -> DiagonalMode {
+        self.diagonal
+    }
+
+    fn is_movable_position(
+        &self,
+        position: numeric::Point2i,
+        obstacles: &HashSet<numeric::Point2i>,
+    ) -> bool {
+        match self.get(position) {
+            Some(node) => !node.blocked && !obstacles.contains(&position),
+            None => false,
+        }
+    }
+
+    pub fn find_path(
+        &self,
+        start: numeric::Point2i,
+        goal: numeric::Point2i,
+    ) -> Option<Vec<numeric::Point2i>> {
+        self.find_path_avoiding(start, goal, &HashSet::new())
+    }
+
+    ///
+    /// obstaclesのマスを一時的に通れないものとして経路を探索する
+    /// 他のキャラクターの位置などを渡す。startとgoalのマスは障害物として扱わない
+    ///
+    pub fn find_path_avoiding(
+        &self,
+        start: numeric::Point2i,
+        goal: numeric::Point2i,
+        obstacles: &HashSet<numeric::Point2i>,
+    ) -> Option<Vec<numeric::Point2i>> {
+        if self.not_contains(start) || self.not_contains(goal) {
+            return None;
+        }
+
+        let heuristic = self.diagonal.heuristic();
+
+        let result = astar(
+            &start,
+            |point| self.successors(point, &goal, obstacles),
+            |point| heuristic.estimate(*point, goal),
+            |point| point.eq(&goal),
+        );
+
+        if let Some((path, _)) = result {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    fn successors(
+        &self,
+        point: &numeric::Point2i,
+        goal: &numeric::Point2i,
+        obstacles: &HashSet<numeric::Point2i>,
+    ) -> Vec<(numeric::Point2i, u32)> {
+        let mut successors_list = Vec::new();
+
+        // ゴールのマスは障害物があっても目指す
+        let movable = |p: numeric::Point2i| {
+            if p.eq(goal) {
+                self.get(p).map_or(false, |node| !node.blocked)
+            } else {
+                self.is_movable_position(p, obstacles)
+            }
+        };
+
+        for (dx, dy) in [(0, -1), (0, 1), (-1, 0), (1, 0)].iter() {
+            let cand_point = numeric::Point2i::new(point.x + dx, point.y + dy);
+            if movable(cand_point) {
+                let cost = self.get(cand_point).unwrap().cost;
+                successors_list.push((cand_point, STRAIGHT_MOVE_COST * cost));
+            }
+        }
+
+        if self.diagonal == DiagonalMode::Never {
+            return successors_list;
+        }
+
+        for (dx, dy) in [(-1, -1), (1, -1), (-1, 1), (1, 1)].iter() {
+            let cand_point = numeric::Point2i::new(point.x + dx, point.y + dy);
+            if !movable(cand_point) {
+                continue;
+            }
+
+            if self.diagonal == DiagonalMode::NoCornerCutting {
+                // 間にある縦横のマスが両方とも空いていなければ、角を曲がれない
+                let side_x = numeric::Point2i::new(point.x + dx, point.y);
+                let side_y = numeric::Point2i::new(point.x, point.y + dy);
+                if !movable(side_x) || !movable(side_y) {
+                    continue;
+                }
+            }
+
+            let cost = self.get(cand_point).unwrap().cost;
+            successors_list.push((cand_point, DIAGONAL_MOVE_COST * cost));
+        }
+
+        successors_list
+    }
+}
+
+///
+/// 経路を歩くキャラクターを区別するためのID
+///
+pub type AgentID = u32;
+
+///
+/// # 複数のキャラクターが同じタイルに入らないようにするための予約表
+///
+/// ## occupied
+/// 各キャラクターが現在立っているタイル。毎フレーム作り直す
+///
+/// ## reserved
+/// 各キャラクターが次に進むために予約したタイル。1キャラクターにつき1つまで
+///
+pub struct TileReservation {
+    occupied: HashMap<numeric::Point2i, AgentID>,
+    reserved: HashMap<numeric::Point2i, AgentID>,
+}
+
+impl TileReservation {
+    pub fn new() -> Self {
+        TileReservation {
+            occupied: HashMap::new(),
+            reserved: HashMap::new(),
+        }
+    }
+
+    ///
+    /// 現在のキャラクターの位置で占有状態を更新する
+    /// 含まれていないキャラクターの予約は取り消す
+    ///
+    pub fn update_occupants<I>(&mut self, occupants: I)
+    where
+        I: IntoIterator<Item = (AgentID, numeric::Vector2u)>,
+    {
+        self.occupied.clear();
+        for (id, tile) in occupants {
+            self.occupied
+                .insert(numeric::Point2i::new(tile.x as i32, tile.y as i32), id);
+        }
+
+        let occupied = &self.occupied;
+        self.reserved
+            .retain(|_, owner| occupied.values().any(|id| id == owner));
+    }
+
+    ///
+    /// idのキャラクターがtileに入れるかを返す
+    ///
+    pub fn is_free_for(&self, id: AgentID, tile: numeric::Vector2u) -> bool {
+        let tile = numeric::Point2i::new(tile.x as i32, tile.y as i32);
+
+        self.occupied.get(&tile).map_or(true, |owner| *owner == id)
+            && self.reserved.get(&tile).map_or(true, |owner| *owner == id)
+    }
+
+    ///
+    /// tileを予約する。他のキャラクターが立っているか予約済みの場合は失敗する
+    /// 成功した場合、以前の予約は取り消される
+    ///
+    pub fn try_reserve(&mut self, id: AgentID, tile: numeric::Vector2u) -> bool {
+        if !self.is_free_for(id, tile) {
+            return false;
+        }
+
+        self.release(id);
+        self.reserved
+            .insert(numeric::Point2i::new(tile.x as i32, tile.y as i32), id);
+        true
+    }
+
+    pub fn release(&mut self, id: AgentID) {
+        self.reserved.retain(|_, owner| *owner != id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(x: i32, y: i32) -> numeric::Point2i {
+        numeric::Point2i::new(x, y)
+    }
+
+    fn blocks(tiles: &[(u32, u32)]) -> Vec<numeric::Vector2u> {
+        tiles
+            .iter()
+            .map(|(x, y)| numeric::Vector2u::new(*x, *y))
+            .collect()
+    }
+
+    fn assert_connected(map: &CollisionMap, path: &[numeric::Point2i]) {
+        for step in path.windows(2) {
+            let (dx, dy) = ((step[1].x - step[0].x).abs(), (step[1].y - step[0].y).abs());
+            assert!(dx <= 1 && dy <= 1 && dx + dy > 0);
+        }
+        for point in path.iter() {
+            assert!(!map.get(*point).unwrap().blocked);
+        }
+    }
+
+    #[test]
+    fn straight_path() {
+        let map = CollisionMap::new(numeric::Vector2u::new(5, 1), Vec::new());
+        let path = map.find_path(p(0, 0), p(4, 0)).unwrap();
+
+        assert_eq!(path, vec![p(0, 0), p(1, 0), p(2, 0), p(3, 0), p(4, 0)]);
+    }
+
+    #[test]
+    fn path_around_obstacles() {
+        let map = CollisionMap::new(
+            numeric::Vector2u::new(5, 5),
+            blocks(&[(2, 0), (2, 1), (2, 2), (2, 3)]),
+        );
+        let path = map.find_path(p(0, 0), p(4, 0)).unwrap();
+
+        assert_connected(&map, &path);
+        assert!(path.contains(&p(2, 4)));
+        assert_eq!(path.len(), 13);
+    }
+
+    #[test]
+    fn unreachable_goal() {
+        let walled = CollisionMap::new(
+            numeric::Vector2u::new(3, 3),
+            blocks(&[(1, 0), (1, 1), (1, 2)]),
+        );
+        assert_eq!(walled.find_path(p(0, 0), p(2, 2)), None);
+
+        let blocked_goal = CollisionMap::new(numeric::Vector2u::new(3, 3), blocks(&[(2, 2)]));
+        assert_eq!(blocked_goal.find_path(p(0, 0), p(2, 2)), None);
+
+        assert_eq!(blocked_goal.find_path(p(0, 0), p(5, 5)), None);
+    }
+
+    #[test]
+    fn avoids_reserved_tiles() {
+        let map = CollisionMap::new(numeric::Vector2u::new(3, 3), Vec::new());
+
+        let mut obstacles = HashSet::new();
+        obstacles.insert(p(1, 1));
+        let path = map
+            .find_path_avoiding(p(0, 1), p(2, 1), &obstacles)
+            .unwrap();
+
+        assert_connected(&map, &path);
+        assert!(!path.contains(&p(1, 1)));
+        assert_eq!(path.len(), 5);
+    }
+
+    #[test]
+    fn reserved_goal_is_still_reachable() {
+        let map = CollisionMap::new(numeric::Vector2u::new(3, 1), Vec::new());
+
+        let mut obstacles = HashSet::new();
+        obstacles.insert(p(2, 0));
+        let path = map
+            .find_path_avoiding(p(0, 0), p(2, 0), &obstacles)
+            .unwrap();
+
+        assert_eq!(path.last(), Some(&p(2, 0)));
+    }
+
+    #[test]
+    fn start_is_goal() {
+        let map = CollisionMap::new(numeric::Vector2u::new(3, 3), Vec::new());
+
+        assert_eq!(map.find_path(p(1, 1), p(1, 1)), Some(vec![p(1, 1)]));
+    }
+
+    #[test]
+    fn diagonal_does_not_cut_corners() {
+        let mut map = CollisionMap::new(numeric::Vector2u::new(2, 2), blocks(&[(1, 0)]));
+
+        map.set_diagonal_mode(DiagonalMode::Always);
+        assert_eq!(map.find_path(p(0, 0), p(1, 1)).unwrap().len(), 2);
+
+        map.set_diagonal_mode(DiagonalMode::NoCornerCutting);
+        assert_eq!(map.find_path(p(0, 0), p(1, 1)).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn prefers_cheaper_tiles() {
+        let mut map = CollisionMap::new(numeric::Vector2u::new(3, 2), Vec::new());
+        map.set_cost(numeric::Vector2u::new(1, 0), 10);
+
+        let path = map.find_path(p(0, 0), p(2, 0)).unwrap();
+        assert!(!path.contains(&p(1, 0)));
+    }
+}
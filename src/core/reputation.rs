@@ -0,0 +1,379 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::GensoDate;
+
+pub const MAX_REPUTATION: f32 = 100.0;
+pub const MIN_REPUTATION: f32 = 0.0;
+
+///
+/// 何もしなければ、評判はこの値に近づいていく
+///
+pub const NEUTRAL_REPUTATION: f32 = 50.0;
+
+///
+/// 1日ごとに、評判がNEUTRAL_REPUTATIONとの差のこの割合だけ戻る
+///
+pub const DAILY_REPUTATION_DECAY_RATE: f32 = 0.05;
+
+///
+/// 待たせずに応対できた客1人あたりの評判
+///
+pub const SERVED_CUSTOMER_REPUTATION_BONUS: f32 = 0.4;
+
+///
+/// 待ちきれずに帰った客1人あたりに下がる評判。客のreputation_sensitivityを掛ける
+///
+pub const ANGRY_CUSTOMER_REPUTATION_PENALTY: f32 = 0.5;
+
+///
+/// 本の状態の評価を1冊間違えるごとに下がる評判
+///
+pub const CONDITION_MISTAKE_REPUTATION_PENALTY: f32 = 0.5;
+
+///
+/// 延滞料金をきちんと受け取ったときの評判
+///
+pub const OVERDUE_HANDLED_REPUTATION_BONUS: f32 = 0.5;
+
+///
+/// 評判の変化の記録を、いくつまで残しておくか
+///
+const REPUTATION_HISTORY_LIMIT: usize = 100;
+
+///
+/// # 評判が変化する出来事
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReputationEvent {
+    DailyWork,
+    ServedQuickly,
+    AngryCustomer,
+    ConditionMistake,
+    BadConditionLent,
+    OverdueHandled,
+    BookLost,
+    CopyExpired,
+    Advertisement,
+    TakingRest,
+    GoingOut,
+    Decay,
+}
+
+impl ReputationEvent {
+    pub fn to_str(&self) -> &str {
+        match self {
+            ReputationEvent::DailyWork => "仕事ぶり",
+            ReputationEvent::ServedQuickly => "手早い応対",
+            ReputationEvent::AngryCustomer => "待たせた客",
+            ReputationEvent::ConditionMistake => "状態の見誤り",
+            ReputationEvent::BadConditionLent => "傷んだ本の貸出",
+            ReputationEvent::OverdueHandled => "延滞の対応",
+            ReputationEvent::BookLost => "本の紛失",
+            ReputationEvent::CopyExpired => "写本の納期遅れ",
+            ReputationEvent::Advertisement => "広告",
+            ReputationEvent::TakingRest => "休業",
+            ReputationEvent::GoingOut => "外出先",
+            ReputationEvent::Decay => "時の流れ",
+        }
+    }
+
+    ///
+    /// 出来事の大きさに掛ける重み
+    ///
+    pub fn weight(&self) -> f32 {
+        match self {
+            ReputationEvent::ServedQuickly | ReputationEvent::OverdueHandled => 0.5,
+            ReputationEvent::BookLost => 1.5,
+            _ => 1.0,
+        }
+    }
+}
+
+///
+/// # 評判の変化1件
+///
+/// ## district
+/// 変化の元になった客が住む地区。地区と関係がない変化ではNone
+///
+/// ## amount
+/// 重みを掛け、上限と下限で切り詰めた後の、実際の変化量
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReputationChange {
+    pub date: GensoDate,
+    pub event: ReputationEvent,
+    pub district: Option<String>,
+    pub amount: f32,
+}
+
+fn clamp_reputation(reputation: f32) -> f32 {
+    reputation.max(MIN_REPUTATION).min(MAX_REPUTATION)
+}
+
+///
+/// # 評判の変化の記録
+///
+/// ## district_standing
+/// 地区ごとの評判。まだ出来事がない地区はNEUTRAL_REPUTATIONとして扱う
+///
+/// ## history
+/// 新しい順ではなく、起きた順に並ぶ
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReputationRecord {
+    district_standing: HashMap<String, f32>,
+    history: VecDeque<ReputationChange>,
+}
+
+impl ReputationRecord {
+    pub fn new() -> Self {
+        ReputationRecord::default()
+    }
+
+    fn push_history(&mut self, change: ReputationChange) {
+        self.history.push_back(change);
+        while self.history.len() > REPUTATION_HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+    }
+
+    ///
+    /// reputationにeventを反映し、実際に変化した量を返す
+    ///
+    pub fn apply(
+        &mut self,
+        reputation: &mut f32,
+        event: ReputationEvent,
+        base_amount: f32,
+        district: Option<&str>,
+        date: GensoDate,
+    ) -> f32 {
+        let weighted = base_amount * event.weight();
+
+        let next = clamp_reputation(*reputation + weighted);
+        let amount = next - *reputation;
+        *reputation = next;
+
+        if let Some(district) = district {
+            let standing = self
+                .district_standing
+                .entry(district.to_string())
+                .or_insert(NEUTRAL_REPUTATION);
+            *standing = clamp_reputation(*standing + weighted);
+        }
+
+        if amount != 0.0 {
+            self.push_history(ReputationChange {
+                date: date,
+                event: event,
+                district: district.map(|d| d.to_string()),
+                amount: amount,
+            });
+        }
+
+        amount
+    }
+
+    ///
+    /// 評判と各地区の評判を、NEUTRAL_REPUTATIONに少し近づける
+    ///
+    pub fn decay(&mut self, reputation: &mut f32, date: GensoDate) -> f32 {
+        for standing in self.district_standing.values_mut() {
+            *standing -= (*standing - NEUTRAL_REPUTATION) * DAILY_REPUTATION_DECAY_RATE;
+        }
+
+        let diff = (NEUTRAL_REPUTATION - *reputation) * DAILY_REPUTATION_DECAY_RATE;
+        self.apply(reputation, ReputationEvent::Decay, diff, None, date)
+    }
+
+    pub fn district_standing(&self, district: &str) -> f32 {
+        *self
+            .district_standing
+            .get(district)
+            .unwrap_or(&NEUTRAL_REPUTATION)
+    }
+
+    ///
+    /// 地区と評判の組を、地区の名前順で返す
+    ///
+    pub fn district_standings(&self) -> Vec<(&str, f32)> {
+        let mut standings: Vec<(&str, f32)> = self
+            .district_standing
+            .iter()
+            .map(|(district, standing)| (district.as_str(), *standing))
+            .collect();
+        standings.sort_by(|a, b| a.0.cmp(b.0));
+
+        standings
+    }
+
+    pub fn iter(&self) -> std::collections::vec_deque::Iter<ReputationChange> {
+        self.history.iter()
+    }
+
+    ///
+    /// 最後に評判が変化した日の変化を、出来事ごとにまとめて返す
+    ///
+    pub fn latest_day_summary(&self) -> Option<(GensoDate, Vec<(ReputationEvent, f32)>)> {
+        let latest = self.history.back()?.date;
+
+        let mut summary: Vec<(ReputationEvent, f32)> = Vec::new();
+        for change in self.history.iter().filter(|change| change.date == latest) {
+            match summary.iter_mut().find(|(event, _)| *event == change.event) {
+                Some((_, amount)) => *amount += change.amount,
+                None => summary.push((change.event, change.amount)),
+            }
+        }
+
+        Some((latest, summary))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    fn today() -> GensoDate {
+        GensoDate::new(1, 4, 1)
+    }
+
+    #[test]
+    fn apply_weights_the_event() {
+        let mut record = ReputationRecord::new();
+        let mut reputation = 50.0;
+
+        let served = record.apply(
+            &mut reputation,
+            ReputationEvent::ServedQuickly,
+            1.0,
+            None,
+            today(),
+        );
+        let lost = record.apply(
+            &mut reputation,
+            ReputationEvent::BookLost,
+            -2.0,
+            None,
+            today(),
+        );
+
+        assert_close(served, 0.5);
+        assert_close(lost, -3.0);
+        assert_close(reputation, 47.5);
+    }
+
+    #[test]
+    fn apply_clamps_and_reports_the_actual_change() {
+        let mut record = ReputationRecord::new();
+        let mut reputation = 99.0;
+
+        let amount = record.apply(
+            &mut reputation,
+            ReputationEvent::DailyWork,
+            5.0,
+            None,
+            today(),
+        );
+        assert_close(amount, 1.0);
+        assert_close(reputation, MAX_REPUTATION);
+
+        // 変化しなかった出来事は記録に残さない
+        let amount = record.apply(
+            &mut reputation,
+            ReputationEvent::DailyWork,
+            5.0,
+            None,
+            today(),
+        );
+        assert_close(amount, 0.0);
+        assert_eq!(record.iter().count(), 1);
+    }
+
+    #[test]
+    fn district_standing_follows_weighted_events() {
+        let mut record = ReputationRecord::new();
+        let mut reputation = 50.0;
+
+        record.apply(
+            &mut reputation,
+            ReputationEvent::AngryCustomer,
+            -4.0,
+            Some("人里"),
+            today(),
+        );
+        record.apply(
+            &mut reputation,
+            ReputationEvent::BookLost,
+            -2.0,
+            Some("人里"),
+            today(),
+        );
+
+        assert_close(record.district_standing("人里"), 43.0);
+        assert_close(record.district_standing("妖怪の山"), NEUTRAL_REPUTATION);
+        assert_eq!(record.district_standings(), vec![("人里", 43.0)]);
+    }
+
+    #[test]
+    fn decay_moves_toward_neutral() {
+        let mut record = ReputationRecord::new();
+        let mut reputation = 50.0;
+        record.apply(
+            &mut reputation,
+            ReputationEvent::DailyWork,
+            10.0,
+            Some("人里"),
+            today(),
+        );
+
+        let amount = record.decay(&mut reputation, today().add_day_chain(1));
+
+        assert_close(amount, -10.0 * DAILY_REPUTATION_DECAY_RATE);
+        assert_close(reputation, 60.0 - 10.0 * DAILY_REPUTATION_DECAY_RATE);
+        assert_close(
+            record.district_standing("人里"),
+            60.0 - 10.0 * DAILY_REPUTATION_DECAY_RATE,
+        );
+
+        let mut low = 30.0;
+        let amount = record.decay(&mut low, today().add_day_chain(1));
+        assert_close(amount, 20.0 * DAILY_REPUTATION_DECAY_RATE);
+    }
+
+    #[test]
+    fn latest_day_summary_groups_by_event() {
+        let mut record = ReputationRecord::new();
+        let mut reputation = 50.0;
+        let tomorrow = today().add_day_chain(1);
+
+        record.apply(
+            &mut reputation,
+            ReputationEvent::DailyWork,
+            1.0,
+            None,
+            today(),
+        );
+        for _ in 0..2 {
+            record.apply(
+                &mut reputation,
+                ReputationEvent::AngryCustomer,
+                -1.0,
+                None,
+                tomorrow,
+            );
+        }
+
+        let (date, summary) = record.latest_day_summary().unwrap();
+        assert_eq!(date, tomorrow);
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].0, ReputationEvent::AngryCustomer);
+        assert_close(summary[0].1, -2.0);
+    }
+}
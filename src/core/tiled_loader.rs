@@ -0,0 +1,519 @@
+use std::collections::HashMap;
+
+use crate::core::util;
+use crate::libsuzu::numeric;
+
+///
+/// # Tiledのカスタムプロパティ
+/// マップ、レイヤー、タイル、オブジェクトに付けられたプロパティを型ごとに取り出す
+///
+#[derive(Debug, Clone, Default)]
+pub struct TiledProperties {
+    properties: tiled::Properties,
+}
+
+impl TiledProperties {
+    pub fn new(properties: tiled::Properties) -> Self {
+        TiledProperties {
+            properties: properties,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&tiled::PropertyValue> {
+        self.properties.get(key)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.properties.contains_key(key)
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i32> {
+        match self.get(key)? {
+            tiled::PropertyValue::IntValue(v) => Some(*v),
+            tiled::PropertyValue::FloatValue(v) => Some(v.round() as i32),
+            _ => None,
+        }
+    }
+
+    pub fn get_float(&self, key: &str) -> Option<f32> {
+        match self.get(key)? {
+            tiled::PropertyValue::FloatValue(v) => Some(*v),
+            tiled::PropertyValue::IntValue(v) => Some(*v as f32),
+            _ => None,
+        }
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            tiled::PropertyValue::BoolValue(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            tiled::PropertyValue::StringValue(v) => Some(v),
+            tiled::PropertyValue::FileValue(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &tiled::PropertyValue)> {
+        self.properties.iter()
+    }
+
+    ///
+    /// プロパティをtomlのテーブルに変換する
+    /// "shelf-info.begin-number"のように'.'を含むキーは、入れ子のテーブルにする
+    ///
+    pub fn to_toml(&self) -> toml::Value {
+        let mut root = toml::value::Table::new();
+
+        for (key, value) in self.properties.iter() {
+            let value = match value {
+                tiled::PropertyValue::BoolValue(v) => toml::Value::Boolean(*v),
+                tiled::PropertyValue::IntValue(v) => toml::Value::Integer(*v as i64),
+                tiled::PropertyValue::FloatValue(v) => toml::Value::Float(*v as f64),
+                tiled::PropertyValue::ColorValue(v) => toml::Value::Integer(*v as i64),
+                tiled::PropertyValue::StringValue(v) => toml::Value::String(v.clone()),
+                tiled::PropertyValue::FileValue(v) => toml::Value::String(v.clone()),
+            };
+
+            let mut keys: Vec<&str> = key.split('.').collect();
+            let last = keys.pop().unwrap();
+
+            let mut table = &mut root;
+            for k in keys {
+                let entry = table
+                    .entry(k.to_string())
+                    .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+                if !entry.is_table() {
+                    *entry = toml::Value::Table(toml::value::Table::new());
+                }
+                table = entry.as_table_mut().unwrap();
+            }
+            table.insert(last.to_string(), value);
+        }
+
+        toml::Value::Table(root)
+    }
+}
+
+///
+/// # 描画や衝突判定に使うタイルレイヤー
+/// 無限マップのチャンクも、マップ全体を覆う1枚の配列にまとめて保持する
+///
+/// ## tiles
+/// tiles[y][x]の形で、マップの原点からの位置に置かれたタイルを保持する
+///
+/// ## offset
+/// Tiledで設定されたレイヤーの描画位置のずれ(ピクセル)
+///
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub offset: numeric::Vector2f,
+    pub tiles: Vec<Vec<tiled::LayerTile>>,
+    pub properties: TiledProperties,
+}
+
+impl TileLayer {
+    pub fn get(&self, x: u32, y: u32) -> Option<&tiled::LayerTile> {
+        self.tiles.get(y as usize)?.get(x as usize)
+    }
+}
+
+///
+/// # オブジェクトレイヤーに置かれたオブジェクト
+///
+/// ## object_type
+/// Tiledのオブジェクトのtype。"spawn"などで用途を区別する
+///
+/// ## area
+/// マップの原点を基準とした、拡大前のピクセル単位の領域。点オブジェクトは大きさ0
+///
+/// ## points
+/// 多角形オブジェクトの頂点。areaと同じ座標系で、多角形以外では空
+///
+#[derive(Debug, Clone)]
+pub struct MapObjectData {
+    pub id: u32,
+    pub name: String,
+    pub object_type: String,
+    pub layer_name: String,
+    pub area: numeric::Rect,
+    pub points: Vec<numeric::Point2f>,
+    pub properties: TiledProperties,
+}
+
+///
+/// # 読み込んだTiledのマップを、扱いやすい形に整理したもの
+///
+/// ## origin
+/// タイル座標(0, 0)に対応する、Tiled上のタイル座標。無限マップでは負になることがある
+///
+/// ## size
+/// 全てのレイヤーを覆うタイルの数
+///
+/// ## tileset_sources
+/// タイルセットのfirst_gidと、そのタイルセットを定義したファイル(tmxかtsx)のパス
+///
+pub struct TiledMapData {
+    pub tilesets: Vec<tiled::Tileset>,
+    pub tileset_sources: HashMap<u32, String>,
+    pub tile_size: numeric::Vector2u,
+    pub origin: numeric::Point2i,
+    pub size: numeric::Vector2u,
+    pub layers: Vec<TileLayer>,
+    pub objects: Vec<MapObjectData>,
+    pub properties: TiledProperties,
+}
+
+impl TiledMapData {
+    ///
+    /// リソースのファイルシステムからtmxファイルを読み込む
+    /// 外部タイルセット(tsx)も、tmxファイルからの相対パスでリソースから読み込む
+    ///
+    pub fn load(ctx: &mut ggez::Context, path: &str) -> Self {
+        let source = util::read_from_resources_as_string(ctx, path);
+        let (source, external_tilesets) = match split_external_tilesets(&source) {
+            Ok(split) => split,
+            Err(e) => panic!("Failed to parse tiled map {} -> {}", path, e),
+        };
+
+        let mut tile_map = match tiled::parse(source.as_bytes()) {
+            Ok(map) => map,
+            Err(e) => panic!("Failed to parse tiled map {} -> {:?}", path, e),
+        };
+
+        let mut tileset_sources = HashMap::new();
+        for tileset in tile_map.tilesets.iter() {
+            tileset_sources.insert(tileset.first_gid, path.to_string());
+        }
+
+        for (first_gid, tsx_path) in external_tilesets {
+            let tsx_path = resolve_relative_path(path, &tsx_path);
+            let tsx_source = util::read_from_resources_as_string(ctx, &tsx_path);
+            match tiled::parse_tileset(tsx_source.as_bytes(), first_gid) {
+                Ok(tileset) => tile_map.tilesets.push(tileset),
+                Err(e) => panic!("Failed to parse tiled tileset {} -> {:?}", tsx_path, e),
+            }
+            tileset_sources.insert(first_gid, tsx_path);
+        }
+        tile_map.tilesets.sort_by_key(|tileset| tileset.first_gid);
+
+        let mut map_data = Self::from_tiled_map(tile_map);
+        map_data.tileset_sources = tileset_sources;
+        map_data
+    }
+
+    ///
+    /// タイルセットの画像のパスを、タイルセットを定義したファイルからの相対パスとして解決する
+    /// 定義したファイルが分からなければ、リソースのルートからのパスとして扱う
+    ///
+    pub fn tileset_image_path(&self, tileset: &tiled::Tileset, source: &str) -> String {
+        let base = self
+            .tileset_sources
+            .get(&tileset.first_gid)
+            .map(|path| path.as_str())
+            .unwrap_or("/");
+        resolve_relative_path(base, source)
+    }
+
+    pub fn from_tiled_map(tile_map: tiled::Map) -> Self {
+        let (origin, size) = Self::calc_bounds(&tile_map);
+        let tile_size = numeric::Vector2u::new(tile_map.tile_width, tile_map.tile_height);
+
+        let layers = tile_map
+            .layers
+            .iter()
+            .map(|layer| Self::flatten_layer(layer, origin, size))
+            .collect();
+
+        // オブジェクトの座標も、マップの原点を基準にする
+        let origin_px = numeric::Vector2f::new(
+            (origin.x * tile_size.x as i32) as f32,
+            (origin.y * tile_size.y as i32) as f32,
+        );
+
+        let mut objects = Vec::new();
+        for group in tile_map.object_groups.iter() {
+            for object in group.objects.iter() {
+                if !object.visible {
+                    continue;
+                }
+
+                let (w, h) = match &object.shape {
+                    tiled::ObjectShape::Rect { width, height } => (*width, *height),
+                    tiled::ObjectShape::Ellipse { width, height } => (*width, *height),
+                    _ => (object.width, object.height),
+                };
+
+                // 多角形の頂点は、オブジェクトの位置からの相対座標になっている
+                let points = match &object.shape {
+                    tiled::ObjectShape::Polygon { points } => points
+                        .iter()
+                        .map(|(px, py)| {
+                            numeric::Point2f::new(
+                                object.x + px - origin_px.x,
+                                object.y + py - origin_px.y,
+                            )
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+
+                // タイルオブジェクトは、左下が基準点になっている
+                let top = if object.gid != 0 {
+                    object.y - h
+                } else {
+                    object.y
+                };
+
+                objects.push(MapObjectData {
+                    id: object.id,
+                    name: object.name.clone(),
+                    object_type: object.obj_type.clone(),
+                    layer_name: group.name.clone(),
+                    area: numeric::Rect::new(object.x - origin_px.x, top - origin_px.y, w, h),
+                    points: points,
+                    properties: TiledProperties::new(object.properties.clone()),
+                });
+            }
+        }
+
+        TiledMapData {
+            tilesets: tile_map.tilesets,
+            tileset_sources: HashMap::new(),
+            tile_size: tile_size,
+            origin: origin,
+            size: size,
+            layers: layers,
+            objects: objects,
+            properties: TiledProperties::new(tile_map.properties),
+        }
+    }
+
+    ///
+    /// 全てのレイヤーを覆う範囲を求める
+    /// 有限マップはマップの大きさ、無限マップはチャンクが存在する範囲になる
+    ///
+    fn calc_bounds(tile_map: &tiled::Map) -> (numeric::Point2i, numeric::Vector2u) {
+        let mut min = numeric::Point2i::new(0, 0);
+        let mut max = numeric::Point2i::new(0, 0);
+        let mut first = true;
+
+        for layer in tile_map.layers.iter() {
+            let rects: Vec<(i32, i32, i32, i32)> = match &layer.tiles {
+                tiled::LayerData::Finite(tiles) => {
+                    let h = tiles.len() as i32;
+                    let w = tiles.iter().map(|row| row.len()).max().unwrap_or(0) as i32;
+                    vec![(0, 0, w, h)]
+                }
+                tiled::LayerData::Infinite(chunks) => chunks
+                    .values()
+                    .map(|chunk| {
+                        (
+                            chunk.x,
+                            chunk.y,
+                            chunk.x + chunk.width as i32,
+                            chunk.y + chunk.height as i32,
+                        )
+                    })
+                    .collect(),
+            };
+
+            for (left, top, right, bottom) in rects {
+                if first {
+                    min = numeric::Point2i::new(left, top);
+                    max = numeric::Point2i::new(right, bottom);
+                    first = false;
+                } else {
+                    min = numeric::Point2i::new(min.x.min(left), min.y.min(top));
+                    max = numeric::Point2i::new(max.x.max(right), max.y.max(bottom));
+                }
+            }
+        }
+
+        if first || !tile_map.infinite {
+            return (
+                numeric::Point2i::new(0, 0),
+                numeric::Vector2u::new(tile_map.width, tile_map.height),
+            );
+        }
+
+        (
+            min,
+            numeric::Vector2u::new((max.x - min.x) as u32, (max.y - min.y) as u32),
+        )
+    }
+
+    fn flatten_layer(
+        layer: &tiled::Layer,
+        origin: numeric::Point2i,
+        size: numeric::Vector2u,
+    ) -> TileLayer {
+        let mut tiles = vec![vec![tiled::LayerTile::new(0); size.x as usize]; size.y as usize];
+
+        match &layer.tiles {
+            tiled::LayerData::Finite(finite) => {
+                for (y, row) in finite.iter().enumerate().take(size.y as usize) {
+                    for (x, tile) in row.iter().enumerate().take(size.x as usize) {
+                        tiles[y][x] = *tile;
+                    }
+                }
+            }
+            tiled::LayerData::Infinite(chunks) => {
+                for chunk in chunks.values() {
+                    for (cy, row) in chunk.tiles.iter().enumerate() {
+                        for (cx, tile) in row.iter().enumerate() {
+                            let x = chunk.x + cx as i32 - origin.x;
+                            let y = chunk.y + cy as i32 - origin.y;
+                            if x < 0 || y < 0 || x >= size.x as i32 || y >= size.y as i32 {
+                                continue;
+                            }
+                            tiles[y as usize][x as usize] = *tile;
+                        }
+                    }
+                }
+            }
+        }
+
+        TileLayer {
+            name: layer.name.clone(),
+            visible: layer.visible,
+            opacity: layer.opacity,
+            offset: numeric::Vector2f::new(layer.offset_x, layer.offset_y),
+            tiles: tiles,
+            properties: TiledProperties::new(layer.properties.clone()),
+        }
+    }
+}
+
+fn xml_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!(" {}=\"", name);
+    let begin = tag.find(&pattern)? + pattern.len();
+    let end = tag[begin..].find('"')? + begin;
+    Some(&tag[begin..end])
+}
+
+///
+/// tmxから外部タイルセットの参照を取り除き、(firstgid, tsxのパス)の一覧と共に返す
+/// tiled::parseはOSのファイルシステムで外部タイルセットを開こうとするため、
+/// 取り除いたものはリソースのファイルシステムから別に読み込む
+/// firstgidが数値として読めない場合は、そのタイルセットのパスを含むエラーを返す
+///
+fn split_external_tilesets(source: &str) -> Result<(String, Vec<(u32, String)>), String> {
+    let mut rest = source;
+    let mut stripped = String::with_capacity(source.len());
+    let mut external = Vec::new();
+
+    while let Some(begin) = rest.find("<tileset") {
+        let end = match rest[begin..].find('>') {
+            Some(end) => begin + end + 1,
+            None => break,
+        };
+        let tag = &rest[begin..end];
+
+        match (xml_attribute(tag, "source"), xml_attribute(tag, "firstgid")) {
+            (Some(tsx), Some(first_gid)) => {
+                let first_gid = match first_gid.parse::<u32>() {
+                    Ok(first_gid) => first_gid,
+                    Err(_) => {
+                        return Err(format!(
+                            "Invalid firstgid \"{}\" for tileset {}",
+                            first_gid, tsx
+                        ))
+                    }
+                };
+
+                stripped.push_str(&rest[..begin]);
+                external.push((first_gid, tsx.to_string()));
+
+                // 自己終了タグでなければ、閉じタグまで取り除く
+                rest = if tag.ends_with("/>") {
+                    &rest[end..]
+                } else {
+                    match rest[end..].find("</tileset>") {
+                        Some(close) => &rest[end + close + "</tileset>".len()..],
+                        None => &rest[end..],
+                    }
+                };
+            }
+            _ => {
+                stripped.push_str(&rest[..end]);
+                rest = &rest[end..];
+            }
+        }
+    }
+
+    stripped.push_str(rest);
+    Ok((stripped, external))
+}
+
+///
+/// baseのファイルがあるディレクトリを基準に、relativeのパスを解決する
+///
+fn resolve_relative_path(base: &str, relative: &str) -> String {
+    if relative.starts_with('/') {
+        return relative.to_string();
+    }
+
+    let mut components: Vec<&str> = match base.rfind('/') {
+        Some(i) => base[..i].split('/').filter(|c| !c.is_empty()).collect(),
+        None => Vec::new(),
+    };
+
+    for component in relative.split('/') {
+        match component {
+            "" | "." => (),
+            ".." => {
+                components.pop();
+            }
+            c => components.push(c),
+        }
+    }
+
+    format!("/{}", components.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_external_tilesets() {
+        let source = concat!(
+            "<map>",
+            "<tileset firstgid=\"1\" source=\"floor.tsx\"/>",
+            "<tileset firstgid=\"65\" source=\"../common/wall.tsx\">",
+            "</tileset>",
+            "<tileset firstgid=\"129\" name=\"inline\">",
+            "</tileset>",
+            "</map>"
+        );
+
+        let (stripped, external) = split_external_tilesets(source).unwrap();
+        assert_eq!(
+            stripped,
+            "<map><tileset firstgid=\"129\" name=\"inline\"></tileset></map>"
+        );
+        assert_eq!(
+            external,
+            vec![
+                (1, "floor.tsx".to_string()),
+                (65, "../common/wall.tsx".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_firstgid_names_tileset() {
+        let source = "<map><tileset firstgid=\"x1\" source=\"floor.tsx\"/></map>";
+
+        let e = split_external_tilesets(source).unwrap_err();
+        assert!(e.contains("floor.tsx"), "{}", e);
+        assert!(e.contains("x1"), "{}", e);
+    }
+}
@@ -10,6 +10,28 @@ pub fn random_select<T>(mut i: std::slice::Iter<T>) -> Option<&T> {
     i.nth(rand::random::<usize>() % i.len())
 }
 
+///
+/// weightsの重みで、インデックスを1つ選ぶ。重みの合計が0以下の場合はNone
+///
+pub fn weighted_random_index(weights: &[f32]) -> Option<usize> {
+    let total: f32 = weights.iter().map(|w| w.max(0.0)).sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    let mut point = rand::random::<f32>() * total;
+    for (index, weight) in weights.iter().enumerate() {
+        let weight = weight.max(0.0);
+        if point < weight {
+            return Some(index);
+        }
+        point -= weight;
+    }
+
+    // 浮動小数の誤差で選ばれなかった場合は、最後の重みのあるもの
+    weights.iter().rposition(|w| *w > 0.0)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DayOfWeek {
     Sunday,
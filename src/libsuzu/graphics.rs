@@ -1,2 +1,6 @@
+pub mod camera;
 pub mod drawable;
+pub mod glyph;
+pub mod layout;
 pub mod object;
+pub mod text_layout;
@@ -0,0 +1,351 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::libsuzu::core::Clock;
+use crate::libsuzu::numeric;
+
+///
+/// ワールド座標を、カメラの視界の左上を原点とする座標に変換する
+/// 拡大率は反映しない。視界を描画するSubScreen側で拡大するので、
+/// SubScreenの中に描画するマップオブジェクトはこの座標を使う
+/// SubScreenの外の画面上の座標が必要な場合は、Camera::world_to_screenを使う
+///
+pub fn world_to_view(world_pos: &numeric::Point2f, view: &numeric::Rect) -> numeric::Point2f {
+    numeric::Point2f::new(world_pos.x - view.x, world_pos.y - view.y)
+}
+
+struct CameraShake {
+    amplitude: f32,
+    begin: Clock,
+    duration: Clock,
+}
+
+///
+/// # 演出でカメラを動かす情報
+///
+/// ## hold
+/// 目的地に着いてから、追従に戻るまでの時間
+///
+struct CameraPan {
+    from: numeric::Point2f,
+    to: numeric::Point2f,
+    begin: Clock,
+    duration: Clock,
+    hold: Clock,
+}
+
+///
+/// # マップを映すカメラ
+///
+/// 視界はRc<RefCell<Rect>>で共有され、StageObjectMapなどはそれを参照して描画する
+///
+/// ## center
+/// 揺れを含まない、視界の中心のワールド座標
+///
+/// ## dead_zone
+/// 視界の中心からこの範囲内で追従対象が動いても、カメラは動かない
+///
+/// ## follow_rate
+/// 1回の更新で、目標の位置に近づく割合。1.0で即座に追いつく
+///
+/// ## bounds
+/// 視界がはみ出さないようにする範囲。マップの大きさなど
+///
+pub struct Camera {
+    view: Rc<RefCell<numeric::Rect>>,
+    screen_size: numeric::Vector2f,
+    center: numeric::Point2f,
+    zoom: f32,
+    dead_zone: numeric::Vector2f,
+    follow_rate: f32,
+    follow_target: Option<numeric::Point2f>,
+    bounds: Option<numeric::Rect>,
+    shake: Option<CameraShake>,
+    pan: Option<CameraPan>,
+}
+
+impl Camera {
+    pub fn new(screen_size: numeric::Vector2f) -> Self {
+        Camera {
+            view: Rc::new(RefCell::new(numeric::Rect::new(
+                0.0,
+                0.0,
+                screen_size.x,
+                screen_size.y,
+            ))),
+            screen_size: screen_size,
+            center: numeric::Point2f::new(screen_size.x / 2.0, screen_size.y / 2.0),
+            zoom: 1.0,
+            dead_zone: numeric::Vector2f::new(0.0, 0.0),
+            follow_rate: 1.0,
+            follow_target: None,
+            bounds: None,
+            shake: None,
+            pan: None,
+        }
+    }
+
+    ///
+    /// 視界の共有参照。マップなど、カメラに合わせて描画するものに渡す
+    ///
+    pub fn get_view_ref(&self) -> Rc<RefCell<numeric::Rect>> {
+        self.view.clone()
+    }
+
+    pub fn get_view(&self) -> numeric::Rect {
+        *self.view.borrow()
+    }
+
+    pub fn set_bounds(&mut self, bounds: Option<numeric::Rect>) {
+        self.bounds = bounds;
+    }
+
+    ///
+    /// 原点からmap_sizeまでの範囲に視界を収める
+    ///
+    pub fn set_map_size(&mut self, map_size: numeric::Vector2f) {
+        self.set_bounds(Some(numeric::Rect::new(0.0, 0.0, map_size.x, map_size.y)));
+    }
+
+    pub fn set_dead_zone(&mut self, dead_zone: numeric::Vector2f) {
+        self.dead_zone = dead_zone;
+    }
+
+    pub fn set_follow_rate(&mut self, rate: f32) {
+        self.follow_rate = rate.max(0.0).min(1.0);
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(0.1);
+    }
+
+    pub fn get_zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    ///
+    /// 拡大率を反映した、視界に映るワールドの大きさ
+    ///
+    pub fn view_size(&self) -> numeric::Vector2f {
+        numeric::Vector2f::new(
+            self.screen_size.x / self.zoom,
+            self.screen_size.y / self.zoom,
+        )
+    }
+
+    ///
+    /// 追従する位置を設定する。毎フレーム、追従対象の位置を渡す
+    ///
+    pub fn follow(&mut self, target: numeric::Point2f) {
+        self.follow_target = Some(target);
+    }
+
+    pub fn stop_following(&mut self) {
+        self.follow_target = None;
+    }
+
+    ///
+    /// 視界の中心をworld_posに即座に合わせる
+    ///
+    pub fn look_at(&mut self, world_pos: numeric::Point2f) {
+        self.center = self.clamp_center(world_pos);
+        self.apply_view(numeric::Vector2f::new(0.0, 0.0));
+    }
+
+    ///
+    /// 時刻tから、amplitudeの強さでdurationの間カメラを揺らす
+    ///
+    pub fn shake(&mut self, amplitude: f32, duration: Clock, t: Clock) {
+        self.shake = Some(CameraShake {
+            amplitude: amplitude,
+            begin: t,
+            duration: duration.max(1),
+        });
+    }
+
+    ///
+    /// 時刻tから、durationかけて視界の中心をtargetへ動かす
+    /// 到着後holdの間留まり、その後は追従に戻る
+    ///
+    pub fn pan_to(&mut self, target: numeric::Point2f, duration: Clock, hold: Clock, t: Clock) {
+        self.pan = Some(CameraPan {
+            from: self.center,
+            to: self.clamp_center(target),
+            begin: t,
+            duration: duration.max(1),
+            hold: hold,
+        });
+    }
+
+    pub fn is_panning(&self) -> bool {
+        self.pan.is_some()
+    }
+
+    fn clamp_center(&self, center: numeric::Point2f) -> numeric::Point2f {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return center,
+        };
+
+        let half = self.view_size() / 2.0;
+
+        // 視界の方が大きい場合は、範囲の中央に置く
+        let clamp_axis = |v: f32, begin: f32, length: f32, half: f32| {
+            if length <= half * 2.0 {
+                begin + (length / 2.0)
+            } else {
+                v.max(begin + half).min(begin + length - half)
+            }
+        };
+
+        numeric::Point2f::new(
+            clamp_axis(center.x, bounds.x, bounds.w, half.x),
+            clamp_axis(center.y, bounds.y, bounds.h, half.y),
+        )
+    }
+
+    ///
+    /// 追従対象がデッドゾーンの外に出ている場合、その境界に来るような中心の位置
+    ///
+    fn follow_goal(&self, target: numeric::Point2f) -> numeric::Point2f {
+        let follow_axis = |center: f32, target: f32, dead: f32| {
+            if target > center + dead {
+                target - dead
+            } else if target < center - dead {
+                target + dead
+            } else {
+                center
+            }
+        };
+
+        numeric::Point2f::new(
+            follow_axis(self.center.x, target.x, self.dead_zone.x),
+            follow_axis(self.center.y, target.y, self.dead_zone.y),
+        )
+    }
+
+    fn shake_offset(&mut self, t: Clock) -> numeric::Vector2f {
+        let (amplitude, progress) = match self.shake.as_ref() {
+            Some(shake) => (
+                shake.amplitude,
+                (t - shake.begin) as f32 / shake.duration as f32,
+            ),
+            None => return numeric::Vector2f::new(0.0, 0.0),
+        };
+
+        if progress >= 1.0 {
+            self.shake = None;
+            return numeric::Vector2f::new(0.0, 0.0);
+        }
+
+        // 時間とともに弱くする
+        let power = amplitude * (1.0 - progress);
+        numeric::Vector2f::new(
+            (rand::random::<f32>() * 2.0 - 1.0) * power,
+            (rand::random::<f32>() * 2.0 - 1.0) * power,
+        )
+    }
+
+    fn apply_view(&mut self, shake_offset: numeric::Vector2f) {
+        let size = self.view_size();
+        let mut view = self.view.borrow_mut();
+
+        view.x = (self.center.x - (size.x / 2.0)) + shake_offset.x;
+        view.y = (self.center.y - (size.y / 2.0)) + shake_offset.y;
+        view.w = size.x;
+        view.h = size.y;
+    }
+
+    ///
+    /// カメラの位置を更新する。視界が動いた場合はtrueを返す
+    ///
+    pub fn update(&mut self, t: Clock) -> bool {
+        let before = self.get_view();
+
+        let pan_state = self.pan.as_ref().map(|pan| {
+            let elapsed = t.saturating_sub(pan.begin);
+            let progress = (elapsed as f32 / pan.duration as f32).min(1.0);
+            // 始めと終わりをゆっくりにする
+            let eased = progress * progress * (3.0 - 2.0 * progress);
+
+            (
+                numeric::Point2f::new(
+                    pan.from.x + ((pan.to.x - pan.from.x) * eased),
+                    pan.from.y + ((pan.to.y - pan.from.y) * eased),
+                ),
+                elapsed >= pan.duration + pan.hold,
+            )
+        });
+
+        match pan_state {
+            Some((center, finished)) => {
+                self.center = center;
+                if finished {
+                    self.pan = None;
+                }
+            }
+            None => {
+                if let Some(target) = self.follow_target {
+                    let goal = self.clamp_center(self.follow_goal(target));
+                    self.center = numeric::Point2f::new(
+                        self.center.x + ((goal.x - self.center.x) * self.follow_rate),
+                        self.center.y + ((goal.y - self.center.y) * self.follow_rate),
+                    );
+                }
+            }
+        }
+
+        self.center = self.clamp_center(self.center);
+
+        let shake_offset = self.shake_offset(t);
+        self.apply_view(shake_offset);
+
+        let after = self.get_view();
+        before.x != after.x || before.y != after.y || before.w != after.w
+    }
+
+    ///
+    /// ワールド座標を、拡大率を反映した画面上の座標に変換する。screen_to_worldの逆変換
+    ///
+    pub fn world_to_screen(&self, world_pos: numeric::Point2f) -> numeric::Point2f {
+        let p = world_to_view(&world_pos, &self.view.borrow());
+        numeric::Point2f::new(p.x * self.zoom, p.y * self.zoom)
+    }
+
+    ///
+    /// 画面上の座標を、ワールド座標に変換する。world_to_screenの逆変換
+    ///
+    pub fn screen_to_world(&self, screen_pos: numeric::Point2f) -> numeric::Point2f {
+        let view = self.view.borrow();
+        numeric::Point2f::new(
+            (screen_pos.x / self.zoom) + view.x,
+            (screen_pos.y / self.zoom) + view.y,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_point_eq(a: numeric::Point2f, b: numeric::Point2f) {
+        assert!((a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3);
+    }
+
+    #[test]
+    fn screen_and_world_round_trip_with_zoom() {
+        let mut camera = Camera::new(numeric::Vector2f::new(800.0, 600.0));
+        camera.set_zoom(2.0);
+        camera.look_at(numeric::Point2f::new(1000.0, 700.0));
+
+        let screen = numeric::Point2f::new(120.0, 45.0);
+        let world = camera.screen_to_world(screen);
+        assert_point_eq(camera.world_to_screen(world), screen);
+
+        // 視界の中心は、画面の中心に映る
+        assert_point_eq(
+            camera.world_to_screen(numeric::Point2f::new(1000.0, 700.0)),
+            numeric::Point2f::new(400.0, 300.0),
+        );
+    }
+}
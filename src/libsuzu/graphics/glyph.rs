@@ -0,0 +1,209 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Read;
+
+use ab_glyph::{Font as _, FontArc, ScaleFont as _};
+use ggez::graphics as ggraphics;
+
+use crate::libsuzu::numeric;
+
+///
+/// FontInformationが保持できる代替フォントの最大数
+///
+pub const MAX_FALLBACK_FONTS: usize = 2;
+
+///
+/// # フォント全体の寸法
+/// descentは、ベースラインより下にはみ出す長さを負の値で表す
+///
+#[derive(Debug, Clone, Copy)]
+pub struct FontMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32,
+}
+
+impl FontMetrics {
+    pub fn line_height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+}
+
+///
+/// # 1文字分の寸法
+///
+/// ## bounds
+/// 文字の描画範囲。行の上端(ベースラインからascent分上)を原点とする
+///
+#[derive(Debug, Clone, Copy)]
+pub struct GlyphMetrics {
+    pub advance: f32,
+    pub bounds: numeric::Rect,
+}
+
+type ScaleKey = (u32, u32);
+
+fn scale_key(scale: numeric::Vector2f) -> ScaleKey {
+    (scale.x.to_bits(), scale.y.to_bits())
+}
+
+///
+/// # 全てのテキストで共有するグリフ情報のキャッシュ
+///
+/// ggezのFontからは字形の情報を引けないため、フォントファイルを別途読み込んで保持する
+///
+struct GlyphCache {
+    outlines: HashMap<ggraphics::Font, FontArc>,
+    coverage: HashMap<(ggraphics::Font, char), bool>,
+    metrics: HashMap<(ggraphics::Font, char, ScaleKey), GlyphMetrics>,
+    default_fallbacks: [Option<ggraphics::Font>; MAX_FALLBACK_FONTS],
+}
+
+impl GlyphCache {
+    fn new() -> Self {
+        GlyphCache {
+            outlines: HashMap::new(),
+            coverage: HashMap::new(),
+            metrics: HashMap::new(),
+            default_fallbacks: [None; MAX_FALLBACK_FONTS],
+        }
+    }
+
+    fn has_glyph(&mut self, font: ggraphics::Font, ch: char) -> bool {
+        if let Some(covered) = self.coverage.get(&(font, ch)) {
+            return *covered;
+        }
+
+        // 字形の情報が無いフォントは、全ての文字を持っているとみなす
+        let covered = match self.outlines.get(&font) {
+            Some(outline) => outline.glyph_id(ch).0 != 0,
+            None => true,
+        };
+
+        self.coverage.insert((font, ch), covered);
+        covered
+    }
+
+    fn glyph_metrics(
+        &mut self,
+        font: ggraphics::Font,
+        ch: char,
+        scale: numeric::Vector2f,
+    ) -> Option<GlyphMetrics> {
+        let key = (font, ch, scale_key(scale));
+        if let Some(metrics) = self.metrics.get(&key) {
+            return Some(*metrics);
+        }
+
+        let outline = self.outlines.get(&font)?;
+        let scaled = outline.as_scaled(ab_glyph::PxScale {
+            x: scale.x,
+            y: scale.y,
+        });
+
+        let id = outline.glyph_id(ch);
+        let glyph = id.with_scale_and_position(
+            ab_glyph::PxScale {
+                x: scale.x,
+                y: scale.y,
+            },
+            ab_glyph::point(0.0, scaled.ascent()),
+        );
+        let bounds = scaled.glyph_bounds(&glyph);
+
+        let metrics = GlyphMetrics {
+            advance: scaled.h_advance(id),
+            bounds: numeric::Rect::new(
+                bounds.min.x,
+                bounds.min.y,
+                bounds.max.x - bounds.min.x,
+                bounds.max.y - bounds.min.y,
+            ),
+        };
+
+        self.metrics.insert(key, metrics);
+        Some(metrics)
+    }
+}
+
+thread_local! {
+    static GLYPH_CACHE: RefCell<GlyphCache> = RefCell::new(GlyphCache::new());
+}
+
+///
+/// フォントを読み込み、グリフ情報のキャッシュに登録する
+/// ggraphics::Font::newの代わりに使う
+///
+pub fn load_font(ctx: &mut ggez::Context, path: &str) -> ggez::GameResult<ggraphics::Font> {
+    let mut file = ggez::filesystem::open(ctx, path)?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    let font = ggraphics::Font::new_glyph_font_bytes(ctx, &bytes)?;
+
+    match FontArc::try_from_vec(bytes) {
+        Ok(outline) => GLYPH_CACHE.with(|cache| {
+            cache.borrow_mut().outlines.insert(font, outline);
+        }),
+        Err(_) => eprintln!("glyph cache: failed to parse font outline -> {}", path),
+    }
+
+    Ok(font)
+}
+
+///
+/// 新しく生成するFontInformationに付ける代替フォントを設定する
+/// 先頭のフォントから順に、文字を持っているかを調べる
+///
+pub fn set_default_fallbacks(fonts: &[ggraphics::Font]) {
+    GLYPH_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        cache.default_fallbacks = [None; MAX_FALLBACK_FONTS];
+        for (slot, font) in cache.default_fallbacks.iter_mut().zip(fonts.iter()) {
+            *slot = Some(*font);
+        }
+    });
+}
+
+pub fn default_fallbacks() -> [Option<ggraphics::Font>; MAX_FALLBACK_FONTS] {
+    GLYPH_CACHE.with(|cache| cache.borrow().default_fallbacks)
+}
+
+///
+/// fontが文字chの字形を持っているかを返す
+///
+pub fn has_glyph(font: ggraphics::Font, ch: char) -> bool {
+    GLYPH_CACHE.with(|cache| cache.borrow_mut().has_glyph(font, ch))
+}
+
+///
+/// fontをscaleの大きさで使った時の寸法を返す
+/// load_fontで読み込まれていないフォントの場合はNone
+///
+pub fn font_metrics(font: ggraphics::Font, scale: numeric::Vector2f) -> Option<FontMetrics> {
+    GLYPH_CACHE.with(|cache| {
+        let cache = cache.borrow();
+        let scaled = cache.outlines.get(&font)?.as_scaled(ab_glyph::PxScale {
+            x: scale.x,
+            y: scale.y,
+        });
+
+        Some(FontMetrics {
+            ascent: scaled.ascent(),
+            descent: scaled.descent(),
+            line_gap: scaled.line_gap(),
+        })
+    })
+}
+
+///
+/// fontをscaleの大きさで使った時の、文字chの寸法を返す
+/// load_fontで読み込まれていないフォントの場合はNone
+///
+pub fn glyph_metrics(
+    font: ggraphics::Font,
+    ch: char,
+    scale: numeric::Vector2f,
+) -> Option<GlyphMetrics> {
+    GLYPH_CACHE.with(|cache| cache.borrow_mut().glyph_metrics(font, ch, scale))
+}
@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+
+use crate::libsuzu::numeric;
+
+///
+/// # 仮想スクリーンをウィンドウへ拡大する方法
+///
+/// ## Stretch
+/// ウィンドウ全体に引き伸ばす。アスペクト比は保たれない
+///
+/// ## Fit
+/// アスペクト比を保ったまま最大まで拡大し、余白は上下(レターボックス)
+/// または左右(ピラーボックス)の黒帯になる
+///
+/// ## IntegerScale
+/// 整数倍でのみ拡大する。ドット絵がにじまない
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ScreenScalingMode {
+    Stretch,
+    Fit,
+    IntegerScale,
+}
+
+impl Default for ScreenScalingMode {
+    fn default() -> Self {
+        ScreenScalingMode::Fit
+    }
+}
+
+impl ScreenScalingMode {
+    ///
+    /// 設定画面で切り替える際の次のモード
+    ///
+    pub fn next(&self) -> Self {
+        match self {
+            ScreenScalingMode::Stretch => ScreenScalingMode::Fit,
+            ScreenScalingMode::Fit => ScreenScalingMode::IntegerScale,
+            ScreenScalingMode::IntegerScale => ScreenScalingMode::Stretch,
+        }
+    }
+
+    pub fn to_str_jp(&self) -> &str {
+        match self {
+            ScreenScalingMode::Stretch => "引き伸ばし",
+            ScreenScalingMode::Fit => "比率維持",
+            ScreenScalingMode::IntegerScale => "整数倍",
+        }
+    }
+}
+
+///
+/// # 固定解像度の仮想スクリーンと実ウィンドウの対応関係
+///
+/// 仮想スクリーンをウィンドウに描画する際のスケールと描画位置を計算し、
+/// ウィンドウ座標を仮想スクリーン座標へ変換する
+///
+#[derive(Debug, Clone)]
+pub struct Viewport {
+    virtual_size: numeric::Vector2f,
+    window_size: numeric::Vector2f,
+    mode: ScreenScalingMode,
+    scale: numeric::Vector2f,
+    offset: numeric::Vector2f,
+}
+
+impl Viewport {
+    pub fn new(
+        virtual_size: numeric::Vector2f,
+        window_size: numeric::Vector2f,
+        mode: ScreenScalingMode,
+    ) -> Self {
+        let mut viewport = Viewport {
+            virtual_size: virtual_size,
+            window_size: window_size,
+            mode: mode,
+            scale: numeric::Vector2f::new(1.0, 1.0),
+            offset: numeric::Vector2f::new(0.0, 0.0),
+        };
+
+        viewport.update_transform();
+        viewport
+    }
+
+    fn update_transform(&mut self) {
+        let ratio_x = self.window_size.x / self.virtual_size.x;
+        let ratio_y = self.window_size.y / self.virtual_size.y;
+
+        let uniform_scale = match self.mode {
+            ScreenScalingMode::Stretch => {
+                self.scale = numeric::Vector2f::new(ratio_x, ratio_y);
+                self.offset = numeric::Vector2f::new(0.0, 0.0);
+                return;
+            }
+            ScreenScalingMode::Fit => ratio_x.min(ratio_y),
+            // ウィンドウが仮想スクリーンより小さい場合は等倍で中央をはみ出させる
+            ScreenScalingMode::IntegerScale => ratio_x.min(ratio_y).floor().max(1.0),
+        };
+
+        self.scale = numeric::Vector2f::new(uniform_scale, uniform_scale);
+        self.offset = numeric::Vector2f::new(
+            ((self.window_size.x - (self.virtual_size.x * uniform_scale)) / 2.0).round(),
+            ((self.window_size.y - (self.virtual_size.y * uniform_scale)) / 2.0).round(),
+        );
+    }
+
+    pub fn resize(&mut self, window_size: numeric::Vector2f) {
+        self.window_size = window_size;
+        self.update_transform();
+    }
+
+    pub fn set_mode(&mut self, mode: ScreenScalingMode) {
+        self.mode = mode;
+        self.update_transform();
+    }
+
+    pub fn get_mode(&self) -> ScreenScalingMode {
+        self.mode
+    }
+
+    ///
+    /// 仮想スクリーンを描画する際のスケール
+    ///
+    pub fn get_scale(&self) -> numeric::Vector2f {
+        self.scale
+    }
+
+    ///
+    /// 仮想スクリーンを描画するウィンドウ上の位置
+    ///
+    pub fn get_position(&self) -> numeric::Point2f {
+        numeric::Point2f::new(self.offset.x, self.offset.y)
+    }
+
+    ///
+    /// ウィンドウ座標を仮想スクリーン座標に変換する
+    ///
+    pub fn window_to_virtual(&self, point: numeric::Point2f) -> numeric::Point2f {
+        numeric::Point2f::new(
+            (point.x - self.offset.x) / self.scale.x,
+            (point.y - self.offset.y) / self.scale.y,
+        )
+    }
+
+    ///
+    /// ウィンドウ上の移動量を仮想スクリーン上の移動量に変換する
+    ///
+    pub fn window_to_virtual_vector(&self, v: numeric::Vector2f) -> numeric::Vector2f {
+        numeric::Vector2f::new(v.x / self.scale.x, v.y / self.scale.y)
+    }
+
+    ///
+    /// 仮想スクリーン座標をウィンドウ座標に変換する
+    ///
+    pub fn virtual_to_window(&self, point: numeric::Point2f) -> numeric::Point2f {
+        numeric::Point2f::new(
+            (point.x * self.scale.x) + self.offset.x,
+            (point.y * self.scale.y) + self.offset.y,
+        )
+    }
+
+    ///
+    /// ウィンドウ全体を仮想スクリーン座標で表した領域を返す
+    /// レターボックスの黒帯の分だけ仮想スクリーンより広く、はみ出して切り取られる分だけ狭くなる
+    /// UIのアンカー配置はこの領域を基準に行う
+    ///
+    pub fn visible_virtual_rect(&self) -> numeric::Rect {
+        let top_left = self.window_to_virtual(numeric::Point2f::new(0.0, 0.0));
+        let bottom_right = self.window_to_virtual(numeric::Point2f::new(
+            self.window_size.x,
+            self.window_size.y,
+        ));
+
+        numeric::Rect::new(
+            top_left.x,
+            top_left.y,
+            (bottom_right.x - top_left.x).max(1.0),
+            (bottom_right.y - top_left.y).max(1.0),
+        )
+    }
+}
+
+///
+/// # UIを画面のどこに固定するか
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    ///
+    /// アンカーの基準点を、領域に対する割合で返す
+    ///
+    pub fn pivot(&self) -> numeric::Vector2f {
+        match self {
+            Anchor::TopLeft => numeric::Vector2f::new(0.0, 0.0),
+            Anchor::Top => numeric::Vector2f::new(0.5, 0.0),
+            Anchor::TopRight => numeric::Vector2f::new(1.0, 0.0),
+            Anchor::Left => numeric::Vector2f::new(0.0, 0.5),
+            Anchor::Center => numeric::Vector2f::new(0.5, 0.5),
+            Anchor::Right => numeric::Vector2f::new(1.0, 0.5),
+            Anchor::BottomLeft => numeric::Vector2f::new(0.0, 1.0),
+            Anchor::Bottom => numeric::Vector2f::new(0.5, 1.0),
+            Anchor::BottomRight => numeric::Vector2f::new(1.0, 1.0),
+        }
+    }
+}
+
+///
+/// # アンカーと余白の組
+///
+/// ## margin
+/// アンカーを付けた辺からの距離。正の値で画面の内側に寄る。
+/// 中央揃えの軸では単純なオフセットとして扱う
+///
+#[derive(Debug, Clone, Copy)]
+pub struct AnchoredLayout {
+    anchor: Anchor,
+    margin: numeric::Vector2f,
+}
+
+impl AnchoredLayout {
+    pub fn new(anchor: Anchor, margin: numeric::Vector2f) -> Self {
+        AnchoredLayout {
+            anchor: anchor,
+            margin: margin,
+        }
+    }
+
+    fn place_axis(begin: f32, length: f32, object_length: f32, pivot: f32, margin: f32) -> f32 {
+        let base = begin + (length * pivot) - (object_length * pivot);
+
+        if pivot >= 1.0 {
+            base - margin
+        } else {
+            base + margin
+        }
+    }
+
+    ///
+    /// area内にsizeの大きさのUIを配置する時の左上座標を返す
+    ///
+    pub fn place(&self, area: numeric::Rect, size: numeric::Vector2f) -> numeric::Point2f {
+        let pivot = self.anchor.pivot();
+
+        numeric::Point2f::new(
+            Self::place_axis(area.x, area.w, size.x, pivot.x, self.margin.x),
+            Self::place_axis(area.y, area.h, size.y, pivot.y, self.margin.y),
+        )
+    }
+
+    pub fn get_anchor(&self) -> Anchor {
+        self.anchor
+    }
+
+    pub fn get_margin(&self) -> numeric::Vector2f {
+        self.margin
+    }
+}
@@ -1,4 +1,6 @@
 pub mod menu;
+pub mod particle;
+pub mod rich_text;
 pub mod shadow;
 pub mod shape;
 pub mod sub_screen;
@@ -6,6 +8,7 @@ pub mod tile_batch;
 
 use super::super::numeric;
 use super::drawable::{Drawable, Node2D, DrawableEss};
+use super::glyph;
 use crate::libsuzu::core::Clock;
 use ggez::graphics as ggraphics;
 use ggez::*;
@@ -645,14 +648,21 @@ impl Transform for Texture {
 /// ### scale
 /// フォントのスケール
 ///
+/// ### fallbacks
+/// fontが文字を持っていない場合に、先頭から順に使う代替フォント
+///
 #[derive(Debug, Clone, Copy)]
 pub struct FontInformation {
     pub font: ggraphics::Font,
     pub scale: numeric::Vector2f,
     pub color: ggraphics::Color,
+    pub fallbacks: [Option<ggraphics::Font>; glyph::MAX_FALLBACK_FONTS],
 }
 
 impl FontInformation {
+    ///
+    /// 代替フォントには、glyph::set_default_fallbacksで設定したものが使われる
+    ///
     pub fn new(
         font: ggraphics::Font,
         scale: numeric::Vector2f,
@@ -662,8 +672,100 @@ impl FontInformation {
             font: font,
             scale: scale,
             color: color,
+            fallbacks: glyph::default_fallbacks(),
         }
     }
+
+    pub fn with_fallbacks(mut self, fonts: &[ggraphics::Font]) -> FontInformation {
+        self.fallbacks = [None; glyph::MAX_FALLBACK_FONTS];
+        for (slot, font) in self.fallbacks.iter_mut().zip(fonts.iter()) {
+            *slot = Some(*font);
+        }
+        self
+    }
+
+    ///
+    /// 文字chを描画するフォントを返す
+    /// どのフォントも文字を持っていない場合は、fontを返す
+    ///
+    pub fn resolve_font(&self, ch: char) -> ggraphics::Font {
+        if ch.is_whitespace() || glyph::has_glyph(self.font, ch) {
+            return self.font;
+        }
+
+        for fallback in self.fallbacks.iter().flatten() {
+            if glyph::has_glyph(*fallback, ch) {
+                return *fallback;
+            }
+        }
+
+        self.font
+    }
+
+    pub fn metrics(&self) -> Option<glyph::FontMetrics> {
+        glyph::font_metrics(self.font, self.scale)
+    }
+
+    pub fn glyph_metrics(&self, ch: char) -> Option<glyph::GlyphMetrics> {
+        glyph::glyph_metrics(self.resolve_font(ch), ch, self.scale)
+    }
+
+    ///
+    /// 文字列を横書きで描画した場合の大きさを、描画せずに求める
+    /// 字形の情報が無いフォントでは、全角文字として概算する
+    ///
+    pub fn measure_str(&self, text: &str) -> numeric::Vector2f {
+        let line_height = match self.metrics() {
+            Some(metrics) => metrics.line_height(),
+            None => self.scale.y,
+        };
+
+        let mut width: f32 = 0.0;
+        let mut lines = 0;
+        for line in text.split('\n') {
+            let line_width = line.chars().fold(0.0, |sum: f32, ch| {
+                sum + match self.glyph_metrics(ch) {
+                    Some(metrics) => metrics.advance,
+                    None => self.scale.x,
+                }
+            });
+            width = width.max(line_width);
+            lines += 1;
+        }
+
+        numeric::Vector2f::new(width, lines as f32 * line_height)
+    }
+
+    ///
+    /// 文字ごとに代替フォントを割り当てたTextを生成する
+    ///
+    pub fn make_text(&self, text: &str) -> ggraphics::Text {
+        let scale = ggraphics::PxScale {
+            x: self.scale.x,
+            y: self.scale.y,
+        };
+
+        let mut ret = ggraphics::Text::default();
+        let mut run = String::new();
+        let mut run_font = self.font;
+
+        for ch in text.chars() {
+            let font = self.resolve_font(ch);
+            if font != run_font && !run.is_empty() {
+                ret.add(ggraphics::TextFragment::new(run.as_str()).font(run_font).scale(scale));
+                run.clear();
+            }
+            run_font = font;
+            run.push(ch);
+        }
+
+        if !run.is_empty() {
+            ret.add(ggraphics::TextFragment::new(run.as_str()).font(run_font).scale(scale));
+        }
+
+        ret.set_font(self.font, scale);
+        ret
+    }
 }
 
 ///
@@ -720,13 +822,7 @@ impl UniText {
     }
 
     fn apply_font_information(&mut self) {
-        self.text.set_font(
-            self.font_info.font,
-            ggraphics::PxScale {
-                x: self.font_info.scale.x,
-                y: self.font_info.scale.y,
-            },
-        );
+        self.text = self.font_info.make_text(&self.text.contents());
         self.draw_param.color = self.font_info.color;
     }
 
@@ -1203,7 +1299,7 @@ impl VerticalText {
         for ch in text.chars() {
             let mut text_fragment = ggraphics::Text::new(ch);
             text_fragment.set_font(
-                font_info.font,
+                font_info.resolve_font(ch),
                 ggraphics::PxScale {
                     x: font_info.scale.x,
                     y: font_info.scale.y,
@@ -1234,7 +1330,7 @@ impl VerticalText {
         for ch in text.chars() {
             let mut text_fragment = ggraphics::Text::new(ch);
             text_fragment.set_font(
-                self.font_info.font,
+                self.font_info.resolve_font(ch),
                 ggraphics::PxScale {
                     x: self.font_info.scale.x,
                     y: self.font_info.scale.y,
@@ -0,0 +1,764 @@
+use std::io::Read;
+
+use ggez::graphics as ggraphics;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::libsuzu::core::Clock;
+use crate::libsuzu::graphics::drawable::*;
+use crate::libsuzu::graphics::object::tile_batch::TileBatch;
+use crate::libsuzu::numeric;
+
+///
+/// # 乱数で値を決める範囲
+///
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct FloatRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl FloatRange {
+    pub fn new(min: f32, max: f32) -> Self {
+        FloatRange { min: min, max: max }
+    }
+
+    pub fn constant(value: f32) -> Self {
+        Self::new(value, value)
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f32 {
+        if self.max > self.min {
+            rng.gen_range(self.min, self.max)
+        } else {
+            self.min
+        }
+    }
+}
+
+impl Default for FloatRange {
+    fn default() -> Self {
+        Self::constant(0.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct CurveKey {
+    pub at: f32,
+    pub value: f32,
+}
+
+///
+/// # 寿命に対する値の変化
+/// atは寿命に対する割合(0.0 ~ 1.0)で、キーの間は線形補間する
+/// データから読み込む場合も、キーはnewでatの順に並べ替えられる
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "Vec<CurveKey>")]
+pub struct ScalarCurve {
+    keys: Vec<CurveKey>,
+}
+
+impl From<Vec<CurveKey>> for ScalarCurve {
+    fn from(keys: Vec<CurveKey>) -> Self {
+        Self::new(keys)
+    }
+}
+
+impl ScalarCurve {
+    pub fn new(mut keys: Vec<CurveKey>) -> Self {
+        // 位置が数でないキーは、どこに置くべきか決まらないので捨てる
+        keys.retain(|key| key.at.is_finite());
+        keys.sort_by(|a, b| a.at.total_cmp(&b.at));
+        ScalarCurve { keys: keys }
+    }
+
+    pub fn constant(value: f32) -> Self {
+        Self::new(vec![CurveKey {
+            at: 0.0,
+            value: value,
+        }])
+    }
+
+    pub fn linear(from: f32, to: f32) -> Self {
+        Self::new(vec![
+            CurveKey {
+                at: 0.0,
+                value: from,
+            },
+            CurveKey { at: 1.0, value: to },
+        ])
+    }
+
+    pub fn evaluate(&self, ratio: f32) -> f32 {
+        let (begin, end) = match curve_segment(&self.keys, |k| k.at, ratio) {
+            Some(segment) => segment,
+            None => return 1.0,
+        };
+
+        let (a, b) = (&self.keys[begin], &self.keys[end]);
+        a.value + (b.value - a.value) * segment_ratio(a.at, b.at, ratio)
+    }
+}
+
+impl Default for ScalarCurve {
+    fn default() -> Self {
+        Self::constant(1.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ColorKey {
+    pub at: f32,
+    pub color: u32,
+}
+
+///
+/// # 寿命に対する色の変化
+/// colorはRGBAの16進数で指定する
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "Vec<ColorKey>")]
+pub struct ColorCurve {
+    keys: Vec<ColorKey>,
+}
+
+impl From<Vec<ColorKey>> for ColorCurve {
+    fn from(keys: Vec<ColorKey>) -> Self {
+        Self::new(keys)
+    }
+}
+
+impl ColorCurve {
+    pub fn new(mut keys: Vec<ColorKey>) -> Self {
+        // 位置が数でないキーは、どこに置くべきか決まらないので捨てる
+        keys.retain(|key| key.at.is_finite());
+        keys.sort_by(|a, b| a.at.total_cmp(&b.at));
+        ColorCurve { keys: keys }
+    }
+
+    pub fn constant(color: u32) -> Self {
+        Self::new(vec![ColorKey {
+            at: 0.0,
+            color: color,
+        }])
+    }
+
+    pub fn evaluate(&self, ratio: f32) -> ggraphics::Color {
+        let (begin, end) = match curve_segment(&self.keys, |k| k.at, ratio) {
+            Some(segment) => segment,
+            None => return ggraphics::Color::WHITE,
+        };
+
+        let a = ggraphics::Color::from_rgba_u32(self.keys[begin].color);
+        let b = ggraphics::Color::from_rgba_u32(self.keys[end].color);
+        let r = segment_ratio(self.keys[begin].at, self.keys[end].at, ratio);
+
+        ggraphics::Color::new(
+            a.r + (b.r - a.r) * r,
+            a.g + (b.g - a.g) * r,
+            a.b + (b.b - a.b) * r,
+            a.a + (b.a - a.a) * r,
+        )
+    }
+}
+
+impl Default for ColorCurve {
+    fn default() -> Self {
+        Self::constant(0xffffffff)
+    }
+}
+
+// ratioを挟む2つのキーの添字を返す。範囲外の場合は端のキーを2つ返す
+fn curve_segment<T, F: Fn(&T) -> f32>(keys: &[T], at: F, ratio: f32) -> Option<(usize, usize)> {
+    if keys.is_empty() {
+        return None;
+    }
+
+    for (i, key) in keys.iter().enumerate() {
+        if ratio < at(key) {
+            return Some((if i == 0 { 0 } else { i - 1 }, i));
+        }
+    }
+
+    Some((keys.len() - 1, keys.len() - 1))
+}
+
+fn segment_ratio(begin: f32, end: f32, ratio: f32) -> f32 {
+    if end > begin {
+        ((ratio - begin) / (end - begin)).max(0.0).min(1.0)
+    } else {
+        0.0
+    }
+}
+
+fn default_max_particles() -> usize {
+    256
+}
+
+fn default_scale() -> FloatRange {
+    FloatRange::constant(1.0)
+}
+
+fn default_frames() -> Vec<[u32; 2]> {
+    vec![[0, 0]]
+}
+
+///
+/// # パーティクルの発生源の定義
+/// TOMLから読み込めるようにしている
+///
+/// ## spawn_rate
+/// 1フレームあたりに発生させる数。1未満の場合は数フレームに1つ発生する
+///
+/// ## burst
+/// 発生開始時に一度に発生させる数
+///
+/// ## duration
+/// 発生させ続けるフレーム数。指定しない場合は止めるまで発生し続ける
+///
+/// ## lifetime
+/// 1つのパーティクルが消えるまでのフレーム数
+///
+/// ## spawn_x, spawn_y
+/// 発生源の位置からの、発生位置のずれ
+///
+/// ## frames
+/// タイルバッチ上のタイルの位置。frame_intervalが0の場合は、発生時にどれか1つを選ぶ
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct ParticleEmitterDef {
+    #[serde(default)]
+    pub spawn_rate: f32,
+    #[serde(default)]
+    pub burst: u32,
+    #[serde(default = "default_max_particles")]
+    pub max_particles: usize,
+    #[serde(default)]
+    pub duration: Option<Clock>,
+    pub lifetime: FloatRange,
+    #[serde(default)]
+    pub spawn_x: FloatRange,
+    #[serde(default)]
+    pub spawn_y: FloatRange,
+    #[serde(default)]
+    pub velocity_x: FloatRange,
+    #[serde(default)]
+    pub velocity_y: FloatRange,
+    #[serde(default)]
+    pub gravity: [f32; 2],
+    #[serde(default)]
+    pub angular_velocity: FloatRange,
+    #[serde(default = "default_scale")]
+    pub scale: FloatRange,
+    #[serde(default)]
+    pub color_curve: ColorCurve,
+    #[serde(default)]
+    pub alpha_curve: ScalarCurve,
+    #[serde(default = "default_frames")]
+    pub frames: Vec<[u32; 2]>,
+    #[serde(default)]
+    pub frame_interval: Clock,
+}
+
+impl ParticleEmitterDef {
+    pub fn from_toml_str(s: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(s)
+    }
+
+    pub fn from_toml_file(ctx: &mut ggez::Context, path: &str) -> Self {
+        let mut file = ggez::filesystem::open(ctx, path).unwrap();
+        let mut buffer = String::new();
+        file.read_to_string(&mut buffer).unwrap();
+
+        match Self::from_toml_str(&buffer) {
+            Ok(def) => def,
+            Err(e) => panic!("Failed to parse particle emitter {}: {}", path, e),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Particle {
+    pub position: numeric::Point2f,
+    pub velocity: numeric::Vector2f,
+    pub rotation: f32,
+    pub angular_velocity: f32,
+    pub scale: f32,
+    age: Clock,
+    lifetime: Clock,
+    frame_offset: usize,
+}
+
+impl Particle {
+    ///
+    /// 寿命に対する経過の割合(0.0 ~ 1.0)
+    ///
+    pub fn life_ratio(&self) -> f32 {
+        if self.lifetime == 0 {
+            1.0
+        } else {
+            self.age as f32 / self.lifetime as f32
+        }
+    }
+}
+
+///
+/// # パーティクルの状態の更新のみを行う構造体
+/// 描画に関わる処理を持たないため、ggez::Context無しで動かせる
+///
+pub struct ParticleSystem {
+    def: ParticleEmitterDef,
+    particles: Vec<Particle>,
+    rng: StdRng,
+    origin: numeric::Point2f,
+    spawn_accumulator: f32,
+    elapsed: Clock,
+    emitting: bool,
+}
+
+impl ParticleSystem {
+    pub fn new(def: ParticleEmitterDef, origin: numeric::Point2f, seed: u64) -> Self {
+        let mut system = ParticleSystem {
+            def: def,
+            particles: Vec::new(),
+            rng: StdRng::seed_from_u64(seed),
+            origin: origin,
+            spawn_accumulator: 0.0,
+            elapsed: 0,
+            emitting: true,
+        };
+
+        let burst = system.def.burst;
+        system.burst(burst);
+        system
+    }
+
+    pub fn set_origin(&mut self, origin: numeric::Point2f) {
+        self.origin = origin;
+    }
+
+    pub fn get_origin(&self) -> numeric::Point2f {
+        self.origin
+    }
+
+    ///
+    /// 一度にcount個のパーティクルを発生させる
+    ///
+    pub fn burst(&mut self, count: u32) {
+        for _ in 0..count {
+            self.spawn();
+        }
+    }
+
+    ///
+    /// 新しいパーティクルの発生を止める。発生済みのものは寿命まで残る
+    ///
+    pub fn stop(&mut self) {
+        self.emitting = false;
+    }
+
+    pub fn restart(&mut self) {
+        self.elapsed = 0;
+        self.spawn_accumulator = 0.0;
+        self.emitting = true;
+
+        let burst = self.def.burst;
+        self.burst(burst);
+    }
+
+    pub fn is_emitting(&self) -> bool {
+        self.emitting
+    }
+
+    ///
+    /// 発生が止まり、全てのパーティクルが消えたか
+    ///
+    pub fn is_finished(&self) -> bool {
+        !self.emitting && self.particles.is_empty()
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn get_def(&self) -> &ParticleEmitterDef {
+        &self.def
+    }
+
+    fn spawn(&mut self) {
+        if self.particles.len() >= self.def.max_particles {
+            return;
+        }
+
+        let frame_offset = if self.def.frames.is_empty() {
+            0
+        } else {
+            self.rng.gen_range(0, self.def.frames.len())
+        };
+
+        let particle = Particle {
+            position: numeric::Point2f::new(
+                self.origin.x + self.def.spawn_x.sample(&mut self.rng),
+                self.origin.y + self.def.spawn_y.sample(&mut self.rng),
+            ),
+            velocity: numeric::Vector2f::new(
+                self.def.velocity_x.sample(&mut self.rng),
+                self.def.velocity_y.sample(&mut self.rng),
+            ),
+            rotation: 0.0,
+            angular_velocity: self.def.angular_velocity.sample(&mut self.rng),
+            scale: self.def.scale.sample(&mut self.rng),
+            age: 0,
+            lifetime: self.def.lifetime.sample(&mut self.rng).max(1.0) as Clock,
+            frame_offset: frame_offset,
+        };
+
+        self.particles.push(particle);
+    }
+
+    ///
+    /// 1フレーム分、状態を進める
+    ///
+    pub fn step(&mut self) {
+        self.elapsed += 1;
+
+        let gravity = numeric::Vector2f::new(self.def.gravity[0], self.def.gravity[1]);
+        for particle in self.particles.iter_mut() {
+            particle.age += 1;
+            particle.velocity += gravity;
+            particle.position += particle.velocity;
+            particle.rotation += particle.angular_velocity;
+        }
+
+        self.particles.retain(|p| p.age < p.lifetime);
+
+        if let Some(duration) = self.def.duration {
+            if self.elapsed > duration {
+                self.emitting = false;
+            }
+        }
+
+        if self.emitting {
+            self.spawn_accumulator += self.def.spawn_rate;
+            while self.spawn_accumulator >= 1.0 {
+                self.spawn();
+                self.spawn_accumulator -= 1.0;
+            }
+        }
+    }
+
+    pub fn particle_color(&self, particle: &Particle) -> ggraphics::Color {
+        let ratio = particle.life_ratio();
+        let mut color = self.def.color_curve.evaluate(ratio);
+        color.a *= self.def.alpha_curve.evaluate(ratio);
+        color
+    }
+
+    ///
+    /// パーティクルが現在表示するタイルの位置
+    ///
+    pub fn particle_frame(&self, particle: &Particle) -> numeric::Vector2u {
+        if self.def.frames.is_empty() {
+            return numeric::Vector2u::new(0, 0);
+        }
+
+        let index = if self.def.frame_interval == 0 {
+            particle.frame_offset
+        } else {
+            (particle.frame_offset + (particle.age / self.def.frame_interval) as usize)
+                % self.def.frames.len()
+        };
+
+        let frame = self.def.frames[index];
+        numeric::Vector2u::new(frame[0], frame[1])
+    }
+}
+
+///
+/// # パーティクルを描画するオブジェクト
+/// 全てのパーティクルを1つのスプライトバッチで描画する
+///
+pub struct ParticleEmitter {
+    system: ParticleSystem,
+    batch: TileBatch,
+    last_update: Clock,
+    drwob_essential: DrawableEss,
+}
+
+impl ParticleEmitter {
+    ///
+    /// batchのタイルが、パーティクルの各フレームになる
+    ///
+    pub fn new(
+        batch: TileBatch,
+        def: ParticleEmitterDef,
+        pos: numeric::Point2f,
+        depth: i8,
+        t: Clock,
+    ) -> Self {
+        let mut emitter = ParticleEmitter {
+            system: ParticleSystem::new(def, pos, rand::random::<u64>()),
+            batch: batch,
+            last_update: t,
+            drwob_essential: DrawableEss::new(true, depth),
+        };
+
+        emitter.batch.set_position(numeric::Point2f::new(0.0, 0.0));
+        emitter.rebuild_batch();
+        emitter
+    }
+
+    pub fn get_system(&self) -> &ParticleSystem {
+        &self.system
+    }
+
+    pub fn get_system_mut(&mut self) -> &mut ParticleSystem {
+        &mut self.system
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.system.is_finished()
+    }
+
+    fn rebuild_batch(&mut self) {
+        self.batch.clear_batch();
+
+        for particle in self.system.particles() {
+            self.batch.add_batch_tile_position_rotated(
+                self.system.particle_frame(particle),
+                particle.position,
+                numeric::Vector2f::new(particle.scale, particle.scale),
+                particle.rotation,
+                self.system.particle_color(particle),
+            );
+        }
+    }
+
+    ///
+    /// 前回の更新からの経過フレーム分だけ状態を進める
+    /// 処理落ちで大きく時間が飛んだ場合は、一度に進めるフレーム数を制限する
+    ///
+    pub fn update(&mut self, t: Clock) {
+        const MAX_STEPS: Clock = 10;
+
+        let steps = t.saturating_sub(self.last_update).min(MAX_STEPS);
+        self.last_update = t;
+
+        if steps == 0 {
+            return;
+        }
+
+        for _ in 0..steps {
+            self.system.step();
+        }
+
+        self.rebuild_batch();
+    }
+}
+
+impl Drawable for ParticleEmitter {
+    fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult<()> {
+        if self.is_visible() {
+            self.batch.draw(ctx)?;
+        }
+
+        Ok(())
+    }
+
+    fn hide(&mut self) {
+        self.drwob_essential.visible = false;
+    }
+
+    fn appear(&mut self) {
+        self.drwob_essential.visible = true;
+    }
+
+    fn is_visible(&self) -> bool {
+        self.drwob_essential.visible
+    }
+
+    fn set_drawing_depth(&mut self, depth: i8) {
+        self.drwob_essential.drawing_depth = depth;
+    }
+
+    fn get_drawing_depth(&self) -> i8 {
+        self.drwob_essential.drawing_depth
+    }
+}
+
+impl Node2D for ParticleEmitter {
+    ///
+    /// 発生源の位置を動かす。発生済みのパーティクルは動かない
+    ///
+    fn set_position(&mut self, pos: numeric::Point2f) {
+        self.system.set_origin(pos);
+    }
+
+    fn get_position(&self) -> numeric::Point2f {
+        self.system.get_origin()
+    }
+
+    fn move_diff(&mut self, offset: numeric::Vector2f) {
+        let p = self.system.get_origin();
+        self.system.set_origin(p + offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system_from_toml(s: &str, origin: numeric::Point2f) -> ParticleSystem {
+        ParticleSystem::new(ParticleEmitterDef::from_toml_str(s).unwrap(), origin, 1)
+    }
+
+    fn approx_eq(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1.0e-4
+    }
+
+    #[test]
+    fn scalar_curve_interpolates_and_clamps() {
+        let curve = ScalarCurve::linear(1.0, 0.0);
+
+        assert!(approx_eq(curve.evaluate(0.0), 1.0));
+        assert!(approx_eq(curve.evaluate(0.25), 0.75));
+        assert!(approx_eq(curve.evaluate(1.0), 0.0));
+        assert!(approx_eq(curve.evaluate(1.5), 0.0));
+        assert!(approx_eq(ScalarCurve::constant(0.3).evaluate(0.7), 0.3));
+    }
+
+    #[test]
+    fn curves_from_data_are_sorted() {
+        let def = ParticleEmitterDef::from_toml_str(
+            r#"
+            lifetime = { min = 10.0, max = 10.0 }
+            alpha_curve = [{ at = 1.0, value = 0.0 }, { at = 0.0, value = 1.0 }]
+            color_curve = [{ at = 1.0, color = 0xffffffff }, { at = 0.0, color = 0x000000ff }]
+            "#,
+        )
+        .unwrap();
+
+        assert!(approx_eq(def.alpha_curve.evaluate(0.5), 0.5));
+        assert!(approx_eq(def.color_curve.evaluate(0.5).r, 0.5));
+        assert!(approx_eq(def.color_curve.evaluate(0.0).r, 0.0));
+    }
+
+    #[test]
+    fn non_finite_curve_keys_are_dropped() {
+        let curve = ScalarCurve::new(vec![
+            CurveKey {
+                at: f32::NAN,
+                value: 5.0,
+            },
+            CurveKey {
+                at: 0.0,
+                value: 1.0,
+            },
+            CurveKey {
+                at: 1.0,
+                value: 0.0,
+            },
+        ]);
+
+        assert!(approx_eq(curve.evaluate(0.5), 0.5));
+    }
+
+    #[test]
+    fn burst_and_spawn_rate() {
+        let mut system = system_from_toml(
+            r#"
+            spawn_rate = 0.5
+            burst = 3
+            lifetime = { min = 10.0, max = 10.0 }
+            "#,
+            numeric::Point2f::new(0.0, 0.0),
+        );
+        assert_eq!(system.particles().len(), 3);
+
+        system.step();
+        assert_eq!(system.particles().len(), 3);
+
+        system.step();
+        assert_eq!(system.particles().len(), 4);
+    }
+
+    #[test]
+    fn max_particles_limits_spawn() {
+        let system = system_from_toml(
+            r#"
+            burst = 10
+            max_particles = 4
+            lifetime = { min = 10.0, max = 10.0 }
+            "#,
+            numeric::Point2f::new(0.0, 0.0),
+        );
+
+        assert_eq!(system.particles().len(), 4);
+    }
+
+    #[test]
+    fn velocity_and_gravity_move_particles() {
+        let mut system = system_from_toml(
+            r#"
+            burst = 1
+            lifetime = { min = 100.0, max = 100.0 }
+            velocity_x = { min = 1.0, max = 1.0 }
+            gravity = [0.0, 2.0]
+            "#,
+            numeric::Point2f::new(10.0, 20.0),
+        );
+
+        system.step();
+        system.step();
+
+        let particle = &system.particles()[0];
+        assert!(approx_eq(particle.position.x, 12.0));
+        assert!(approx_eq(particle.position.y, 26.0));
+        assert!(approx_eq(particle.velocity.y, 4.0));
+    }
+
+    #[test]
+    fn alpha_follows_life_ratio() {
+        let mut system = system_from_toml(
+            r#"
+            burst = 1
+            lifetime = { min = 4.0, max = 4.0 }
+            alpha_curve = [{ at = 0.0, value = 1.0 }, { at = 1.0, value = 0.0 }]
+            "#,
+            numeric::Point2f::new(0.0, 0.0),
+        );
+
+        system.step();
+        system.step();
+
+        let particle = system.particles()[0].clone();
+        assert!(approx_eq(particle.life_ratio(), 0.5));
+        assert!(approx_eq(system.particle_color(&particle).a, 0.5));
+    }
+
+    #[test]
+    fn particles_die_and_emitter_finishes() {
+        let mut system = system_from_toml(
+            r#"
+            spawn_rate = 1.0
+            duration = 2
+            lifetime = { min = 2.0, max = 2.0 }
+            "#,
+            numeric::Point2f::new(0.0, 0.0),
+        );
+
+        system.step();
+        system.step();
+        assert_eq!(system.particles().len(), 2);
+        assert!(system.is_emitting());
+
+        system.step();
+        assert_eq!(system.particles().len(), 1);
+        assert!(!system.is_emitting());
+
+        system.step();
+        assert!(system.is_finished());
+    }
+}
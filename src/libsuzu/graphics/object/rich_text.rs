@@ -0,0 +1,1508 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use ggez::graphics as ggraphics;
+
+use crate::libsuzu::core::Clock;
+use crate::libsuzu::graphics::drawable::*;
+use crate::libsuzu::graphics::glyph;
+use crate::libsuzu::graphics::object::*;
+use crate::libsuzu::graphics::text_layout::*;
+use crate::libsuzu::numeric;
+
+use crate::{
+    get_param_offset, get_param_pos, get_param_rotation, get_param_scale, mintp, mintp_new, mintv,
+    mintv_new, move_param, set_param_offset, set_param_pos, set_param_rotation, set_param_scale,
+};
+
+///
+/// # 文字の動き
+///
+/// ## Shake
+/// 細かく震える
+///
+/// ## Wave
+/// 文字ごとに位相をずらして上下に揺れる
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextMotion {
+    Still,
+    Shake,
+    Wave,
+}
+
+///
+/// # マークアップで指定された文字の装飾
+/// Noneのものは、描画時にFontInformationの値が使われる
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextStyle {
+    pub color: Option<ggraphics::Color>,
+    pub size: Option<f32>,
+    pub font: Option<String>,
+    pub emphasis: bool,
+    pub motion: TextMotion,
+}
+
+impl TextStyle {
+    pub fn new() -> Self {
+        TextStyle {
+            color: None,
+            size: None,
+            font: None,
+            emphasis: false,
+            motion: TextMotion::Still,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RichTextElement {
+    Run {
+        text: String,
+        style: TextStyle,
+        ruby: Option<String>,
+    },
+    Icon {
+        name: String,
+        style: TextStyle,
+    },
+    NewLine,
+}
+
+impl RichTextElement {
+    ///
+    /// 文字送りで数える文字数。改行とアイコンも1文字と数える
+    ///
+    fn glyph_len(&self) -> usize {
+        match self {
+            RichTextElement::Run { text, .. } => text.chars().count(),
+            RichTextElement::Icon { .. } => 1,
+            RichTextElement::NewLine => 1,
+        }
+    }
+}
+
+///
+/// # マークアップを解析した結果
+///
+/// ## 書式
+/// - `[color=ff0000ff]赤[/color]` RGBAの16進数で色を指定
+/// - `[size=40]大[/size]` 文字の大きさ
+/// - `[font=JpFude1]筆[/font]` FontIDの名前でフォントを指定
+/// - `[ruby=すずなあん]鈴奈庵[/ruby]` ルビ
+/// - `[em]強調[/em]` 傍点
+/// - `[shake]震[/shake]` `[wave]波[/wave]` 文字の動き
+/// - `[icon=TextureID]` 文字の大きさのアイコンを埋め込む
+/// - `[[` で `[` そのもの
+///
+/// 解釈できないタグは、そのまま文字列として扱う
+///
+#[derive(Debug, Clone)]
+pub struct RichTextMarkup {
+    elements: Vec<RichTextElement>,
+}
+
+enum StyleTag {
+    Color,
+    Size,
+    Font,
+    Emphasis,
+    Shake,
+    Wave,
+}
+
+impl StyleTag {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "color" => Some(StyleTag::Color),
+            "size" => Some(StyleTag::Size),
+            "font" => Some(StyleTag::Font),
+            "em" => Some(StyleTag::Emphasis),
+            "shake" => Some(StyleTag::Shake),
+            "wave" => Some(StyleTag::Wave),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            StyleTag::Color => "color",
+            StyleTag::Size => "size",
+            StyleTag::Font => "font",
+            StyleTag::Emphasis => "em",
+            StyleTag::Shake => "shake",
+            StyleTag::Wave => "wave",
+        }
+    }
+
+    ///
+    /// タグを現在のスタイルに適用する。値が不正な場合はNoneを返す
+    ///
+    fn apply(&self, style: &TextStyle, value: Option<&str>) -> Option<TextStyle> {
+        let mut next = style.clone();
+
+        match self {
+            StyleTag::Color => {
+                let code = u32::from_str_radix(value?.trim_start_matches('#'), 16).ok()?;
+                next.color = Some(ggraphics::Color::from_rgba_u32(code));
+            }
+            StyleTag::Size => next.size = Some(value?.parse::<f32>().ok()?),
+            StyleTag::Font => next.font = Some(value?.to_string()),
+            StyleTag::Emphasis => next.emphasis = true,
+            StyleTag::Shake => next.motion = TextMotion::Shake,
+            StyleTag::Wave => next.motion = TextMotion::Wave,
+        }
+
+        Some(next)
+    }
+}
+
+struct MarkupParser {
+    elements: Vec<RichTextElement>,
+    buffer: String,
+    style_stack: Vec<(String, TextStyle)>,
+}
+
+impl MarkupParser {
+    fn new() -> Self {
+        MarkupParser {
+            elements: Vec::new(),
+            buffer: String::new(),
+            style_stack: Vec::new(),
+        }
+    }
+
+    fn current_style(&self) -> TextStyle {
+        match self.style_stack.last() {
+            Some((_, style)) => style.clone(),
+            None => TextStyle::new(),
+        }
+    }
+
+    fn flush(&mut self) {
+        if !self.buffer.is_empty() {
+            let text = std::mem::replace(&mut self.buffer, String::new());
+            self.elements.push(RichTextElement::Run {
+                text: text,
+                style: self.current_style(),
+                ruby: None,
+            });
+        }
+    }
+
+    fn push_char(&mut self, ch: char) {
+        if ch == '\n' {
+            self.flush();
+            self.elements.push(RichTextElement::NewLine);
+        } else {
+            self.buffer.push(ch);
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        for ch in s.chars() {
+            self.push_char(ch);
+        }
+    }
+
+    ///
+    /// タグを処理する。解釈できた場合はtrueを返す
+    /// restは、タグの直後から始まる未処理の文字列で、ルビの親文字の読み取りに使う
+    ///
+    fn handle_tag<'a>(&mut self, tag: &str, rest: &mut &'a str) -> bool {
+        if let Some(name) = tag.strip_prefix('/') {
+            let matched = match self.style_stack.last() {
+                Some((top, _)) => top == name,
+                None => false,
+            };
+
+            if matched {
+                self.flush();
+                self.style_stack.pop();
+            }
+
+            return matched;
+        }
+
+        let (name, value) = match tag.find('=') {
+            Some(index) => (&tag[..index], Some(&tag[index + 1..])),
+            None => (tag, None),
+        };
+
+        match name {
+            "icon" => match value {
+                Some(icon_name) => {
+                    self.flush();
+                    self.elements.push(RichTextElement::Icon {
+                        name: icon_name.to_string(),
+                        style: self.current_style(),
+                    });
+                    true
+                }
+                None => false,
+            },
+            "ruby" => {
+                // ルビは入れ子のタグを許さず、[/ruby]までを親文字とする
+                let src: &'a str = *rest;
+                let (ruby, end) = match (value, src.find("[/ruby]")) {
+                    (Some(ruby), Some(end)) => (ruby, end),
+                    _ => return false,
+                };
+
+                self.flush();
+                self.elements.push(RichTextElement::Run {
+                    text: src[..end].to_string(),
+                    style: self.current_style(),
+                    ruby: Some(ruby.to_string()),
+                });
+                *rest = &src[end + "[/ruby]".len()..];
+                true
+            }
+            _ => match StyleTag::from_name(name) {
+                Some(style_tag) => match style_tag.apply(&self.current_style(), value) {
+                    Some(next_style) => {
+                        self.flush();
+                        self.style_stack
+                            .push((style_tag.name().to_string(), next_style));
+                        true
+                    }
+                    None => false,
+                },
+                None => false,
+            },
+        }
+    }
+
+    fn parse(mut self, src: &str) -> Vec<RichTextElement> {
+        let mut rest = src;
+
+        while let Some(ch) = rest.chars().next() {
+            rest = &rest[ch.len_utf8()..];
+
+            if ch != '[' {
+                self.push_char(ch);
+                continue;
+            }
+
+            if rest.starts_with('[') {
+                rest = &rest[1..];
+                self.push_char('[');
+                continue;
+            }
+
+            match rest.find(']') {
+                Some(end) => {
+                    let tag = &rest[..end];
+                    let mut after_tag = &rest[end + 1..];
+
+                    if self.handle_tag(tag, &mut after_tag) {
+                        rest = after_tag;
+                    } else {
+                        self.push_char('[');
+                    }
+                }
+                None => self.push_char('['),
+            }
+        }
+
+        // 閉じられていないタグは、文字列の終わりで閉じる
+        self.flush();
+        self.elements
+    }
+}
+
+impl RichTextMarkup {
+    pub fn parse(src: &str) -> Self {
+        RichTextMarkup {
+            elements: MarkupParser::new().parse(src),
+        }
+    }
+
+    ///
+    /// マークアップとして解釈せず、そのままの文字列として扱う
+    ///
+    pub fn plain(src: &str) -> Self {
+        let mut parser = MarkupParser::new();
+        parser.push_str(src);
+        parser.flush();
+
+        RichTextMarkup {
+            elements: parser.elements,
+        }
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<RichTextElement> {
+        self.elements.iter()
+    }
+
+    ///
+    /// タグを除いた文字数
+    ///
+    pub fn glyph_len(&self) -> usize {
+        self.elements.iter().fold(0, |sum, e| sum + e.glyph_len())
+    }
+
+    ///
+    /// タグを除いた文字列
+    ///
+    pub fn plain_text(&self) -> String {
+        let mut s = String::new();
+
+        for element in self.elements.iter() {
+            match element {
+                RichTextElement::Run { text, .. } => s.push_str(text),
+                RichTextElement::Icon { .. } => (),
+                RichTextElement::NewLine => s.push('\n'),
+            }
+        }
+
+        s
+    }
+
+    ///
+    /// 先頭からlength文字分を切り出す。ルビは親文字と一緒に切り出される
+    ///
+    pub fn slice(&self, length: usize) -> Self {
+        let mut remain = length;
+        let mut elements = Vec::new();
+
+        for element in self.elements.iter() {
+            if remain == 0 {
+                break;
+            }
+
+            let len = element.glyph_len();
+            if len <= remain {
+                elements.push(element.clone());
+                remain -= len;
+                continue;
+            }
+
+            if let RichTextElement::Run { text, style, ruby } = element {
+                elements.push(RichTextElement::Run {
+                    text: text.chars().take(remain).collect(),
+                    style: style.clone(),
+                    ruby: ruby.clone(),
+                });
+            }
+            break;
+        }
+
+        RichTextMarkup { elements: elements }
+    }
+
+    ///
+    /// begin文字目からend文字目の手前までを切り出す
+    /// ルビは親文字の一部でも範囲に含まれれば、親文字全体を含める
+    ///
+    pub fn sub_markup(&self, begin: usize, end: usize) -> Self {
+        let mut position = 0;
+        let mut elements = Vec::new();
+
+        for element in self.elements.iter() {
+            let len = element.glyph_len();
+            let element_begin = position;
+            position += len;
+
+            if position <= begin || element_begin >= end {
+                continue;
+            }
+
+            match element {
+                RichTextElement::Run {
+                    text,
+                    style,
+                    ruby: None,
+                } => {
+                    let skip = begin.saturating_sub(element_begin);
+                    let take = end.min(position) - element_begin - skip;
+                    elements.push(RichTextElement::Run {
+                        text: text.chars().skip(skip).take(take).collect(),
+                        style: style.clone(),
+                        ruby: None,
+                    });
+                }
+                _ => elements.push(element.clone()),
+            }
+        }
+
+        RichTextMarkup { elements: elements }
+    }
+
+    ///
+    /// 改行で分割する
+    ///
+    pub fn lines(&self) -> Vec<Self> {
+        let mut lines = vec![RichTextMarkup {
+            elements: Vec::new(),
+        }];
+
+        for element in self.elements.iter() {
+            match element {
+                RichTextElement::NewLine => lines.push(RichTextMarkup {
+                    elements: Vec::new(),
+                }),
+                _ => lines.last_mut().unwrap().elements.push(element.clone()),
+            }
+        }
+
+        lines
+    }
+
+    pub fn has_motion(&self) -> bool {
+        self.elements.iter().any(|e| match e {
+            RichTextElement::Run { style, .. } | RichTextElement::Icon { style, .. } => {
+                style.motion != TextMotion::Still
+            }
+            RichTextElement::NewLine => false,
+        })
+    }
+}
+
+///
+/// # マークアップ中の名前からフォントやアイコンを引くためのトレイト
+///
+pub trait RichTextResource {
+    fn get_font_by_name(&self, name: &str) -> Option<ggraphics::Font>;
+    fn get_icon_by_name(&mut self, ctx: &mut ggez::Context, name: &str)
+        -> Option<ggraphics::Image>;
+}
+
+///
+/// # マークアップが参照するフォントとアイコンの実体
+///
+#[derive(Clone)]
+pub struct RichTextAssets {
+    fonts: HashMap<String, ggraphics::Font>,
+    icons: HashMap<String, ggraphics::Image>,
+}
+
+impl RichTextAssets {
+    pub fn new_empty() -> Self {
+        RichTextAssets {
+            fonts: HashMap::new(),
+            icons: HashMap::new(),
+        }
+    }
+
+    pub fn resolve(
+        ctx: &mut ggez::Context,
+        resource: &mut dyn RichTextResource,
+        markup: &RichTextMarkup,
+    ) -> Self {
+        let mut assets = Self::new_empty();
+
+        for element in markup.iter() {
+            let (style, icon_name) = match element {
+                RichTextElement::Run { style, .. } => (style, None),
+                RichTextElement::Icon { name, style } => (style, Some(name)),
+                RichTextElement::NewLine => continue,
+            };
+
+            if let Some(font_name) = style.font.as_ref() {
+                if !assets.fonts.contains_key(font_name) {
+                    match resource.get_font_by_name(font_name) {
+                        Some(font) => {
+                            assets.fonts.insert(font_name.to_string(), font);
+                        }
+                        None => eprintln!("RichText: unknown font -> {}", font_name),
+                    }
+                }
+            }
+
+            if let Some(icon_name) = icon_name {
+                if !assets.icons.contains_key(icon_name) {
+                    match resource.get_icon_by_name(ctx, icon_name) {
+                        Some(icon) => {
+                            assets.icons.insert(icon_name.to_string(), icon);
+                        }
+                        None => eprintln!("RichText: unknown icon -> {}", icon_name),
+                    }
+                }
+            }
+        }
+
+        assets
+    }
+}
+
+#[derive(Clone)]
+enum GlyphBody {
+    Text(ggraphics::Text),
+    Icon(ggraphics::Image),
+}
+
+#[derive(Clone)]
+struct LaidOutGlyph {
+    body: GlyphBody,
+    offset: numeric::Point2f,
+    size: f32,
+    color: Option<ggraphics::Color>,
+    motion: TextMotion,
+    index: usize,
+}
+
+#[derive(Clone)]
+struct RichTextLayout {
+    glyphs: Vec<LaidOutGlyph>,
+    size: numeric::Vector2f,
+}
+
+// レイアウト前の1文字分の情報
+struct PendingGlyph {
+    body: GlyphBody,
+    ch: Option<char>,
+    advance: f32,
+    size: f32,
+    color: Option<ggraphics::Color>,
+    motion: TextMotion,
+    emphasis: bool,
+    index: usize,
+}
+
+// ルビ1つ分の情報。親文字の範囲をglyphsの添字で保持する
+struct PendingRuby {
+    glyphs: Vec<PendingGlyph>,
+    base_begin: usize,
+    base_end: usize,
+}
+
+// paragraph_endは、改行文字または文字列の終わりで終わる行かどうか
+struct PendingLine {
+    glyphs: Vec<PendingGlyph>,
+    rubies: Vec<PendingRuby>,
+    paragraph_end: bool,
+}
+
+impl PendingLine {
+    fn new() -> Self {
+        PendingLine {
+            glyphs: Vec::new(),
+            rubies: Vec::new(),
+            paragraph_end: true,
+        }
+    }
+
+    fn layout_units(&self) -> Vec<LayoutUnit> {
+        let mut units: Vec<LayoutUnit> = self
+            .glyphs
+            .iter()
+            .map(|g| LayoutUnit::new(g.ch, g.advance, false))
+            .collect();
+
+        // ルビの親文字の途中では改行しない
+        for ruby in self.rubies.iter() {
+            for unit in units[ruby.base_begin..ruby.base_end.saturating_sub(1).max(ruby.base_begin)]
+                .iter_mut()
+            {
+                unit.glue = true;
+            }
+        }
+
+        units
+    }
+
+    ///
+    /// max_extentに収まるように、複数の行に分割する
+    ///
+    fn wrap(self, max_extent: f32) -> Vec<PendingLine> {
+        let ranges = TextLayoutEngine::break_lines(&self.layout_units(), max_extent);
+        let paragraph_end = self.paragraph_end;
+        let mut rubies = self.rubies;
+        let mut glyphs = self.glyphs.into_iter().enumerate().peekable();
+        let mut lines = Vec::with_capacity(ranges.len());
+        let last = ranges.len() - 1;
+
+        for (i, range) in ranges.into_iter().enumerate() {
+            let mut line = PendingLine::new();
+            line.paragraph_end = paragraph_end && i == last;
+
+            // 行頭で詰めた空白は捨てる
+            while let Some((index, glyph)) = glyphs.next_if(|(index, _)| *index < range.end) {
+                if index >= range.start {
+                    line.glyphs.push(glyph);
+                }
+            }
+
+            let (inner, outer): (Vec<PendingRuby>, Vec<PendingRuby>) = rubies
+                .into_iter()
+                .partition(|r| range.start <= r.base_begin && r.base_begin < range.end);
+            rubies = outer;
+
+            for mut ruby in inner {
+                ruby.base_begin -= range.start;
+                ruby.base_end = ruby.base_end.min(range.end) - range.start;
+                line.rubies.push(ruby);
+            }
+
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    fn core_size(&self, default_size: f32) -> f32 {
+        self.glyphs
+            .iter()
+            .fold(0.0, |max: f32, g| max.max(g.size))
+            .max(if self.glyphs.is_empty() {
+                default_size
+            } else {
+                0.0
+            })
+    }
+
+    // ルビや傍点のために確保する幅
+    fn reserve_size(&self) -> f32 {
+        let ruby_reserve = self
+            .rubies
+            .iter()
+            .flat_map(|r| r.glyphs.iter())
+            .fold(0.0, |max: f32, g| max.max(g.size));
+        let emphasis_reserve = self
+            .glyphs
+            .iter()
+            .filter(|g| g.emphasis)
+            .fold(0.0, |max: f32, g| {
+                max.max(g.size * RichText::EMPHASIS_RATIO)
+            });
+
+        ruby_reserve.max(emphasis_reserve)
+    }
+}
+
+///
+/// # 装飾付きのテキスト
+///
+/// 文字ごとに色、大きさ、フォント、ルビ、傍点、動きを変えられる。
+/// 横書きと縦書きに対応する
+///
+#[derive(Clone)]
+pub struct RichText {
+    markup: RichTextMarkup,
+    assets: RichTextAssets,
+    font_info: FontInformation,
+    direction: TextDirection,
+    layout_option: TextLayoutOption,
+    layout: RefCell<Option<RichTextLayout>>,
+    motion_clock: Clock,
+    drwob_essential: DrawableEss,
+    draw_param: ggraphics::DrawParam,
+}
+
+impl RichText {
+    const RUBY_RATIO: f32 = 0.5;
+    const EMPHASIS_RATIO: f32 = 0.4;
+
+    pub fn new(
+        markup: RichTextMarkup,
+        assets: RichTextAssets,
+        pos: numeric::Point2f,
+        scale: numeric::Vector2f,
+        rotation: f32,
+        drawing_depth: i8,
+        font_info: FontInformation,
+        direction: TextDirection,
+    ) -> Self {
+        let param = ggraphics::DrawParam::new()
+            .dest(mintp!(pos))
+            .scale(mintv!(scale))
+            .rotation(rotation)
+            .color(font_info.color);
+
+        RichText {
+            markup: markup,
+            assets: assets,
+            font_info: font_info,
+            direction: direction,
+            layout_option: TextLayoutOption::no_wrap(),
+            layout: RefCell::new(None),
+            motion_clock: 0,
+            drwob_essential: DrawableEss::new(true, drawing_depth),
+            draw_param: param,
+        }
+    }
+
+    ///
+    /// マークアップを解釈しない、装飾無しのテキストを生成する
+    ///
+    pub fn new_plain(
+        text: &str,
+        pos: numeric::Point2f,
+        scale: numeric::Vector2f,
+        rotation: f32,
+        drawing_depth: i8,
+        font_info: FontInformation,
+        direction: TextDirection,
+    ) -> Self {
+        Self::new(
+            RichTextMarkup::plain(text),
+            RichTextAssets::new_empty(),
+            pos,
+            scale,
+            rotation,
+            drawing_depth,
+            font_info,
+            direction,
+        )
+    }
+
+    ///
+    /// マークアップ文字列を解析し、必要なフォントとアイコンを読み込んで生成する
+    ///
+    pub fn from_markup(
+        ctx: &mut ggez::Context,
+        resource: &mut dyn RichTextResource,
+        src: &str,
+        pos: numeric::Point2f,
+        drawing_depth: i8,
+        font_info: FontInformation,
+        direction: TextDirection,
+    ) -> Self {
+        let markup = RichTextMarkup::parse(src);
+        let assets = RichTextAssets::resolve(ctx, resource, &markup);
+
+        Self::new(
+            markup,
+            assets,
+            pos,
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            drawing_depth,
+            font_info,
+            direction,
+        )
+    }
+
+    pub fn replace_markup(&mut self, markup: RichTextMarkup, assets: RichTextAssets) {
+        self.markup = markup;
+        self.assets = assets;
+        self.layout.replace(None);
+    }
+
+    pub fn get_markup(&self) -> &RichTextMarkup {
+        &self.markup
+    }
+
+    pub fn get_text(&self) -> String {
+        self.markup.plain_text()
+    }
+
+    pub fn get_font_scale(&self) -> numeric::Vector2f {
+        self.font_info.scale
+    }
+
+    pub fn set_direction(&mut self, direction: TextDirection) {
+        self.direction = direction;
+        self.layout.replace(None);
+    }
+
+    ///
+    /// 折り返しと揃えの設定を変更する
+    ///
+    pub fn set_layout_option(&mut self, option: TextLayoutOption) {
+        self.layout_option = option;
+        self.layout.replace(None);
+    }
+
+    pub fn get_layout_option(&self) -> &TextLayoutOption {
+        &self.layout_option
+    }
+
+    ///
+    /// 描画を待たずにレイアウトを済ませる。複製したRichTextは、済ませたレイアウトを引き継ぐ
+    ///
+    pub fn prepare_layout(&self, ctx: &ggez::Context) {
+        self.ensure_layout(ctx);
+    }
+
+    ///
+    /// 折り返した結果の1行ずつを、別々のRichTextとして返す
+    /// 行単位で表示を制御するテキストボックスなどで使う
+    ///
+    pub fn wrapped_lines(&self, ctx: &ggez::Context) -> Vec<RichText> {
+        let mut result = Vec::new();
+        let mut paragraph_offset = 0;
+
+        for (paragraph, pending) in self
+            .markup
+            .lines()
+            .into_iter()
+            .zip(self.build_lines(ctx).into_iter())
+        {
+            let paragraph_len = paragraph.glyph_len();
+            let wrapped = match self.layout_option.max_extent {
+                Some(max) => pending.wrap(max),
+                None => vec![pending],
+            };
+
+            // 折り返し位置の空白は、前の行の末尾に残す
+            let heads: Vec<usize> = wrapped
+                .iter()
+                .skip(1)
+                .map(|line| match line.glyphs.first() {
+                    Some(glyph) => glyph.index - paragraph_offset,
+                    None => paragraph_len,
+                })
+                .chain(std::iter::once(paragraph_len))
+                .collect();
+
+            let mut begin = 0;
+            for end in heads {
+                let mut text = RichText::new(
+                    paragraph.sub_markup(begin, end),
+                    self.assets.clone(),
+                    self.get_position(),
+                    self.get_scale(),
+                    self.get_rotation(),
+                    self.get_drawing_depth(),
+                    self.font_info,
+                    self.direction,
+                );
+                text.set_layout_option(self.layout_option);
+                result.push(text);
+
+                begin = end;
+            }
+
+            // 改行文字の分も進める
+            paragraph_offset += paragraph_len + 1;
+        }
+
+        result
+    }
+
+    pub fn has_motion(&self) -> bool {
+        self.markup.has_motion()
+    }
+
+    ///
+    /// 文字の動きを進める。動きのある文字を含む場合はtrueを返す
+    ///
+    pub fn update_motion(&mut self, t: Clock) -> bool {
+        self.motion_clock = t;
+        self.has_motion()
+    }
+
+    fn glyph_text(&self, s: &str, font: ggraphics::Font, size: f32) -> ggraphics::Text {
+        ggraphics::Text::new(
+            ggraphics::TextFragment::new(s)
+                .font(font)
+                .scale(ggraphics::PxScale { x: size, y: size }),
+        )
+    }
+
+    ///
+    /// 文字chを描画するフォントを返す
+    /// 指定されたフォントが文字を持っていない場合は、代替フォントを使う
+    ///
+    fn style_font(&self, style: &TextStyle, ch: char) -> ggraphics::Font {
+        let primary = match style
+            .font
+            .as_ref()
+            .and_then(|name| self.assets.fonts.get(name))
+        {
+            Some(font) => *font,
+            None => self.font_info.font,
+        };
+
+        FontInformation {
+            font: primary,
+            ..self.font_info
+        }
+        .resolve_font(ch)
+    }
+
+    fn style_size(&self, style: &TextStyle) -> f32 {
+        style.size.unwrap_or(self.font_info.scale.y)
+    }
+
+    fn pending_text_glyph(
+        &self,
+        ctx: &ggez::Context,
+        ch: char,
+        style: &TextStyle,
+        size: f32,
+        index: usize,
+    ) -> PendingGlyph {
+        let font = self.style_font(style, ch);
+        let text = self.glyph_text(&ch.to_string(), font, size);
+        let advance = match self.direction {
+            TextDirection::Horizontal => {
+                match glyph::glyph_metrics(font, ch, numeric::Vector2f::new(size, size)) {
+                    Some(metrics) => metrics.advance,
+                    None => text.width(ctx) as f32,
+                }
+            }
+            TextDirection::Vertical => size,
+        };
+
+        PendingGlyph {
+            body: GlyphBody::Text(text),
+            ch: Some(ch),
+            advance: advance,
+            size: size,
+            color: style.color,
+            motion: style.motion,
+            emphasis: style.emphasis,
+            index: index,
+        }
+    }
+
+    fn build_lines(&self, ctx: &ggez::Context) -> Vec<PendingLine> {
+        let mut lines = vec![PendingLine::new()];
+        let mut index = 0;
+
+        for element in self.markup.iter() {
+            match element {
+                RichTextElement::Run { text, style, ruby } => {
+                    let size = self.style_size(style);
+                    let line = lines.last_mut().unwrap();
+                    let base_begin = line.glyphs.len();
+
+                    for ch in text.chars() {
+                        let glyph = self.pending_text_glyph(ctx, ch, style, size, index);
+                        line.glyphs.push(glyph);
+                        index += 1;
+                    }
+
+                    if let Some(ruby) = ruby {
+                        let ruby_size = size * Self::RUBY_RATIO;
+                        let ruby_style = TextStyle {
+                            size: Some(ruby_size),
+                            emphasis: false,
+                            ..style.clone()
+                        };
+
+                        let glyphs = ruby
+                            .chars()
+                            .map(|ch| {
+                                self.pending_text_glyph(ctx, ch, &ruby_style, ruby_size, index)
+                            })
+                            .collect();
+
+                        line.rubies.push(PendingRuby {
+                            glyphs: glyphs,
+                            base_begin: base_begin,
+                            base_end: line.glyphs.len(),
+                        });
+                    }
+                }
+                RichTextElement::Icon { name, style } => {
+                    if let Some(icon) = self.assets.icons.get(name) {
+                        let size = self.style_size(style);
+                        lines.last_mut().unwrap().glyphs.push(PendingGlyph {
+                            body: GlyphBody::Icon(icon.clone()),
+                            ch: None,
+                            advance: size,
+                            size: size,
+                            color: None,
+                            motion: style.motion,
+                            emphasis: style.emphasis,
+                            index: index,
+                        });
+                    }
+                    index += 1;
+                }
+                RichTextElement::NewLine => {
+                    lines.push(PendingLine::new());
+                    index += 1;
+                }
+            }
+        }
+
+        lines
+    }
+
+    fn emphasis_glyph(&self, base: &PendingGlyph, offset: numeric::Point2f) -> LaidOutGlyph {
+        let size = base.size * Self::EMPHASIS_RATIO;
+
+        LaidOutGlyph {
+            body: GlyphBody::Text(self.glyph_text("・", self.font_info.font, size)),
+            offset: offset,
+            size: size,
+            color: base.color,
+            motion: base.motion,
+            index: base.index,
+        }
+    }
+
+    fn layout_horizontal(&self, lines: Vec<PendingLine>) -> RichTextLayout {
+        let mut glyphs = Vec::new();
+        let mut width: f32 = 0.0;
+        let mut line_top = 0.0;
+
+        for line in lines {
+            let core = line.core_size(self.font_info.scale.y);
+            let reserve = line.reserve_size();
+            let baseline_top = line_top + reserve;
+
+            // 親文字の左端を先に求めておく
+            let advances: Vec<f32> = line.glyphs.iter().map(|g| g.advance).collect();
+            let arrangement =
+                TextLayoutEngine::arrange(&advances, &self.layout_option, line.paragraph_end);
+            let lefts = arrangement.offsets;
+            width = width.max(arrangement.extent);
+
+            for ruby in line.rubies {
+                if ruby.base_begin >= ruby.base_end {
+                    continue;
+                }
+
+                let base_left = lefts[ruby.base_begin];
+                let base_right = lefts[ruby.base_end - 1] + line.glyphs[ruby.base_end - 1].advance;
+                let ruby_width = ruby.glyphs.iter().fold(0.0, |sum, g| sum + g.advance);
+                let mut ruby_x = base_left + ((base_right - base_left) - ruby_width) / 2.0;
+
+                for glyph in ruby.glyphs {
+                    let advance = glyph.advance;
+                    glyphs.push(LaidOutGlyph {
+                        offset: numeric::Point2f::new(ruby_x, baseline_top - glyph.size),
+                        size: glyph.size,
+                        color: glyph.color,
+                        motion: glyph.motion,
+                        index: glyph.index,
+                        body: glyph.body,
+                    });
+                    ruby_x += advance;
+                }
+            }
+
+            for (glyph, left) in line.glyphs.into_iter().zip(lefts.into_iter()) {
+                // 大きさの違う文字は下揃え
+                let top = baseline_top + (core - glyph.size);
+
+                if glyph.emphasis {
+                    let em_size = glyph.size * Self::EMPHASIS_RATIO;
+                    let em = self.emphasis_glyph(
+                        &glyph,
+                        numeric::Point2f::new(
+                            left + (glyph.advance - em_size) / 2.0,
+                            baseline_top - em_size,
+                        ),
+                    );
+                    glyphs.push(em);
+                }
+
+                glyphs.push(LaidOutGlyph {
+                    offset: numeric::Point2f::new(left, top),
+                    size: glyph.size,
+                    color: glyph.color,
+                    motion: glyph.motion,
+                    index: glyph.index,
+                    body: glyph.body,
+                });
+            }
+
+            line_top += reserve + core;
+        }
+
+        RichTextLayout {
+            glyphs: glyphs,
+            size: numeric::Vector2f::new(width, line_top),
+        }
+    }
+
+    fn layout_vertical(&self, lines: Vec<PendingLine>) -> RichTextLayout {
+        let mut glyphs = Vec::new();
+
+        // 縦書きは右の行から並べるため、先に全体の幅を求める
+        let column_widths: Vec<(f32, f32)> = lines
+            .iter()
+            .map(|line| (line.core_size(self.font_info.scale.x), line.reserve_size()))
+            .collect();
+        let width = column_widths
+            .iter()
+            .fold(0.0, |sum: f32, (core, reserve)| sum + core + reserve);
+
+        let mut column_right = width;
+        let mut height: f32 = 0.0;
+
+        for (line, (core, reserve)) in lines.into_iter().zip(column_widths.into_iter()) {
+            let column_left = column_right - reserve - core;
+            // ルビと傍点は行の右側に置く
+            let side_left = column_left + core;
+
+            let advances: Vec<f32> = line.glyphs.iter().map(|g| g.advance).collect();
+            let arrangement =
+                TextLayoutEngine::arrange(&advances, &self.layout_option, line.paragraph_end);
+            let tops = arrangement.offsets;
+            height = height.max(arrangement.extent);
+
+            for ruby in line.rubies {
+                if ruby.base_begin >= ruby.base_end {
+                    continue;
+                }
+
+                let base_top = tops[ruby.base_begin];
+                let base_bottom = tops[ruby.base_end - 1] + line.glyphs[ruby.base_end - 1].advance;
+                let ruby_height = ruby.glyphs.iter().fold(0.0, |sum, g| sum + g.advance);
+                let mut ruby_y = base_top + ((base_bottom - base_top) - ruby_height) / 2.0;
+
+                for glyph in ruby.glyphs {
+                    let advance = glyph.advance;
+                    glyphs.push(LaidOutGlyph {
+                        offset: numeric::Point2f::new(side_left, ruby_y),
+                        size: glyph.size,
+                        color: glyph.color,
+                        motion: glyph.motion,
+                        index: glyph.index,
+                        body: glyph.body,
+                    });
+                    ruby_y += advance;
+                }
+            }
+
+            for (glyph, top) in line.glyphs.into_iter().zip(tops.into_iter()) {
+                // 大きさの違う文字は行の中央に揃える
+                let left = column_left + (core - glyph.size) / 2.0;
+
+                if glyph.emphasis {
+                    let em_size = glyph.size * Self::EMPHASIS_RATIO;
+                    let em = self.emphasis_glyph(
+                        &glyph,
+                        numeric::Point2f::new(side_left, top + (glyph.advance - em_size) / 2.0),
+                    );
+                    glyphs.push(em);
+                }
+
+                glyphs.push(LaidOutGlyph {
+                    offset: numeric::Point2f::new(left, top),
+                    size: glyph.size,
+                    color: glyph.color,
+                    motion: glyph.motion,
+                    index: glyph.index,
+                    body: glyph.body,
+                });
+            }
+
+            column_right = column_left;
+        }
+
+        RichTextLayout {
+            glyphs: glyphs,
+            size: numeric::Vector2f::new(width, height),
+        }
+    }
+
+    fn ensure_layout(&self, ctx: &ggez::Context) {
+        if self.layout.borrow().is_some() {
+            return;
+        }
+
+        let mut lines = self.build_lines(ctx);
+        if let Some(max_extent) = self.layout_option.max_extent {
+            lines = lines
+                .into_iter()
+                .flat_map(|line| line.wrap(max_extent))
+                .collect();
+        }
+
+        let layout = match self.direction {
+            TextDirection::Horizontal => self.layout_horizontal(lines),
+            TextDirection::Vertical => self.layout_vertical(lines),
+        };
+
+        self.layout.replace(Some(layout));
+    }
+
+    fn motion_offset(&self, glyph: &LaidOutGlyph) -> numeric::Vector2f {
+        match glyph.motion {
+            TextMotion::Still => numeric::Vector2f::new(0.0, 0.0),
+            TextMotion::Wave => {
+                let phase = (self.motion_clock as f32 * 0.12) + (glyph.index as f32 * 0.7);
+                numeric::Vector2f::new(0.0, phase.sin() * glyph.size * 0.12)
+            }
+            TextMotion::Shake => {
+                // 乱数を使わず、時刻と文字位置から揺れ幅を決める
+                let seed = (self.motion_clock / 2)
+                    .wrapping_mul(2654435761)
+                    .wrapping_add((glyph.index as u64).wrapping_mul(40503));
+                let amplitude = glyph.size * 0.04;
+                numeric::Vector2f::new(
+                    ((seed % 5) as f32 - 2.0) * amplitude,
+                    (((seed / 5) % 5) as f32 - 2.0) * amplitude,
+                )
+            }
+        }
+    }
+
+    fn glyph_color(&self, glyph: &LaidOutGlyph) -> ggraphics::Color {
+        let mut color = glyph.color.unwrap_or(self.draw_param.color);
+        color.a *= self.draw_param.color.a;
+        color
+    }
+}
+
+impl Drawable for RichText {
+    fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult<()> {
+        if !self.drwob_essential.visible {
+            return Ok(());
+        }
+
+        self.ensure_layout(ctx);
+
+        let layout = self.layout.borrow();
+        let layout = layout.as_ref().unwrap();
+
+        let dest = get_param_pos!(self.draw_param);
+        let scale = get_param_scale!(self.draw_param);
+
+        for glyph in layout.glyphs.iter() {
+            let p = glyph.offset + self.motion_offset(glyph);
+
+            match &glyph.body {
+                GlyphBody::Text(text) => {
+                    ggraphics::queue_text(ctx, text, mintp!(p), Some(self.glyph_color(glyph)))
+                }
+                GlyphBody::Icon(icon) => {
+                    let param = ggraphics::DrawParam::new()
+                        .dest(mintp_new!(
+                            dest.x + (p.x * scale.x),
+                            dest.y + (p.y * scale.y)
+                        ))
+                        .scale(mintv_new!(
+                            scale.x * glyph.size / icon.width() as f32,
+                            scale.y * glyph.size / icon.height() as f32
+                        ))
+                        .color(self.glyph_color(glyph));
+                    ggraphics::draw(ctx, icon, param)?;
+                }
+            }
+        }
+
+        ggraphics::draw_queued_text(ctx, self.draw_param, None, ggraphics::FilterMode::Linear)
+    }
+
+    #[inline(always)]
+    fn hide(&mut self) {
+        self.drwob_essential.visible = false;
+    }
+
+    #[inline(always)]
+    fn appear(&mut self) {
+        self.drwob_essential.visible = true;
+    }
+
+    #[inline(always)]
+    fn is_visible(&self) -> bool {
+        self.drwob_essential.visible
+    }
+
+    #[inline(always)]
+    fn set_drawing_depth(&mut self, depth: i8) {
+        self.drwob_essential.drawing_depth = depth;
+    }
+
+    #[inline(always)]
+    fn get_drawing_depth(&self) -> i8 {
+        self.drwob_essential.drawing_depth
+    }
+}
+
+impl Node2D for RichText {
+    #[inline(always)]
+    fn set_position(&mut self, pos: numeric::Point2f) {
+        set_param_pos!(self.draw_param, pos);
+    }
+
+    #[inline(always)]
+    fn get_position(&self) -> numeric::Point2f {
+        let p = get_param_pos!(self.draw_param);
+        numeric::Point2f::new(p.x, p.y)
+    }
+
+    #[inline(always)]
+    fn move_diff(&mut self, offset: numeric::Vector2f) {
+        move_param!(self.draw_param, offset);
+    }
+}
+
+impl Transform for RichText {
+    #[inline(always)]
+    fn set_scale(&mut self, scale: numeric::Vector2f) {
+        set_param_scale!(self.draw_param, scale);
+    }
+
+    #[inline(always)]
+    fn get_scale(&self) -> numeric::Vector2f {
+        let scale = get_param_scale!(self.draw_param);
+        numeric::Vector2f::new(scale.x, scale.y)
+    }
+
+    #[inline(always)]
+    fn set_rotation(&mut self, rad: f32) {
+        set_param_rotation!(self.draw_param, rad);
+    }
+
+    #[inline(always)]
+    fn get_rotation(&self) -> f32 {
+        get_param_rotation!(self.draw_param)
+    }
+
+    #[inline(always)]
+    fn set_crop(&mut self, crop: ggraphics::Rect) {
+        self.draw_param.src = crop;
+    }
+
+    #[inline(always)]
+    fn get_crop(&self) -> ggraphics::Rect {
+        self.draw_param.src
+    }
+
+    #[inline(always)]
+    fn set_drawing_color(&mut self, color: ggraphics::Color) {
+        self.draw_param.color = color;
+    }
+
+    #[inline(always)]
+    fn get_drawing_color(&self) -> ggraphics::Color {
+        self.draw_param.color
+    }
+
+    #[inline(always)]
+    fn set_alpha(&mut self, alpha: f32) {
+        self.draw_param.color.a = alpha;
+    }
+
+    #[inline(always)]
+    fn get_alpha(&self) -> f32 {
+        self.draw_param.color.a
+    }
+
+    #[inline(always)]
+    fn set_transform_offset(&mut self, offset: numeric::Point2f) {
+        set_param_offset!(self.draw_param, offset);
+    }
+
+    #[inline(always)]
+    fn get_transform_offset(&self) -> numeric::Point2f {
+        let p = get_param_offset!(self.draw_param);
+        numeric::Point2f::new(p.x, p.y)
+    }
+
+    fn get_texture_size(&self, ctx: &mut ggez::Context) -> numeric::Vector2f {
+        self.ensure_layout(ctx);
+        self.layout.borrow().as_ref().unwrap().size
+    }
+
+    #[inline(always)]
+    fn set_color(&mut self, color: ggraphics::Color) {
+        self.draw_param.color = color;
+    }
+
+    #[inline(always)]
+    fn get_color(&mut self) -> ggraphics::Color {
+        self.draw_param.color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(markup: &RichTextMarkup) -> Vec<(String, TextStyle)> {
+        markup
+            .iter()
+            .filter_map(|element| match element {
+                RichTextElement::Run { text, style, .. } => Some((text.clone(), style.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn red() -> Option<ggraphics::Color> {
+        Some(ggraphics::Color::from_rgba_u32(0xff0000ff))
+    }
+
+    #[test]
+    fn nested_tags_stack_styles() {
+        let markup = RichTextMarkup::parse("[color=ff0000ff]赤[size=40]大[/size]赤[/color]白");
+        let runs = runs(&markup);
+
+        assert_eq!(runs.len(), 4);
+        assert_eq!(runs[0].0, "赤");
+        assert_eq!(runs[0].1.color, red());
+        assert_eq!(runs[0].1.size, None);
+        assert_eq!(runs[1].0, "大");
+        assert_eq!(runs[1].1.color, red());
+        assert_eq!(runs[1].1.size, Some(40.0));
+        assert_eq!(runs[2].0, "赤");
+        assert_eq!(runs[2].1.color, red());
+        assert_eq!(runs[2].1.size, None);
+        assert_eq!(runs[3], ("白".to_string(), TextStyle::new()));
+    }
+
+    #[test]
+    fn mismatched_close_tag_is_text() {
+        let markup = RichTextMarkup::parse("[em]強[/wave]調[/em]");
+        let runs = runs(&markup);
+
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].0, "強[/wave]調");
+        assert!(runs[0].1.emphasis);
+    }
+
+    #[test]
+    fn unclosed_tag_lasts_until_end() {
+        let markup = RichTextMarkup::parse("普[shake]震える\n行");
+        let elements: Vec<&RichTextElement> = markup.iter().collect();
+
+        assert_eq!(elements.len(), 4);
+        assert!(matches!(elements[2], RichTextElement::NewLine));
+        let runs = runs(&markup);
+        assert_eq!(runs[0].1.motion, TextMotion::Still);
+        assert_eq!(runs[1].1.motion, TextMotion::Shake);
+        assert_eq!(runs[2].1.motion, TextMotion::Shake);
+        assert!(markup.has_motion());
+    }
+
+    #[test]
+    fn unterminated_bracket_is_text() {
+        let markup = RichTextMarkup::parse("鈴[color=ff0000ff");
+
+        assert_eq!(
+            runs(&markup),
+            vec![("鈴[color=ff0000ff".to_string(), TextStyle::new())]
+        );
+    }
+
+    #[test]
+    fn ruby_without_close_tag_is_text() {
+        let markup = RichTextMarkup::parse("[ruby=すずなあん]鈴奈庵");
+
+        assert_eq!(markup.plain_text(), "[ruby=すずなあん]鈴奈庵");
+        assert!(markup.iter().all(|element| match element {
+            RichTextElement::Run { ruby, .. } => ruby.is_none(),
+            _ => true,
+        }));
+    }
+
+    #[test]
+    fn ruby_takes_text_until_close_tag() {
+        let markup = RichTextMarkup::parse("[em][ruby=すずなあん]鈴奈庵[/ruby]へ[/em]");
+        let elements: Vec<&RichTextElement> = markup.iter().collect();
+
+        match elements[0] {
+            RichTextElement::Run { text, style, ruby } => {
+                assert_eq!(text, "鈴奈庵");
+                assert_eq!(ruby.as_deref(), Some("すずなあん"));
+                assert!(style.emphasis);
+            }
+            _ => panic!("expected ruby run"),
+        }
+        assert_eq!(markup.plain_text(), "鈴奈庵へ");
+        assert_eq!(markup.glyph_len(), 4);
+    }
+
+    #[test]
+    fn invalid_value_and_escape_are_text() {
+        let markup = RichTextMarkup::parse("[size=big]大[[icon=Book]");
+
+        assert_eq!(markup.plain_text(), "[size=big]大[icon=Book]");
+        assert!(markup
+            .iter()
+            .all(|element| !matches!(element, RichTextElement::Icon { .. })));
+    }
+}
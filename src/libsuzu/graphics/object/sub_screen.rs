@@ -34,6 +34,7 @@ pub struct SubScreen {
     drwob_essential: DrawableEss,
     draw_param: ggraphics::DrawParam,
     size: numeric::Vector2f,
+    view_origin: numeric::Point2f,
     back_color: ggraphics::Color,
 }
 
@@ -61,6 +62,7 @@ impl SubScreen {
             drwob_essential: DrawableEss::new(true, depth),
             draw_param: dparam,
             size: numeric::Vector2f::new(pos.w, pos.h),
+            view_origin: numeric::Point2f::new(0.0, 0.0),
             back_color: back_color,
         }
     }
@@ -88,6 +90,13 @@ impl SubScreen {
     pub fn set_filter(&mut self, mode: ggraphics::FilterMode) {
         Rc::get_mut(&mut self.canvas).unwrap().set_filter(mode);
     }
+
+    ///
+    /// キャンバスの左上に映す座標。負の値にすると、(0, 0)より左上の領域も描画できる
+    ///
+    pub fn set_view_origin(&mut self, origin: numeric::Point2f) {
+        self.view_origin = origin;
+    }
 }
 
 impl Drawable for SubScreen {
@@ -233,7 +242,12 @@ fn setup_new_drawing_target(ctx: &mut ggez::Context, screen: &SubScreen) {
     ggraphics::clear(ctx, screen.back_color);
     ggraphics::set_screen_coordinates(
         ctx,
-        ggraphics::Rect::new(0.0, 0.0, screen.size.x, screen.size.y),
+        ggraphics::Rect::new(
+            screen.view_origin.x,
+            screen.view_origin.y,
+            screen.size.x,
+            screen.size.y,
+        ),
     )
     .unwrap();
 }
@@ -242,7 +256,12 @@ fn setup_poped_drawing_target(ctx: &mut ggez::Context, screen: &SubScreen) {
     ggraphics::set_canvas(ctx, Some(&screen.canvas));
     ggraphics::set_screen_coordinates(
         ctx,
-        ggraphics::Rect::new(0.0, 0.0, screen.size.x, screen.size.y),
+        ggraphics::Rect::new(
+            screen.view_origin.x,
+            screen.view_origin.y,
+            screen.size.x,
+            screen.size.y,
+        ),
     )
     .unwrap();
 }
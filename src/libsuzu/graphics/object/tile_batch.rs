@@ -4,7 +4,7 @@ use crate::libsuzu::numeric;
 
 use crate::libsuzu::graphics::drawable::*;
 
-use crate::{mintp, mintv, move_param, set_param_pos, get_param_pos};
+use crate::{get_param_pos, mintp, mintp_new, mintv, move_param, set_param_pos};
 
 ///
 /// Tile状に画像を切り取って表示する
@@ -93,6 +93,33 @@ impl TileBatch {
         self.add_batch_ratio_float(ratio_pos, dest_pos, scale, color);
     }
 
+    ///
+    /// 回転を伴うバッチ処理を追加するメソッド
+    /// 回転の中心はタイルの中央で、dest_posもタイルの中央の位置になる
+    ///
+    pub fn add_batch_tile_position_rotated(
+        &mut self,
+        tile_pos: numeric::Vector2u,
+        dest_pos: numeric::Point2f,
+        scale: numeric::Vector2f,
+        rotation: f32,
+        color: ggraphics::Color,
+    ) {
+        let draw_param = ggraphics::DrawParam::default()
+            .src(numeric::Rect::new(
+                (tile_pos.x * self.tile_size.x) as f32 / self.image_size.x as f32,
+                (tile_pos.y * self.tile_size.y) as f32 / self.image_size.y as f32,
+                self.tile_size_ratio_float.x,
+                self.tile_size_ratio_float.y,
+            ))
+            .offset(mintp_new!(0.5, 0.5))
+            .rotation(rotation)
+            .scale(mintv!(scale))
+            .dest(mintp!(dest_pos))
+            .color(color);
+        self.sprite_batch.add(draw_param);
+    }
+
     ///
     /// 追加したバッチ処理をクリアするメソッド
     ///
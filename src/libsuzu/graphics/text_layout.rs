@@ -0,0 +1,420 @@
+use std::ops::Range;
+
+use ggez::graphics as ggraphics;
+
+use crate::libsuzu::graphics::object::FontInformation;
+use crate::libsuzu::numeric;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextDirection {
+    Horizontal,
+    Vertical,
+}
+
+///
+/// # 行内での揃え方
+/// 横書きではStartが左、縦書きではStartが上になる
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextAlign {
+    Start,
+    Center,
+    End,
+}
+
+///
+/// # 折り返しと揃えの設定
+///
+/// ## max_extent
+/// 1行の最大の長さ。横書きなら幅、縦書きなら高さ。Noneの場合は折り返さない
+///
+/// ## justify
+/// trueの場合、段落の最終行以外は文字間を広げて行の長さをmax_extentに揃える
+///
+#[derive(Debug, Clone, Copy)]
+pub struct TextLayoutOption {
+    pub max_extent: Option<f32>,
+    pub align: TextAlign,
+    pub justify: bool,
+}
+
+impl TextLayoutOption {
+    pub fn new(max_extent: Option<f32>, align: TextAlign, justify: bool) -> Self {
+        TextLayoutOption {
+            max_extent: max_extent,
+            align: align,
+            justify: justify,
+        }
+    }
+
+    ///
+    /// 折り返しなし、先頭揃え
+    ///
+    pub fn no_wrap() -> Self {
+        Self::new(None, TextAlign::Start, false)
+    }
+
+    pub fn wrap(max_extent: f32) -> Self {
+        Self::new(Some(max_extent), TextAlign::Start, false)
+    }
+}
+
+impl Default for TextLayoutOption {
+    fn default() -> Self {
+        Self::no_wrap()
+    }
+}
+
+///
+/// 行頭に置いてはいけない文字(行頭禁則)
+///
+pub fn is_forbidden_line_head(ch: char) -> bool {
+    match ch {
+        '、' | '。' | '，' | '．' | ',' | '.' | '・' | '：' | '；' | ':' | ';' | '？' | '！'
+        | '?' | '!' | '‼' | '⁇' | '⁈' | '⁉' | 'ー' | '～' | '…' | '‥' | '々' | 'ゝ' | 'ゞ'
+        | 'ヽ' | 'ヾ' | '」' | '』' | '）' | '］' | '｝' | '〕' | '〉' | '》' | '】' | '〙'
+        | '〗' | ')' | ']' | '}' | '’' | '”' | 'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ' | 'っ' | 'ゃ'
+        | 'ゅ' | 'ょ' | 'ゎ' | 'ゕ' | 'ゖ' | 'ァ' | 'ィ' | 'ゥ' | 'ェ' | 'ォ' | 'ッ' | 'ャ'
+        | 'ュ' | 'ョ' | 'ヮ' | 'ヵ' | 'ヶ' => true,
+        _ => false,
+    }
+}
+
+///
+/// 行末に置いてはいけない文字(行末禁則)
+///
+pub fn is_forbidden_line_tail(ch: char) -> bool {
+    match ch {
+        '「' | '『' | '（' | '［' | '｛' | '〔' | '〈' | '《' | '【' | '〘' | '〖' | '(' | '['
+        | '{' | '‘' | '“' => true,
+        _ => false,
+    }
+}
+
+///
+/// 行末からはみ出させてよい文字(ぶら下げ組み)
+///
+fn is_hanging_punctuation(ch: char) -> bool {
+    match ch {
+        '、' | '。' | '，' | '．' | ',' | '.' => true,
+        _ => false,
+    }
+}
+
+// 英単語の途中では改行しない
+fn is_word_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '\'' || ch == '-'
+}
+
+///
+/// # 折り返しの単位となる1文字分の情報
+///
+/// ## ch
+/// 禁則の判定に使う文字。アイコンなど文字でないものはNone
+///
+/// ## advance
+/// 行方向に進む長さ
+///
+/// ## glue
+/// trueの場合、次の文字との間で改行しない。ルビの親文字などに使う
+///
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutUnit {
+    pub ch: Option<char>,
+    pub advance: f32,
+    pub glue: bool,
+}
+
+impl LayoutUnit {
+    pub fn new(ch: Option<char>, advance: f32, glue: bool) -> Self {
+        LayoutUnit {
+            ch: ch,
+            advance: advance,
+            glue: glue,
+        }
+    }
+}
+
+///
+/// # 1行分の文字の配置
+///
+/// ## offsets
+/// 各文字の行方向の開始位置
+///
+/// ## extent
+/// 揃えを含めた、行の終端の位置
+///
+#[derive(Debug, Clone)]
+pub struct LineArrangement {
+    pub offsets: Vec<f32>,
+    pub extent: f32,
+}
+
+pub struct TextLayoutEngine;
+
+impl TextLayoutEngine {
+    ///
+    /// unitsのindex番目の文字の直前で改行できるかを返す
+    ///
+    pub fn can_break_before(units: &[LayoutUnit], index: usize) -> bool {
+        if index == 0 || index >= units.len() {
+            return false;
+        }
+
+        let prev = units[index - 1];
+        let next = units[index];
+
+        if prev.glue {
+            return false;
+        }
+
+        match (prev.ch, next.ch) {
+            (Some(p), Some(n)) => {
+                !is_forbidden_line_head(n)
+                    && !is_forbidden_line_tail(p)
+                    && !(is_word_char(p) && is_word_char(n))
+            }
+            (Some(p), None) => !is_forbidden_line_tail(p),
+            (None, Some(n)) => !is_forbidden_line_head(n),
+            (None, None) => true,
+        }
+    }
+
+    ///
+    /// 改行を含まない1段落を、max_extentに収まるように行に分割する
+    /// 返り値は、各行に含まれるunitsの範囲
+    ///
+    pub fn break_lines(units: &[LayoutUnit], max_extent: f32) -> Vec<Range<usize>> {
+        let mut lines = Vec::new();
+        let mut begin = 0;
+        let mut extent = 0.0;
+        let mut last_break: Option<usize> = None;
+        let mut index = 0;
+
+        while index < units.len() {
+            if index > begin && Self::can_break_before(units, index) {
+                last_break = Some(index);
+            }
+
+            let advance = units[index].advance;
+
+            if index > begin && extent + advance > max_extent {
+                // 句読点は行末にぶら下げて、行頭に来ないようにする
+                // 「。」」のように、ぶら下げた次の文字も行頭に置けない場合はぶら下げない
+                if units[index].ch.map_or(false, is_hanging_punctuation)
+                    && (index + 1 >= units.len() || Self::can_break_before(units, index + 1))
+                {
+                    index += 1;
+                    lines.push(begin..index);
+                    begin = Self::skip_spaces(units, index);
+                    index = begin;
+                    extent = 0.0;
+                    last_break = None;
+                    continue;
+                }
+
+                // 改行できる位置が無い場合は、禁則を破って強制的に改行する
+                let end = match last_break {
+                    Some(b) if b > begin => b,
+                    _ => index,
+                };
+
+                lines.push(begin..end);
+                begin = Self::skip_spaces(units, end);
+                index = index.max(begin);
+                extent = units[begin..index]
+                    .iter()
+                    .fold(0.0, |sum, u| sum + u.advance);
+                // 次の行に持ち越した文字の間にも、改行できる位置があるかもしれない
+                last_break = ((begin + 1)..index)
+                    .rev()
+                    .find(|i| Self::can_break_before(units, *i));
+                continue;
+            }
+
+            extent += advance;
+            index += 1;
+        }
+
+        if begin < units.len() || lines.is_empty() {
+            lines.push(begin..units.len());
+        }
+
+        lines
+    }
+
+    // 折り返した行の先頭の空白は詰める
+    fn skip_spaces(units: &[LayoutUnit], mut index: usize) -> usize {
+        while index < units.len() && units[index].ch == Some(' ') {
+            index += 1;
+        }
+        index
+    }
+
+    ///
+    /// 1行分の文字を、揃えの設定に従って配置する
+    /// paragraph_endは、その行が段落の最終行かどうか。最終行は両端揃えしない
+    ///
+    pub fn arrange(
+        advances: &[f32],
+        option: &TextLayoutOption,
+        paragraph_end: bool,
+    ) -> LineArrangement {
+        let natural = advances.iter().fold(0.0, |sum: f32, a| sum + a);
+
+        let (start, gap) = match option.max_extent {
+            Some(max)
+                if option.justify && !paragraph_end && advances.len() > 1 && natural < max =>
+            {
+                (0.0, (max - natural) / (advances.len() - 1) as f32)
+            }
+            Some(max) => {
+                let free = (max - natural).max(0.0);
+                match option.align {
+                    TextAlign::Start => (0.0, 0.0),
+                    TextAlign::Center => (free / 2.0, 0.0),
+                    TextAlign::End => (free, 0.0),
+                }
+            }
+            None => (0.0, 0.0),
+        };
+
+        let mut offsets = Vec::with_capacity(advances.len());
+        let mut p = start;
+        for advance in advances.iter() {
+            offsets.push(p);
+            p += advance + gap;
+        }
+
+        LineArrangement {
+            offsets: offsets,
+            extent: if advances.is_empty() { start } else { p - gap },
+        }
+    }
+
+    ///
+    /// 単一のフォントで描画した場合の大きさを、描画せずに求める
+    ///
+    pub fn measure(
+        ctx: &ggez::Context,
+        text: &str,
+        font_info: &FontInformation,
+        direction: TextDirection,
+        option: &TextLayoutOption,
+    ) -> numeric::Vector2f {
+        let mut line_count = 0;
+        let mut max_extent: f32 = 0.0;
+
+        for paragraph in text.split('\n') {
+            let units: Vec<LayoutUnit> = paragraph
+                .chars()
+                .map(|ch| {
+                    let advance = match direction {
+                        TextDirection::Horizontal => match font_info.glyph_metrics(ch) {
+                            Some(metrics) => metrics.advance,
+                            None => {
+                                let mut fragment = ggraphics::Text::new(ch);
+                                fragment.set_font(
+                                    font_info.resolve_font(ch),
+                                    ggraphics::PxScale {
+                                        x: font_info.scale.x,
+                                        y: font_info.scale.y,
+                                    },
+                                );
+                                fragment.width(ctx) as f32
+                            }
+                        },
+                        TextDirection::Vertical => font_info.scale.y,
+                    };
+                    LayoutUnit::new(Some(ch), advance, false)
+                })
+                .collect();
+
+            let ranges = match option.max_extent {
+                Some(max) => Self::break_lines(&units, max),
+                None => vec![0..units.len()],
+            };
+
+            let last = ranges.len() - 1;
+            for (i, range) in ranges.into_iter().enumerate() {
+                let advances: Vec<f32> = units[range].iter().map(|u| u.advance).collect();
+                let arrangement = Self::arrange(&advances, option, i == last);
+                max_extent = max_extent.max(arrangement.extent);
+                line_count += 1;
+            }
+        }
+
+        match direction {
+            TextDirection::Horizontal => {
+                numeric::Vector2f::new(max_extent, line_count as f32 * font_info.scale.y)
+            }
+            TextDirection::Vertical => {
+                numeric::Vector2f::new(line_count as f32 * font_info.scale.x, max_extent)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn units(s: &str) -> Vec<LayoutUnit> {
+        s.chars()
+            .map(|ch| LayoutUnit::new(Some(ch), 1.0, false))
+            .collect()
+    }
+
+    fn break_str(s: &str, max_extent: f32) -> Vec<String> {
+        let chars: Vec<char> = s.chars().collect();
+        TextLayoutEngine::break_lines(&units(s), max_extent)
+            .into_iter()
+            .map(|range| chars[range].iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn hangs_punctuation_at_line_end() {
+        assert_eq!(break_str("あいう。えお", 3.0), vec!["あいう。", "えお"]);
+    }
+
+    #[test]
+    fn does_not_hang_when_closing_bracket_follows() {
+        let lines = break_str("あいう。」えお", 3.0);
+
+        assert_eq!(lines, vec!["あい", "う。」", "えお"]);
+        for line in lines.iter() {
+            assert!(!is_forbidden_line_head(line.chars().next().unwrap()));
+        }
+    }
+
+    #[test]
+    fn keeps_forbidden_head_off_line_start() {
+        assert_eq!(break_str("あいうっえ", 3.0), vec!["あい", "うっえ"]);
+        assert_eq!(break_str("あい「うえ", 3.0), vec!["あい", "「うえ"]);
+    }
+
+    #[test]
+    fn forces_break_without_break_point() {
+        assert_eq!(break_str("abcdefg", 3.0), vec!["abc", "def", "g"]);
+    }
+
+    #[test]
+    fn breaks_normally_after_forced_break() {
+        assert_eq!(break_str("abcdあいう", 3.0), vec!["abc", "dあい", "う"]);
+    }
+
+    #[test]
+    fn skips_spaces_at_line_start() {
+        assert_eq!(break_str("ab cd", 2.0), vec!["ab", "cd"]);
+    }
+
+    #[test]
+    fn glue_prevents_break() {
+        let mut glued = units("あいうえ");
+        glued[1].glue = true;
+        glued[2].glue = true;
+
+        assert_eq!(TextLayoutEngine::break_lines(&glued, 3.0), vec![0..1, 1..4]);
+    }
+}
@@ -1,7 +1,11 @@
+use std::str::FromStr;
+
+use crate::libsuzu::graphics::camera;
 use crate::libsuzu::numeric;
 
 use super::map_object::*;
 use super::*;
+use crate::core::character_definition::CharacterDefinition;
 use crate::core::TextureID;
 use crate::object::util_object::*;
 
@@ -39,7 +43,7 @@ fn create_playable_doremy1<'a>(
 
     let obj = Box::new(Texture::new(
         ctx.ref_texture(TextureID::KosuzuDotFront1),
-        mp::map_to_display(&map_position, camera),
+        camera::world_to_view(&map_position, camera),
         numeric::Vector2f::new(1.5, 1.5),
         0.0,
         0,
@@ -108,7 +112,7 @@ fn create_customer_sample<'a>(
 
     let obj = Box::new(Texture::new(
         ctx.ref_texture(TextureID::Mob1DotFront1),
-        mp::map_to_display(&map_position, camera),
+        camera::world_to_view(&map_position, camera),
         numeric::Vector2f::new(1.5, 1.5),
         0.0,
         0,
@@ -183,7 +187,7 @@ pub fn create_endroll_sample<'a>(
 
     let mut obj = Box::new(Texture::new(
         ctx.ref_texture(TextureID::KosuzuDotFront1),
-        mp::map_to_display(&map_position, camera),
+        camera::world_to_view(&map_position, camera),
         numeric::Vector2f::new(2.0, 2.0),
         0.0,
         0,
@@ -259,7 +263,7 @@ pub fn create_kuyou_kosuzu<'a>(
 
     let mut obj = Box::new(Texture::new(
         ctx.ref_texture(TextureID::KosuzuDotFront1),
-        mp::map_to_display(&map_position, camera),
+        camera::world_to_view(&map_position, camera),
         numeric::Vector2f::new(2.0, 2.0),
         0.0,
         0,
@@ -335,7 +339,7 @@ pub fn create_customer_kuyou<'a>(
 
     let mut obj = Box::new(Texture::new(
         ctx.ref_texture(TextureID::Mob1DotFront1),
-        mp::map_to_display(&map_position, camera),
+        camera::world_to_view(&map_position, camera),
         numeric::Vector2f::new(2.0, 2.0),
         0.0,
         0,
@@ -371,18 +375,147 @@ pub fn create_customer_kuyou<'a>(
     )
 }
 
+///
+/// キャラクター定義のアニメーションの名前と、MapObjectに渡す向きの順番
+/// 3番目は、その向きのアニメーションが定義されていない場合に代わりに使う名前
+///
+const DEFINITION_DIRECTIONS: [(ObjectDirection, &str, &str); 8] = [
+    (ObjectDirection::MoveDown, "move_down", "stop_down"),
+    (ObjectDirection::MoveUp, "move_up", "stop_up"),
+    (ObjectDirection::MoveRight, "move_right", "stop_right"),
+    (ObjectDirection::MoveLeft, "move_left", "stop_left"),
+    (ObjectDirection::StopDown, "stop_down", "move_down"),
+    (ObjectDirection::StopUp, "stop_up", "move_up"),
+    (ObjectDirection::StopRight, "stop_right", "move_right"),
+    (ObjectDirection::StopLeft, "stop_left", "move_left"),
+];
+
+fn ref_texture_by_name<'a>(ctx: &mut SuzuContext<'a>, name: &str) -> ggraphics::Image {
+    match TextureID::from_str(name) {
+        Ok(id) => ctx.ref_texture(id),
+        Err(_) => panic!("Unknown texture in character definition: {}", name),
+    }
+}
+
+///
+/// tomlファイルで定義されたキャラクターを生成する
+///
+pub fn create_character_from_definition<'a>(
+    ctx: &mut SuzuContext<'a>,
+    definition: &CharacterDefinition,
+    camera: &numeric::Rect,
+    map_position: numeric::Point2f,
+) -> MapObject {
+    let mut textures = Vec::new();
+
+    for (_, name, alt_name) in DEFINITION_DIRECTIONS.iter() {
+        let frames = definition
+            .animation
+            .get(*name)
+            .filter(|frames| !frames.is_empty())
+            .or_else(|| {
+                definition
+                    .animation
+                    .get(*alt_name)
+                    .filter(|frames| !frames.is_empty())
+            })
+            .or_else(|| {
+                definition
+                    .animation
+                    .values()
+                    .find(|frames| !frames.is_empty())
+            })
+            .unwrap_or_else(|| panic!("No animation frames in character {}", definition.id));
+
+        let mut images: Vec<ggraphics::Image> = frames
+            .iter()
+            .map(|frame| ref_texture_by_name(ctx, frame))
+            .collect();
+
+        if definition.pixel_art {
+            for texture in images.iter_mut() {
+                texture.set_filter(ggraphics::FilterMode::Nearest);
+            }
+        }
+
+        textures.push(images);
+    }
+
+    let initial_index = DEFINITION_DIRECTIONS
+        .iter()
+        .position(|(_, name, _)| *name == definition.initial_direction)
+        .unwrap_or(4);
+
+    let mut obj = Box::new(Texture::new(
+        textures[initial_index][0].clone(),
+        camera::world_to_view(&map_position, camera),
+        numeric::Vector2f::new(definition.scale, definition.scale),
+        0.0,
+        0,
+    ));
+    if definition.pixel_art {
+        obj.set_filter(ggraphics::FilterMode::Nearest);
+    }
+
+    MapObject::new(
+        tobj::SimpleObject::new(tobj::MovableTexture::new(obj, None, 0), vec![]),
+        DEFINITION_DIRECTIONS
+            .iter()
+            .map(|(direction, _, _)| *direction)
+            .collect(),
+        textures,
+        DEFINITION_DIRECTIONS[initial_index].0,
+        TextureSpeedInfo::new(
+            numeric::Vector2f::new(0.0, 0.0),
+            SpeedBorder {
+                positive_x: definition.walk_speed,
+                negative_x: -definition.walk_speed,
+                positive_y: definition.walk_speed,
+                negative_y: -definition.walk_speed,
+            },
+        ),
+        map_position,
+        definition.collision.to_rect(),
+        definition.frame_speed,
+    )
+}
+
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum CharacterFactoryOrder {
     PlayableDoremy1,
     CustomerSample,
 }
 
+impl CharacterFactoryOrder {
+    ///
+    /// キャラクター定義のファイルで、この種類を上書きするときのid
+    ///
+    pub fn definition_id(&self) -> &'static str {
+        match self {
+            CharacterFactoryOrder::PlayableDoremy1 => "playable-doremy1",
+            CharacterFactoryOrder::CustomerSample => "customer-sample",
+        }
+    }
+}
+
+///
+/// キャラクターを生成する。同じidの定義がtomlファイルにあれば、そちらを優先する
+///
 pub fn create_character<'a>(
     order: CharacterFactoryOrder,
     ctx: &mut SuzuContext<'a>,
     camera: &numeric::Rect,
     map_position: numeric::Point2f,
 ) -> MapObject {
+    if let Some(definition) = ctx
+        .resource
+        .get_character_definitions()
+        .get(order.definition_id())
+        .cloned()
+    {
+        return create_character_from_definition(ctx, &definition, camera, map_position);
+    }
+
     match order {
         CharacterFactoryOrder::PlayableDoremy1 => {
             create_playable_doremy1(ctx, camera, map_position)
@@ -390,3 +523,48 @@ pub fn create_character<'a>(
         CharacterFactoryOrder::CustomerSample => create_customer_sample(ctx, camera, map_position),
     }
 }
+
+///
+/// 定義された客のキャラクターから1つ選んで生成する
+/// 地区を狙った広告を出していると、その地区の客が選ばれやすい
+/// 外出先の出来事で知り合う客は、その出来事を済ませるまで選ばれない
+/// 客の定義が1つもない場合は、CustomerSampleを生成する
+///
+pub fn create_random_customer<'a>(
+    ctx: &mut SuzuContext<'a>,
+    camera: &numeric::Rect,
+    map_position: numeric::Point2f,
+) -> (MapObject, Option<CharacterDefinition>) {
+    let ad_effects = ctx.todays_ad_effects();
+    let going_out_events = ctx.resource.get_going_out_events();
+    let event_progress = &ctx.take_save_data().event_progress;
+    let definition = ctx
+        .resource
+        .get_character_definitions()
+        .random_customer(|definition| {
+            if going_out_events.locks_customer(&definition.id)
+                && !event_progress.customer_is_unlocked(&definition.id)
+            {
+                return 0.0;
+            }
+
+            ad_effects.district_weight(definition.district.as_deref())
+        })
+        .cloned();
+
+    match definition {
+        Some(definition) => (
+            create_character_from_definition(ctx, &definition, camera, map_position),
+            Some(definition),
+        ),
+        None => (
+            create_character(
+                CharacterFactoryOrder::CustomerSample,
+                ctx,
+                camera,
+                map_position,
+            ),
+            None,
+        ),
+    }
+}
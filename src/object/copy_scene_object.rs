@@ -2,16 +2,16 @@ use std::collections::VecDeque;
 
 use ggez::graphics as ggraphics;
 
-use torifune::distance;
-use torifune::graphics::drawable::*;
-use torifune::graphics::object::shape;
-use torifune::graphics::object::shape::*;
-use torifune::graphics::object::sub_screen;
-use torifune::graphics::object::sub_screen::SubScreen;
-use torifune::graphics::object::*;
-use torifune::impl_drawable_object_for_wrapped;
-use torifune::impl_texture_object_for_wrapped;
-use torifune::numeric;
+use crate::distance;
+use crate::impl_node2d_for_wrapped;
+use crate::impl_transform_object_for_wrapped;
+use crate::libsuzu::graphics::drawable::*;
+use crate::libsuzu::graphics::object::shape;
+use crate::libsuzu::graphics::object::shape::*;
+use crate::libsuzu::graphics::object::sub_screen;
+use crate::libsuzu::graphics::object::sub_screen::SubScreen;
+use crate::libsuzu::graphics::object::*;
+use crate::libsuzu::numeric;
 
 use crate::core::{SuzuContext, TextureID};
 
@@ -49,7 +49,7 @@ impl DragDistanceCalculator {
 pub struct PointerLag {
     lag_buffer: VecDeque<numeric::Point2f>,
     mesh: Option<ggraphics::Mesh>,
-    drwob_essential: DrawableObjectEssential,
+    drwob_essential: DrawableEss,
 }
 
 impl PointerLag {
@@ -57,7 +57,7 @@ impl PointerLag {
         PointerLag {
             lag_buffer: VecDeque::new(),
             mesh: None,
-            drwob_essential: DrawableObjectEssential::new(true, 0),
+            drwob_essential: DrawableEss::new(true, 0),
         }
     }
 
@@ -88,7 +88,7 @@ impl PointerLag {
     }
 }
 
-impl DrawableComponent for PointerLag {
+impl Drawable for PointerLag {
     fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult<()> {
         if self.is_visible() {
             if let Some(mesh) = self.mesh.as_mut() {
@@ -126,7 +126,7 @@ impl DrawableComponent for PointerLag {
 }
 
 pub struct EffectableHangi {
-    hangi_texture: UniTexture,
+    hangi_texture: Texture,
     canvas: SubScreen,
     drag_distance: DragDistanceCalculator,
     lag_effect: PointerLag,
@@ -135,7 +135,7 @@ pub struct EffectableHangi {
 impl EffectableHangi {
     pub fn new<'a>(ctx: &mut SuzuContext<'a>, rect: numeric::Rect) -> Self {
         EffectableHangi {
-            hangi_texture: UniTexture::new(
+            hangi_texture: Texture::new(
                 ctx.ref_texture(TextureID::Wood1),
                 numeric::Point2f::new(0.0, 0.0),
                 numeric::Vector2f::new(1.0, 1.0),
@@ -164,9 +164,16 @@ impl EffectableHangi {
         self.drag_distance.release();
         self.lag_effect.update_mesh(ctx);
     }
+
+    ///
+    /// 版木の上をなぞった距離の合計
+    ///
+    pub fn get_drag_distance(&self) -> f32 {
+        self.drag_distance.get_distance()
+    }
 }
 
-impl DrawableComponent for EffectableHangi {
+impl Drawable for EffectableHangi {
     fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult<()> {
         if self.is_visible() {
             sub_screen::stack_screen(ctx, &self.canvas);
@@ -201,10 +208,10 @@ impl DrawableComponent for EffectableHangi {
     }
 }
 
-impl DrawableObject for EffectableHangi {
-    impl_drawable_object_for_wrapped! {canvas}
+impl Node2D for EffectableHangi {
+    impl_node2d_for_wrapped! {canvas}
 }
 
-impl TextureObject for EffectableHangi {
-    impl_texture_object_for_wrapped! {canvas}
+impl Transform for EffectableHangi {
+    impl_transform_object_for_wrapped! {canvas}
 }
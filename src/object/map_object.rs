@@ -1,16 +1,20 @@
 use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 use ggez::graphics as ggraphics;
 
 use crate::libsuzu::core::Clock;
 use crate::distance;
+use crate::libsuzu::graphics::camera;
 use crate::libsuzu::graphics::drawable::*;
 use crate::libsuzu::graphics::object::*;
 use crate::libsuzu::numeric;
 
 use crate::core::map_parser as mp;
+use crate::core::character_definition::CustomerPersonality;
+use crate::core::path_search::{AgentID, TileReservation};
 use crate::core::*;
 use crate::flush_delay_event;
 use crate::object::collision::*;
@@ -315,7 +319,7 @@ impl MapObject {
     /// マップ上の座標から、ディスプレイの描画位置を算出し、更新する
     ///
     pub fn update_display_position(&mut self, camera: &numeric::Rect) {
-        let dp = mp::map_to_display(&self.map_position.current, camera);
+        let dp = camera::world_to_view(&self.map_position.current, camera);
         self.object.get_mut_object().set_position(dp);
     }
 
@@ -599,6 +603,17 @@ impl CustomerMoveQueue {
         self.queue.pop_front()
     }
 
+    pub fn front(&self) -> Option<numeric::Point2f> {
+        self.queue.front().copied()
+    }
+
+    ///
+    /// 最終的な目的地
+    ///
+    pub fn back(&self) -> Option<numeric::Point2f> {
+        self.queue.back().copied()
+    }
+
     pub fn len(&self) -> usize {
         self.queue.len()
     }
@@ -623,21 +638,54 @@ pub enum CustomerCharacterStatus {
     GettingOut,
 }
 
+///
+/// # 客の情報
+///
+/// ## definition_id
+/// 見た目に使ったキャラクター定義のid。定義を使っていなければNone
+///
 pub struct CustomerInformation {
     pub name: String,
+    pub personality: CustomerPersonality,
+    pub definition_id: Option<String>,
 }
 
 impl CustomerInformation {
     pub fn new(name: &str) -> Self {
         CustomerInformation {
             name: name.to_string(),
+            personality: CustomerPersonality::new_default(),
+            definition_id: None,
         }
     }
 }
 
+///
+/// 他の客に道を塞がれてから、別のルートを探し直すまでの時間
+///
+const CUSTOMER_DEADLOCK_WAIT: Clock = 90;
+
+static NEXT_CUSTOMER_AGENT_ID: AtomicU32 = AtomicU32::new(0);
+
+///
+/// 次の経由地へ進もうとした結果
+///
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum WaypointStep {
+    Advanced,
+    Waiting,
+    Arrived,
+}
+
 ///
 /// マップ上に表示するキャラクターの情報
 ///
+/// ## agent_id
+/// タイルの予約で、客同士を区別するためのID
+///
+/// ## blocked_since
+/// 他の客に道を塞がれて待ち始めた時刻
+///
 pub struct CustomerCharacter {
     event_list: DelayEventList<Self>,
     character: MapObject,
@@ -647,6 +695,8 @@ pub struct CustomerCharacter {
     shopping_is_done: bool,
     current_goal: numeric::Point2f,
     customer_info: CustomerInformation,
+    agent_id: AgentID,
+    blocked_since: Option<Clock>,
 }
 
 impl CustomerCharacter {
@@ -664,9 +714,35 @@ impl CustomerCharacter {
             shopping_is_done: false,
             current_goal: numeric::Point2f::new(0.0, 0.0),
             customer_info: CustomerInformation::new(game_data.customer_random_select()),
+            agent_id: NEXT_CUSTOMER_AGENT_ID.fetch_add(1, Ordering::Relaxed),
+            blocked_since: None,
         }
     }
 
+    pub fn get_agent_id(&self) -> AgentID {
+        self.agent_id
+    }
+
+    pub fn set_customer_name(&mut self, name: &str) {
+        self.customer_info.name = name.to_string();
+    }
+
+    pub fn get_customer_info(&self) -> &CustomerInformation {
+        &self.customer_info
+    }
+
+    pub fn set_definition_id(&mut self, definition_id: &str) {
+        self.customer_info.definition_id = Some(definition_id.to_string());
+    }
+
+    pub fn set_personality(&mut self, personality: CustomerPersonality) {
+        self.customer_info.personality = personality;
+    }
+
+    pub fn get_personality(&self) -> &CustomerPersonality {
+        &self.customer_info.personality
+    }
+
     ///
     /// 現在のマップ位置から、指定された目的地までのルートを計算するメソッド
     ///
@@ -791,7 +867,7 @@ impl CustomerCharacter {
         // ルート検索
         let maybe_next_route = self.find_route(ctx, map_data, goal);
 
-        // 一定時間後にルートを設定し、状態をReadyに変更する。
+        // 本を眺める時間の後にルートを設定し、状態をReadyに変更する。
         // 移動開始するまでは、ストップ
         self.event_list.add_event(
             Box::new(move |customer, _, _| {
@@ -800,7 +876,7 @@ impl CustomerCharacter {
                     customer.customer_status = CustomerCharacterStatus::Ready;
                 }
             }),
-            t + 100,
+            t + self.customer_info.personality.browsing_time,
         );
 
         self.customer_status = CustomerCharacterStatus::WaitOnBookShelf;
@@ -813,6 +889,7 @@ impl CustomerCharacter {
         &mut self,
         ctx: &mut ggez::Context,
         map_data: &mp::StageObjectMap,
+        reservation: &mut TileReservation,
         t: Clock,
     ) {
         // 移動情報キューが空（目的地に到達してる or 初めて目的地を設定する）
@@ -823,14 +900,82 @@ impl CustomerCharacter {
 
         // キューが空ではない場合
         // 情報をキューから取り出し、速度を計算し直す
-        let maybe_next_position = self.move_queue.dequeue();
-        if let Some(next_position) = maybe_next_position {
-            self.override_move_effect(ctx, next_position);
-            self.current_goal = next_position;
+        if self.step_to_next_waypoint(ctx, map_data, reservation, t) == WaypointStep::Advanced {
             self.customer_status = CustomerCharacterStatus::Moving;
         }
     }
 
+    ///
+    /// 次の経由地のタイルを予約できれば、そこへ向けて移動を開始する
+    /// 他の客が立っているか予約している場合は、その場で待つ
+    ///
+    fn step_to_next_waypoint(
+        &mut self,
+        ctx: &mut ggez::Context,
+        map_data: &mp::StageObjectMap,
+        reservation: &mut TileReservation,
+        t: Clock,
+    ) -> WaypointStep {
+        let next_position = match self.move_queue.front() {
+            Some(p) => p,
+            None => return WaypointStep::Arrived,
+        };
+
+        if let Some(tile) = map_data.map_position_to_tile_position(next_position) {
+            if !reservation.try_reserve(self.agent_id, tile) {
+                self.wait_for_blocking_customer(ctx, map_data, t);
+                return WaypointStep::Waiting;
+            }
+        }
+
+        self.blocked_since = None;
+        self.move_queue.dequeue();
+        self.override_move_effect(ctx, next_position);
+        self.current_goal = next_position;
+
+        WaypointStep::Advanced
+    }
+
+    ///
+    /// 道を塞がれている間は立ち止まり、長く続く場合は行き詰まりとみなして
+    /// 他の客を避けるルートを探し直す
+    ///
+    fn wait_for_blocking_customer(
+        &mut self,
+        ctx: &mut ggez::Context,
+        map_data: &mp::StageObjectMap,
+        t: Clock,
+    ) {
+        self.reset_speed();
+        self.update_animation_for_stop();
+
+        let since = *self.blocked_since.get_or_insert(t);
+
+        // 向かい合った客が同時にルートを変えないよう、待ち時間をずらす
+        let limit = CUSTOMER_DEADLOCK_WAIT + (self.agent_id % 4) as Clock * 20;
+        if t - since < limit {
+            return;
+        }
+
+        self.blocked_since = None;
+
+        let final_goal = match self
+            .move_queue
+            .back()
+            .and_then(|p| map_data.map_position_to_tile_position(p))
+        {
+            Some(goal) => goal,
+            None => return,
+        };
+
+        // 新しいルートが見つからなければ、元のルートで待ち続ける
+        let current_route = std::mem::replace(&mut self.move_queue, CustomerMoveQueue::new());
+        match self.find_route(ctx, map_data, final_goal) {
+            Some(route) => self.move_queue.enqueue(route),
+            None => self.move_queue = current_route,
+        }
+    }
+
     ///
     /// 目的地を強制的に上書きし設定するメソッド
     ///
@@ -839,7 +984,7 @@ impl CustomerCharacter {
         ctx: &mut ggez::Context,
         map_data: &mp::StageObjectMap,
         dest: numeric::Vector2u,
-        t: Clock,
+        _t: Clock,
     ) -> Result<(), ()> {
         // 現在の移動キューをクリア
         self.move_queue.clear();
@@ -849,8 +994,14 @@ impl CustomerCharacter {
         // ルートが見つかれば、その情報をキューに追加
         if let Some(next_route) = maybe_next_route {
             self.move_queue.enqueue(next_route);
-            self.customer_status = CustomerCharacterStatus::Ready;
-            self.update_move_effect(ctx, map_data, t);
+            self.blocked_since = None;
+
+            // ルートの先頭は現在いるタイルなので、予約せずに向かう
+            if let Some(next_position) = self.move_queue.dequeue() {
+                self.override_move_effect(ctx, next_position);
+                self.current_goal = next_position;
+            }
+            self.customer_status = CustomerCharacterStatus::Moving;
             Ok(())
         } else {
             println!(
@@ -913,34 +1064,64 @@ impl CustomerCharacter {
         distance!(current, self.current_goal) < 1.5
     }
 
+    ///
+    /// 客の好みの大きさとジャンルに合う本を1冊選ぶ。好みに合う本がなければ、どれか1冊を選ぶ
+    /// ジャンルは蔵書目録から引く
+    ///
+    fn select_preferred_book<'a>(&self, ctx: &mut SuzuContext<'a>) -> BookInformation {
+        let personality = &self.customer_info.personality;
+        let catalogue = ctx.resource.get_book_catalogue();
+        ctx.resource
+            .book_random_select_where(|book| {
+                personality.prefers_size(&book.size)
+                    && personality
+                        .prefers_genre(catalogue.get(&book.name).map(|entry| entry.genre.as_str()))
+            })
+            .clone()
+    }
+
+    ///
+    /// 返却の依頼を選ぶ。自分が借りている本があれば、最も古い貸出を返す
+    /// 他の客の貸出は返さない
+    ///
+    fn select_returning_request<'a>(
+        &self,
+        ctx: &mut SuzuContext<'a>,
+    ) -> Option<ReturnBookInformation> {
+        ctx.take_save_data()
+            .record_book_data
+            .pick_returning_request_of(&self.customer_info.name)
+    }
+
     fn generate_hold_request<'a>(&mut self, ctx: &mut SuzuContext<'a>) -> CustomerRequest {
-        let random_select = rand::random::<usize>() % 2
-            + if !ctx
-                .take_save_data()
-                .record_book_data
-                .has_returning_request()
-            {
-                1
-            } else {
-                0
-            };
         let today = ctx.take_save_data().date.clone();
 
-        match random_select {
-            0 => CustomerRequest::Returning(ReturnBookInformation::new_random(
-                ctx.resource,
-                today,
-                GensoDate::new(128, 12, 20),
-            )),
-            _ => CustomerRequest::Borrowing(BorrowingInformation::new(
-                vec![ctx.resource.book_random_select().clone()],
-                &self.customer_info.name,
-                today,
-                RentalLimit::random(),
-            )),
+        // borrow_rateの確率で貸出を頼む。返却できる本がなければ、必ず貸出になる
+        let returning = if rand::random::<f32>() < self.customer_info.personality.borrow_rate {
+            None
+        } else {
+            self.select_returning_request(ctx)
+        };
+
+        match returning {
+            Some(request) => CustomerRequest::Returning(request),
+            None => self.generate_borrowing_request(ctx, today),
         }
     }
 
+    fn generate_borrowing_request<'a>(
+        &mut self,
+        ctx: &mut SuzuContext<'a>,
+        today: GensoDate,
+    ) -> CustomerRequest {
+        CustomerRequest::Borrowing(BorrowingInformation::new(
+            vec![self.select_preferred_book(ctx)],
+            &self.customer_info.name,
+            today,
+            RentalLimit::random(),
+        ))
+    }
+
     fn generate_hold_request_with_order<'a>(
         &mut self,
         ctx: &mut SuzuContext<'a>,
@@ -948,18 +1129,13 @@ impl CustomerCharacter {
     ) -> CustomerRequest {
         let today = ctx.take_save_data().date.clone();
 
+        // 返却を頼まれても、自分の貸出がなければ借りに来たことにする
         match order {
-            CustomerRequestOrder::ReturningOrder => CustomerRequest::Returning(
-                ReturnBookInformation::new_random(ctx.resource, today, GensoDate::new(128, 12, 20)),
-            ),
-            CustomerRequestOrder::BorrowingOrder => {
-                CustomerRequest::Borrowing(BorrowingInformation::new(
-                    vec![ctx.resource.book_random_select().clone()],
-                    &self.customer_info.name,
-                    today,
-                    RentalLimit::random(),
-                ))
-            }
+            CustomerRequestOrder::ReturningOrder => match self.select_returning_request(ctx) {
+                Some(request) => CustomerRequest::Returning(request),
+                None => self.generate_borrowing_request(ctx, today),
+            },
+            CustomerRequestOrder::BorrowingOrder => self.generate_borrowing_request(ctx, today),
         }
     }
 
@@ -1005,6 +1181,7 @@ impl CustomerCharacter {
         &mut self,
         ctx: &mut SuzuContext<'a>,
         map_data: &mp::StageObjectMap,
+        reservation: &mut TileReservation,
         counter: numeric::Vector2u,
         exit: numeric::Vector2u,
         t: Clock,
@@ -1015,7 +1192,7 @@ impl CustomerCharacter {
         match self.customer_status {
             CustomerCharacterStatus::Ready => {
                 // 移動可能状態であれば、移動を開始する
-                self.update_move_effect(ctx.context, map_data, t);
+                self.update_move_effect(ctx.context, map_data, reservation, t);
             }
             CustomerCharacterStatus::Moving => {
                 // 移動中, 目的地に到着したか？
@@ -1056,15 +1233,13 @@ impl CustomerCharacter {
 
                 // GotOutなら、終了し、あとで削除されるのを待つ
                 if self.customer_status == CustomerCharacterStatus::GotOut {
+                    reservation.release(self.agent_id);
                     return;
                 }
 
                 // キューが空ではない場合
                 // 情報をキューから取り出し、速度を計算し直す
-                if let Some(next_position) = self.move_queue.dequeue() {
-                    self.override_move_effect(ctx.context, next_position);
-                    self.current_goal = next_position;
-                }
+                self.step_to_next_waypoint(ctx.context, map_data, reservation, t);
             }
             CustomerCharacterStatus::GoToCheck => {
                 // まだゴールしていない
@@ -1084,10 +1259,10 @@ impl CustomerCharacter {
 
                 // キューが空ではない場合
                 // 情報をキューから取り出し、速度を計算し直す
-                if let Some(next_position) = self.move_queue.dequeue() {
-                    self.override_move_effect(ctx.context, next_position);
-                    self.current_goal = next_position;
-                } else {
+                // 前の客が詰まっている場合は、その後ろで待つ
+                if self.step_to_next_waypoint(ctx.context, map_data, reservation, t)
+                    == WaypointStep::Arrived
+                {
                     self.check_been_counter(map_data, goal, counter);
                     // 速度もリセット
                     self.reset_speed();
@@ -1126,6 +1301,20 @@ impl CustomerCharacter {
         self.character.get_map_position()
     }
 
+    ///
+    /// 当たり判定領域の上端が乗っているタイルの位置を返す
+    ///
+    pub fn get_tile_position(
+        &self,
+        ctx: &mut ggez::Context,
+        map_data: &mp::StageObjectMap,
+    ) -> Option<numeric::Vector2u> {
+        map_data.map_position_to_tile_position(
+            self.character
+                .get_map_position_with_collision_top_offset(ctx),
+        )
+    }
+
     pub fn get_character_object(&self) -> &MapObject {
         &self.character
     }
@@ -1224,10 +1413,25 @@ impl OnMap for CustomerCharacter {
     }
 }
 
+///
+/// # イベントが発動するきっかけ
+///
+/// ## Action
+/// イベントのあるマスでアクションボタンを押したとき
+///
+/// ## Touch
+/// イベントのあるマスに触れている間
+///
+/// ## Enter, Exit, Stay
+/// トリガーゾーンに入ったとき、出たとき、留まり続けたとき
+///
 #[derive(PartialEq, Clone, Copy)]
 pub enum EventTrigger {
     Action,
     Touch,
+    Enter,
+    Exit,
+    Stay,
 }
 
 impl FromStr for EventTrigger {
@@ -1237,7 +1441,10 @@ impl FromStr for EventTrigger {
         match trigger_str {
             "action" => Ok(Self::Action),
             "touch" => Ok(Self::Touch),
-            _ => panic!("Error: EventTrigger::from_str"),
+            "enter" => Ok(Self::Enter),
+            "exit" => Ok(Self::Exit),
+            "stay" => Ok(Self::Stay),
+            _ => Err(()),
         }
     }
 }
@@ -1246,23 +1453,32 @@ pub trait MapEvent {
     fn get_trigger_method(&self) -> EventTrigger;
 }
 
+///
+/// イベントの定義からkeyの文字列を取り出す。ない場合は、何が足りないかを返す
+///
+fn event_field_str<'v>(toml_script: &'v toml::value::Value, key: &str) -> Result<&'v str, String> {
+    toml_script
+        .get(key)
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| format!("missing string field \"{}\"", key))
+}
+
+fn event_field_trigger(toml_script: &toml::value::Value) -> Result<EventTrigger, String> {
+    let trigger_str = event_field_str(toml_script, "trigger")?;
+    EventTrigger::from_str(trigger_str).map_err(|_| format!("unknown trigger \"{}\"", trigger_str))
+}
+
 pub struct MapTextEvent {
     trigger: EventTrigger,
     text: String,
 }
 
 impl MapTextEvent {
-    pub fn from_toml_object(toml_script: &toml::value::Value) -> Self {
-        MapTextEvent {
-            trigger: EventTrigger::from_str(toml_script.get("trigger").unwrap().as_str().unwrap())
-                .unwrap(),
-            text: toml_script
-                .get("text")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .to_string(),
-        }
+    pub fn from_toml_object(toml_script: &toml::value::Value) -> Result<Self, String> {
+        Ok(MapTextEvent {
+            trigger: event_field_trigger(toml_script)?,
+            text: event_field_str(toml_script, "text")?.to_string(),
+        })
     }
 
     pub fn get_text(&self) -> &str {
@@ -1289,19 +1505,14 @@ impl MapEventSceneSwitch {
         }
     }
 
-    pub fn from_toml_object(toml_script: &toml::value::Value) -> Self {
-        MapEventSceneSwitch {
-            trigger: EventTrigger::from_str(toml_script.get("trigger").unwrap().as_str().unwrap())
-                .unwrap(),
-            switch_scene: SceneID::from_str(
-                toml_script
-                    .get("switch-scene-id")
-                    .unwrap()
-                    .as_str()
-                    .unwrap(),
-            )
-            .unwrap(),
-        }
+    pub fn from_toml_object(toml_script: &toml::value::Value) -> Result<Self, String> {
+        let scene_str = event_field_str(toml_script, "switch-scene-id")?;
+
+        Ok(MapEventSceneSwitch {
+            trigger: event_field_trigger(toml_script)?,
+            switch_scene: SceneID::from_str(scene_str)
+                .map_err(|_| format!("unknown scene id \"{}\"", scene_str))?,
+        })
     }
 
     pub fn get_switch_scene_id(&self) -> SceneID {
@@ -1321,22 +1532,24 @@ pub struct BookStoreEvent {
 }
 
 impl BookStoreEvent {
-    pub fn from_toml_object(toml_script: &toml::value::Value) -> Self {
-        let shelf_info = toml_script.get("shelf-info").unwrap().as_table().unwrap();
-        let book_shelf_info = BookShelfInformation::new(
+    pub fn from_toml_object(toml_script: &toml::value::Value) -> Result<Self, String> {
+        let shelf_info = toml_script
+            .get("shelf-info")
+            .ok_or_else(|| "missing field \"shelf-info\"".to_string())?;
+        let number = |key: &str| {
             shelf_info
-                .get("begin-number")
-                .unwrap()
-                .as_integer()
-                .unwrap() as u16,
-            shelf_info.get("end-number").unwrap().as_integer().unwrap() as u16,
-        );
+                .get(key)
+                .and_then(|value| value.as_integer())
+                .map(|value| value as u16)
+                .ok_or_else(|| format!("missing integer field \"shelf-info.{}\"", key))
+        };
+        let book_shelf_info =
+            BookShelfInformation::new(number("begin-number")?, number("end-number")?);
 
-        BookStoreEvent {
-            trigger: EventTrigger::from_str(toml_script.get("trigger").unwrap().as_str().unwrap())
-                .unwrap(),
+        Ok(BookStoreEvent {
+            trigger: event_field_trigger(toml_script)?,
             book_shelf_info: book_shelf_info,
-        }
+        })
     }
 
     pub fn get_book_shelf_info(&self) -> &BookShelfInformation {
@@ -1353,6 +1566,9 @@ impl MapEvent for BookStoreEvent {
 #[derive(PartialEq, Clone, Copy)]
 pub enum BuiltinEventSymbol {
     SelectShelvingBook = 0,
+    ShowShopMap,
+    ShowShopMenu,
+    RepairBooks,
 }
 
 impl FromStr for BuiltinEventSymbol {
@@ -1361,7 +1577,10 @@ impl FromStr for BuiltinEventSymbol {
     fn from_str(builtin_event_symbol: &str) -> Result<Self, Self::Err> {
         match builtin_event_symbol {
             "select-shelving-book" => Ok(Self::SelectShelvingBook),
-            _ => panic!("Error: BuiltinEventSymbol::from_str"),
+            "show-shop-map" => Ok(Self::ShowShopMap),
+            "show-shop-menu" => Ok(Self::ShowShopMenu),
+            "repair-books" => Ok(Self::RepairBooks),
+            _ => Err(()),
         }
     }
 }
@@ -1373,20 +1592,17 @@ pub struct BuiltinEvent {
 }
 
 impl BuiltinEvent {
-    pub fn from_toml_object(toml_script: &toml::value::Value) -> Self {
+    pub fn from_toml_object(toml_script: &toml::value::Value) -> Result<Self, String> {
         let builtin_event_info = toml_script
             .get("builtin-event-info")
-            .unwrap()
-            .as_table()
-            .unwrap();
-        BuiltinEvent {
-            trigger: EventTrigger::from_str(toml_script.get("trigger").unwrap().as_str().unwrap())
-                .unwrap(),
-            event_symbol: BuiltinEventSymbol::from_str(
-                builtin_event_info.get("symbol").unwrap().as_str().unwrap(),
-            )
-            .unwrap(),
-        }
+            .ok_or_else(|| "missing field \"builtin-event-info\"".to_string())?;
+        let symbol = event_field_str(builtin_event_info, "symbol")?;
+
+        Ok(BuiltinEvent {
+            trigger: event_field_trigger(toml_script)?,
+            event_symbol: BuiltinEventSymbol::from_str(symbol)
+                .map_err(|_| format!("unknown builtin event symbol \"{}\"", symbol))?,
+        })
     }
 
     pub fn get_event_symbol(&self) -> BuiltinEventSymbol {
@@ -1418,8 +1634,452 @@ impl MapEvent for MapEventElement {
     }
 }
 
+fn toml_as_f32(value: &toml::Value) -> Option<f32> {
+    value
+        .as_float()
+        .map(|f| f as f32)
+        .or_else(|| value.as_integer().map(|i| i as f32))
+}
+
+fn toml_as_genso_date(value: &toml::Value) -> Option<GensoDate> {
+    Some(GensoDate::new(
+        value.get("season")?.as_integer()? as u32,
+        value.get("month")?.as_integer()? as u8,
+        value.get("day")?.as_integer()? as u8,
+    ))
+}
+
+///
+/// # トリガーゾーンの形
+///
+/// 座標はタイル単位。タイルの途中も表せるように実数で持つ
+///
+#[derive(Clone)]
+pub enum TriggerShape {
+    Rect(numeric::Rect),
+    Polygon(Vec<numeric::Point2f>),
+}
+
+impl TriggerShape {
+    ///
+    /// rect = { x, y, w, h } か、polygon = [[x, y], ...] から生成する
+    ///
+    pub fn from_toml_object(toml_script: &toml::value::Value) -> Option<Self> {
+        if let Some(rect) = toml_script.get("rect") {
+            let value = |key: &str| rect.get(key).and_then(toml_as_f32);
+            return Some(TriggerShape::Rect(numeric::Rect::new(
+                value("x")?,
+                value("y")?,
+                value("w")?,
+                value("h")?,
+            )));
+        }
+
+        let polygon = toml_script
+            .get("polygon")?
+            .as_array()?
+            .iter()
+            .map(|point| {
+                let point = point.as_array()?;
+                Some(numeric::Point2f::new(
+                    toml_as_f32(point.get(0)?)?,
+                    toml_as_f32(point.get(1)?)?,
+                ))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        if polygon.len() < 3 {
+            None
+        } else {
+            Some(TriggerShape::Polygon(polygon))
+        }
+    }
+
+    ///
+    /// 形と位置から作る文字列。idのないトリガーゾーンを、並び順に依らず見分けるのに使う
+    ///
+    pub fn position_key(&self) -> String {
+        match self {
+            TriggerShape::Rect(rect) => {
+                format!("rect({},{},{},{})", rect.x, rect.y, rect.w, rect.h)
+            }
+            TriggerShape::Polygon(vertices) => format!(
+                "polygon({})",
+                vertices
+                    .iter()
+                    .map(|point| format!("{},{}", point.x, point.y))
+                    .collect::<Vec<String>>()
+                    .join(";")
+            ),
+        }
+    }
+
+    pub fn contains(&self, point: numeric::Point2f) -> bool {
+        match self {
+            TriggerShape::Rect(rect) => {
+                point.x >= rect.x
+                    && point.x < rect.x + rect.w
+                    && point.y >= rect.y
+                    && point.y < rect.y + rect.h
+            }
+            TriggerShape::Polygon(vertices) => {
+                // 点から右に伸ばした半直線が辺と交わる回数の偶奇で判定する
+                let mut inside = false;
+                let mut j = vertices.len() - 1;
+                for i in 0..vertices.len() {
+                    let (a, b) = (vertices[i], vertices[j]);
+                    if (a.y > point.y) != (b.y > point.y)
+                        && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+                    {
+                        inside = !inside;
+                    }
+                    j = i;
+                }
+                inside
+            }
+        }
+    }
+}
+
+///
+/// # トリガーゾーンの発動条件
+///
+/// ## DateFrom, DateUntil
+/// この日付以降、この日付以前
+///
+/// ## ReputationMin, ReputationMax
+/// 評判がこの値以上、この値以下
+///
+/// ## Flag
+/// マップイベントのフラグが指定の値
+///
+#[derive(Clone)]
+pub enum TriggerCondition {
+    DateFrom(GensoDate),
+    DateUntil(GensoDate),
+    ReputationMin(f32),
+    ReputationMax(f32),
+    Flag(String, bool),
+}
+
+impl TriggerCondition {
+    ///
+    /// conditionテーブルから条件を集める
+    /// date-from, date-until = { season, month, day }, reputation-min, reputation-max,
+    /// flags = { フラグ名 = true/false }
+    ///
+    pub fn from_toml_object(condition: &toml::value::Value) -> Vec<Self> {
+        let mut conditions = Vec::new();
+
+        if let Some(date) = condition.get("date-from").and_then(toml_as_genso_date) {
+            conditions.push(TriggerCondition::DateFrom(date));
+        }
+        if let Some(date) = condition.get("date-until").and_then(toml_as_genso_date) {
+            conditions.push(TriggerCondition::DateUntil(date));
+        }
+        if let Some(min) = condition.get("reputation-min").and_then(toml_as_f32) {
+            conditions.push(TriggerCondition::ReputationMin(min));
+        }
+        if let Some(max) = condition.get("reputation-max").and_then(toml_as_f32) {
+            conditions.push(TriggerCondition::ReputationMax(max));
+        }
+        if let Some(flags) = condition.get("flags").and_then(|flags| flags.as_table()) {
+            for (name, value) in flags.iter() {
+                if let Some(value) = value.as_bool() {
+                    conditions.push(TriggerCondition::Flag(name.to_string(), value));
+                }
+            }
+        }
+
+        conditions
+    }
+
+    pub fn is_satisfied(&self, save_data: &SavableData) -> bool {
+        match self {
+            TriggerCondition::DateFrom(date) => !date.is_past(&save_data.date),
+            TriggerCondition::DateUntil(date) => !save_data.date.is_past(date),
+            TriggerCondition::ReputationMin(min) => {
+                save_data.suzunaan_status.get_current_reputation() >= *min
+            }
+            TriggerCondition::ReputationMax(max) => {
+                save_data.suzunaan_status.get_current_reputation() <= *max
+            }
+            TriggerCondition::Flag(name, value) => {
+                save_data.map_event_record.get_flag(name) == *value
+            }
+        }
+    }
+}
+
+///
+/// # トリガーゾーンが発動したときに行う処理
+///
+/// ## Scenario
+/// シナリオファイルを読み込み、テキストボックスで表示する
+///
+/// ## SoundEffect
+/// 効果音を鳴らす
+///
+/// ## ShopUI
+/// 棚の選択画面など、店のUIを開く
+///
+/// ## SetFlag
+/// マップイベントのフラグを書き換える
+///
+#[derive(Clone)]
+pub enum TriggerAction {
+    Scenario(String),
+    SoundEffect(SoundID),
+    ShopUI(BuiltinEventSymbol),
+    SetFlag(String, bool),
+}
+
+impl TriggerAction {
+    pub fn from_toml_object(toml_script: &toml::value::Value) -> Option<Self> {
+        let get_str = |key: &str| toml_script.get(key).and_then(|value| value.as_str());
+
+        match get_str("type")? {
+            "scenario" => Some(TriggerAction::Scenario(get_str("path")?.to_string())),
+            "se" => Some(TriggerAction::SoundEffect(
+                SoundID::from_str(get_str("sound")?).ok()?,
+            )),
+            "ui" => {
+                let symbol = get_str("symbol")?;
+                match BuiltinEventSymbol::from_str(symbol) {
+                    Ok(symbol) => Some(TriggerAction::ShopUI(symbol)),
+                    Err(_) => {
+                        eprintln!("Warning: unknown ui symbol in trigger action -> {}", symbol);
+                        None
+                    }
+                }
+            }
+            "set-flag" => Some(TriggerAction::SetFlag(
+                get_str("flag")?.to_string(),
+                toml_script
+                    .get("value")
+                    .and_then(|value| value.as_bool())
+                    .unwrap_or(true),
+            )),
+            _ => {
+                eprintln!("Error: unknown trigger action");
+                None
+            }
+        }
+    }
+
+    ///
+    /// actionの配列から、処理の列を生成する
+    /// Tiledのプロパティのように配列が書けない場合は、action.0.type, action.1.typeのように
+    /// 番号をキーにしたテーブルでもよい。その場合は番号順に実行する
+    ///
+    pub fn list_from_toml_object(actions: Option<&toml::value::Value>) -> Vec<Self> {
+        let actions = match actions {
+            Some(actions) => actions,
+            None => return Vec::new(),
+        };
+
+        if let Some(array) = actions.as_array() {
+            return array.iter().filter_map(Self::from_toml_object).collect();
+        }
+
+        if let Some(table) = actions.as_table() {
+            let mut numbered: Vec<(u32, &toml::Value)> = table
+                .iter()
+                .filter_map(|(key, value)| Some((key.parse::<u32>().ok()?, value)))
+                .collect();
+            numbered.sort_by_key(|(index, _)| *index);
+
+            return numbered
+                .into_iter()
+                .filter_map(|(_, value)| Self::from_toml_object(value))
+                .collect();
+        }
+
+        Vec::new()
+    }
+}
+
+///
+/// # トリガーゾーンの発動回数
+///
+/// ## Once
+/// 一度だけ発動する。発動済みかどうかはセーブデータに記録する
+///
+/// ## Repeat
+/// 条件を満たすたびに何度でも発動する
+///
+#[derive(PartialEq, Clone, Copy)]
+pub enum TriggerMode {
+    Once,
+    Repeat,
+}
+
+impl FromStr for TriggerMode {
+    type Err = ();
+
+    fn from_str(mode_str: &str) -> Result<Self, Self::Err> {
+        match mode_str {
+            "once" => Ok(Self::Once),
+            "repeat" => Ok(Self::Repeat),
+            _ => Err(()),
+        }
+    }
+}
+
+///
+/// # 発動したトリガーゾーンの情報
+///
+pub struct FiredTriggerZone {
+    pub id: String,
+    pub mode: TriggerMode,
+    pub actions: Vec<TriggerAction>,
+}
+
+///
+/// # 領域への出入りで発動するイベント
+///
+/// ## trigger
+/// Enter, Exit, Stayのいずれか
+///
+/// ## stay_interval
+/// Stayの場合に、領域に留まってから発動するまでの時間。Repeatなら、この間隔で繰り返す
+///
+/// ## inside
+/// 直前の更新で、プレイヤーが領域の中にいたか
+///
+/// ## stay_begin
+/// 領域に入った、もしくは最後にStayで発動した時刻
+///
+pub struct TriggerZone {
+    id: String,
+    shape: TriggerShape,
+    trigger: EventTrigger,
+    mode: TriggerMode,
+    stay_interval: Clock,
+    conditions: Vec<TriggerCondition>,
+    actions: Vec<TriggerAction>,
+    inside: bool,
+    stay_begin: Clock,
+}
+
+impl TriggerZone {
+    ///
+    /// trigger-zoneの要素1つ分から生成する。shapeがNoneの場合は、toml_scriptのrectかpolygonを使う
+    ///
+    pub fn from_toml_object(
+        toml_script: &toml::value::Value,
+        default_id: String,
+        shape: Option<TriggerShape>,
+    ) -> Option<Self> {
+        let shape = match shape {
+            Some(shape) => shape,
+            None => TriggerShape::from_toml_object(toml_script)?,
+        };
+
+        let trigger_str = toml_script.get("trigger")?.as_str()?;
+        let trigger = match EventTrigger::from_str(trigger_str) {
+            Ok(trigger) => trigger,
+            Err(_) => {
+                eprintln!(
+                    "Warning: unknown trigger in trigger zone -> {}",
+                    trigger_str
+                );
+                return None;
+            }
+        };
+        if trigger != EventTrigger::Enter
+            && trigger != EventTrigger::Exit
+            && trigger != EventTrigger::Stay
+        {
+            eprintln!("Error: trigger zone supports only enter, exit and stay");
+            return None;
+        }
+
+        Some(TriggerZone {
+            id: toml_script
+                .get("id")
+                .and_then(|id| id.as_str())
+                .map(|id| id.to_string())
+                .unwrap_or(default_id),
+            shape: shape,
+            trigger: trigger,
+            mode: toml_script
+                .get("mode")
+                .and_then(|mode| mode.as_str())
+                .and_then(|mode| TriggerMode::from_str(mode).ok())
+                .unwrap_or(TriggerMode::Once),
+            stay_interval: toml_script
+                .get("stay-interval")
+                .and_then(|interval| interval.as_integer())
+                .unwrap_or(60) as Clock,
+            conditions: toml_script
+                .get("condition")
+                .map(TriggerCondition::from_toml_object)
+                .unwrap_or(Vec::new()),
+            actions: TriggerAction::list_from_toml_object(toml_script.get("action")),
+            inside: false,
+            stay_begin: 0,
+        })
+    }
+
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    ///
+    /// プレイヤーの位置(タイル単位)で出入りを更新し、発動する場合はtrueを返す
+    ///
+    pub fn update(&mut self, point: numeric::Point2f, t: Clock, save_data: &SavableData) -> bool {
+        let was_inside = self.inside;
+        self.inside = self.shape.contains(point);
+
+        if self.inside && !was_inside {
+            self.stay_begin = t;
+        }
+
+        let triggered = match self.trigger {
+            EventTrigger::Enter => self.inside && !was_inside,
+            EventTrigger::Exit => !self.inside && was_inside,
+            EventTrigger::Stay => {
+                self.inside && t.saturating_sub(self.stay_begin) >= self.stay_interval
+            }
+            _ => false,
+        };
+
+        if !triggered {
+            return false;
+        }
+
+        if self.mode == TriggerMode::Once && save_data.map_event_record.is_fired(&self.id) {
+            return false;
+        }
+
+        if !self
+            .conditions
+            .iter()
+            .all(|condition| condition.is_satisfied(save_data))
+        {
+            return false;
+        }
+
+        // Stayの場合は、次の発動までもう一度待つ
+        self.stay_begin = t;
+
+        true
+    }
+
+    pub fn fire(&self) -> FiredTriggerZone {
+        FiredTriggerZone {
+            id: self.id.clone(),
+            mode: self.mode,
+            actions: self.actions.clone(),
+        }
+    }
+}
+
 pub struct MapEventList {
     event_table: HashMap<numeric::Point2i, MapEventElement>,
+    trigger_zones: Vec<TriggerZone>,
 }
 
 impl MapEventList {
@@ -1432,47 +2092,138 @@ impl MapEventList {
         let array = root["event-panel"].as_array().unwrap();
 
         for elem in array {
-            let position_data = elem.get("position").unwrap().as_table().unwrap();
-            let position = numeric::Point2i::new(
-                position_data.get("x").unwrap().as_integer().unwrap() as i32,
-                position_data.get("y").unwrap().as_integer().unwrap() as i32,
-            );
-            if let Some(type_info) = elem.get("type") {
-                match type_info.as_str().unwrap() {
-                    "text" => {
-                        table.insert(
-                            position,
-                            MapEventElement::TextEvent(MapTextEvent::from_toml_object(elem)),
-                        );
-                    }
-                    "switch-scene" => {
-                        table.insert(
-                            position,
-                            MapEventElement::SwitchScene(MapEventSceneSwitch::from_toml_object(
-                                elem,
-                            )),
-                        );
-                    }
-                    "book-shelf" => {
-                        table.insert(
-                            position,
-                            MapEventElement::BookStoreEvent(BookStoreEvent::from_toml_object(elem)),
-                        );
-                    }
-                    "builtin-event" => {
-                        table.insert(
-                            position,
-                            MapEventElement::BuiltinEvent(BuiltinEvent::from_toml_object(elem)),
-                        );
-                    }
-                    _ => eprintln!("Error"),
+            let position = elem.get("position").and_then(|position| {
+                Some(numeric::Point2i::new(
+                    position.get("x")?.as_integer()? as i32,
+                    position.get("y")?.as_integer()? as i32,
+                ))
+            });
+            let position = match position {
+                Some(position) => position,
+                None => {
+                    eprintln!(
+                        "Warning: skipped map event without position in {} -> {}",
+                        file_path, elem
+                    );
+                    continue;
+                }
+            };
+
+            if let Some(event) = Self::parse_event_element(elem) {
+                table.insert(position, event);
+            }
+        }
+
+        let mut trigger_zones = Vec::new();
+        if let Some(zones) = root.get("trigger-zone").and_then(|zones| zones.as_array()) {
+            for elem in zones.iter() {
+                // 発動済みの記録がずれないように、idがなければ名前か位置からidを作る
+                let default_id = match elem.get("name").and_then(|name| name.as_str()) {
+                    Some(name) => format!("{}#{}", file_path, name),
+                    None => match TriggerShape::from_toml_object(elem) {
+                        Some(shape) => format!("{}@{}", file_path, shape.position_key()),
+                        None => {
+                            eprintln!(
+                                "Warning: skipped trigger zone without shape in {} -> {}",
+                                file_path, elem
+                            );
+                            continue;
+                        }
+                    },
+                };
+
+                if let Some(zone) = TriggerZone::from_toml_object(elem, default_id, None) {
+                    trigger_zones.push(zone);
+                }
+            }
+        }
+
+        MapEventList {
+            event_table: table,
+            trigger_zones: trigger_zones,
+        }
+    }
+
+    ///
+    /// event-panelの要素1つ分から、イベントを生成する
+    /// 定義に誤りがあるイベントは、理由を表示して読み飛ばす
+    ///
+    fn parse_event_element(elem: &toml::Value) -> Option<MapEventElement> {
+        let type_info = match elem.get("type") {
+            Some(type_info) => type_info,
+            None => {
+                eprintln!("Map event has no type field -> {}", elem);
+                return None;
+            }
+        };
+
+        let event = match type_info.as_str().unwrap_or("") {
+            "text" => MapTextEvent::from_toml_object(elem).map(MapEventElement::TextEvent),
+            "switch-scene" => {
+                MapEventSceneSwitch::from_toml_object(elem).map(MapEventElement::SwitchScene)
+            }
+            "book-shelf" => {
+                BookStoreEvent::from_toml_object(elem).map(MapEventElement::BookStoreEvent)
+            }
+            "builtin-event" => {
+                BuiltinEvent::from_toml_object(elem).map(MapEventElement::BuiltinEvent)
+            }
+            _ => {
+                eprintln!("Unknown map event type -> {}", type_info);
+                return None;
+            }
+        };
+
+        match event {
+            Ok(event) => Some(event),
+            Err(message) => {
+                eprintln!(
+                    "Warning: skipped {} map event, {} -> {}",
+                    type_info, message, elem
+                );
+                None
+            }
+        }
+    }
+
+    ///
+    /// マップのオブジェクトレイヤーで定義されたイベント領域を登録する
+    /// 領域が覆う全てのタイルに、同じ内容のイベントを置く
+    ///
+    pub fn register_map_regions(&mut self, map_data: &mp::StageObjectMap) -> &mut Self {
+        for (tiles, properties) in map_data.event_regions() {
+            let elem = properties.to_toml();
+            for tile in tiles {
+                if let Some(event) = Self::parse_event_element(&elem) {
+                    self.event_table.insert(tile, event);
                 }
+            }
+        }
+
+        // typeが"trigger"のオブジェクトは、オブジェクトの形をそのままトリガーゾーンにする
+        for object in map_data.trigger_objects() {
+            let polygon = map_data.object_tile_polygon(object);
+            let shape = if polygon.len() >= 3 {
+                TriggerShape::Polygon(polygon)
+            } else {
+                TriggerShape::Rect(map_data.object_tile_area(object))
+            };
+
+            // Tiledのオブジェクトidは作ったときに決まり、並べ替えても変わらない
+            let default_id = if object.name.is_empty() {
+                format!("trigger#{}", object.id)
             } else {
-                eprintln!("Error");
+                object.name.clone()
+            };
+
+            if let Some(zone) =
+                TriggerZone::from_toml_object(&object.properties.to_toml(), default_id, Some(shape))
+            {
+                self.trigger_zones.push(zone);
             }
         }
 
-        MapEventList { event_table: table }
+        self
     }
 
     pub fn register_event(&mut self, point: numeric::Point2i, event: MapEventElement) -> &mut Self {
@@ -1493,4 +2244,30 @@ impl MapEventList {
 
         None
     }
+
+    pub fn register_trigger_zone(&mut self, zone: TriggerZone) -> &mut Self {
+        self.trigger_zones.push(zone);
+        self
+    }
+
+    ///
+    /// プレイヤーの位置(タイル単位)で全てのトリガーゾーンを更新し、発動したものを返す
+    ///
+    pub fn update_trigger_zones(
+        &mut self,
+        point: numeric::Point2f,
+        t: Clock,
+        save_data: &SavableData,
+    ) -> Vec<FiredTriggerZone> {
+        self.trigger_zones
+            .iter_mut()
+            .filter_map(|zone| {
+                if zone.update(point, t, save_data) {
+                    Some(zone.fire())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
 }
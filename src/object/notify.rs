@@ -5,8 +5,11 @@ use ggez::graphics as ggraphics;
 use sub_screen::SubScreen;
 use crate::libsuzu::core::Clock;
 use crate::libsuzu::graphics::drawable::{Drawable, DrawableEss};
+use crate::libsuzu::graphics::layout::AnchoredLayout;
+use crate::libsuzu::graphics::object::rich_text::RichText;
 use crate::libsuzu::graphics::object::sub_screen;
 use crate::libsuzu::graphics::object::*;
+use crate::libsuzu::graphics::text_layout::{TextDirection, TextLayoutOption};
 use crate::libsuzu::numeric;
 
 use crate::core::{FontID, SuzuContext, TextureID, TileBatchTextureID};
@@ -19,6 +22,10 @@ use crate::scene::*;
 pub enum NotificationType {
     Time = 0,
     CustomerCalling,
+    OverdueReminder,
+    LostLoan,
+    Repair,
+    CopyRequest,
 }
 
 pub trait NotificationContents: Drawable {
@@ -47,7 +54,7 @@ impl NotificationContentsData {
 }
 
 pub struct GeneralNotificationContents {
-    main_text: VerticalText,
+    main_text: RichText,
     header_text: UniText,
     required_size: numeric::Vector2f,
     notification_type: NotificationType,
@@ -55,6 +62,9 @@ pub struct GeneralNotificationContents {
 }
 
 impl GeneralNotificationContents {
+    // 本文の1列に入る最大の文字数
+    const COLUMN_CHARS: f32 = 10.0;
+
     pub fn new(ctx: &mut SuzuContext, data: NotificationContentsData, depth: i8) -> Self {
         let font_info = FontInformation::new(
             ctx.resource.get_font(FontID::Cinema),
@@ -62,14 +72,18 @@ impl GeneralNotificationContents {
             ggraphics::Color::from_rgba_u32(0xff),
         );
 
-        let mut main_text = VerticalText::new(
-            data.main_text.to_string(),
+        let mut main_text = RichText::new_plain(
+            &data.main_text,
             numeric::Point2f::new(0.0, 0.0),
             numeric::Vector2f::new(1.0, 1.0),
             0.0,
             0,
             font_info,
+            TextDirection::Vertical,
         );
+        main_text.set_layout_option(TextLayoutOption::wrap(
+            font_info.scale.y * Self::COLUMN_CHARS,
+        ));
 
         let mut header_text = UniText::new(
             data.header_text.to_string(),
@@ -147,6 +161,7 @@ pub struct NotificationArea {
     background: Texture,
     appearance_frame: Option<TileBatchFrame>,
     event_list: DelayEventList<Self>,
+    layout: AnchoredLayout,
     right_top_position: numeric::Point2f,
     queued_contents: VecDeque<Box<dyn NotificationContents>>,
     contents: Option<Box<dyn NotificationContents>>,
@@ -156,11 +171,10 @@ pub struct NotificationArea {
 }
 
 impl NotificationArea {
-    pub fn new<'a>(
-        ctx: &mut SuzuContext<'a>,
-        right_top_position: numeric::Point2f,
-        depth: i8,
-    ) -> Self {
+    ///
+    /// layoutには、通知領域の右上の角をどこに固定するかを指定する
+    ///
+    pub fn new<'a>(ctx: &mut SuzuContext<'a>, layout: AnchoredLayout, depth: i8) -> Self {
         let texture = Texture::new(
             ctx.ref_texture(TextureID::Paper1),
             numeric::Point2f::new(0.0, 0.0),
@@ -174,7 +188,11 @@ impl NotificationArea {
             appearance_frame: None,
             background: texture,
             event_list: DelayEventList::new(),
-            right_top_position: right_top_position,
+            layout: layout,
+            right_top_position: layout.place(
+                crate::core::virtual_screen_rect(),
+                numeric::Vector2f::new(0.0, 0.0),
+            ),
             queued_contents: VecDeque::new(),
             contents: None,
             area: None,
@@ -183,6 +201,14 @@ impl NotificationArea {
         }
     }
 
+    ///
+    /// 表示領域の変化に合わせて、通知領域の基準点を計算し直す
+    /// 表示中の通知は、次の通知から新しい位置に表示される
+    ///
+    pub fn relayout(&mut self, area: numeric::Rect) {
+        self.right_top_position = self.layout.place(area, numeric::Vector2f::new(0.0, 0.0));
+    }
+
     fn new_appearance_frame<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
         let size = self.area.as_ref().unwrap().get_drawing_size(ctx.context);
         self.appearance_frame = Some(TileBatchFrame::new(
@@ -240,7 +266,13 @@ impl NotificationArea {
         if let Some(area) = self.area.as_mut() {
             area.clear_effect();
             area.override_move_func(
-                move_fn::devide_distance(numeric::Point2f::new(1366.0, 10.0), 0.2),
+                move_fn::devide_distance(
+                    numeric::Point2f::new(
+                        self.right_top_position.x + self.layout.get_margin().x,
+                        self.right_top_position.y,
+                    ),
+                    0.2,
+                ),
                 t,
             );
 
@@ -283,7 +315,7 @@ impl NotificationArea {
                     ctx,
                     numeric::Rect::new(
                         self.right_top_position.x - area_size.x,
-                        10.0,
+                        self.right_top_position.y,
                         area_size.x,
                         area_size.y,
                     ),
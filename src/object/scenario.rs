@@ -1,8 +1,11 @@
+use std::cell::RefCell;
 use std::collections::VecDeque;
 
+use crate::libsuzu::graphics::object::rich_text::*;
 use crate::libsuzu::graphics::object::sub_screen;
 use crate::libsuzu::graphics::object::sub_screen::SubScreen;
 use crate::libsuzu::graphics::object::*;
+use crate::libsuzu::graphics::text_layout::*;
 use crate::libsuzu::numeric;
 use crate::libsuzu::{graphics::drawable::*, sound::SoundHandler};
 
@@ -27,19 +30,32 @@ pub struct ScenarioTextAttribute {
     pub font_info: FontInformation,
 }
 
+// 文字送りの途中で毎回作り直さないように、最後に作った行を覚えておく
+struct SegmentLinesCache {
+    length: usize,
+    max_extent: f32,
+    lines: Vec<RichText>,
+}
+
+///
+/// # シナリオのテキストの一区切り
+/// テキストはRichTextのマークアップとして解釈される
+///
 pub struct ScenarioTextSegment {
-    text: String,
+    markup: RichTextMarkup,
     attribute: ScenarioTextAttribute,
+    lines_cache: RefCell<Option<SegmentLinesCache>>,
 }
 
 impl ScenarioTextSegment {
     pub fn new(text: &str, fpc: f32, font_info: FontInformation) -> Self {
         ScenarioTextSegment {
-            text: text.to_string(),
+            markup: RichTextMarkup::parse(text),
             attribute: ScenarioTextAttribute {
                 fpc: fpc,
                 font_info: font_info,
             },
+            lines_cache: RefCell::new(None),
         }
     }
 
@@ -77,7 +93,7 @@ impl ScenarioTextSegment {
         };
 
         ScenarioTextSegment {
-            text: text,
+            markup: RichTextMarkup::parse(&text),
             attribute: ScenarioTextAttribute {
                 fpc: fpc,
                 font_info: FontInformation::new(
@@ -86,43 +102,15 @@ impl ScenarioTextSegment {
                     color,
                 ),
             },
+            lines_cache: RefCell::new(None),
         }
     }
 
-    fn slice_text_bytes(&self, begin: usize, end: usize) -> &str {
-        unsafe { self.text.as_str().get_unchecked(begin..end) }
-    }
-
-    // fn last_line_length(&self) -> usize {
-    //     let mut length: usize = 0;
-    //     let it = self.text.chars().rev();
-
-    //     for ch in it {
-    //         if ch == '\n' {
-    //             break;
-    //         }
-    //         length += 1
-    //     }
-
-    //     length
-    // }
-
-    pub fn slice(&self, length: usize) -> &str {
-        let mut reached_flag = false;
-
-        let str_bytes = self.text.as_str().as_bytes().len();
-
-        for (count, (bytes, _)) in self.text.as_str().char_indices().enumerate() {
-            if reached_flag {
-                return self.slice_text_bytes(0, bytes as usize);
-            }
-
-            if count == length {
-                reached_flag = true;
-            }
-        }
-
-        return self.slice_text_bytes(0, str_bytes as usize);
+    ///
+    /// 先頭からlength文字分のマークアップを返す。タグは文字数に数えない
+    ///
+    pub fn slice(&self, length: usize) -> RichTextMarkup {
+        self.markup.slice(length)
     }
 
     pub fn get_fpc(&self) -> f32 {
@@ -130,7 +118,7 @@ impl ScenarioTextSegment {
     }
 
     pub fn str_len(&self) -> usize {
-        self.text.chars().count()
+        self.markup.glyph_len()
     }
 
     pub fn get_font_info(&self) -> &FontInformation {
@@ -396,10 +384,10 @@ impl ScenarioText {
         self.iterator as usize
     }
 
-    // 表示する文字数を更新する
-    pub fn update_iterator(&mut self) {
+    // 表示する文字数を更新する。speed_rateで文字送りの速さを変えられる
+    pub fn update_iterator(&mut self, speed_rate: f32) {
         let current_segment = self.seq_text.get(self.current_segment_index).unwrap();
-        self.iterator += current_segment.get_fpc();
+        self.iterator += current_segment.get_fpc() * speed_rate;
 
         if self.iterator as usize >= self.total_length {
             self.iterator = self.total_length as f32;
@@ -1181,10 +1169,10 @@ impl Scenario {
         self.scenario.len() - 1 == self.current_page
     }
 
-    pub fn update_current_page(&mut self) {
+    pub fn update_current_page(&mut self, speed_rate: f32) {
         match self.ref_current_element_mut() {
             ScenarioElement::Text(scenario_text) => {
-                scenario_text.update_iterator();
+                scenario_text.update_iterator(speed_rate);
             }
             _ => (),
         }
@@ -1219,9 +1207,11 @@ pub enum TextBoxStatus {
 
 pub struct TextBox {
     box_lines: usize,
-    buffered_text: VecDeque<SimpleText>,
+    buffered_text: VecDeque<RichText>,
     head_line_number: u32,
-    text: VecDeque<SimpleText>,
+    text: VecDeque<RichText>,
+    text_extent: f32,
+    motion_clock: Clock,
     line_arrow: Texture,
     text_box_status: TextBoxStatus,
     appearance_frame: TileBatchFrame,
@@ -1232,6 +1222,8 @@ pub struct TextBox {
 }
 
 impl TextBox {
+    const TEXT_PADDING: f32 = 60.0;
+
     pub fn new<'a>(
         ctx: &mut SuzuContext<'a>,
         rect: numeric::Rect,
@@ -1266,6 +1258,8 @@ impl TextBox {
             buffered_text: VecDeque::new(),
             head_line_number: 0,
             text: VecDeque::new(),
+            text_extent: rect.w - (TextBox::TEXT_PADDING * 2.0),
+            motion_clock: 0,
             text_box_status: TextBoxStatus::UpdatingText,
             background: background,
             appearance_frame: appr_frame,
@@ -1297,31 +1291,66 @@ impl TextBox {
         sub_screen::pop_screen(ctx.context);
     }
 
-    // ScenarioTextSegmentを改行で分割しVec<SimpleText>に変換する
-    pub fn text_from_segment(segment: &ScenarioTextSegment, length: usize) -> Vec<SimpleText> {
+    // ScenarioTextSegmentを改行と折り返しで分割しVec<RichText>に変換する
+    // 表示する文字数が前回と同じセグメントは、作っておいた行を使い回す
+    pub fn text_from_segment<'a>(
+        ctx: &mut SuzuContext<'a>,
+        segment: &ScenarioTextSegment,
+        length: usize,
+        max_extent: f32,
+    ) -> Vec<RichText> {
+        if let Some(cache) = segment.lines_cache.borrow().as_ref() {
+            if cache.length == length && cache.max_extent == max_extent {
+                return cache.lines.clone();
+            }
+        }
+
         let mut text_lines = Vec::new();
 
         for line in segment.slice(length).lines() {
-            text_lines.push(SimpleText::new(
-                tobj::MovableText::new(
-                    Box::new(tobj::UniText::new(
-                        line.to_string(),
-                        numeric::Point2f::new(0.0, 0.0),
-                        numeric::Vector2f::new(1.0, 1.0),
-                        0.0,
-                        0,
-                        segment.attribute.font_info,
-                    )),
-                    None,
-                    0,
-                ),
-                Vec::new(),
-            ));
+            let assets = RichTextAssets::resolve(ctx.context, ctx.resource, &line);
+            let mut text = RichText::new(
+                line,
+                assets,
+                numeric::Point2f::new(0.0, 0.0),
+                numeric::Vector2f::new(1.0, 1.0),
+                0.0,
+                0,
+                segment.attribute.font_info,
+                TextDirection::Horizontal,
+            );
+            text.set_layout_option(TextLayoutOption::wrap(max_extent));
+
+            text_lines.extend(text.wrapped_lines(ctx.context));
+        }
+
+        for line in text_lines.iter() {
+            line.prepare_layout(ctx.context);
         }
 
+        segment.lines_cache.replace(Some(SegmentLinesCache {
+            length: length,
+            max_extent: max_extent,
+            lines: text_lines.clone(),
+        }));
+
         text_lines
     }
 
+    ///
+    /// 揺れる文字などの動きを進める。動きのある文字が表示されている場合はtrueを返す
+    ///
+    pub fn update_text_motion(&mut self) -> bool {
+        self.motion_clock += 1;
+
+        let mut moving = false;
+        for line in self.text.iter_mut() {
+            moving |= line.update_motion(self.motion_clock);
+        }
+
+        moving
+    }
+
     pub fn update_scenario_text<'a>(
         &mut self,
         ctx: &mut SuzuContext<'a>,
@@ -1360,7 +1389,7 @@ impl TextBox {
 
         let mut text_lines = VecDeque::new();
         for (slice_len, seg) in segs.iter() {
-            for line in Self::text_from_segment(seg, *slice_len as usize) {
+            for line in Self::text_from_segment(ctx, seg, *slice_len as usize, self.text_extent) {
                 text_lines.push_back(line);
             }
         }
@@ -1383,11 +1412,16 @@ impl TextBox {
 
         self.buffered_text = text_lines;
 
-        // ボックスに入ったSimpleTextの位置を設定
-        let mut pos = numeric::Point2f::new(60.0, 60.0);
+        // ボックスに入ったRichTextの位置を設定
+        // ルビや大きな文字を含む行は、その分だけ行の高さが広がる
+        let mut pos = numeric::Point2f::new(Self::TEXT_PADDING, Self::TEXT_PADDING);
         for line in &mut self.text {
             line.set_position(pos);
-            pos.y += line.get_font_scale().y;
+            line.update_motion(self.motion_clock);
+            pos.y += line
+                .get_drawing_size(ctx.context)
+                .y
+                .max(line.get_font_scale().y);
         }
 
         if self.text_box_status == TextBoxStatus::WaitNextLineKey || scenario.iterator_finish() {
@@ -1405,21 +1439,17 @@ impl TextBox {
 
     pub fn set_fixed_text(&mut self, text: String, font_info: FontInformation) {
         self.text.clear();
-        self.text.push_back(SimpleText::new(
-            tobj::MovableText::new(
-                Box::new(tobj::UniText::new(
-                    text,
-                    numeric::Point2f::new(60.0, 60.0),
-                    numeric::Vector2f::new(1.0, 1.0),
-                    0.0,
-                    0,
-                    font_info,
-                )),
-                None,
-                0,
-            ),
-            Vec::new(),
-        ));
+        let mut text = RichText::new_plain(
+            &text,
+            numeric::Point2f::new(Self::TEXT_PADDING, Self::TEXT_PADDING),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+            font_info,
+            TextDirection::Horizontal,
+        );
+        text.set_layout_option(TextLayoutOption::wrap(self.text_extent));
+        self.text.push_back(text);
 
         self.set_text_box_status(TextBoxStatus::FixedText);
     }
@@ -1810,11 +1840,16 @@ impl ScenarioEvent {
         ctx: &mut SuzuContext<'a>,
         scno_ctx: Option<&mut ScenarioContext>,
     ) {
+        if self.scenario_box.text_box.update_text_motion() {
+            ctx.process_utility.redraw();
+            self.redraw_request = DrawRequest::Draw;
+        }
+
         match self.scenario.ref_current_element_mut() {
             ScenarioElement::Text(scenario_text) => {
                 if self.scenario_box.get_text_box_status() == TextBoxStatus::UpdatingText {
-                    // 表示する文字数を更新
-                    scenario_text.update_iterator();
+                    // 表示する文字数を更新。小鈴が疲れていると、文字送りが遅くなる
+                    scenario_text.update_iterator(ctx.text_speed_rate());
 
                     // 何行目までのテキストが表示されたか？
                     let current_segment =
@@ -38,11 +38,48 @@ pub struct SuzunaStatusMainPage {
     money_text: UniText,
     day_text: VerticalText,
     reputation_meter: ResultMeter,
+    reputation_reason_text: UniText,
     hp_meter: ResultMeter,
     event_list: DelayEventList<Self>,
     drwob_essential: DrawableEss,
 }
 
+///
+/// 最後に評判が動いた日に、何で評判が動いたかと、地区ごとの評判を表す文字列
+///
+fn reputation_reason_string<'a>(ctx: &mut SuzuContext<'a>) -> String {
+    let record = ctx.take_save_data().suzunaan_status.get_reputation_record();
+
+    let mut reason = match record.latest_day_summary() {
+        Some((date, mut summary)) => {
+            summary.sort_by(|a, b| {
+                b.1.abs()
+                    .partial_cmp(&a.1.abs())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            let events: Vec<String> = summary
+                .iter()
+                .take(4)
+                .map(|(event, amount)| format!("{}{:+.1}", event.to_str(), amount))
+                .collect();
+            format!("{}の評判 {}", date.to_short_string(), events.join(" "))
+        }
+        None => "評判はまだ動いていない".to_string(),
+    };
+
+    let districts: Vec<String> = record
+        .district_standings()
+        .iter()
+        .take(4)
+        .map(|(district, standing)| format!("{}{:.0}点", district, standing))
+        .collect();
+    if !districts.is_empty() {
+        reason.push_str(&format!("\n地区 {}", districts.join(" ")));
+    }
+
+    reason
+}
+
 impl SuzunaStatusMainPage {
     pub fn new<'a>(ctx: &mut SuzuContext<'a>) -> Self {
         let normal_scale_font = FontInformation::new(
@@ -201,6 +238,19 @@ impl SuzunaStatusMainPage {
             1,
         );
 
+        let reputation_reason_text = UniText::new(
+            reputation_reason_string(ctx),
+            numeric::Point2f::new(90.0, 410.0),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+            FontInformation::new(
+                ctx.resource.get_font(FontID::Cinema),
+                numeric::Vector2f::new(18.0, 18.0),
+                ggraphics::Color::from_rgba_u32(0x000000ff),
+            ),
+        );
+
         SuzunaStatusMainPage {
             table_frame: table_frame,
             desc_text: desc_text,
@@ -222,6 +272,7 @@ impl SuzunaStatusMainPage {
             ad_money_gain_text: ad_money_gain_text,
             todays_sched_text: todays_sched_text,
             reputation_meter: reputation_meter,
+            reputation_reason_text: reputation_reason_text,
             hp_meter: hp_meter,
             event_list: DelayEventList::new(),
             drwob_essential: DrawableEss::new(true, 0),
@@ -234,6 +285,8 @@ impl SuzunaStatusMainPage {
 
     pub fn change_suzunaan_reputation<'a>(&mut self, ctx: &mut SuzuContext<'a>, diff_hp: f32) {
         self.reputation_meter.apply_offset(ctx, diff_hp, 100);
+        self.reputation_reason_text
+            .replace_text(reputation_reason_string(ctx));
     }
 
     pub fn run_money_change_effect<'a>(&mut self, ctx: &mut SuzuContext<'a>, diff: i32, t: Clock) {
@@ -343,6 +396,7 @@ impl Drawable for SuzunaStatusMainPage {
             self.todays_sched_text.draw(ctx)?;
 
             self.reputation_meter.draw(ctx)?;
+            self.reputation_reason_text.draw(ctx)?;
             self.hp_meter.draw(ctx)?;
         }
 
@@ -375,7 +429,7 @@ impl Drawable for SuzunaStatusMainPage {
     }
 }
 
-#[derive(Copy, Clone, Hash, Eq, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Deserialize, Serialize)]
 pub enum SuzunaAdType {
     ShopNobori,
     TownNobori,
@@ -397,6 +451,17 @@ impl SuzunaAdType {
             _ => panic!("Unknown SuzunaAdType => {:?}", s),
         }
     }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::AdPaper => "チラシ",
+            Self::Chindon => "ちんどん屋",
+            Self::ShopNobori => "のぼり（店前）",
+            Self::TownNobori => "のぼり（里）",
+            Self::NewsPaper => "新聞",
+            Self::BunBunMaruPaper => "文々。新聞",
+        }
+    }
 }
 
 pub struct AdEntry {
@@ -455,6 +520,10 @@ impl AdEntry {
     pub fn apply_check(&mut self) {
         self.check_box.apply_check();
     }
+
+    pub fn replace_desc_text(&mut self, desc_text: String) {
+        self.desc_text.replace_text(desc_text);
+    }
 }
 
 impl Drawable for AdEntry {
@@ -510,13 +579,13 @@ impl ScenarioAdPage {
 
         let mut entry_pos = numeric::Point2f::new(pos.x + 50.0, pos.y + 100.0);
 
-        for (index, (ty_str, ad_type)) in vec![
-            ("チラシ", SuzunaAdType::AdPaper),
-            ("ちんどん屋", SuzunaAdType::Chindon),
-            ("のぼり（店前）", SuzunaAdType::ShopNobori),
-            ("のぼり（里）", SuzunaAdType::TownNobori),
-            ("新聞", SuzunaAdType::NewsPaper),
-            ("文々。新聞", SuzunaAdType::BunBunMaruPaper),
+        for (index, ad_type) in vec![
+            SuzunaAdType::AdPaper,
+            SuzunaAdType::Chindon,
+            SuzunaAdType::ShopNobori,
+            SuzunaAdType::TownNobori,
+            SuzunaAdType::NewsPaper,
+            SuzunaAdType::BunBunMaruPaper,
         ]
         .iter()
         .enumerate()
@@ -526,12 +595,7 @@ impl ScenarioAdPage {
                 entry_pos,
                 numeric::Vector2f::new(34.0, 34.0),
                 ctx.take_save_data().get_ad_status(*ad_type),
-                format!(
-                    "{:　<7}{:　>4}円/日\n {:　>7}点評判増加",
-                    ty_str,
-                    ctx.resource.get_default_ad_cost(*ad_type),
-                    ctx.resource.get_default_ad_reputation_gain(*ad_type),
-                ),
+                Self::ad_entry_text(ctx, *ad_type),
                 depth,
             );
 
@@ -591,6 +655,27 @@ impl ScenarioAdPage {
         ad_page
     }
 
+    ///
+    /// 広告の値段と評判に加えて、今日の客足への効果を載せる
+    ///
+    fn ad_entry_text<'a>(ctx: &mut SuzuContext<'a>, ad_type: SuzunaAdType) -> String {
+        let effect_text = match ctx.todays_ad_effects().get(ad_type) {
+            Some(effect) => match effect.remaining_days {
+                Some(days) => format!("客足+{:.0}% 残り{}日", effect.customer_boost * 100.0, days),
+                None => format!("客足+{:.0}% 余韻", effect.customer_boost * 100.0),
+            },
+            None => "効果なし".to_string(),
+        };
+
+        format!(
+            "{:　<7}{:　>4}円/日\n {:　>7}点評判増加\n {}",
+            ad_type.to_str(),
+            ctx.resource.get_default_ad_cost(ad_type),
+            ctx.resource.get_default_ad_reputation_gain(ad_type),
+            effect_text,
+        )
+    }
+
     pub fn click_handler<'a>(&mut self, ctx: &mut SuzuContext<'a>, click_point: numeric::Point2f) {
         let total_ad_cost = self.total_ad_cost(ctx);
 
@@ -626,7 +711,14 @@ impl ScenarioAdPage {
 
             // 追加実行
             entry.check_box.click_handler(click_point);
-            ctx.change_ad_status(*ad_type, entry.is_checked());
+            if entry.is_checked() != ctx.take_save_data().get_ad_status(*ad_type) {
+                ctx.change_ad_status(*ad_type, entry.is_checked());
+            }
+        }
+
+        // 重ねた広告の効果は互いに目減りするので、すべての広告の効果を書き直す
+        for (ad_type, entry) in self.ad_table.iter_mut() {
+            entry.replace_desc_text(Self::ad_entry_text(ctx, *ad_type));
         }
     }
 
@@ -668,6 +760,11 @@ impl ScenarioAdPage {
         return total_ad_reputation_gain as i32;
     }
 
+    ///
+    /// 所持金が足りないときに、広告のチェックを外す
+    /// 広告費を払わなくなるだけで、出している広告の期間は打ち切らない
+    /// 広告を打ち切るのは、小鈴がチェックを外したときだけ
+    ///
     pub fn uncheck_most_expensive<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
         for ad_type in vec![
             SuzunaAdType::AdPaper,
@@ -687,7 +784,12 @@ impl ScenarioAdPage {
                     .unwrap()
                     .check_box
                     .try_check(false);
-                ctx.change_ad_status(*ad_type, false);
+                ctx.take_save_data_mut().change_ad_status(*ad_type, false);
+                let desc_text = Self::ad_entry_text(ctx, *ad_type);
+                self.ad_table
+                    .get_mut(ad_type)
+                    .unwrap()
+                    .replace_desc_text(desc_text);
             }
         }
     }
@@ -770,6 +872,17 @@ impl SuzunaAdAgencyType {
             _ => panic!("Unknown SuzunaAdType => {:?}", s),
         }
     }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::HakureiJinja => "博麗神社",
+            Self::KirisameMahoten => "霧雨魔法店",
+            Self::GettoDango => "月兎団子屋",
+            Self::Kusuriya => "薬屋",
+            Self::Hieda => "稗田家",
+            Self::YamaJinja => "山頂の神社",
+        }
+    }
 }
 
 pub struct ScenarioAgencyPage {
@@ -789,13 +902,13 @@ impl ScenarioAgencyPage {
 
         let mut entry_pos = numeric::Point2f::new(pos.x + 50.0, pos.y + 100.0);
 
-        for (index, (ty_str, ad_type)) in vec![
-            ("博麗神社", SuzunaAdAgencyType::HakureiJinja),
-            ("霧雨魔法店", SuzunaAdAgencyType::KirisameMahoten),
-            ("月兎団子屋", SuzunaAdAgencyType::GettoDango),
-            ("薬屋", SuzunaAdAgencyType::Kusuriya),
-            ("稗田家", SuzunaAdAgencyType::Hieda),
-            ("山頂の神社", SuzunaAdAgencyType::YamaJinja),
+        for (index, ad_type) in vec![
+            SuzunaAdAgencyType::HakureiJinja,
+            SuzunaAdAgencyType::KirisameMahoten,
+            SuzunaAdAgencyType::GettoDango,
+            SuzunaAdAgencyType::Kusuriya,
+            SuzunaAdAgencyType::Hieda,
+            SuzunaAdAgencyType::YamaJinja,
         ]
         .iter()
         .enumerate()
@@ -807,7 +920,7 @@ impl ScenarioAgencyPage {
                 ctx.take_save_data().get_ad_agency_status(ad_type),
                 format!(
                     "{:　<6}評判{:　>2}点以上\n{:　>9}円収入増加",
-                    ty_str,
+                    ad_type.to_str(),
                     ctx.resource.get_default_ad_agency_cost(ad_type),
                     ctx.resource.get_default_ad_agency_money_gain(ad_type),
                 ),
@@ -865,12 +978,7 @@ impl ScenarioAgencyPage {
 
     pub fn click_handler<'a>(&mut self, ctx: &mut SuzuContext<'a>, click_point: numeric::Point2f) {
         for (ad_type, entry) in self.ad_table.iter_mut() {
-            if ctx.resource.get_default_ad_agency_cost(ad_type) as f32
-                <= ctx
-                    .take_save_data()
-                    .suzunaan_status
-                    .get_current_reputation()
-            {
+            if ctx.ad_agency_is_available(ad_type) {
                 entry.check_box.click_handler(click_point);
                 ctx.change_ad_agency_status(*ad_type, entry.is_checked());
             }
@@ -926,12 +1034,7 @@ impl ScenarioAgencyPage {
             SuzunaAdAgencyType::YamaJinja,
         ] {
             if self.ad_table.get(&ad_type).unwrap().is_checked() {
-                if ctx.resource.get_default_ad_agency_cost(&ad_type) as f32
-                    > ctx
-                        .take_save_data()
-                        .suzunaan_status
-                        .get_current_reputation()
-                {
+                if !ctx.ad_agency_is_available(&ad_type) {
                     self.ad_table
                         .get_mut(&ad_type)
                         .unwrap()
@@ -982,18 +1085,206 @@ impl Drawable for ScenarioAgencyPage {
     }
 }
 
+///
+/// 仕入れのページに並べる本の数
+///
+const PURCHASE_PAGE_OFFERS_COUNT: usize = 6;
+
+///
+/// # 本の仕入れのページ
+///
+/// 需要が高い本から順に並べ、チェックで発注、チェックを外すと取り消し
+///
+pub struct ScenarioPurchasePage {
+    header_text: UniText,
+    status_text: UniText,
+    supplier: book_management::SupplierCatalogue,
+    entries: Vec<(String, AdEntry)>,
+    drwob_essential: DrawableEss,
+}
+
+impl ScenarioPurchasePage {
+    pub fn new<'a>(
+        ctx: &mut SuzuContext<'a>,
+        pos: numeric::Point2f,
+        area_size: numeric::Vector2f,
+        depth: i8,
+    ) -> Self {
+        let supplier = book_management::SupplierCatalogue::from_resource(
+            ctx.resource,
+            &ctx.take_save_data().event_progress,
+        );
+        let today = ctx.take_save_data().date;
+
+        let mut entries = Vec::new();
+        let mut entry_pos = numeric::Point2f::new(pos.x + 50.0, pos.y + 100.0);
+
+        let offers: Vec<book_management::SupplierOffer> = supplier
+            .offers_by_demand(ctx.resource, &today)
+            .into_iter()
+            .take(PURCHASE_PAGE_OFFERS_COUNT)
+            .cloned()
+            .collect();
+
+        for (index, offer) in offers.iter().enumerate() {
+            let entry = AdEntry::new(
+                ctx,
+                entry_pos,
+                numeric::Vector2f::new(34.0, 34.0),
+                ctx.take_save_data()
+                    .purchase_record
+                    .has_pending_order(&offer.book.name),
+                format!(
+                    "{:　<7}{:　>4}円/{}冊\n {:　>7}日後着",
+                    offer.book.name,
+                    offer.price,
+                    book_management::PURCHASE_UNIT,
+                    offer.lead_days,
+                ),
+                depth,
+            );
+
+            entries.push((offer.book.name.clone(), entry));
+
+            if index % 2 == 0 {
+                entry_pos.x = 380.0;
+            } else {
+                entry_pos.x = pos.x + 50.0;
+                entry_pos.y += 80.0;
+            }
+        }
+
+        let font_info = FontInformation::new(
+            ctx.resource.get_font(FontID::Cinema),
+            numeric::Vector2f::new(30.0, 30.0),
+            ggraphics::Color::BLACK,
+        );
+        let mut header_text = UniText::new(
+            "本の仕入れ".to_string(),
+            numeric::Point2f::new(0.0, 0.0),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+            font_info,
+        );
+
+        header_text.make_center(ctx.context, numeric::Point2f::new(area_size.x / 2.0, 50.0));
+
+        let status_text = UniText::new(
+            String::new(),
+            numeric::Point2f::new(pos.x + 50.0, entry_pos.y + 40.0),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+            FontInformation::new(
+                ctx.resource.get_font(FontID::Cinema),
+                numeric::Vector2f::new(20.0, 20.0),
+                ggraphics::Color::BLACK,
+            ),
+        );
+
+        let mut purchase_page = ScenarioPurchasePage {
+            header_text: header_text,
+            status_text: status_text,
+            supplier: supplier,
+            entries: entries,
+            drwob_essential: DrawableEss::new(true, depth),
+        };
+
+        purchase_page.update_status_text(ctx);
+        purchase_page
+    }
+
+    fn update_status_text<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
+        let save_data = ctx.take_save_data();
+        self.status_text.replace_text(format!(
+            "所持金　{}円　　発注中　{}件",
+            save_data.task_result.total_money,
+            save_data.purchase_record.pending_orders_count()
+        ));
+    }
+
+    pub fn click_handler<'a>(&mut self, ctx: &mut SuzuContext<'a>, click_point: numeric::Point2f) {
+        for (title, entry) in self.entries.iter_mut() {
+            let was_checked = entry.is_checked();
+            entry.check_box.click_handler(click_point);
+
+            if was_checked == entry.is_checked() {
+                continue;
+            }
+
+            let accepted = if entry.is_checked() {
+                match self.supplier.find(title) {
+                    Some(offer) => ctx.take_save_data_mut().place_purchase_order(offer),
+                    None => false,
+                }
+            } else {
+                ctx.take_save_data_mut().cancel_purchase_order(title)
+            };
+
+            // 所持金が足りないなどで発注できなければ、元に戻す
+            if !accepted {
+                entry.check_box.try_check(was_checked);
+            }
+        }
+
+        self.update_status_text(ctx);
+    }
+}
+
+impl Drawable for ScenarioPurchasePage {
+    fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult<()> {
+        if self.is_visible() {
+            self.header_text.draw(ctx)?;
+            self.status_text.draw(ctx)?;
+            for (_, entry) in self.entries.iter_mut() {
+                entry.draw(ctx)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn hide(&mut self) {
+        self.drwob_essential.visible = false;
+    }
+
+    #[inline(always)]
+    fn appear(&mut self) {
+        self.drwob_essential.visible = true;
+    }
+
+    #[inline(always)]
+    fn is_visible(&self) -> bool {
+        self.drwob_essential.visible
+    }
+
+    #[inline(always)]
+    fn set_drawing_depth(&mut self, depth: i8) {
+        self.drwob_essential.drawing_depth = depth;
+    }
+
+    #[inline(always)]
+    fn get_drawing_depth(&self) -> i8 {
+        self.drwob_essential.drawing_depth
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub enum SuzunaStatusPageID {
     Main = 0,
     Ad,
     AdAgency,
     Schedule,
+    Purchase,
 }
 
 pub struct SuzunaStatusPages {
     main_page: SuzunaStatusMainPage,
     ad_page: ScenarioAdPage,
     ad_agency_page: ScenarioAgencyPage,
+    purchase_page: ScenarioPurchasePage,
     sched_page: ScenarioSchedPage,
     current_page: SuzunaStatusPageID,
 }
@@ -1018,12 +1309,20 @@ impl SuzunaStatusPages {
             0,
         );
 
+        let purchase_page = ScenarioPurchasePage::new(
+            ctx,
+            numeric::Point2f::new(0.0, 0.0),
+            numeric::Vector2f::new(rect.w, rect.h),
+            0,
+        );
+
         let main_page = SuzunaStatusMainPage::new(ctx);
 
         SuzunaStatusPages {
             main_page: main_page,
             ad_page: ad_page,
             ad_agency_page: ad_agency_page,
+            purchase_page: purchase_page,
             sched_page: ScenarioSchedPage::new(
                 ctx,
                 scno_ctx,
@@ -1039,6 +1338,7 @@ impl SuzunaStatusPages {
             SuzunaStatusPageID::Main => self.main_page.draw(ctx).unwrap(),
             SuzunaStatusPageID::Ad => self.ad_page.draw(ctx).unwrap(),
             SuzunaStatusPageID::AdAgency => self.ad_agency_page.draw(ctx).unwrap(),
+            SuzunaStatusPageID::Purchase => self.purchase_page.draw(ctx).unwrap(),
             SuzunaStatusPageID::Schedule => self.sched_page.draw(ctx).unwrap(),
         }
     }
@@ -1047,7 +1347,8 @@ impl SuzunaStatusPages {
         match self.current_page {
             SuzunaStatusPageID::Main => self.current_page = SuzunaStatusPageID::Ad,
             SuzunaStatusPageID::Ad => self.current_page = SuzunaStatusPageID::AdAgency,
-            SuzunaStatusPageID::AdAgency => self.current_page = SuzunaStatusPageID::Schedule,
+            SuzunaStatusPageID::AdAgency => self.current_page = SuzunaStatusPageID::Purchase,
+            SuzunaStatusPageID::Purchase => self.current_page = SuzunaStatusPageID::Schedule,
             SuzunaStatusPageID::Schedule => (),
         }
     }
@@ -1060,7 +1361,8 @@ impl SuzunaStatusPages {
                 self.update_main_ad_and_agency_status(ctx);
             }
             SuzunaStatusPageID::AdAgency => self.current_page = SuzunaStatusPageID::Ad,
-            SuzunaStatusPageID::Schedule => self.current_page = SuzunaStatusPageID::AdAgency,
+            SuzunaStatusPageID::Purchase => self.current_page = SuzunaStatusPageID::AdAgency,
+            SuzunaStatusPageID::Schedule => self.current_page = SuzunaStatusPageID::Purchase,
         }
     }
 
@@ -1069,7 +1371,7 @@ impl SuzunaStatusPages {
     }
 
     pub fn page_len(&self) -> usize {
-        4
+        5
     }
 
     pub fn click_handler<'a>(
@@ -1081,6 +1383,7 @@ impl SuzunaStatusPages {
         match self.current_page {
             SuzunaStatusPageID::Ad => self.ad_page.click_handler(ctx, click_point),
             SuzunaStatusPageID::AdAgency => self.ad_agency_page.click_handler(ctx, click_point),
+            SuzunaStatusPageID::Purchase => self.purchase_page.click_handler(ctx, click_point),
             SuzunaStatusPageID::Schedule => self.sched_page.click_handler(ctx, click_point, button),
             _ => (),
         }
@@ -1121,6 +1424,10 @@ impl SuzunaStatusPages {
         self.current_page = SuzunaStatusPageID::Ad;
     }
 
+    pub fn show_purchase_page(&mut self) {
+        self.current_page = SuzunaStatusPageID::Purchase;
+    }
+
     pub fn change_kosuzu_hp<'a>(&mut self, ctx: &mut SuzuContext<'a>, diff: f32) {
         self.main_page.change_kosuzu_hp(ctx, diff);
     }
@@ -1313,6 +1620,12 @@ impl SuzunaStatusScreen {
         self.redraw_request = DrawRequest::Draw;
     }
 
+    pub fn show_purchase_page(&mut self) {
+        self.pages.show_purchase_page();
+        self.check_move_page_icon_visibility();
+        self.redraw_request = DrawRequest::Draw;
+    }
+
     pub fn change_main_page_money<'a>(&mut self, ctx: &mut SuzuContext<'a>, diff: i32, t: Clock) {
         self.pages.main_page.run_money_change_effect(ctx, diff, t);
         self.redraw_request = DrawRequest::Draw;
@@ -1328,6 +1641,7 @@ impl SuzunaStatusScreen {
 	    SuzunaStatusPageID::Main => self.show_main_page(ctx),
 	    SuzunaStatusPageID::Ad => self.show_ad_page(),
 	    SuzunaStatusPageID::AdAgency => self.show_ad_agency_page(),
+	    SuzunaStatusPageID::Purchase => self.show_purchase_page(),
 	    SuzunaStatusPageID::Schedule => self.show_schedule_page(),
 	}
     }
@@ -1960,7 +2274,7 @@ impl ScheduleSelectWindow {
             ctx.resource,
             numeric::Point2f::new(30.0, 30.0),
             TileBatchTextureID::OldStyleFrame,
-            FrameData::new(vec![220.0], vec![56.0; 3]),
+            FrameData::new(vec![220.0], vec![56.0; 6]),
             numeric::Vector2f::new(0.3, 0.3),
 	    ggraphics::FilterMode::Nearest,
             0,
@@ -1982,7 +2296,10 @@ impl ScheduleSelectWindow {
         );
 
         let mut candidate_vtext = Vec::new();
-        for (index, s) in vec!["店番", "外出", "家で休む"].iter().enumerate() {
+        for (index, s) in vec!["店番", "稗田邸", "団子屋", "寺子屋", "家で休む", "写本"]
+            .iter()
+            .enumerate()
+        {
             let mut vtext = VerticalText::new(
                 s.to_string(),
                 numeric::Point2f::new(0.0, 0.0),
@@ -2124,7 +2441,14 @@ impl StackMessagePassingWindow<WeekScheduleMessage> for ScheduleSelectWindow {
                 1 => Some(game_system::DayWorkType::GoingOut(
                     game_system::GoingOutEvent::AkyuTei,
                 )),
-                2 => Some(game_system::DayWorkType::TakingRest),
+                2 => Some(game_system::DayWorkType::GoingOut(
+                    game_system::GoingOutEvent::Dangoya,
+                )),
+                3 => Some(game_system::DayWorkType::GoingOut(
+                    game_system::GoingOutEvent::Terakoya,
+                )),
+                4 => Some(game_system::DayWorkType::TakingRest),
+                5 => Some(game_system::DayWorkType::Copying),
                 _ => None,
             }
         }
@@ -7,6 +7,7 @@ use sub_screen::SubScreen;
 use crate::libsuzu::core::Clock;
 use crate::libsuzu::device::*;
 use crate::libsuzu::graphics::drawable::*;
+use crate::libsuzu::graphics::layout::{Anchor, AnchoredLayout};
 use crate::libsuzu::graphics::object::sub_screen;
 use crate::libsuzu::graphics::object::*;
 use crate::impl_node2d_for_wrapped;
@@ -491,7 +492,19 @@ impl SelectShelvingBookUI {
         ui_rect: numeric::Rect,
         mut shelving_book: Vec<BookInformation>,
     ) -> Self {
-        let mut box_book_info = ctx.take_save_data().task_result.not_shelved_books.clone();
+        // 台帳の返却箱の本のうち、持ち歩いていない本が返却箱に残っている
+        let mut box_book_info: Vec<BookInformation> = ctx
+            .take_save_data()
+            .book_ledger
+            .return_box_books()
+            .into_iter()
+            .filter(|book| {
+                !shelving_book
+                    .iter()
+                    .any(|shelving| shelving.get_unique_id() == book.get_unique_id())
+            })
+            .cloned()
+            .collect();
 
         box_book_info.sort_by(|a, b| a.billing_number.cmp(&b.billing_number));
         shelving_book.sort_by(|a, b| a.billing_number.cmp(&b.billing_number));
@@ -1195,6 +1208,9 @@ impl SelectStoreBookUI {
             let returned = self.shelving_books.swap_remove(*selecting_index);
             self.stored_books.push(returned);
             ctx.take_save_data_mut().award_data.shelving_count += 1;
+            ctx.take_save_data_mut()
+                .suzunaan_status
+                .add_kosuzu_hp(-fatigue::SHELVING_HP_COST);
         }
 
         self.update_window(ctx.context);
@@ -1209,6 +1225,10 @@ impl SelectStoreBookUI {
     pub fn get_storing_result(&self) -> (Vec<BookInformation>, Vec<BookInformation>) {
         (self.stored_books.clone(), self.shelving_books.clone())
     }
+
+    pub fn get_book_shelf_info(&self) -> &BookShelfInformation {
+        &self.book_shelf_info
+    }
 }
 
 impl Drawable for SelectStoreBookUI {
@@ -1527,6 +1547,231 @@ impl Drawable for ShelvingDetailContents {
     }
 }
 
+///
+/// # 蔵書目録を、今の需要が高い順に表示するメニュー
+///
+pub struct CatalogueDetailContents {
+    canvas: MovableWrap<SubScreen>,
+    menu_rect: numeric::Rect,
+    catalogue_frame: TableFrame,
+    title: VerticalText,
+    cell_desc: Vec<VerticalText>,
+    entry_text: Vec<VerticalText>,
+    background: Texture,
+}
+
+impl CatalogueDetailContents {
+    pub fn new<'a>(ctx: &mut SuzuContext<'a>, menu_rect: numeric::Rect, t: Clock) -> Self {
+        let title = VerticalText::new(
+            "目録".to_string(),
+            numeric::Point2f::new(menu_rect.w - 110.0, 70.0),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+            FontInformation::new(
+                ctx.resource.get_font(FontID::JpFude1),
+                numeric::Vector2f::new(40.0, 40.0),
+                ggraphics::Color::from_rgba_u32(0xff),
+            ),
+        );
+
+        let frame = TableFrame::new(
+            ctx.resource,
+            numeric::Point2f::new(25.0, 60.0),
+            TileBatchTextureID::OldStyleFrame,
+            FrameData::new(vec![260.0, 160.0, 100.0, 70.0, 70.0], vec![44.0; 6]),
+            numeric::Vector2f::new(0.3, 0.3),
+            ggraphics::FilterMode::Nearest,
+            0,
+        );
+
+        let font_info = FontInformation::new(
+            ctx.resource.get_font(FontID::JpFude1),
+            numeric::Vector2f::new(30.0, 30.0),
+            ggraphics::Color::from_rgba_u32(0xff),
+        );
+
+        let mut cell_desc = Vec::new();
+        for (index, desc) in ["題名", "作者", "分類", "稀少", "需要"].iter().enumerate() {
+            let mut vtext = VerticalText::new(
+                desc.to_string(),
+                numeric::Point2f::new(0.0, 0.0),
+                numeric::Vector2f::new(1.0, 1.0),
+                0.0,
+                0,
+                font_info.clone(),
+            );
+            set_table_frame_cell_center!(
+                ctx.context,
+                frame,
+                vtext,
+                numeric::Vector2u::new(5, index as u32)
+            );
+            cell_desc.push(vtext);
+        }
+
+        let background = Texture::new(
+            ctx.ref_texture(TextureID::MenuArt2),
+            numeric::Point2f::new(menu_rect.w - 1366.0, 0.0),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+        );
+
+        let mut contents = CatalogueDetailContents {
+            canvas: MovableWrap::new(
+                Box::new(SubScreen::new(
+                    ctx.context,
+                    menu_rect,
+                    0,
+                    ggraphics::Color::from_rgba_u32(0xffffffff),
+                )),
+                None,
+                t,
+            ),
+            catalogue_frame: frame,
+            menu_rect: menu_rect,
+            title: title,
+            cell_desc: cell_desc,
+            entry_text: Vec::new(),
+            background: background,
+        };
+
+        contents.update_contents(ctx);
+        contents
+    }
+
+    ///
+    /// 今日の需要で並べ直す
+    ///
+    pub fn update_contents<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
+        self.entry_text.clear();
+
+        let font_info = FontInformation::new(
+            ctx.resource.get_font(FontID::JpFude1),
+            numeric::Vector2f::new(30.0, 30.0),
+            ggraphics::Color::from_rgba_u32(0xff),
+        );
+
+        let today = ctx.take_save_data().date;
+        let catalogue = ctx.resource.get_book_catalogue();
+        let shown_count = self.catalogue_frame.get_rows() - 1;
+
+        for (index, (entry, demand)) in catalogue
+            .entries_by_demand(&today)
+            .into_iter()
+            .take(shown_count)
+            .enumerate()
+        {
+            let table_pos_x = (self.catalogue_frame.get_rows() - 2 - index) as u32;
+            let cells = [
+                entry.title.clone(),
+                entry.author.clone(),
+                entry.genre.clone(),
+                entry.rarity.to_str().to_string(),
+                book_catalogue::demand_level_str(demand).to_string(),
+            ];
+
+            for (cell_index, text) in cells.iter().enumerate() {
+                let mut vtext = VerticalText::new(
+                    text.clone(),
+                    numeric::Point2f::new(0.0, 0.0),
+                    numeric::Vector2f::new(1.0, 1.0),
+                    0.0,
+                    0,
+                    font_info.clone(),
+                );
+                set_table_frame_cell_center!(
+                    ctx.context,
+                    self.catalogue_frame,
+                    vtext,
+                    numeric::Vector2u::new(table_pos_x, cell_index as u32)
+                );
+                self.entry_text.push(vtext);
+            }
+        }
+    }
+
+    ///
+    /// 移動関数を変更しスライドインするように見せる
+    ///
+    pub fn slide_appear(&mut self, slide_position: numeric::Point2f, t: Clock) {
+        self.canvas
+            .override_move_func(move_fn::devide_distance(slide_position, 0.5), t);
+    }
+
+    ///
+    /// 移動関数を変更しスライドアウトするように見せる
+    ///
+    pub fn slide_hide(&mut self, t: Clock) {
+        self.canvas.override_move_func(
+            move_fn::devide_distance(numeric::Point2f::new(-self.menu_rect.w, 0.0), 0.2),
+            t,
+        );
+    }
+
+    ///
+    /// # 再描画要求有り
+    ///
+    pub fn move_and_effect<'a>(&mut self, ctx: &mut SuzuContext<'a>, t: Clock) {
+        if !self.canvas.is_stop() {
+            self.canvas.move_with_func(t);
+            ctx.process_utility.redraw();
+        }
+    }
+}
+
+impl Drawable for CatalogueDetailContents {
+    fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult<()> {
+        if self.is_visible() {
+            sub_screen::stack_screen(ctx, &self.canvas);
+
+            self.background.draw(ctx)?;
+
+            self.catalogue_frame.draw(ctx)?;
+
+            self.title.draw(ctx)?;
+
+            for vtext in &mut self.cell_desc {
+                vtext.draw(ctx)?;
+            }
+
+            for vtext in &mut self.entry_text {
+                vtext.draw(ctx)?;
+            }
+
+            sub_screen::pop_screen(ctx);
+            self.canvas.draw(ctx)?;
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn hide(&mut self) {
+        self.canvas.hide();
+    }
+
+    #[inline(always)]
+    fn appear(&mut self) {
+        self.canvas.appear();
+    }
+
+    #[inline(always)]
+    fn is_visible(&self) -> bool {
+        self.canvas.is_visible()
+    }
+
+    #[inline(always)]
+    fn set_drawing_depth(&mut self, depth: i8) {
+        self.canvas.set_drawing_depth(depth);
+    }
+
+    #[inline(always)]
+    fn get_drawing_depth(&self) -> i8 {
+        self.canvas.get_drawing_depth()
+    }
+}
+
 ///
 /// メニューに表示するやつ
 ///
@@ -1658,7 +1903,7 @@ impl ShopMenuContents {
             ggraphics::Color::from_rgba_u32(0x000000ff),
         );
 
-        let task_result = &ctx.take_save_data().task_result;
+        let book_ledger = &ctx.take_save_data().book_ledger;
 
         let large_scale_font = FontInformation::new(
             ctx.resource.get_font(FontID::JpFude1),
@@ -1681,10 +1926,7 @@ impl ShopMenuContents {
         );
 
         self.wait_for_return_num = VerticalText::new(
-            format!(
-                "{}冊",
-                number_to_jk(task_result.borrowing_books.len() as u64)
-            ),
+            format!("{}冊", number_to_jk(book_ledger.lent_books_count() as u64)),
             numeric::Point2f::new(130.0, 170.0),
             numeric::Vector2f::new(1.0, 1.0),
             0.0,
@@ -1695,7 +1937,7 @@ impl ShopMenuContents {
         self.not_shelved_num = VerticalText::new(
             format!(
                 "{}冊",
-                number_to_jk(task_result.not_shelved_books.len() as u64)
+                number_to_jk(book_ledger.return_box_books_count() as u64)
             ),
             numeric::Point2f::new(30.0, 170.0),
             numeric::Vector2f::new(1.0, 1.0),
@@ -1872,11 +2114,13 @@ impl Drawable for ShopMenu {
 pub enum ShopDetailMenuSymbol {
     ShelvingBooks = 0,
     SuzunaMap,
+    Catalogue,
     None,
 }
 
 pub struct ShopDetailMenuContents {
     shelving_info: ShelvingDetailContents,
+    catalogue_info: CatalogueDetailContents,
     drwob_essential: DrawableEss,
     contents_switch: ShopDetailMenuSymbol,
     appear_position: numeric::Point2f,
@@ -1892,6 +2136,7 @@ impl ShopDetailMenuContents {
     ) -> Self {
         ShopDetailMenuContents {
             shelving_info: ShelvingDetailContents::new(ctx, shelving_rect, t),
+            catalogue_info: CatalogueDetailContents::new(ctx, shelving_rect, t),
             drwob_essential: DrawableEss::new(false, 0),
             contents_switch: ShopDetailMenuSymbol::None,
             appear_position: appear_position,
@@ -1905,27 +2150,41 @@ impl ShopDetailMenuContents {
         player_shelving: &Vec<BookInformation>,
     ) {
         self.shelving_info.update_contents(ctx, player_shelving);
+        self.catalogue_info.update_contents(ctx);
     }
 
     pub fn detail_menu_is_open(&self) -> bool {
         self.now_appear
     }
 
+    pub fn get_slide_contents(&self) -> ShopDetailMenuSymbol {
+        self.contents_switch
+    }
+
     pub fn hide_toggle(&mut self, t: Clock) {
         self.now_appear = false;
         self.shelving_info.slide_hide(t);
+        self.catalogue_info.slide_hide(t);
         self.hide();
     }
 
     pub fn appear_toggle(&mut self, t: Clock) {
         self.now_appear = true;
-        self.shelving_info.slide_appear(self.appear_position, t);
+        match self.contents_switch {
+            ShopDetailMenuSymbol::ShelvingBooks => {
+                self.shelving_info.slide_appear(self.appear_position, t);
+            }
+            ShopDetailMenuSymbol::Catalogue => {
+                self.catalogue_info.slide_appear(self.appear_position, t);
+            }
+            _ => (),
+        }
         self.appear();
     }
 
     pub fn slide_toggle(&mut self, t: Clock) {
         match self.contents_switch {
-            ShopDetailMenuSymbol::ShelvingBooks => {
+            ShopDetailMenuSymbol::ShelvingBooks | ShopDetailMenuSymbol::Catalogue => {
                 if self.now_appear {
                     self.hide_toggle(t);
                 } else {
@@ -1948,6 +2207,7 @@ impl ShopDetailMenuContents {
     ///
     pub fn move_and_effect<'a>(&mut self, ctx: &mut SuzuContext<'a>, t: Clock) {
         self.shelving_info.move_and_effect(ctx, t);
+        self.catalogue_info.move_and_effect(ctx, t);
     }
 }
 
@@ -1955,6 +2215,7 @@ impl Drawable for ShopDetailMenuContents {
     fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult<()> {
         if self.is_visible() {
             self.shelving_info.draw(ctx)?;
+            self.catalogue_info.draw(ctx)?;
         }
 
         Ok(())
@@ -2203,6 +2464,11 @@ impl ShopMenuMaster {
                 .shelving_info
                 .canvas
                 .contains(ctx.context, point)
+            || self
+                .detail_menu
+                .catalogue_info
+                .canvas
+                .contains(ctx.context, point)
     }
 
     pub fn update_contents(
@@ -2234,16 +2500,39 @@ impl ShopMenuMaster {
             VirtualKey::Action3 => {
                 self.toggle_detail_menu(t);
             }
+            VirtualKey::Action5 => {
+                self.toggle_catalogue_menu(t);
+            }
             _ => (),
         }
     }
 
-    pub fn toggle_detail_menu(&mut self, t: Clock) {
-        if self.first_menu_is_open() {
-            self.detail_menu
-                .set_slide_contents(ShopDetailMenuSymbol::ShelvingBooks);
-            self.detail_menu.slide_toggle(t);
+    ///
+    /// 別の内容が開いていれば閉じてから、contentsの詳細メニューを開閉する
+    ///
+    fn switch_detail_menu(&mut self, contents: ShopDetailMenuSymbol, t: Clock) {
+        if !self.first_menu_is_open() {
+            return;
         }
+
+        if self.detail_menu_is_open() && self.detail_menu.get_slide_contents() != contents {
+            self.detail_menu.hide_toggle(t);
+        }
+
+        self.detail_menu.set_slide_contents(contents);
+        self.detail_menu.slide_toggle(t);
+    }
+
+    pub fn toggle_detail_menu(&mut self, t: Clock) {
+        self.switch_detail_menu(ShopDetailMenuSymbol::ShelvingBooks, t);
+    }
+
+    pub fn toggle_catalogue_menu(&mut self, t: Clock) {
+        self.switch_detail_menu(ShopDetailMenuSymbol::Catalogue, t);
+    }
+
+    pub fn close_detail_menu(&mut self, t: Clock) {
+        self.detail_menu.hide_toggle(t);
     }
 
     ///
@@ -2415,6 +2704,12 @@ impl ShopSpecialObject {
         }
     }
 
+    pub fn get_storing_shelf_info(&self) -> Option<BookShelfInformation> {
+        self.storing_select_ui
+            .as_ref()
+            .map(|ui| ui.get_book_shelf_info().clone())
+    }
+
     pub fn hide_storing_select_ui(
         &mut self,
         t: Clock,
@@ -2629,6 +2924,7 @@ pub struct DrawableShopClock {
     long_needle: Texture,
     short_needle: Texture,
     time: ShopClock,
+    layout: AnchoredLayout,
     drwob_essential: DrawableEss,
 }
 
@@ -2735,10 +3031,27 @@ impl DrawableShopClock {
             long_needle: long_needle_texture,
             short_needle: short_needle_texture,
             time: time,
+            // 設定ファイルの座標は、画面左上からの余白として扱う
+            layout: AnchoredLayout::new(
+                Anchor::TopLeft,
+                numeric::Vector2f::new(background_pos.x, background_pos.y),
+            ),
             drwob_essential: DrawableEss::new(true, 0),
         }
     }
 
+    ///
+    /// 表示領域の変化に合わせて、時計を画面左上に配置し直す
+    ///
+    pub fn relayout(&mut self, area: numeric::Rect) {
+        let dest = self.layout.place(area, numeric::Vector2f::new(0.0, 0.0));
+        let diff = dest - self.background.get_position();
+
+        self.background.move_diff(diff);
+        self.long_needle.move_diff(diff);
+        self.short_needle.move_diff(diff);
+    }
+
     pub fn update_needle_angle(&mut self) {
         let (short_needle_angle, long_needle_angle) =
             util::clock_needle_angle_inverse(self.time.hour, self.time.minute);
@@ -2857,6 +3170,7 @@ pub enum CommandPaletteFunc {
     Action,
     ShowShopMenu,
     ShowMap,
+    RepairBooks,
     Pause,
 }
 
@@ -2865,11 +3179,30 @@ pub struct ShopCommandPalette {
     action_button: FramedButton,
     shop_menu_button: FramedButton,
     show_map_button: FramedButton,
+    repair_button: FramedButton,
     pause_button: FramedButton,
+    layout: AnchoredLayout,
+    size: numeric::Vector2f,
+    hidden_position: numeric::Point2f,
 }
 
 impl ShopCommandPalette {
-    pub fn new<'a>(ctx: &mut SuzuContext<'a>, pos: numeric::Rect, depth: i8, t: Clock) -> Self {
+    // パレットがせり出す量
+    const SLIDE_DISTANCE: f32 = 70.0;
+
+    ///
+    /// layoutには、パレットが引っ込んでいる状態の配置を指定する
+    ///
+    pub fn new<'a>(
+        ctx: &mut SuzuContext<'a>,
+        size: numeric::Vector2f,
+        layout: AnchoredLayout,
+        depth: i8,
+        t: Clock,
+    ) -> Self {
+        let hidden_position = layout.place(crate::core::virtual_screen_rect(), size);
+        let pos = numeric::Rect::new(hidden_position.x, hidden_position.y, size.x, size.y);
+
         let font_info = FontInformation::new(
             ctx.resource.get_font(FontID::JpFude1),
             numeric::Vector2f::new(32.0, 32.0),
@@ -2878,7 +3211,7 @@ impl ShopCommandPalette {
 
         let action_button = FramedButton::new(
             ctx,
-            numeric::Rect::new(350.0, 15.0, 120.0, 85.0),
+            numeric::Rect::new(250.0, 15.0, 120.0, 85.0),
             10.0,
             2.0,
             ggraphics::Color::from_rgba(90, 80, 63, 255),
@@ -2890,7 +3223,7 @@ impl ShopCommandPalette {
 
         let shop_menu_button = FramedButton::new(
             ctx,
-            numeric::Rect::new(150.0, 15.0, 120.0, 85.0),
+            numeric::Rect::new(50.0, 15.0, 120.0, 85.0),
             10.0,
             2.0,
             ggraphics::Color::from_rgba(90, 80, 63, 255),
@@ -2902,7 +3235,7 @@ impl ShopCommandPalette {
 
         let show_map_button = FramedButton::new(
             ctx,
-            numeric::Rect::new(550.0, 15.0, 120.0, 85.0),
+            numeric::Rect::new(450.0, 15.0, 120.0, 85.0),
             10.0,
             2.0,
             ggraphics::Color::from_rgba(90, 80, 63, 255),
@@ -2912,9 +3245,21 @@ impl ShopCommandPalette {
             0,
         );
 
+        let repair_button = FramedButton::new(
+            ctx,
+            numeric::Rect::new(650.0, 15.0, 120.0, 85.0),
+            10.0,
+            2.0,
+            ggraphics::Color::from_rgba(90, 80, 63, 255),
+            ggraphics::Color::from_rgba(219, 212, 184, 255),
+            "修繕".to_string(),
+            font_info,
+            0,
+        );
+
         let pause_button = FramedButton::new(
             ctx,
-            numeric::Rect::new(750.0, 15.0, 120.0, 85.0),
+            numeric::Rect::new(850.0, 15.0, 120.0, 85.0),
             10.0,
             2.0,
             ggraphics::Color::from_rgba(90, 80, 63, 255),
@@ -2941,10 +3286,30 @@ impl ShopCommandPalette {
             action_button: action_button,
             shop_menu_button: shop_menu_button,
             show_map_button: show_map_button,
+            repair_button: repair_button,
             pause_button: pause_button,
+            layout: layout,
+            size: size,
+            hidden_position: hidden_position,
         }
     }
 
+    fn shown_position(&self) -> numeric::Point2f {
+        numeric::Point2f::new(
+            self.hidden_position.x,
+            self.hidden_position.y - Self::SLIDE_DISTANCE,
+        )
+    }
+
+    ///
+    /// 表示領域の変化に合わせて、パレットを画面下端に配置し直す
+    ///
+    pub fn relayout(&mut self, area: numeric::Rect, t: Clock) {
+        self.hidden_position = self.layout.place(area, self.size);
+        self.canvas.set_position(self.hidden_position);
+        self.slide_out(t);
+    }
+
     pub fn mouse_motion_handler<'a>(
         &mut self,
         ctx: &mut SuzuContext<'a>,
@@ -2956,10 +3321,11 @@ impl ShopCommandPalette {
         self.action_button.mouse_motion_handler(ctx, rpoint);
         self.shop_menu_button.mouse_motion_handler(ctx, rpoint);
         self.show_map_button.mouse_motion_handler(ctx, rpoint);
+        self.repair_button.mouse_motion_handler(ctx, rpoint);
         self.pause_button.mouse_motion_handler(ctx, rpoint);
 
         if !is_dragged {
-            if p.y <= 650.0 {
+            if p.y <= self.shown_position().y {
                 self.slide_out(t);
             } else {
                 self.slide_in(t);
@@ -2976,6 +3342,7 @@ impl ShopCommandPalette {
         self.action_button.mouse_left_button_down(ctx, rpoint);
         self.shop_menu_button.mouse_left_button_down(ctx, rpoint);
         self.show_map_button.mouse_left_button_down(ctx, rpoint);
+        self.repair_button.mouse_left_button_down(ctx, rpoint);
         self.pause_button.mouse_left_button_down(ctx, rpoint);
     }
 
@@ -2988,6 +3355,7 @@ impl ShopCommandPalette {
         self.action_button.mouse_left_button_up(ctx, rpoint);
         self.shop_menu_button.mouse_left_button_up(ctx, rpoint);
         self.show_map_button.mouse_left_button_up(ctx, rpoint);
+        self.repair_button.mouse_left_button_up(ctx, rpoint);
         self.pause_button.mouse_left_button_up(ctx, rpoint);
     }
 
@@ -2996,6 +3364,7 @@ impl ShopCommandPalette {
         self.action_button.contains(rpoint)
             || self.shop_menu_button.contains(rpoint)
             || self.show_map_button.contains(rpoint)
+            || self.repair_button.contains(rpoint)
             || self.pause_button.contains(rpoint)
     }
 
@@ -3014,6 +3383,10 @@ impl ShopCommandPalette {
             return Some(CommandPaletteFunc::ShowMap);
         }
 
+        if self.repair_button.contains(rpoint) {
+            return Some(CommandPaletteFunc::RepairBooks);
+        }
+
         if self.pause_button.contains(rpoint) {
             return Some(CommandPaletteFunc::Pause);
         }
@@ -3024,7 +3397,7 @@ impl ShopCommandPalette {
     pub fn slide_in(&mut self, t: Clock) {
         self.canvas.override_move_func(
             move_fn::move_constant_dest(
-                numeric::Point2f::new(160.0, 650.0),
+                self.shown_position(),
                 numeric::Vector2f::new(0.0, -10.0),
             ),
             t,
@@ -3034,7 +3407,7 @@ impl ShopCommandPalette {
     pub fn slide_out(&mut self, t: Clock) {
         self.canvas.override_move_func(
             move_fn::move_constant_dest(
-                numeric::Point2f::new(160.0, 720.0),
+                self.hidden_position,
                 numeric::Vector2f::new(0.0, 10.0),
             ),
             t,
@@ -3057,6 +3430,7 @@ impl Drawable for ShopCommandPalette {
             self.action_button.draw(ctx)?;
             self.shop_menu_button.draw(ctx)?;
             self.show_map_button.draw(ctx)?;
+            self.repair_button.draw(ctx)?;
             self.pause_button.draw(ctx)?;
 
             sub_screen::pop_screen(ctx);
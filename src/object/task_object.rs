@@ -30,8 +30,8 @@ use tt_sub_component::*;
 
 use super::{Clickable, DarkEffectPanel};
 use crate::core::{
-    BorrowingInformation, GensoDate, RentalLimit, ReturnBookInformation, SuzuContext, TextureID,
-    TileBatchTextureID,
+    calc_late_fee, BorrowingInformation, GensoDate, RentalLimit, ReturnBookInformation,
+    SuzuContext, TextureID, TileBatchTextureID,
 };
 
 use number_to_jk::number_to_jk;
@@ -57,6 +57,7 @@ pub struct TaskTable {
     today: GensoDate,
     task_is_done: bool,
     return_late_checked: bool,
+    pending_late_fee: Option<i32>,
     appearance_frame: TileBatchFrame,
     current_page_book_condition_report: Option<BookConditionEvalReport>,
 }
@@ -183,6 +184,7 @@ impl TaskTable {
             today: ctx.take_save_data().date,
             task_is_done: false,
             return_late_checked: false,
+            pending_late_fee: None,
             appearance_frame: appr_frame,
             current_page_book_condition_report: None,
         }
@@ -532,6 +534,46 @@ impl TaskTable {
         self.task_is_done
     }
 
+    pub fn get_current_customer_request(&self) -> Option<&CustomerRequest> {
+        self.current_customer_request.as_ref()
+    }
+
+    ///
+    /// 受け取った延滞料金を取り出す。返却を受け付けたときに、帳簿に記録するために使う
+    ///
+    pub fn take_late_fee(&mut self) -> Option<i32> {
+        self.pending_late_fee.take()
+    }
+
+    ///
+    /// 客の名簿から、客の台詞を組み立てる
+    /// 常連は親しげに挨拶し、よく借りる本や延滞した本に触れる
+    ///
+    fn customer_dialogue<'a>(
+        ctx: &mut SuzuContext<'a>,
+        name: &str,
+        request_line: &str,
+    ) -> CustomerDialogue {
+        let mut lines = Vec::new();
+
+        match ctx.take_save_data().customer_registry.get(name) {
+            Some(customer) if customer.is_regular() => {
+                lines.push("小鈴ちゃん、また来たよ".to_string());
+                if customer.overdue_count > 0 {
+                    lines.push("この前は返すのが遅れてごめんね".to_string());
+                }
+                if let Some(title) = customer.favourite_titles(1).first() {
+                    lines.push(format!("{}、面白かったなあ", title));
+                }
+            }
+            _ => lines.push("こんにちは".to_string()),
+        }
+        lines.push(request_line.to_string());
+
+        let durations = vec![100; lines.len()];
+        CustomerDialogue::new(lines, durations)
+    }
+
     fn start_borrowing_customer_event<'a>(
         &mut self,
         ctx: &mut SuzuContext<'a>,
@@ -576,10 +618,7 @@ impl TaskTable {
             ctx.context,
             new_silhouette,
             info.borrower.to_string(),
-            CustomerDialogue::new(
-                vec!["こんにちは".to_string(), "この本貸してください".to_string()],
-                vec![100, 100],
-            ),
+            Self::customer_dialogue(ctx, &info.borrower, "この本貸してください"),
             t,
         );
     }
@@ -628,16 +667,26 @@ impl TaskTable {
             ctx.context,
             new_silhouette,
             info.borrower.to_string(),
-            CustomerDialogue::new(
-                vec!["こんにちは".to_string(), "本の返却お願いします".to_string()],
-                vec![100, 100],
-            ),
+            Self::customer_dialogue(ctx, &info.borrower, "本の返却お願いします"),
             t,
         );
     }
 
     pub fn start_customer_event(&mut self, ctx: &mut SuzuContext, info: CustomerRequest, t: Clock) {
         self.current_customer_request = Some(info.clone());
+        self.return_late_checked = false;
+        self.pending_late_fee = None;
+
+        // 常連の客の名前は、聞かなくても覚えている
+        let name = info.get_customer_name();
+        let is_regular = ctx
+            .take_save_data()
+            .customer_registry
+            .get(&name)
+            .map_or(false, |customer| customer.is_regular());
+        if is_regular {
+            self.kosuzu_memory.add_customer_name(name);
+        }
 
         match info {
             CustomerRequest::Borrowing(info) => self.start_borrowing_customer_event(ctx, info, t),
@@ -879,16 +928,29 @@ impl TaskTable {
                     if self.return_late_checked {
                         self.insert_kosuzu_message_set(ctx, "もう延滞料金を受け取った", t);
                     } else {
-                        self.add_fee_coins(ctx, 300, t);
+                        // 延滞料金は、客が返しに来た本の冊数で決まる
+                        let returning_count = match self.current_customer_request.as_ref() {
+                            Some(CustomerRequest::Returning(info)) => info.returning.len(),
+                            _ => 0,
+                        };
+                        let late_fee = calc_late_fee(&return_date, &self.today, returning_count);
+
+                        self.borrowing_record_book
+                            .set_current_page_late_fee(ctx, late_fee as u32);
+                        self.add_fee_coins(ctx, late_fee as u32, t);
                         self.slide_hide_record_book(t);
                         self.return_late_checked = true;
                         self.insert_kosuzu_message_set(
                             ctx,
-                            "延滞してます\n延滞料金は三百円です",
+                            &format!(
+                                "延滞してます\n延滞料金は{}円です",
+                                number_to_jk(late_fee as u64)
+                            ),
                             t,
                         );
-			ctx.take_save_data_mut().task_result.total_money += 300;
 
+                        // 帳簿と延滞の記録は、返却を受け付けたときに付ける
+                        self.pending_late_fee = Some(late_fee);
                     }
                 } else {
                     self.insert_kosuzu_message_set(ctx, "延滞はしていない", t);
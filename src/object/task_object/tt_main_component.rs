@@ -5,10 +5,12 @@ use ggez::input::mouse::CursorIcon;
 
 use crate::libsuzu::core::Clock;
 use crate::libsuzu::graphics::drawable::*;
+use crate::libsuzu::graphics::object::rich_text::*;
 use crate::libsuzu::graphics::object::shape;
 use crate::libsuzu::graphics::object::sub_screen;
 use crate::libsuzu::graphics::object::sub_screen::SubScreen;
 use crate::libsuzu::graphics::object::*;
+use crate::libsuzu::graphics::text_layout::*;
 use crate::impl_node2d_for_wrapped;
 use crate::impl_transform_object_for_wrapped;
 use crate::roundup2f;
@@ -730,12 +732,15 @@ impl CustomerDialogue {
 pub struct TextBalloon {
     canvas: SubScreen,
     back_canvas: SubScreen,
-    text: VerticalText,
+    text: RichText,
     phrase_type: TextBalloonPhraseType,
     text_balloon: shape::FramedLeadingRect,
 }
 
 impl TextBalloon {
+    // 吹き出しの1列に入る最大の文字数
+    const COLUMN_CHARS: f32 = 8.0;
+
     pub fn new(
         ctx: &mut ggez::Context,
         balloon_rect: numeric::Rect,
@@ -743,14 +748,18 @@ impl TextBalloon {
         phrase_type: TextBalloonPhraseType,
         font_info: FontInformation,
     ) -> Self {
-        let mut vtext = VerticalText::new(
-            text.to_string(),
+        let mut vtext = RichText::new_plain(
+            text,
             numeric::Point2f::new(0.0, 0.0),
             numeric::Vector2f::new(1.0, 1.0),
             0.0,
             0,
             font_info,
+            TextDirection::Vertical,
         );
+        vtext.set_layout_option(TextLayoutOption::wrap(
+            font_info.scale.y * Self::COLUMN_CHARS,
+        ));
 
         let vtext_size = vtext.get_drawing_size(ctx);
 
@@ -795,7 +804,8 @@ impl TextBalloon {
         text: &str,
         phrase_type: TextBalloonPhraseType,
     ) {
-        self.text.replace_text(text.to_string());
+        self.text
+            .replace_markup(RichTextMarkup::plain(text), RichTextAssets::new_empty());
         let vtext_size = self.text.get_drawing_size(ctx);
 
         self.text_balloon = shape::FramedLeadingRect::new(
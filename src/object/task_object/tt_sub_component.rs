@@ -536,6 +536,35 @@ impl BookConditionEvalReport {
         }
     }
 
+    ///
+    /// 評価した本と、その評価の組を返す
+    ///
+    pub fn iter(&self) -> impl Iterator<Item = (&BookInformation, &BookCondition)> {
+        self.originals.iter().zip(self.each_evaluation.iter())
+    }
+
+    ///
+    /// 正しく評価した本を、それぞれrateの確率で見誤ったことにする。見誤った冊数を返す
+    ///
+    pub fn apply_fatigue_mistakes(&mut self, rate: f32) -> usize {
+        let mut count = 0;
+
+        for (original, eval) in self.originals.iter().zip(self.each_evaluation.iter_mut()) {
+            if original.get_condition() != *eval || rand::random::<f32>() >= rate {
+                continue;
+            }
+
+            *eval = match eval {
+                BookCondition::Good => BookCondition::Fair,
+                BookCondition::Fair => BookCondition::Good,
+                BookCondition::Bad => BookCondition::Fair,
+            };
+            count += 1;
+        }
+
+        count
+    }
+
     pub fn count_mistake(&self) -> usize {
         let mut count: usize = 0;
 
@@ -562,11 +591,16 @@ pub struct BorrowingRecordBookPageData {
     pub rental_limit: Option<RentalLimit>,
     pub borrowing_is_signed: bool,
     pub returning_is_signed: bool,
+    #[serde(default)]
+    pub late_fee: Option<u32>,
+    #[serde(default)]
+    pub is_lost: bool,
 }
 
 impl BorrowingRecordBookPageData {
     pub fn is_maybe_waiting_returning(&self) -> bool {
         !self.returning_is_signed
+            && !self.is_lost
             && self.borrowing_is_signed
             && !self.borrowing_book_title.is_empty()
             && self.borrowing_book_status.is_empty()
@@ -639,6 +673,8 @@ impl From<&ReturnBookInformation> for BorrowingRecordBookPageData {
             rental_date: Some(info.borrow_date),
             borrowing_is_signed: true,
             returning_is_signed: false,
+            late_fee: None,
+            is_lost: false,
         }
     }
 }
@@ -674,35 +710,127 @@ impl BorrowingRecordBookData {
         count > 0
     }
 
-    pub fn pick_returning_request_up(&self) -> Option<ReturnBookInformation> {
-        let count = self
-            .pages_data
+    ///
+    /// 返却待ちの貸出を、全て返却の依頼にして返す
+    ///
+    pub fn waiting_returning_requests(&self) -> Vec<ReturnBookInformation> {
+        self.pages_data
             .iter()
-            .map(|data| {
-                if data.is_maybe_waiting_returning() {
-                    1
+            .filter_map(|data| data.generate_return_book_information())
+            .collect()
+    }
+
+    ///
+    /// 返却待ちの貸出がある客の名前を、重複なしで返す
+    ///
+    pub fn waiting_borrower_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+
+        for data in self.pages_data.iter() {
+            if !data.is_maybe_waiting_returning() {
+                continue;
+            }
+
+            let name = data.customer_name.as_ref().unwrap();
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+
+        names
+    }
+
+    ///
+    /// nameの客が借りている貸出のうち、最も古いものを返却の依頼にする
+    ///
+    pub fn pick_returning_request_of(&self, name: &str) -> Option<ReturnBookInformation> {
+        self.pages_data
+            .iter()
+            .filter(|data| {
+                data.is_maybe_waiting_returning()
+                    && data.customer_name.as_ref().map_or(false, |n| n == name)
+            })
+            .min_by(|a, b| {
+                let (a, b) = (a.rental_date.unwrap(), b.rental_date.unwrap());
+                if a == b {
+                    std::cmp::Ordering::Equal
+                } else if a.is_past(&b) {
+                    std::cmp::Ordering::Greater
                 } else {
-                    0
+                    std::cmp::Ordering::Less
                 }
             })
-            .fold(0, |m, v| m + v);
-        if count == 0 {
-            return None;
-        }
+            .and_then(|data| data.generate_return_book_information())
+    }
 
-        let mut picked_data = rand::random::<usize>() % count;
+    ///
+    /// 返却期限を過ぎても返却されていない貸出の数
+    ///
+    pub fn overdue_loan_count(&self, today: &GensoDate) -> usize {
+        self.pages_data
+            .iter()
+            .filter(|data| {
+                data.is_maybe_waiting_returning()
+                    && today.is_past(data.return_date.as_ref().unwrap())
+            })
+            .count()
+    }
 
-        for data in self.pages_data.iter() {
-            if data.is_maybe_waiting_returning() {
-                if picked_data == 0 {
-                    return data.generate_return_book_information();
-                }
+    ///
+    /// 返却期限からlost_days日以上過ぎた貸出を紛失したものとし、その貸出を返す
+    ///
+    pub fn mark_lost_loans(
+        &mut self,
+        today: &GensoDate,
+        lost_days: i32,
+    ) -> Vec<ReturnBookInformation> {
+        let mut lost = Vec::new();
+
+        for data in self.pages_data.iter_mut() {
+            if !data.is_maybe_waiting_returning() {
+                continue;
+            }
 
-                picked_data -= 1;
+            if today.overdue_days(data.return_date.as_ref().unwrap()) >= lost_days {
+                lost.push(data.generate_return_book_information().unwrap());
+                data.is_lost = true;
             }
         }
 
-        None
+        lost
+    }
+
+    ///
+    /// 返却される貸出の本を、貸出期限と客の不注意さに応じた確率で傷めた返却の依頼を返す
+    /// 貸出の記録は変えない。傷んだ本は、返却を受け付けた時に蔵書台帳へ記録される
+    ///
+    pub fn worn_returning_request(
+        &self,
+        request: &ReturnBookInformation,
+        carelessness: f32,
+    ) -> ReturnBookInformation {
+        let maybe_page = self.pages_data.iter().find(|data| {
+            data.is_maybe_waiting_returning()
+                && data.customer_name.as_ref() == Some(&request.borrower)
+                && data.rental_date == Some(request.borrow_date)
+        });
+
+        let page = match maybe_page {
+            Some(page) => page,
+            None => return request.clone(),
+        };
+
+        let mut worn_request = match page.generate_return_book_information() {
+            Some(worn_request) => worn_request,
+            None => return request.clone(),
+        };
+
+        let chance = page.rental_limit.as_ref().unwrap().wear_rate() * carelessness;
+        for book_info in worn_request.returning.iter_mut() {
+            book_info.wear(chance);
+        }
+
+        worn_request
     }
 }
 
@@ -855,10 +983,12 @@ pub struct PayFrame {
     rental_limit_text: Option<VerticalText>,
     borrowing_number_text: Option<VerticalText>,
     pay_money_text: Option<VerticalText>,
+    late_fee_text: Option<VerticalText>,
     rental_limit_data: Option<RentalLimit>,
     listed_books_number: usize,
     drwob_essential: DrawableEss,
     calculated_price: Option<u32>,
+    late_fee: Option<u32>,
 }
 
 impl PayFrame {
@@ -867,7 +997,7 @@ impl PayFrame {
             ctx.resource,
             position,
             TileBatchTextureID::RedOldStyleFrame,
-            FrameData::new(vec![160.0, 300.0], vec![42.0; 3]),
+            FrameData::new(vec![160.0, 300.0], vec![42.0; 4]),
             numeric::Vector2f::new(0.3, 0.3),
 	    ggraphics::FilterMode::Nearest,
             0,
@@ -890,7 +1020,7 @@ impl PayFrame {
             ctx.context,
             pay_frame,
             borrowing_number,
-            numeric::Vector2u::new(2, 0)
+            numeric::Vector2u::new(3, 0)
         );
 
         let mut rental_limit = VerticalText::new(
@@ -910,6 +1040,26 @@ impl PayFrame {
             ctx.context,
             pay_frame,
             rental_limit,
+            numeric::Vector2u::new(2, 0)
+        );
+
+        let mut late_fee = VerticalText::new(
+            "延滞料".to_string(),
+            numeric::Point2f::new(0.0, 0.0),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+            FontInformation::new(
+                ctx.resource.get_font(FontID::JpFude1),
+                numeric::Vector2f::new(24.0, 24.0),
+                ggraphics::Color::from_rgba_u32(0x000000ff),
+            ),
+        );
+
+        set_table_frame_cell_center!(
+            ctx.context,
+            pay_frame,
+            late_fee,
             numeric::Vector2u::new(1, 0)
         );
 
@@ -930,14 +1080,16 @@ impl PayFrame {
 
         let mut pay_frame = PayFrame {
             pay_frame: pay_frame,
-            cell_desc_text: vec![borrowing_number, rental_limit, total],
+            cell_desc_text: vec![borrowing_number, rental_limit, late_fee, total],
             rental_limit_text: None,
             borrowing_number_text: None,
             pay_money_text: None,
+            late_fee_text: None,
             rental_limit_data: None,
             listed_books_number: 0,
             drwob_essential: DrawableEss::new(true, depth),
             calculated_price: None,
+            late_fee: None,
         };
 
         pay_frame.update_book_count(ctx, 0, 0);
@@ -975,7 +1127,7 @@ impl PayFrame {
             ctx.context,
             self.pay_frame,
             vtext,
-            numeric::Vector2u::new(1, 1)
+            numeric::Vector2u::new(2, 1)
         );
 
         self.rental_limit_text = Some(vtext);
@@ -1007,7 +1159,7 @@ impl PayFrame {
             ctx.context,
             self.pay_frame,
             vtext,
-            numeric::Vector2u::new(2, 1)
+            numeric::Vector2u::new(3, 1)
         );
 
         self.borrowing_number_text = Some(vtext);
@@ -1048,6 +1200,34 @@ impl PayFrame {
         }
     }
 
+    ///
+    /// 受け取った延滞料金を記入する
+    ///
+    pub fn update_late_fee<'a>(&mut self, ctx: &mut SuzuContext<'a>, late_fee: u32) {
+        let mut vtext = VerticalText::new(
+            format!("{}円", number_to_jk(late_fee as u64)),
+            numeric::Point2f::new(0.0, 0.0),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+            FontInformation::new(
+                ctx.resource.get_font(FontID::JpFude1),
+                numeric::Vector2f::new(24.0, 24.0),
+                ggraphics::Color::from_rgba_u32(0x000000ff),
+            ),
+        );
+
+        set_table_frame_cell_center!(
+            ctx.context,
+            self.pay_frame,
+            vtext,
+            numeric::Vector2u::new(1, 1)
+        );
+
+        self.late_fee_text = Some(vtext);
+        self.late_fee = Some(late_fee);
+    }
+
     pub fn get_pay_frame(&self) -> &TableFrame {
         &self.pay_frame
     }
@@ -1055,6 +1235,10 @@ impl PayFrame {
     pub fn get_calculated_price(&self) -> Option<u32> {
         self.calculated_price.clone()
     }
+
+    pub fn get_late_fee(&self) -> Option<u32> {
+        self.late_fee
+    }
 }
 
 impl Drawable for PayFrame {
@@ -1077,6 +1261,10 @@ impl Drawable for PayFrame {
             if let Some(vtext) = self.pay_money_text.as_mut() {
                 vtext.draw(ctx)?;
             }
+
+            if let Some(vtext) = self.late_fee_text.as_mut() {
+                vtext.draw(ctx)?;
+            }
         }
 
         Ok(())
@@ -1116,6 +1304,7 @@ pub struct BorrowingRecordBookPage {
     pay_frame: PayFrame,
     sign_frame: SignFrame,
     paper_texture: SimpleObject,
+    is_lost: bool,
     drwob_essential: DrawableEss,
 }
 
@@ -1192,6 +1381,12 @@ impl BorrowingRecordBookPage {
         page.pay_frame
             .update_book_count(ctx, page_data.borrowing_book_title.len(), base_price);
 
+        if let Some(late_fee) = page_data.late_fee {
+            page.pay_frame.update_late_fee(ctx, late_fee);
+        }
+
+        page.is_lost = page_data.is_lost;
+
         if page_data.borrowing_is_signed {
             page.sign_frame.sign_borrowing_frame(ctx);
         }
@@ -1522,6 +1717,7 @@ impl BorrowingRecordBookPage {
                 0,
             ),
             return_date: return_date,
+            is_lost: false,
             drwob_essential: DrawableEss::new(true, 0),
         }
     }
@@ -1664,7 +1860,7 @@ impl BorrowingRecordBookPage {
         self.borrow_book
             .iter()
             .map(|(_, data)| match data.ref_hold_data() {
-                HoldData::BookName(info) => info.base_price,
+                HoldData::BookName(info) => info.rental_price(),
                 _ => 0,
             })
             .fold(0, |sum, c| sum + c)
@@ -1952,6 +2148,8 @@ impl BorrowingRecordBookPage {
             rental_limit: rental_limit,
             borrowing_is_signed: self.sign_frame.borrowing_is_done,
             returning_is_signed: self.sign_frame.returning_is_done,
+            late_fee: self.pay_frame.get_late_fee(),
+            is_lost: self.is_lost,
         }
     }
 
@@ -2307,6 +2505,13 @@ impl BorrowingRecordBook {
         }
     }
 
+    pub fn set_current_page_late_fee<'a>(&mut self, ctx: &mut SuzuContext<'a>, late_fee: u32) {
+        if let Some(page) = self.get_current_page_mut() {
+            page.pay_frame.update_late_fee(ctx, late_fee);
+            self.redraw_request = DrawRequest::Draw;
+        }
+    }
+
     pub fn is_current_books_table_some_data_at(&self, position: numeric::Vector2u) -> bool {
         if let Some(page) = self.get_current_page() {
             page.is_books_table_some_data_at(position)
@@ -11,6 +11,8 @@ use crate::impl_transform_object_for_wrapped;
 use crate::libsuzu::numeric;
 use crate::roundup2f;
 
+use crate::core::financial_ledger::IncomeStatement;
+use crate::core::reputation::ReputationEvent;
 use crate::core::{FontID, GensoDate, ResultReport, SavableData, SuzuContext, TileBatchTextureID};
 use crate::object::effect;
 use crate::object::util_object::*;
@@ -20,6 +22,11 @@ use crate::object::simulation_ui::*;
 
 use number_to_jk::number_to_jk;
 
+///
+/// 帳簿を書き出すファイルの名前。ユーザーデータのディレクトリに書き出す
+///
+pub const ACCOUNTING_CSV_FILE_NAME: &str = "ledger.csv";
+
 struct DrawableEvaluationFlow {
     eval_frame: TableFrame,
     desc_text: Vec<VerticalText>,
@@ -42,7 +49,7 @@ impl DrawableEvaluationFlow {
             ctx.resource,
             pos,
             TileBatchTextureID::OldStyleFrame,
-            FrameData::new(vec![250.0, 250.0], vec![50.0; 4]),
+            FrameData::new(vec![250.0, 250.0], vec![50.0; 6]),
             numeric::Vector2f::new(0.5, 0.5),
 	    ggraphics::FilterMode::Nearest,
             0,
@@ -57,6 +64,8 @@ impl DrawableEvaluationFlow {
         effect_time_list.push_back(t + effect_clock_offset + 50);
         effect_time_list.push_back(t + effect_clock_offset + 100);
         effect_time_list.push_back(t + effect_clock_offset + 150);
+        effect_time_list.push_back(t + effect_clock_offset + 200);
+        effect_time_list.push_back(t + effect_clock_offset + 250);
 
         let font_info = FontInformation::new(
             ctx.resource.get_font(FontID::JpFude1),
@@ -64,9 +73,16 @@ impl DrawableEvaluationFlow {
             ggraphics::Color::from_rgba_u32(0xff),
         );
 
-        for (index, s) in vec!["総合評価", "誤評価数", "配架完了", "客を待たせた時間"]
-            .iter()
-            .enumerate()
+        for (index, s) in vec![
+            "総合評価",
+            "誤評価数",
+            "配架完了",
+            "客を待たせた時間",
+            "怒った客",
+            "満足した客",
+        ]
+        .iter()
+        .enumerate()
         {
             let mut vtext = VerticalText::new(
                 s.to_string(),
@@ -188,6 +204,56 @@ impl DrawableEvaluationFlow {
             numeric::Vector2u::new(3, 1)
         );
 
+        let mut angry_vtext = EffectableWrap::new(
+            MovableWrap::new(
+                Box::new(VerticalText::new(
+                    result_report_string_table.angry_customers,
+                    numeric::Point2f::new(0.0, 0.0),
+                    numeric::Vector2f::new(1.0, 1.0),
+                    0.0,
+                    0,
+                    font_info.clone(),
+                )),
+                None,
+                t,
+            ),
+            Vec::new(),
+        );
+        angry_vtext.set_crop(init_crop);
+
+        set_table_frame_cell_center!(
+            ctx.context,
+            eval_frame,
+            angry_vtext,
+            numeric::Vector2u::new(4, 1)
+        );
+
+        let mut satisfied_vtext = EffectableWrap::new(
+            MovableWrap::new(
+                Box::new(VerticalText::new(
+                    result_report_string_table.satisfied_customers,
+                    numeric::Point2f::new(0.0, 0.0),
+                    numeric::Vector2f::new(1.0, 1.0),
+                    0.0,
+                    0,
+                    font_info.clone(),
+                )),
+                None,
+                t,
+            ),
+            Vec::new(),
+        );
+        satisfied_vtext.set_crop(init_crop);
+
+        set_table_frame_cell_center!(
+            ctx.context,
+            eval_frame,
+            satisfied_vtext,
+            numeric::Vector2u::new(5, 1)
+        );
+
+        yet_effect_text.push_back(satisfied_vtext);
+        yet_effect_text.push_back(angry_vtext);
         yet_effect_text.push_back(waiting_vtext);
         yet_effect_text.push_back(shelving_vtext);
         yet_effect_text.push_back(eval_mistakes_vtext);
@@ -301,6 +367,234 @@ impl Drawable for DrawableEvaluationFlow {
     }
 }
 
+fn money_string(amount: i32) -> String {
+    format!(
+        "{}{}円",
+        if amount < 0 { "-" } else { "" },
+        number_to_jk(amount.abs() as u64)
+    )
+}
+
+fn statement_line(label: &str, amount: i32) -> String {
+    format!("{:　<5}{}", label, money_string(amount))
+}
+
+fn income_statement_lines(header: &str, statement: &IncomeStatement) -> Vec<String> {
+    let mut lines = vec![header.to_string()];
+
+    for (category, amount) in statement.iter() {
+        lines.push(statement_line(category.to_str(), *amount));
+    }
+
+    lines.push(statement_line("収入計", statement.income()));
+    lines.push(statement_line("支出計", -statement.expense()));
+    lines.push(statement_line("差引", statement.net()));
+
+    lines
+}
+
+///
+/// # 帳簿から作る、その日と今週の収支報告
+///
+/// ## graph
+/// 今週の日ごとの収支の推移。グラフを作れなかった場合はNone
+///
+/// ## export_button
+/// 帳簿をCSVとして書き出すボタン
+///
+pub struct DrawableAccountingReport {
+    canvas: SubScreen,
+    statement_text: Vec<UniText>,
+    graph_caption: UniText,
+    graph: Option<GraphDrawer>,
+    export_button: FramedButton,
+    export_status_text: UniText,
+}
+
+impl DrawableAccountingReport {
+    pub fn new<'a>(ctx: &mut SuzuContext<'a>, rect: numeric::Rect, depth: i8) -> Self {
+        let font_info = FontInformation::new(
+            ctx.resource.get_font(FontID::JpFude1),
+            numeric::Vector2f::new(22.0, 22.0),
+            ggraphics::Color::from_rgba_u32(0x000000ff),
+        );
+
+        let save_data = ctx.take_save_data();
+        let today = save_data.date;
+        let first_day = save_data.week_schedule.get_first_day();
+        let ledger = &save_data.financial_ledger;
+
+        let columns = vec![
+            income_statement_lines("本日の収支", &ledger.daily_statement(today)),
+            income_statement_lines("今週の収支", &ledger.weekly_statement(first_day)),
+        ];
+        let elapsed_days = (first_day.signed_diff_day(&today).max(0) as usize + 1).min(7);
+        let history = ledger.daily_net_history(first_day, elapsed_days);
+
+        let mut statement_text = Vec::new();
+        for (column_index, lines) in columns.iter().enumerate() {
+            for (line_index, line) in lines.iter().enumerate() {
+                statement_text.push(UniText::new(
+                    line.to_string(),
+                    numeric::Point2f::new(
+                        40.0 + (column_index as f32 * 300.0),
+                        40.0 + (line_index as f32 * 36.0),
+                    ),
+                    numeric::Vector2f::new(1.0, 1.0),
+                    0.0,
+                    0,
+                    font_info.clone(),
+                ));
+            }
+        }
+
+        let graph_caption = UniText::new(
+            "今週の日ごとの収支".to_string(),
+            numeric::Point2f::new(640.0, 40.0),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+            font_info.clone(),
+        );
+
+        let graph = match GraphDrawer::new(
+            ctx,
+            numeric::Rect::new(640.0, 80.0, 320.0, 280.0),
+            numeric::Rect::new(20.0, 20.0, 280.0, 240.0),
+            history
+                .iter()
+                .enumerate()
+                .map(|(index, (_, net))| numeric::Vector2f::new(index as f32, *net as f32))
+                .collect(),
+            5.0,
+            ggraphics::Color::from_rgba_u32(0x5a4f3fff),
+            2.0,
+            ggraphics::Color::from_rgba_u32(0x000000ff),
+            0,
+        ) {
+            Ok(graph) => Some(graph),
+            Err(e) => {
+                eprintln!("Failed to create the accounting graph -> {}", e);
+                None
+            }
+        };
+
+        let export_button = FramedButton::create_design1(
+            ctx,
+            numeric::Point2f::new(640.0, 420.0),
+            "書出",
+            numeric::Vector2f::new(24.0, 24.0),
+        );
+
+        let export_status_text = UniText::new(
+            String::new(),
+            numeric::Point2f::new(640.0, 520.0),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+            font_info,
+        );
+
+        let mut canvas = SubScreen::new(
+            ctx.context,
+            rect,
+            depth,
+            ggraphics::Color::from_rgba_u32(0xf0e8d8f0),
+        );
+        canvas.hide();
+
+        DrawableAccountingReport {
+            canvas: canvas,
+            statement_text: statement_text,
+            graph_caption: graph_caption,
+            graph: graph,
+            export_button: export_button,
+            export_status_text: export_status_text,
+        }
+    }
+
+    ///
+    /// 書出ボタンが押されたら、帳簿をユーザーデータのディレクトリのfile_nameに書き出す
+    ///
+    pub fn click_handler<'a>(
+        &mut self,
+        ctx: &mut SuzuContext<'a>,
+        point: numeric::Point2f,
+        file_name: &str,
+    ) {
+        let rpoint = self.canvas.relative_point(point);
+        if !self.export_button.contains(rpoint) {
+            return;
+        }
+
+        let dir = ggez::filesystem::user_data_dir(ctx.context).to_path_buf();
+        let path = dir.join(file_name);
+        let result = std::fs::create_dir_all(&dir)
+            .and_then(|_| ctx.take_save_data().financial_ledger.export_csv(&path));
+
+        let message = match result {
+            Ok(_) => format!("{}に書き出しました", path.display()),
+            Err(e) => {
+                eprintln!("Failed to export the ledger to {} -> {}", path.display(), e);
+                "書き出せませんでした".to_string()
+            }
+        };
+        self.export_status_text.replace_text(message);
+        ctx.process_utility.redraw();
+    }
+}
+
+impl Drawable for DrawableAccountingReport {
+    fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult<()> {
+        if self.is_visible() {
+            sub_screen::stack_screen(ctx, &self.canvas);
+
+            for text in self.statement_text.iter_mut() {
+                text.draw(ctx)?;
+            }
+
+            self.graph_caption.draw(ctx)?;
+            if let Some(graph) = self.graph.as_mut() {
+                graph.draw(ctx)?;
+            }
+            self.export_button.draw(ctx)?;
+            self.export_status_text.draw(ctx)?;
+
+            sub_screen::pop_screen(ctx);
+            self.canvas.draw(ctx).unwrap();
+        }
+        Ok(())
+    }
+
+    fn hide(&mut self) {
+        self.canvas.hide()
+    }
+
+    fn appear(&mut self) {
+        self.canvas.appear()
+    }
+
+    fn is_visible(&self) -> bool {
+        self.canvas.is_visible()
+    }
+
+    fn set_drawing_depth(&mut self, depth: i8) {
+        self.canvas.set_drawing_depth(depth)
+    }
+
+    fn get_drawing_depth(&self) -> i8 {
+        self.canvas.get_drawing_depth()
+    }
+}
+
+impl Node2D for DrawableAccountingReport {
+    impl_node2d_for_wrapped! {canvas}
+}
+
+impl Transform for DrawableAccountingReport {
+    impl_transform_object_for_wrapped! {canvas}
+}
+
 pub struct DrawableTaskResult {
     result_frame: TableFrame,
     effect_time_list: VecDeque<Clock>,
@@ -310,6 +604,7 @@ pub struct DrawableTaskResult {
     meters: ResultMeter,
     background: SimpleObject,
     evaluation: DrawableEvaluationFlow,
+    accounting_report: DrawableAccountingReport,
     const_canvas: SubScreen,
     canvas: SubScreen,
 }
@@ -528,7 +823,7 @@ impl DrawableTaskResult {
         );
 
 	let eval = result_report.generate_eval_result();
-	ctx.take_save_data_mut().suzunaan_status.add_reputation(eval);
+	ctx.take_save_data_mut().apply_reputation_event(ReputationEvent::DailyWork, eval, None);
 	let goal = ctx.take_save_data().suzunaan_status.get_current_reputation();
         let goal = if goal >= 0.0
         {
@@ -556,6 +851,11 @@ impl DrawableTaskResult {
             fixed_text: fixed_text,
             meters: meters,
             evaluation: evaluation,
+            accounting_report: DrawableAccountingReport::new(
+                ctx,
+                numeric::Rect::new(183.0, 84.0, 1000.0, 600.0),
+                0,
+            ),
             background: background,
             const_canvas: SubScreen::new(
                 ctx.context,
@@ -583,6 +883,28 @@ impl DrawableTaskResult {
         self.canvas.draw(ctx.context).unwrap();
     }
 
+    pub fn toggle_accounting_report<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
+        if self.accounting_report.is_visible() {
+            self.accounting_report.hide();
+        } else {
+            self.accounting_report.appear();
+        }
+        ctx.process_utility.redraw();
+    }
+
+    pub fn accounting_report_is_visible(&self) -> bool {
+        self.accounting_report.is_visible()
+    }
+
+    pub fn accounting_report_click_handler<'a>(
+        &mut self,
+        ctx: &mut SuzuContext<'a>,
+        point: numeric::Point2f,
+    ) {
+        self.accounting_report
+            .click_handler(ctx, point, ACCOUNTING_CSV_FILE_NAME);
+    }
+
     pub fn evaluation_flow_is_done(&self) -> bool {
 	self.evaluation.is_done()
     }
@@ -650,6 +972,7 @@ impl Drawable for DrawableTaskResult {
 
             self.meters.draw(ctx)?;
             self.evaluation.draw(ctx)?;
+            self.accounting_report.draw(ctx)?;
 
             sub_screen::pop_screen(ctx);
             self.canvas.draw(ctx).unwrap();
@@ -11,6 +11,7 @@ use crate::impl_transform_object_for_wrapped;
 
 use crate::libsuzu::core::Clock;
 use crate::libsuzu::graphics::drawable::*;
+use crate::libsuzu::graphics::layout::ScreenScalingMode;
 use crate::libsuzu::graphics::object::*;
 use crate::libsuzu::numeric;
 use crate::roundup2f;
@@ -390,6 +391,7 @@ type DynamicTitleSoundPlayer = MovableWrap<TitleSoundPlayer>;
 pub enum GameConfigElement {
     BGMVolume,
     SEVolume,
+    ScreenScaling,
 }
 
 struct TemporaryConfigData {
@@ -397,6 +399,7 @@ struct TemporaryConfigData {
     se_volume: f32,
     pause_when_inactive: bool,
     fullscreen_mode: bool,
+    screen_scaling_mode: ScreenScalingMode,
 }
 
 impl TemporaryConfigData {
@@ -406,6 +409,7 @@ impl TemporaryConfigData {
             se_volume: ctx.config.get_se_volume(),
             pause_when_inactive: ctx.config.is_pause_when_inactive(),
 	    fullscreen_mode: ctx.config.is_fullscreen_mode_configed(),
+            screen_scaling_mode: ctx.config.get_screen_scaling_mode(),
         }
     }
 }
@@ -420,6 +424,7 @@ pub struct ConfigPanel {
     se_volume_bar: SeekBar,
     checkbox: CheckBox,
     fullscreen_checkbox: CheckBox,
+    scaling_mode_button: SelectButton,
     apply_button: SelectButton,
     cancel_button: SelectButton,
     original_config_data: TemporaryConfigData,
@@ -581,6 +586,44 @@ impl ConfigPanel {
             0,
         );
 
+        let scaling_text = UniText::new(
+            "画面の拡大".to_string(),
+            numeric::Point2f::new(750.0, 180.0),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+            hrzn_text_font_info.clone(),
+        );
+        hrzn_text_list.push(scaling_text);
+
+        sb_dynamic_text.insert(
+            GameConfigElement::ScreenScaling,
+            UniText::new(
+                ctx.config.get_screen_scaling_mode().to_str_jp().to_string(),
+                numeric::Point2f::new(750.0, 230.0),
+                numeric::Vector2f::new(1.0, 1.0),
+                0.0,
+                0,
+                hrzn_text_font_info.clone(),
+            ),
+        );
+
+        let text_texture = Box::new(TextButtonTexture::new(
+            ctx,
+            numeric::Point2f::new(0.0, 0.0),
+            "切替".to_string(),
+            hrzn_text_font_info.clone(),
+            8.0,
+            ggraphics::Color::from_rgba_u32(0x362d33ff),
+            0,
+        ));
+
+        let scaling_mode_button = SelectButton::new(
+            ctx,
+            numeric::Rect::new(950.0, 225.0, 100.0, 50.0),
+            text_texture,
+        );
+
         ConfigPanel {
             header_text: header_text,
             sb_dynamic_text: sb_dynamic_text,
@@ -615,9 +658,20 @@ impl ConfigPanel {
             original_config_data: TemporaryConfigData::new(ctx),
             checkbox: check_box,
 	    fullscreen_checkbox: fullscreen_check,
+            scaling_mode_button: scaling_mode_button,
         }
     }
 
+    fn change_screen_scaling_mode<'a>(&mut self, ctx: &mut SuzuContext<'a>, mode: ScreenScalingMode) {
+        // SceneControllerが設定の変化を検知して、ウィンドウへの拡大方法を更新する
+        ctx.config.set_screen_scaling_mode(mode);
+
+        self.sb_dynamic_text
+            .get_mut(&GameConfigElement::ScreenScaling)
+            .unwrap()
+            .replace_text(mode.to_str_jp().to_string());
+    }
+
     fn update_seek_bar_value(&mut self) {
         let bgm_volume = self.bgm_volume_bar.get_current_value() as i32;
         let se_volume = self.se_volume_bar.get_current_value() as i32;
@@ -637,6 +691,7 @@ impl ConfigPanel {
         let original_se = self.original_config_data.se_volume * 100.0;
         let original_pause = self.original_config_data.pause_when_inactive;
 	let original_fullscreen_config = self.original_config_data.fullscreen_mode;
+        let original_scaling_mode = self.original_config_data.screen_scaling_mode;
 
         ctx.change_bgm_volume(original_bgm);
         ctx.change_se_volume(original_se);
@@ -647,6 +702,7 @@ impl ConfigPanel {
         self.se_volume_bar.set_value(ctx, original_se);
         self.checkbox.try_check(original_pause);
 	self.fullscreen_checkbox.try_check(original_fullscreen_config);
+        self.change_screen_scaling_mode(ctx, original_scaling_mode);
 
 	match ggraphics::set_fullscreen(
 	    ctx.context,
@@ -716,6 +772,11 @@ impl ConfigPanel {
         self.checkbox.click_handler(rpoint);
 	self.fullscreen_checkbox_handler(ctx, rpoint);
 
+        if self.scaling_mode_button.contains(ctx.context, rpoint) {
+            let next_mode = ctx.config.get_screen_scaling_mode().next();
+            self.change_screen_scaling_mode(ctx, next_mode);
+        }
+
         if self.apply_button.contains(ctx.context, rpoint) {
             ctx.change_bgm_volume(self.bgm_volume_bar.get_current_value());
             ctx.change_se_volume(self.se_volume_bar.get_current_value());
@@ -775,6 +836,7 @@ impl Drawable for ConfigPanel {
 
             self.apply_button.draw(ctx)?;
             self.cancel_button.draw(ctx)?;
+            self.scaling_mode_button.draw(ctx)?;
 
             self.checkbox.draw(ctx)?;
 	    self.fullscreen_checkbox.draw(ctx)?;
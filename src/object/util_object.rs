@@ -1407,10 +1407,13 @@ impl TextureAnimation {
 
 pub struct GraphDrawer {
     canvas: SubScreen,
-    shapes: ggraphics::Mesh,
+    shapes: Option<ggraphics::Mesh>,
 }
 
 impl GraphDrawer {
+    ///
+    /// 点が1つだけなら点のみ、点がなければ何も描かない
+    ///
     pub fn new<'a>(
         ctx: &mut SuzuContext<'a>,
         rect: numeric::Rect,
@@ -1421,52 +1424,66 @@ impl GraphDrawer {
         line_width: f32,
         line_color: ggraphics::Color,
         depth: i8,
-    ) -> Self {
+    ) -> ggez::GameResult<Self> {
         let mut builder = ggraphics::MeshBuilder::new();
 
         let max_data = data.iter().fold(numeric::Vector2f::new(0.0, 0.0), |m, v| {
             numeric::Vector2f::new(m.x.max(v.x), m.y.max(v.y))
         });
+        // 負の値も描けるように、0か最小値のうち小さい方を下端にする
+        let min_data = data.iter().fold(numeric::Vector2f::new(0.0, 0.0), |m, v| {
+            numeric::Vector2f::new(m.x.min(v.x), m.y.min(v.y))
+        });
+        let range = max_data - min_data;
+        let range = numeric::Vector2f::new(
+            if range.x > 0.0 { range.x } else { 1.0 },
+            if range.y > 0.0 { range.y } else { 1.0 },
+        );
 
         let scaled_points: Vec<numeric::Point2f> = data
             .iter()
             .map(|p| {
                 numeric::Point2f::new(
-                    graph_area.x + (graph_area.w * (p.x / max_data.x)),
-                    graph_area.y + (graph_area.h - (graph_area.h * (p.y / max_data.y))),
+                    graph_area.x + (graph_area.w * ((p.x - min_data.x) / range.x)),
+                    graph_area.y + (graph_area.h - (graph_area.h * ((p.y - min_data.y) / range.y))),
                 )
             })
             .collect();
 
         for scaled_point in scaled_points.iter() {
-            builder
-                .circle(
-                    ggraphics::DrawMode::fill(),
-                    mint::Point2::from_slice(&[scaled_point.x, scaled_point.y]),
-                    point_radius,
-                    0.01,
-                    point_color,
-                )
-                .expect("failed to create ");
+            builder.circle(
+                ggraphics::DrawMode::fill(),
+                mint::Point2::from_slice(&[scaled_point.x, scaled_point.y]),
+                point_radius,
+                0.01,
+                point_color,
+            )?;
         }
 
-        let mint_p_vec: Vec<mint::Point2<f32>> = scaled_points
-            .iter()
-            .map(|p| mint::Point2::from_slice(&[p.x, p.y]))
-            .collect();
-        builder
-            .line(mint_p_vec.as_slice(), line_width, line_color)
-            .unwrap();
+        // 線は2点以上ないと引けない
+        if scaled_points.len() >= 2 {
+            let mint_p_vec: Vec<mint::Point2<f32>> = scaled_points
+                .iter()
+                .map(|p| mint::Point2::from_slice(&[p.x, p.y]))
+                .collect();
+            builder.line(mint_p_vec.as_slice(), line_width, line_color)?;
+        }
+
+        let shapes = if scaled_points.is_empty() {
+            None
+        } else {
+            Some(builder.build(ctx.context)?)
+        };
 
-        GraphDrawer {
+        Ok(GraphDrawer {
             canvas: SubScreen::new(
                 ctx.context,
                 rect,
                 depth,
                 ggraphics::Color::from_rgba_u32(0xffffffff),
             ),
-            shapes: builder.build(ctx.context).unwrap(),
-        }
+            shapes: shapes,
+        })
     }
 }
 
@@ -1475,10 +1492,12 @@ impl Drawable for GraphDrawer {
         if self.is_visible() {
             sub_screen::stack_screen(ctx, &self.canvas);
 
-            ggraphics::draw(ctx, &self.shapes, ggraphics::DrawParam::default()).unwrap();
+            if let Some(shapes) = self.shapes.as_ref() {
+                ggraphics::draw(ctx, shapes, ggraphics::DrawParam::default())?;
+            }
 
             sub_screen::pop_screen(ctx);
-            self.canvas.draw(ctx).unwrap();
+            self.canvas.draw(ctx)?;
         }
         Ok(())
     }
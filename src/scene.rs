@@ -1,3 +1,4 @@
+pub mod copying_scene;
 pub mod end_scene;
 pub mod save_scene;
 pub mod scenario_scene;
@@ -61,6 +62,7 @@ impl FromStr for SceneID {
             "SuzunaShop" => Ok(Self::SuzunaShop),
             "WorkResult" => Ok(Self::DayResult),
             "Save" => Ok(Self::Save),
+            "Copying" => Ok(Self::Copying),
             "Title" => Ok(Self::Title),
             "End" => Ok(Self::End),
             _ => Err(()),
@@ -151,6 +153,12 @@ pub trait SceneManager {
 
     fn focus_event<'a>(&mut self, _ctx: &mut SuzuContext<'a>) {}
     fn unfocus_event<'a>(&mut self, _ctx: &mut SuzuContext<'a>) {}
+
+    ///
+    /// ウィンドウ上に見えている仮想スクリーンの領域が変化した時に呼ばれる
+    /// 画面端に固定するUIはここで再配置する
+    ///
+    fn layout_event<'a>(&mut self, _ctx: &mut SuzuContext<'a>, _area: numeric::Rect) {}
 }
 
 pub struct NullScene {}
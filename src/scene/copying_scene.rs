@@ -0,0 +1,331 @@
+use ggez::graphics as ggraphics;
+
+use crate::libsuzu::core::Clock;
+use crate::libsuzu::graphics::drawable::*;
+use crate::libsuzu::graphics::object::*;
+
+use crate::core::copy_work::*;
+use crate::core::{FontID, SuzuContext, TextureID, TileBatchTextureID};
+use crate::object::copy_scene_object::*;
+use crate::object::effect_object;
+use crate::object::util_object::*;
+use crate::scene::*;
+
+use crate::flush_delay_event;
+
+///
+/// # 写本の仕事をするシーン
+///
+/// 版木をなぞった距離に応じて、納期が近い依頼から写し進める
+///
+/// ## hangi
+/// 写本の作業をする版木
+///
+/// ## work_finished
+/// 今日の作業を終えたかどうか
+///
+pub struct CopyingScene {
+    background: Texture,
+    hangi: EffectableHangi,
+    request_text: UniText,
+    progress_text: UniText,
+    finish_button: SelectButton,
+    dragging: bool,
+    work_finished: bool,
+    event_list: DelayEventList<Self>,
+    scene_transition_effect: Option<effect_object::ScreenTileEffect>,
+    scene_transition: SceneID,
+    scene_transition_type: SceneTransition,
+    clock: Clock,
+}
+
+impl CopyingScene {
+    pub fn new<'a>(ctx: &mut SuzuContext<'a>) -> Self {
+        let background = Texture::new(
+            ctx.ref_texture(TextureID::JpHouseTexture),
+            numeric::Point2f::new(0.0, 0.0),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+        );
+
+        let hangi = EffectableHangi::new(ctx, numeric::Rect::new(100.0, 150.0, 800.0, 500.0));
+
+        let font_info = FontInformation::new(
+            ctx.resource.get_font(FontID::Cinema),
+            numeric::Vector2f::new(24.0, 24.0),
+            ggraphics::Color::from_rgba_u32(0xf6e1d5ff),
+        );
+
+        let request_text = UniText::new(
+            Self::request_text_string(ctx),
+            numeric::Point2f::new(100.0, 50.0),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+            font_info.clone(),
+        );
+
+        let progress_text = UniText::new(
+            String::new(),
+            numeric::Point2f::new(950.0, 150.0),
+            numeric::Vector2f::new(1.0, 1.0),
+            0.0,
+            0,
+            font_info,
+        );
+
+        let scene_transition_effect = Some(effect_object::ScreenTileEffect::new(
+            ctx,
+            TileBatchTextureID::Shoji,
+            numeric::Rect::new(
+                0.0,
+                0.0,
+                crate::core::WINDOW_SIZE_X as f32,
+                crate::core::WINDOW_SIZE_Y as f32,
+            ),
+            30,
+            effect_object::SceneTransitionEffectType::Open,
+            effect_object::TilingEffectType::WholeTile,
+            -128,
+            0,
+        ));
+
+        let texture = Box::new(TextButtonTexture::new(
+            ctx,
+            numeric::Point2f::new(0.0, 0.0),
+            "終える".to_string(),
+            FontInformation::new(
+                ctx.resource.get_font(FontID::Cinema),
+                numeric::Vector2f::new(24.0, 24.0),
+                ggraphics::Color::from_rgba_u32(0xf6e1d5ff),
+            ),
+            10.0,
+            ggraphics::Color::from_rgba_u32(0x5a4f3fff),
+            0,
+        ));
+
+        let finish_button = SelectButton::new(
+            ctx,
+            numeric::Rect::new(
+                1050.0,
+                (crate::core::WINDOW_SIZE_Y as f32) - 120.0,
+                100.0,
+                50.0,
+            ),
+            texture,
+        );
+
+        let mut event_list = DelayEventList::new();
+        event_list.add_event(
+            Box::new(move |slf: &mut Self, _, _| {
+                slf.scene_transition_effect = None;
+            }),
+            31,
+        );
+
+        let mut scene = CopyingScene {
+            background: background,
+            hangi: hangi,
+            request_text: request_text,
+            progress_text: progress_text,
+            finish_button: finish_button,
+            dragging: false,
+            work_finished: false,
+            event_list: event_list,
+            scene_transition_effect: scene_transition_effect,
+            scene_transition: SceneID::Copying,
+            scene_transition_type: SceneTransition::Keep,
+            clock: 0,
+        };
+        scene.update_progress_text(ctx);
+
+        scene
+    }
+
+    fn request_text_string<'a>(ctx: &mut SuzuContext<'a>) -> String {
+        let record = &ctx.take_save_data().copy_work_record;
+
+        match record.current_request() {
+            Some(request) => format!(
+                "「{}」 {}様の依頼 残り{}頁 納期 {}",
+                request.book_title,
+                request.customer,
+                request.remaining_pages(),
+                request.deadline.to_short_string()
+            ),
+            None => "写本の依頼はありません".to_string(),
+        }
+    }
+
+    ///
+    /// 今日なぞった距離で、何頁写せたか。依頼の残り頁数を超えることはない
+    ///
+    fn copied_pages_today<'a>(&self, ctx: &mut SuzuContext<'a>) -> usize {
+        let remaining: usize = ctx
+            .take_save_data()
+            .copy_work_record
+            .iter()
+            .map(|request| request.remaining_pages())
+            .sum();
+        let pages = (self.hangi.get_drag_distance() / COPY_DRAG_DISTANCE_PER_PAGE) as usize;
+
+        pages.min(remaining)
+    }
+
+    fn update_progress_text<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
+        let rest_sec = COPY_WORK_TIME_LIMIT.saturating_sub(self.get_current_clock()) / 60;
+        let pages = self.copied_pages_today(ctx);
+
+        self.progress_text
+            .replace_text(format!("写した頁 {}\n残り時間 {}秒", pages, rest_sec));
+    }
+
+    ///
+    /// 今日写した頁を依頼に反映し、写し終えた依頼を納品して次の日に進む
+    ///
+    fn finish_work<'a>(&mut self, ctx: &mut SuzuContext<'a>, t: Clock) {
+        if self.work_finished {
+            return;
+        }
+        self.work_finished = true;
+
+        let pages = self.copied_pages_today(ctx);
+        let save_data = ctx.take_save_data_mut();
+        save_data.copy_work_record.copy_pages(pages);
+        let payment = save_data.deliver_copy_works();
+
+        self.progress_text
+            .replace_text(format!("写した頁 {}\n受け取った代金 {}円", pages, payment));
+        ctx.process_utility.redraw();
+
+        self.event_list.add_event(
+            Box::new(move |slf: &mut Self, ctx, t| {
+                slf.scene_transition_effect = Some(effect_object::ScreenTileEffect::new(
+                    ctx,
+                    TileBatchTextureID::Shoji,
+                    numeric::Rect::new(
+                        0.0,
+                        0.0,
+                        crate::core::WINDOW_SIZE_X as f32,
+                        crate::core::WINDOW_SIZE_Y as f32,
+                    ),
+                    30,
+                    effect_object::SceneTransitionEffectType::Close,
+                    effect_object::TilingEffectType::WholeTile,
+                    -128,
+                    t,
+                ));
+            }),
+            t + 120,
+        );
+
+        self.event_list.add_event(
+            Box::new(move |slf: &mut Self, ctx, _| {
+                slf.scene_transition = SceneID::Scenario;
+                slf.scene_transition_type = SceneTransition::SwapTransition;
+                ctx.go_next_day();
+            }),
+            t + 151,
+        );
+    }
+}
+
+impl SceneManager for CopyingScene {
+    fn mouse_motion_event<'a>(
+        &mut self,
+        ctx: &mut SuzuContext<'a>,
+        point: numeric::Point2f,
+        _offset: numeric::Vector2f,
+    ) {
+        if self.dragging && !self.work_finished && self.hangi.contains(ctx.context, point) {
+            self.hangi.dragging_handler(ctx.context, point);
+            self.update_progress_text(ctx);
+            ctx.process_utility.redraw();
+        }
+    }
+
+    fn mouse_button_down_event<'a>(
+        &mut self,
+        _ctx: &mut SuzuContext<'a>,
+        button: ginput::mouse::MouseButton,
+        _point: numeric::Point2f,
+    ) {
+        if button == ginput::mouse::MouseButton::Left {
+            self.dragging = true;
+        }
+    }
+
+    fn mouse_button_up_event<'a>(
+        &mut self,
+        ctx: &mut SuzuContext<'a>,
+        button: ginput::mouse::MouseButton,
+        point: numeric::Point2f,
+    ) {
+        if button != ginput::mouse::MouseButton::Left {
+            return;
+        }
+
+        self.dragging = false;
+        self.hangi.release_handler(ctx.context);
+        ctx.process_utility.redraw();
+
+        if self.finish_button.contains(ctx.context, point) {
+            let t = self.get_current_clock();
+            self.finish_work(ctx, t);
+        }
+    }
+
+    fn pre_process<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
+        let t = self.get_current_clock();
+
+        if let Some(transition_effect) = self.scene_transition_effect.as_mut() {
+            transition_effect.effect(ctx.context, t);
+            ctx.process_utility.redraw();
+        }
+
+        if !self.work_finished {
+            if t >= COPY_WORK_TIME_LIMIT {
+                self.finish_work(ctx, t);
+            } else if t % 60 == 0 {
+                self.update_progress_text(ctx);
+                ctx.process_utility.redraw();
+            }
+        }
+
+        if flush_delay_event!(self, self.event_list, ctx, self.get_current_clock()) > 0 {
+            ctx.process_utility.redraw();
+        }
+    }
+
+    fn drawing_process(&mut self, ctx: &mut ggez::Context) {
+        self.background.draw(ctx).unwrap();
+        self.hangi.draw(ctx).unwrap();
+        self.request_text.draw(ctx).unwrap();
+        self.progress_text.draw(ctx).unwrap();
+
+        self.finish_button.draw(ctx).unwrap();
+
+        if let Some(transition_effect) = self.scene_transition_effect.as_mut() {
+            transition_effect.draw(ctx).unwrap();
+        }
+    }
+
+    fn post_process<'a>(&mut self, _ctx: &mut SuzuContext<'a>) -> SceneTransition {
+        self.update_current_clock();
+
+        self.scene_transition_type
+    }
+
+    fn transition(&self) -> SceneID {
+        self.scene_transition
+    }
+
+    fn get_current_clock(&self) -> Clock {
+        self.clock
+    }
+
+    fn update_current_clock(&mut self) {
+        self.clock += 1;
+    }
+}
@@ -8,8 +8,10 @@ use crate::libsuzu::{core::Clock, sound::SoundPlayFlags};
 use crate::core::{GameMode, GeneralScenarioID, MouseInformation, ScenarioSceneSaveData, SoundID, SuzuContext, TileBatchTextureID};
 
 use crate::add_delay_event;
+use crate::core::financial_ledger::TransactionCategory;
 use crate::core::game_system;
 use crate::core::game_system::*;
+use crate::core::reputation::ReputationEvent;
 use crate::flush_delay_event;
 use crate::flush_delay_event_and_redraw_check;
 use crate::object::effect_object;
@@ -51,6 +53,7 @@ pub struct ScenarioScene {
     scene_transition_effect: Option<effect_object::ScreenTileEffect>,
     scene_transition: SceneID,
     scenario_ctx: ScenarioContext,
+    going_out_event: Option<String>,
     clock: Clock,
 }
 
@@ -149,6 +152,7 @@ impl ScenarioScene {
             status_screen: status_screen,
             scene_transition_type: SceneTransition::Keep,
             scenario_ctx: scenario_ctx,
+            going_out_event: None,
             clock: 0,
         }
     }
@@ -293,23 +297,12 @@ impl ScenarioScene {
                     "NextDay" => {
                         self.status_screen.show_main_page(ctx);
 
-                        let diff = if ctx
-                            .take_save_data_mut()
-                            .suzunaan_status
-                            .get_current_reputation()
-                            < game_system::TAKING_REST_REPUTATION_COST as f32
-                        {
-                            ctx.take_save_data_mut()
-                                .suzunaan_status
-                                .get_current_reputation()
-                        } else {
-                            game_system::TAKING_REST_REPUTATION_COST as f32
-                        };
-
-                        self.status_screen.change_suzunaan_reputation(ctx, -diff);
-                        ctx.take_save_data_mut()
-                            .suzunaan_status
-                            .add_reputation(-diff);
+                        let diff = ctx.take_save_data_mut().apply_reputation_event(
+                            ReputationEvent::TakingRest,
+                            -(game_system::TAKING_REST_REPUTATION_COST as f32),
+                            None,
+                        );
+                        self.status_screen.change_suzunaan_reputation(ctx, diff);
 
                         add_delay_event!(
                             self.event_list,
@@ -349,6 +342,9 @@ impl ScenarioScene {
                             self.get_current_clock() + 1
                         );
                     }
+                    "FinishGoingOut" => {
+                        self.finish_going_out(ctx);
+                    }
                     "ShowStatusScreen" => {
                         self.status_screen.appear();
                         self.scenario_event.release_scenario_waiting(ctx);
@@ -370,29 +366,50 @@ impl ScenarioScene {
         }
     }
 
+    ///
+    /// 意欲が足りず、予定をこなせないときのシナリオに切り替える
+    ///
+    fn start_no_enough_hp_scenario<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
+        let path = ctx
+            .resource
+            .get_general_scenario_path(&GeneralScenarioID::NoRnoughHP)
+            .unwrap_or_else(|| "/scenario/no_enough_hp.toml".to_string());
+        let t = self.get_current_clock();
+        self.scenario_event
+            .replace_scenario(ctx, &mut self.scenario_ctx, &path, t);
+    }
+
+    ///
+    /// 予定に応じて意欲を増減させ、ステータス画面に反映する
+    ///
+    fn apply_day_work_hp<'a>(&mut self, ctx: &mut SuzuContext<'a>, work: DayWorkType) {
+        let hp_diff = ctx
+            .take_save_data_mut()
+            .suzunaan_status
+            .add_kosuzu_hp(work.hp_diff());
+        self.status_screen.change_kosuzu_hp(ctx, hp_diff);
+    }
+
     fn start_shop_work_schedule<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
-	let next_hp = ctx.take_save_data().suzunaan_status.kosuzu_hp - 20.0;
-        if next_hp < 0.0 {
-            let t = self.get_current_clock();
-            self.scenario_event
-                .replace_scenario(ctx, &mut self.scenario_ctx, "/scenario/no_enough_hp.toml", t);
+        if !ctx
+            .take_save_data()
+            .suzunaan_status
+            .can_start_day_work(&DayWorkType::ShopWork)
+        {
+            self.start_no_enough_hp_scenario(ctx);
             return;
         }
-	
+
         self.scenario_ctx.builtin_command_inexec = true;
         self.status_screen.show_main_page(ctx);
-        self.status_screen.change_kosuzu_hp(ctx, -20.0);
-        ctx.take_save_data_mut().suzunaan_status.kosuzu_hp -= 20.0;
+        self.apply_day_work_hp(ctx, DayWorkType::ShopWork);
 
         add_delay_event!(
             self.event_list,
             |slf, ctx, _| {
-                let reputation_diff = ctx.current_total_ad_reputation_gain();
+                let reputation_diff = ctx.apply_ad_reputation();
                 slf.status_screen
-                    .change_suzunaan_reputation(ctx, reputation_diff as f32);
-                ctx.take_save_data_mut()
-                    .suzunaan_status
-                    .add_reputation(reputation_diff as f32);
+                    .change_suzunaan_reputation(ctx, reputation_diff);
             },
             self.get_current_clock() + 100
         );
@@ -400,7 +417,7 @@ impl ScenarioScene {
         let money_diff = ctx.current_total_ad_agency_money_gain() - ctx.current_total_ad_cost();
         self.status_screen
             .change_main_page_money(ctx, money_diff, self.get_current_clock());
-        ctx.take_save_data_mut().task_result.total_money += money_diff;
+        ctx.settle_ad_money();
 
         add_delay_event!(
             self.event_list,
@@ -428,7 +445,60 @@ impl ScenarioScene {
         );
     }
 
-    fn start_going_out_schedule<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
+    fn start_copying_schedule<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
+        if !ctx
+            .take_save_data()
+            .suzunaan_status
+            .can_start_day_work(&DayWorkType::Copying)
+        {
+            self.start_no_enough_hp_scenario(ctx);
+            return;
+        }
+
+        self.scenario_ctx.builtin_command_inexec = true;
+        self.status_screen.show_main_page(ctx);
+        self.apply_day_work_hp(ctx, DayWorkType::Copying);
+
+        let money_diff = ctx.current_total_ad_agency_money_gain() - ctx.current_total_ad_cost();
+        self.status_screen
+            .change_main_page_money(ctx, money_diff, self.get_current_clock());
+        ctx.settle_ad_money();
+
+        add_delay_event!(
+            self.event_list,
+            |slf, ctx, _| {
+                let reputation_diff = ctx.apply_ad_reputation();
+                slf.status_screen
+                    .change_suzunaan_reputation(ctx, reputation_diff);
+            },
+            self.get_current_clock() + 100
+        );
+
+        add_delay_event!(
+            self.event_list,
+            |slf, _, _| {
+                slf.scene_transition = SceneID::Copying;
+                slf.scene_transition_type = SceneTransition::SwapTransition;
+            },
+            self.get_current_clock() + 300
+        );
+        add_delay_event!(
+            self.event_list,
+            |slf, _, _| {
+                slf.scenario_ctx.builtin_command_inexec = false;
+            },
+            self.get_current_clock() + 301
+        );
+        add_delay_event!(
+            self.event_list,
+            |slf, ctx, t| {
+                slf.scene_transition_close_effect(ctx, t);
+            },
+            self.get_current_clock() + 250
+        );
+    }
+
+    fn start_going_out_schedule<'a>(&mut self, ctx: &mut SuzuContext<'a>, dest: GoingOutEvent) {
         self.status_screen.show_main_page(ctx);
         let money_diff =
             ctx.current_total_ad_agency_money_gain() - ctx.current_total_ad_cost() - 400;
@@ -446,31 +516,76 @@ impl ScenarioScene {
 
         self.status_screen
             .change_main_page_money(ctx, money_diff, self.get_current_clock());
-        ctx.take_save_data_mut()
-            .task_result
-            .add_total_money(money_diff);
+        ctx.settle_ad_money();
+        ctx.take_save_data_mut().record_transaction(
+            TransactionCategory::GoingOutCost,
+            -(game_system::GOING_OUT_MONEY_COST as i32),
+            "",
+        );
 
         self.scenario_ctx.builtin_command_inexec = true;
         self.status_screen.show_main_page(ctx);
-        self.status_screen.change_kosuzu_hp(ctx, 40.0);
-        ctx.take_save_data_mut().suzunaan_status.kosuzu_hp += 40.0;
-        if ctx.take_save_data().suzunaan_status.kosuzu_hp > 100.0 {
-            ctx.take_save_data_mut().suzunaan_status.kosuzu_hp = 100.0;
-        }
+        self.apply_day_work_hp(ctx, DayWorkType::GoingOut(dest));
 
         add_delay_event!(
             self.event_list,
             |slf, ctx, _| {
-                let reputation_diff = ctx.current_total_ad_reputation_gain() as f32;
+                let reputation_diff = ctx.apply_ad_reputation();
                 slf.status_screen
                     .change_suzunaan_reputation(ctx, reputation_diff);
-                ctx.take_save_data_mut()
-                    .suzunaan_status
-                    .add_reputation(reputation_diff);
             },
             self.get_current_clock() + 100
         );
 
+        let next_event = ctx
+            .resource
+            .get_going_out_events()
+            .next_event(dest, &ctx.take_save_data().event_progress)
+            .cloned();
+
+        match next_event {
+            Some(event) => {
+                // 出来事のシナリオを見せる。外出はシナリオのFinishGoingOutで終える
+                self.going_out_event = Some(event.id.clone());
+                add_delay_event!(
+                    self.event_list,
+                    move |slf, ctx, t| {
+                        slf.scenario_ctx.builtin_command_inexec = false;
+                        slf.scenario_event.replace_scenario(
+                            ctx,
+                            &mut slf.scenario_ctx,
+                            &event.scenario,
+                            t,
+                        );
+                    },
+                    self.get_current_clock() + 150
+                );
+            }
+            None => self.finish_going_out(ctx),
+        }
+    }
+
+    ///
+    /// 外出先での出来事の結果を反映し、次の日に進む
+    ///
+    fn finish_going_out<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
+        self.scenario_ctx.builtin_command_inexec = true;
+        self.status_screen.show_main_page(ctx);
+
+        let maybe_event = self
+            .going_out_event
+            .take()
+            .and_then(|id| ctx.resource.get_going_out_events().get(&id).cloned());
+        if let Some(event) = maybe_event {
+            let (reputation_diff, hp_diff, money_diff) =
+                ctx.take_save_data_mut().apply_going_out_event(&event);
+            self.status_screen
+                .change_main_page_money(ctx, money_diff, self.get_current_clock());
+            self.status_screen
+                .change_suzunaan_reputation(ctx, reputation_diff);
+            self.status_screen.change_kosuzu_hp(ctx, hp_diff);
+        }
+
         add_delay_event!(
             self.event_list,
             |slf, ctx, _| {
@@ -500,42 +615,25 @@ impl ScenarioScene {
     fn start_taking_rest_schedule<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
         self.scenario_ctx.builtin_command_inexec = true;
         self.status_screen.show_main_page(ctx);
-        self.status_screen.change_kosuzu_hp(ctx, 20.0);
-        ctx.take_save_data_mut().suzunaan_status.kosuzu_hp += 20.0;
-        if ctx.take_save_data().suzunaan_status.kosuzu_hp > 100.0 {
-            ctx.take_save_data_mut().suzunaan_status.kosuzu_hp = 100.0;
-        }
+        self.apply_day_work_hp(ctx, DayWorkType::TakingRest);
 
         let money_diff = ctx.current_total_ad_agency_money_gain() - ctx.current_total_ad_cost();
         self.status_screen
             .change_main_page_money(ctx, money_diff, self.get_current_clock());
-        ctx.take_save_data_mut().task_result.total_money += money_diff;
+        ctx.settle_ad_money();
 
         add_delay_event!(
             self.event_list,
             |slf, ctx, _| {
-                let reputation_diff = ctx.current_total_ad_reputation_gain() as f32
-                    - game_system::TAKING_REST_REPUTATION_COST as f32;
-
-                let reputation_diff = if ctx
-                    .take_save_data()
-                    .suzunaan_status
-                    .get_current_reputation()
-                    + reputation_diff
-                    < 0.0
-                {
-                    ctx.take_save_data()
-                        .suzunaan_status
-                        .get_current_reputation()
-                } else {
-                    reputation_diff
-                };
+                let ad_diff = ctx.apply_ad_reputation();
+                let rest_diff = ctx.take_save_data_mut().apply_reputation_event(
+                    ReputationEvent::TakingRest,
+                    -(game_system::TAKING_REST_REPUTATION_COST as f32),
+                    None,
+                );
 
                 slf.status_screen
-                    .change_suzunaan_reputation(ctx, reputation_diff);
-                ctx.take_save_data_mut()
-                    .suzunaan_status
-                    .add_reputation(reputation_diff);
+                    .change_suzunaan_reputation(ctx, ad_diff + rest_diff);
             },
             self.get_current_clock() + 100
         );
@@ -574,8 +672,11 @@ impl ScenarioScene {
             DayWorkType::TakingRest => {
                 self.start_taking_rest_schedule(ctx);
             }
-            DayWorkType::GoingOut(_) => {
-                self.start_going_out_schedule(ctx);
+            DayWorkType::GoingOut(dest) => {
+                self.start_going_out_schedule(ctx, dest);
+            }
+            DayWorkType::Copying => {
+                self.start_copying_schedule(ctx);
             }
         }
     }
@@ -10,14 +10,24 @@ use crate::libsuzu::{device as tdev, sound::SoundPlayFlags};
 use ggez::graphics as ggraphics;
 use crate::libsuzu::core::Clock;
 use crate::libsuzu::core::Updatable;
+use crate::libsuzu::graphics::camera::Camera;
 use crate::libsuzu::graphics::drawable::*;
+use crate::libsuzu::graphics::layout::{Anchor, AnchoredLayout};
+use crate::libsuzu::graphics::object::sub_screen::SubScreen;
 
 use ggez::input::mouse::MouseButton;
 use crate::libsuzu::numeric;
 
 use super::suzuna_scene::suzuna_sub_scene::TaskTutorialContext;
 use super::*;
+use crate::core::book_ledger::BookLocation;
+use crate::core::character_definition::CharacterDefinition;
+use crate::core::copy_work::COPY_REQUEST_RATE;
+use crate::core::fatigue;
+use crate::core::going_out::ACQUAINTANCE_VISIT_RATE;
 use crate::core::map_parser as mp;
+use crate::core::path_search::TileReservation;
+use crate::core::reputation::*;
 use crate::core::{
     BookInformation, FontID, MouseInformation, ResultReport, SavableData, SuzuContext,
     TileBatchTextureID,
@@ -44,6 +54,17 @@ use crate::perf_measure;
 
 use effect_object::{SceneTransitionEffectType, TilingEffectType};
 use notify::*;
+use number_to_jk::number_to_jk;
+
+///
+/// 新しく来る客が、本を借りている客のうちの誰かである確率
+///
+const RETURNING_VISITOR_RATE: f32 = 0.4;
+
+///
+/// 紛失した貸出の通知に、1件ずつ書き出す最大の件数
+///
+const LOST_LOAN_NOTIFICATION_LINES: usize = 3;
 
 pub struct CharacterGroup {
     group: Vec<CustomerCharacter>,
@@ -230,19 +251,49 @@ impl MapData {
     ) -> Self {
         let map_constract_data = ctx.resource.get_map_data(map_id).unwrap();
 
+        let tile_map = mp::StageObjectMap::new(
+            ctx.context,
+            &map_constract_data.map_file_path,
+            camera.clone(),
+            numeric::Rect::new(0.0, 0.0, 1366.0, 768.0),
+            numeric::Vector2f::new(3.0, 3.0),
+        );
+
+        let mut event_map = MapEventList::from_file(ctx, &map_constract_data.event_map_file_path);
+        event_map.register_map_regions(&tile_map);
+
         MapData {
-            tile_map: mp::StageObjectMap::new(
-                ctx.context,
-                &map_constract_data.map_file_path,
-                camera.clone(),
-                numeric::Rect::new(0.0, 0.0, 1366.0, 768.0),
-                numeric::Vector2f::new(3.0, 3.0),
-            ),
-            event_map: MapEventList::from_file(ctx, &map_constract_data.event_map_file_path),
+            tile_map: tile_map,
+            event_map: event_map,
             scenario_event: None,
         }
     }
 
+    ///
+    /// 客が現れる位置。マップにspawnオブジェクト"customer"が無ければ、店の入口
+    ///
+    pub fn customer_spawn_point(&self) -> numeric::Point2f {
+        self.tile_map
+            .find_spawn_point("customer")
+            .unwrap_or(numeric::Point2f::new(1430.0, 1246.0))
+    }
+
+    ///
+    /// 客が向かう本棚の候補。マップにcustomer-destオブジェクトが無ければ、既定の本棚
+    ///
+    pub fn customer_dest_point(&self) -> CustomerDestPoint {
+        let candidates = self.tile_map.customer_dest_candidates();
+
+        if candidates.is_empty() {
+            CustomerDestPoint::new(vec![
+                numeric::Vector2u::new(10, 4),
+                numeric::Vector2u::new(6, 4),
+            ])
+        } else {
+            CustomerDestPoint::new(candidates)
+        }
+    }
+
     pub fn get_tile_size(&self) -> numeric::Vector2f {
         self.tile_map.get_tile_drawing_size()
     }
@@ -262,6 +313,23 @@ impl MapData {
             ),
         )
     }
+
+    ///
+    /// マップ上の位置でトリガーゾーンを更新し、発動したものを返す
+    ///
+    pub fn check_trigger_zones(
+        &mut self,
+        point: numeric::Point2f,
+        t: Clock,
+        save_data: &SavableData,
+    ) -> Vec<FiredTriggerZone> {
+        let tile_size = self.get_tile_size();
+        self.event_map.update_trigger_zones(
+            numeric::Point2f::new(point.x / tile_size.x, point.y / tile_size.y),
+            t,
+            save_data,
+        )
+    }
 }
 
 struct MapObjectDrawer<'a> {
@@ -330,13 +398,23 @@ impl CustomerQueue {
         self.customer_queue.len()
     }
 
-    pub fn drain_giveup_customers(&mut self, now: Clock) -> Vec<CustomerCharacter> {
+    ///
+    /// 客それぞれの我慢できる時間を超えて待っている客を、列から取り除いて返す
+    ///
+    pub fn drain_giveup_customers(
+        &mut self,
+        now: Clock,
+        reputation: f32,
+    ) -> Vec<CustomerCharacter> {
         let mut giveup_customers = Vec::new();
 
         for index in (0..self.customer_queue.len()).rev() {
-            let (_, t) = self.customer_queue.get(index).unwrap();
+            let (customer, t) = self.customer_queue.get(index).unwrap();
+            let patience = customer
+                .get_personality()
+                .patience_with_reputation(reputation);
 
-            if (now - t) > 1200 {
+            if (now - t) > patience {
                 let (giveup, _) = self.customer_queue.remove(index).unwrap();
                 giveup_customers.push(giveup);
             }
@@ -474,6 +552,7 @@ impl GoToCheckCustomers {
         ctx: &mut SuzuContext<'a>,
         camera: &numeric::Rect,
         map_data: &mp::StageObjectMap,
+        reservation: &mut TileReservation,
         t: Clock,
     ) {
         self.move_and_collision_check(ctx.context, camera, map_data, t);
@@ -482,6 +561,7 @@ impl GoToCheckCustomers {
             customer.try_update_move_effect(
                 ctx,
                 map_data,
+                reservation,
                 numeric::Vector2u::new(5, 14),
                 numeric::Vector2u::new(15, 14),
                 t,
@@ -664,6 +744,9 @@ impl ShopTutorialList {
 /// ### camera
 /// マップを覗くカメラ
 ///
+/// ### map_view
+/// マップとキャラクターを描画するSubScreen。カメラの拡大率で拡大して表示する
+///
 pub struct ShopScene {
     mouse_info: MouseInformation,
     player: PlayableCharacter,
@@ -680,7 +763,9 @@ pub struct ShopScene {
     customer_request_queue: VecDeque<CustomerRequest>,
     customer_queue: CustomerQueue,
     goto_check_customers: GoToCheckCustomers,
-    camera: Rc<RefCell<numeric::Rect>>,
+    tile_reservation: TileReservation,
+    camera: Camera,
+    map_view: SubScreen,
     dark_effect_panel: DarkEffectPanel,
     dark_effect_panel_shallow: DarkEffectPanel,
     pause_screen_set: PauseScreenSet,
@@ -709,32 +794,44 @@ impl ShopScene {
     ) -> ShopScene {
         let begining_save_data = ctx.take_save_data().clone();
 
-        let camera = Rc::new(RefCell::new(numeric::Rect::new(0.0, 0.0, 1366.0, 768.0)));
+        let mut camera = Camera::new(numeric::Vector2f::new(1366.0, 768.0));
 
         let map_position = numeric::Point2f::new(172.0, 1330.0);
 
         let player = PlayableCharacter::new(character_factory::create_character(
             character_factory::CharacterFactoryOrder::PlayableDoremy1,
             ctx,
-            &camera.borrow(),
+            &camera.get_view(),
             map_position,
         ));
 
         let character_group = CharacterGroup::new();
 
-        let mut map = MapData::new(ctx, map_id, camera.clone());
+        let mut map = MapData::new(ctx, map_id, camera.get_view_ref());
         map.tile_map.build_collision_map();
 
+        // プレイヤーを少し遅れて追いかけ、マップの外は映さない
+        if let Some(zoom) = map.tile_map.camera_zoom_property() {
+            camera.set_zoom(zoom);
+        }
+        camera.set_map_size(map.tile_map.get_map_size());
+        camera.set_dead_zone(numeric::Vector2f::new(48.0, 32.0));
+        camera.set_follow_rate(0.2);
+        camera.look_at(map_position);
+
         let shop_time = ShopClock::new(8, 0);
         let drawble_shop_clock =
             DrawableShopClock::from_toml(ctx, "/other_config/shop_clock.toml", shop_time.clone());
 
         let mut result_report = ResultReport::new();
         for new_book in new_books.iter() {
-            ctx.take_save_data_mut()
-                .task_result
-                .not_shelved_books
-                .push(new_book.clone());
+            if let Err(e) = ctx
+                .take_save_data_mut()
+                .book_ledger
+                .place(new_book, BookLocation::ReturnBox)
+            {
+                eprintln!("Failed to put a new book into the return box -> {}", e);
+            }
             result_report.add_new_book_id(new_book.get_unique_id());
         }
 
@@ -751,7 +848,7 @@ impl ShopScene {
                 }),
                 31,
             );
-        } else {
+        } else if !new_books.is_empty() {
             let cloned_new_books = new_books.clone();
             delay_event_list.add_event(
                 Box::new(move |slf: &mut ShopScene, ctx, t| {
@@ -816,6 +913,7 @@ impl ShopScene {
             customer_request_queue: VecDeque::new(),
             customer_queue: CustomerQueue::new(0),
             goto_check_customers: GoToCheckCustomers::new(numeric::Vector2u::new(15, 4), 0),
+            tile_reservation: TileReservation::new(),
             dark_effect_panel: DarkEffectPanel::new(
                 ctx.context,
                 numeric::Rect::new(0.0, 0.0, 1366.0, 768.0),
@@ -828,27 +926,35 @@ impl ShopScene {
             ),
             pause_screen_set: PauseScreenSet::new(ctx, 0, 0),
             camera: camera,
+            map_view: SubScreen::new(
+                ctx.context,
+                numeric::Rect::new(0.0, 0.0, 1366.0, 768.0),
+                0,
+                ggraphics::Color::from_rgba_u32(0),
+            ),
             transition_scene: SceneID::SuzunaShop,
             transition_status: SceneTransition::Keep,
             scene_transition_effect: None,
             notification_area: NotificationArea::new(
                 ctx,
-                numeric::Point2f::new((crate::core::WINDOW_SIZE_X - 20) as f32, 20.0),
+                AnchoredLayout::new(Anchor::TopRight, numeric::Vector2f::new(20.0, 10.0)),
                 0,
             ),
             begining_save_data: begining_save_data,
             drawable_shop_clock: drawble_shop_clock,
             shop_command_palette: ShopCommandPalette::new(
                 ctx,
-                numeric::Rect::new(160.0, 720.0, 1066.0, 100.0),
+                numeric::Vector2f::new(1066.0, 100.0),
+                AnchoredLayout::new(Anchor::Bottom, numeric::Vector2f::new(10.0, -52.0)),
                 0,
                 0,
             ),
             shop_time_status: ShopTimeStatus::Preparing,
             shop_time_status_header: shop_time_status_header,
-            random_customer_add_timing: ctx
-                .resource
-                .get_todays_customer_dist(&ctx.take_save_data().date),
+            random_customer_add_timing: ctx.resource.get_todays_customer_dist(
+                &ctx.take_save_data().date,
+                ctx.todays_ad_effects().customer_rate(),
+            ),
             new_books: new_books,
             tutorial_list: if ctx.take_save_data().date.first_day()
                 && ctx.take_save_data().game_mode.is_story_mode()
@@ -864,44 +970,79 @@ impl ShopScene {
     }
 
     ///
-    /// カメラを動かすメソッド
+    /// カメラの位置を更新する
+    /// 視界が動いた場合は、マップを描き直し、キャラクターの描画位置も合わせる
     ///
-    pub fn move_camera(&mut self, offset: numeric::Vector2f) {
-        self.camera.borrow_mut().x += offset.x;
-        self.camera.borrow_mut().y += offset.y;
+    fn update_camera(&mut self, t: Clock) {
+        if !self.camera.update(t) {
+            return;
+        }
+
+        self.map.tile_map.request_redraw();
+        self.map.tile_map.request_updating_tile_batch();
+
+        let view = self.camera.get_view();
 
-        if offset.x != 0.0 || offset.y != 0.0 {
-            self.map.tile_map.request_redraw();
-            self.map.tile_map.request_updating_tile_batch();
+        self.player
+            .get_mut_character_object()
+            .update_display_position(&view);
+
+        for customer in self.character_group.iter_mut() {
+            customer
+                .get_mut_character_object()
+                .update_display_position(&view);
         }
-    }
 
-    pub fn set_camera_x(&mut self, offset: f32) {
-        self.camera.borrow_mut().x = offset;
+        for customer in self.goto_check_customers.iter_mut() {
+            customer
+                .get_mut_character_object()
+                .update_display_position(&view);
+        }
 
-        if offset != 0.0 {
-            self.map.tile_map.request_redraw();
-            self.map.tile_map.request_updating_tile_batch();
+        for (customer, _) in self.customer_queue.iter_mut() {
+            customer
+                .get_mut_character_object()
+                .update_display_position(&view);
         }
     }
 
-    pub fn set_camera_y(&mut self, offset: f32) {
-        self.camera.borrow_mut().y = offset;
+    ///
+    /// 客がベルを鳴らした時などに、カメラをその位置へ動かして見せる
+    ///
+    pub fn pan_camera_to(&mut self, world_pos: numeric::Point2f, t: Clock) {
+        self.camera.pan_to(world_pos, 40, 60, t);
+    }
+
+    ///
+    /// 客同士が経路探索で互いを避けるように、全ての客のタイル位置を障害物として登録する
+    /// 同時に、タイルの予約表の占有状態も更新する
+    ///
+    fn update_customer_obstacles(&mut self, ctx: &mut ggez::Context) {
+        let map_data = &self.map.tile_map;
+        let mut occupants = Vec::new();
 
-        if offset != 0.0 {
-            self.map.tile_map.request_redraw();
-            self.map.tile_map.request_updating_tile_batch();
+        for customer in self.character_group.iter_mut() {
+            if let Some(tile) = customer.get_tile_position(ctx, map_data) {
+                occupants.push((customer.get_agent_id(), tile));
+            }
         }
-    }
 
-    pub fn set_camera(&mut self, offset: numeric::Vector2f) {
-        self.camera.borrow_mut().x = offset.x;
-        self.camera.borrow_mut().y = offset.y;
+        for customer in self.goto_check_customers.iter_mut() {
+            if let Some(tile) = customer.get_tile_position(ctx, map_data) {
+                occupants.push((customer.get_agent_id(), tile));
+            }
+        }
 
-        if offset.x != 0.0 || offset.y != 0.0 {
-            self.map.tile_map.request_redraw();
-            self.map.tile_map.request_updating_tile_batch();
+        for (customer, _) in self.customer_queue.iter_mut() {
+            if let Some(tile) = customer.get_tile_position(ctx, map_data) {
+                occupants.push((customer.get_agent_id(), tile));
+            }
         }
+
+        self.map
+            .tile_map
+            .set_dynamic_obstacles(occupants.iter().map(|(_, tile)| *tile));
+        self.tile_reservation.update_occupants(occupants);
     }
 
     pub fn customer_move_and_collision_check(
@@ -933,32 +1074,9 @@ impl ShopScene {
             .update_display_position(camera);
     }
 
-    fn camera_focus_character_x(&mut self) {
-        let chara_pos = self.player.get_map_position();
-
-        let x = if chara_pos.x <= 683.0 {
-            0.0
-        } else if chara_pos.x >= 853.0 {
-            170.0
-        } else {
-            chara_pos.x - 683.0
-        };
-
-        self.set_camera_x(x);
-    }
-
-    fn camera_focus_character_y(&mut self) {
+    fn camera_focus_character(&mut self) {
         let chara_pos = self.player.get_map_position();
-
-        let y = if chara_pos.y <= 384.0 {
-            0.0
-        } else if chara_pos.y >= 1344.0 {
-            960.0
-        } else {
-            chara_pos.y - 384.0
-        };
-
-        self.set_camera_y(y);
+        self.camera.follow(chara_pos);
     }
 
     ///
@@ -1016,9 +1134,9 @@ impl ShopScene {
         // プレイヤーのマップ座標を更新
         self.player
             .get_mut_character_object()
-            .update_display_position(&self.camera.borrow());
+            .update_display_position(&self.camera.get_view());
 
-        self.camera_focus_character_x();
+        self.camera_focus_character();
     }
 
     ///
@@ -1038,10 +1156,10 @@ impl ShopScene {
         // プレイヤーのマップ座標を更新
         self.player
             .get_mut_character_object()
-            .update_display_position(&self.camera.borrow());
+            .update_display_position(&self.camera.get_view());
 
         // カメラをプレイヤーにフォーカス
-        self.camera_focus_character_y();
+        self.camera_focus_character();
     }
 
     fn check_character_collision_x_sub(
@@ -1123,7 +1241,7 @@ impl ShopScene {
         // マップ座標を更新
         self.player
             .get_mut_character_object()
-            .update_display_position(&self.camera.borrow());
+            .update_display_position(&self.camera.get_view());
 
         // 他キャラクターすべてとの衝突判定を行う
         for e in self.character_group.iter_mut() {
@@ -1131,7 +1249,7 @@ impl ShopScene {
                 ctx,
                 self.player.get_mut_character_object(),
                 e.get_mut_character_object(),
-                &self.camera.borrow(),
+                &self.camera.get_view(),
                 t,
             );
         }
@@ -1141,7 +1259,7 @@ impl ShopScene {
                 ctx,
                 self.player.get_mut_character_object(),
                 e.get_mut_character_object(),
-                &self.camera.borrow(),
+                &self.camera.get_view(),
                 t,
             );
         }
@@ -1151,13 +1269,13 @@ impl ShopScene {
                 ctx,
                 self.player.get_mut_character_object(),
                 e.get_mut_character_object(),
-                &self.camera.borrow(),
+                &self.camera.get_view(),
                 t,
             );
         }
 
         // カメラをプレイヤーに合わせる
-        self.camera_focus_character_x();
+        self.camera_focus_character();
     }
 
     ///
@@ -1167,7 +1285,7 @@ impl ShopScene {
         // マップ座標を更新
         self.player
             .get_mut_character_object()
-            .update_display_position(&self.camera.borrow());
+            .update_display_position(&self.camera.get_view());
 
         // 他キャラクターすべてとの衝突判定を行う
         for e in self.character_group.iter_mut() {
@@ -1175,7 +1293,7 @@ impl ShopScene {
                 ctx,
                 &mut self.player,
                 e,
-                &self.camera.borrow(),
+                &self.camera.get_view(),
                 t,
             );
         }
@@ -1186,7 +1304,7 @@ impl ShopScene {
                 ctx,
                 &mut self.player,
                 e,
-                &self.camera.borrow(),
+                &self.camera.get_view(),
                 t,
             );
         }
@@ -1196,13 +1314,13 @@ impl ShopScene {
                 ctx,
                 &mut self.player,
                 e,
-                &self.camera.borrow(),
+                &self.camera.get_view(),
                 t,
             );
         }
 
         // カメラをプレイヤーに合わせる
-        self.camera_focus_character_y();
+        self.camera_focus_character();
     }
 
     fn move_playable_character_x(&mut self, ctx: &mut ggez::Context, t: Clock) {
@@ -1215,7 +1333,7 @@ impl ShopScene {
         // マップ座標を更新, これで、衝突判定を行えるようになる
         self.player
             .get_mut_character_object()
-            .update_display_position(&self.camera.borrow());
+            .update_display_position(&self.camera.get_view());
 
         // マップチップとの衝突判定（横）
         self.playable_check_collision_horizon(ctx);
@@ -1233,7 +1351,7 @@ impl ShopScene {
         // マップ座標を更新, これで、衝突判定を行えるようになる
         self.player
             .get_mut_character_object()
-            .update_display_position(&self.camera.borrow());
+            .update_display_position(&self.camera.get_view());
 
         // マップチップとの衝突判定（縦）
         self.playable_check_collision_vertical(ctx);
@@ -1262,6 +1380,7 @@ impl ShopScene {
         match func {
             CommandPaletteFunc::Action
             | CommandPaletteFunc::ShowMap
+            | CommandPaletteFunc::RepairBooks
             | CommandPaletteFunc::Pause => {
                 if self.shop_menu.first_menu_is_open() {
                     return;
@@ -1273,6 +1392,10 @@ impl ShopScene {
                     CommandPaletteFunc::ShowMap => {
                         self.toggle_shop_map_appearing();
                     }
+                    CommandPaletteFunc::RepairBooks => {
+                        let t = self.get_current_clock();
+                        self.repair_books(ctx, t);
+                    }
                     CommandPaletteFunc::Pause => {
                         let t = self.get_current_clock();
                         self.enter_pause_screen(t);
@@ -1291,7 +1414,28 @@ impl ShopScene {
         ctx: &mut SuzuContext<'a>,
         builtin_event: BuiltinEvent,
     ) {
-        match builtin_event.get_event_symbol() {
+        self.run_builtin_event_symbol(ctx, builtin_event.get_event_symbol());
+    }
+
+    pub fn run_builtin_event_symbol<'a>(
+        &mut self,
+        ctx: &mut SuzuContext<'a>,
+        event_symbol: BuiltinEventSymbol,
+    ) {
+        match event_symbol {
+            BuiltinEventSymbol::ShowShopMap => {
+                if !self.shop_map_is_staged {
+                    self.toggle_shop_map_appearing();
+                }
+            }
+            BuiltinEventSymbol::ShowShopMenu => {
+                if !self.shop_menu.first_menu_is_open() {
+                    self.special_button_handler(ctx);
+                }
+            }
+            BuiltinEventSymbol::RepairBooks => {
+                self.repair_books(ctx, self.get_current_clock());
+            }
             BuiltinEventSymbol::SelectShelvingBook => {
                 self.dark_effect_panel
                     .new_effect(8, self.get_current_clock(), 0, 200);
@@ -1358,13 +1502,19 @@ impl ShopScene {
 
                         self.event_list.add_event(
                             Box::new(move |slf: &mut ShopScene, ctx, _| {
-                                let mut customer = if let Some((customer, _)) =
-                                    slf.customer_queue.pop_head_customer()
-                                {
-                                    customer
-                                } else {
-                                    return;
-                                };
+                                let (mut customer, queued_t) =
+                                    if let Some(queued) = slf.customer_queue.pop_head_customer() {
+                                        queued
+                                    } else {
+                                        return;
+                                    };
+
+                                slf.result_report.add_satisfied_customer();
+                                slf.apply_served_reputation(
+                                    ctx,
+                                    &customer,
+                                    t.saturating_sub(queued_t),
+                                );
 
                                 customer.get_out_shop(
                                     ctx.context,
@@ -1437,6 +1587,52 @@ impl ShopScene {
         return None;
     }
 
+    ///
+    /// プレイヤーの位置でトリガーゾーンを更新し、発動したゾーンの処理を順に実行する
+    ///
+    fn update_trigger_zones<'a>(&mut self, ctx: &mut SuzuContext<'a>, t: Clock) {
+        let map_position = self.player.get_center_map_position(ctx.context);
+        let fired_zones = self
+            .map
+            .check_trigger_zones(map_position, t, ctx.take_save_data());
+
+        for zone in fired_zones {
+            if zone.mode == TriggerMode::Once {
+                ctx.take_save_data_mut()
+                    .map_event_record
+                    .mark_fired(&zone.id);
+            }
+
+            for action in zone.actions {
+                self.run_trigger_action(ctx, action, t);
+            }
+        }
+    }
+
+    fn run_trigger_action<'a>(
+        &mut self,
+        ctx: &mut SuzuContext<'a>,
+        action: TriggerAction,
+        t: Clock,
+    ) {
+        match action {
+            TriggerAction::Scenario(path) => {
+                self.set_fixed_text_into_scenario_box(ctx, &path, t);
+            }
+            TriggerAction::SoundEffect(sound_id) => {
+                ctx.play_sound_as_se(sound_id, None);
+            }
+            TriggerAction::ShopUI(event_symbol) => {
+                self.run_builtin_event_symbol(ctx, event_symbol);
+            }
+            TriggerAction::SetFlag(name, value) => {
+                ctx.take_save_data_mut()
+                    .map_event_record
+                    .set_flag(&name, value);
+            }
+        }
+    }
+
     fn check_event_panel_onmap<'a>(&mut self, ctx: &mut SuzuContext<'a>, trigger: EventTrigger) {
         let map_position = self.player.get_center_map_position(ctx.context);
         let result = self.run_event_panel_onmap_at(ctx, trigger, map_position);
@@ -1496,17 +1692,31 @@ impl ShopScene {
         }
     }
 
-    pub fn start_mouse_move(&mut self, ctx: &mut ggez::Context, point: numeric::Point2f) {
-        let current = self.player.get_character_object().obj().get_center(ctx);
+    pub fn start_mouse_move<'a>(&mut self, ctx: &mut SuzuContext<'a>, point: numeric::Point2f) {
+        // クリックした位置と自機の位置を、どちらも拡大率を反映した画面上の座標で比べる
+        let view = self.camera.get_view();
+        let center = self
+            .player
+            .get_character_object()
+            .obj()
+            .get_center(ctx.context);
+        let current = self
+            .camera
+            .world_to_screen(numeric::Point2f::new(center.x + view.x, center.y + view.y));
 
-        let offset = numeric::Point2f::new(point.x - current.x, point.y - current.y);
+        // 画面上の差を、ワールド上の移動量に戻す
+        let zoom = self.camera.get_zoom();
+        let offset =
+            numeric::Point2f::new((point.x - current.x) / zoom, (point.y - current.y) / zoom);
 
         if offset.x == 0.0 && offset.y == 0.0 {
             return;
         }
 
         let d = (offset.x.powf(2.0) + offset.y.powf(2.0)).sqrt();
-        let speed_k = if d > 300.0 { 300.0 } else { d } / 200.0;
+        // 疲れていると、歩くのが遅くなる
+        let speed_k = if d > 300.0 { 300.0 } else { d } / 200.0
+            * fatigue::move_speed_rate(ctx.take_save_data().suzunaan_status.kosuzu_hp);
 
         let rad = if offset.x >= 0.0 {
             if offset.y >= 0.0 {
@@ -1552,9 +1762,21 @@ impl ShopScene {
         ));
 
         self.shop_clock.add_minute((elapsed_clock / 360) as u8);
-        if let Some(report) = condition_eval_report {
+        if let Some(mut report) = condition_eval_report {
+            // 疲れていると、正しく評価した本でも見誤ることがある
+            report.apply_fatigue_mistakes(fatigue::mistake_rate(
+                ctx.take_save_data().suzunaan_status.kosuzu_hp,
+            ));
+
+            // 傷んでいると判断した本は、修繕の候補になる
+            ctx.take_save_data_mut().add_repair_candidates(&report);
             self.result_report
                 .add_condition_eval_mistakes(report.count_mistake());
+            ctx.take_save_data_mut().apply_reputation_event(
+                ReputationEvent::ConditionMistake,
+                -CONDITION_MISTAKE_REPUTATION_PENALTY * report.count_mistake() as f32,
+                None,
+            );
         }
 
         self.task_tutorial_context = task_tutorial;
@@ -1574,8 +1796,8 @@ impl ShopScene {
         let select_result = self
             .shop_special_object
             .hide_shelving_select_ui(self.get_current_clock());
-        if let Some((boxed, shelving)) = select_result {
-            ctx.take_save_data_mut().task_result.not_shelved_books = boxed;
+        // 持ち歩いている本も台帳では返却箱にあるので、台帳は変わらない
+        if let Some((_, shelving)) = select_result {
             self.player.update_shelving_book(shelving);
             self.shop_menu
                 .update_contents(ctx, self.player.get_shelving_book());
@@ -1595,6 +1817,7 @@ impl ShopScene {
     }
 
     fn try_hide_storing_select_ui<'a>(&mut self, ctx: &mut SuzuContext<'a>, t: Clock) {
+        let shelf_info = self.shop_special_object.get_storing_shelf_info();
         let store_result = self
             .shop_special_object
             .hide_storing_select_ui(self.get_current_clock());
@@ -1605,6 +1828,15 @@ impl ShopScene {
             self.dark_effect_panel
                 .new_effect(8, self.get_current_clock(), 200, 0);
 
+            if let Some(shelf_info) = shelf_info {
+                if let Err(e) = ctx
+                    .take_save_data_mut()
+                    .book_ledger
+                    .place_all(stored.iter(), BookLocation::on_shelf(&shelf_info))
+                {
+                    eprintln!("Failed to record shelved books in the ledger -> {}", e);
+                }
+            }
 	    ctx.take_save_data_mut().suzuna_book_pool.push_book_vec(stored);
 
             if !self.tutorial_list.customer_is_comming {
@@ -1665,6 +1897,8 @@ impl ShopScene {
     pub fn check_shop_clock_regular<'a>(&mut self, ctx: &mut SuzuContext<'a>, t: Clock) {
         if self.shop_time_status == ShopTimeStatus::Preparing && self.shop_clock.is_past(9, 0) {
             self.shop_time_status = ShopTimeStatus::Opening;
+            self.notify_overdue_loans(ctx, t);
+            self.notify_lost_loans(ctx, t);
             self.shop_time_status_header.make_center(
                 ctx.context,
                 numeric::Point2f::new(WINDOW_SIZE_X as f32 / 2.0, 45.0),
@@ -1714,7 +1948,7 @@ impl ShopScene {
             self.event_list.add_event(
                 Box::new(move |slf: &mut Self, ctx, _| {
                     // reportに未配架の本のIDをメモする
-                    for book_info in ctx.take_save_data().task_result.not_shelved_books.iter() {
+                    for book_info in ctx.take_save_data().book_ledger.return_box_books() {
                         slf.result_report
                             .add_yet_shelved_book_id(book_info.get_unique_id());
                     }
@@ -1732,23 +1966,99 @@ impl ShopScene {
         }
     }
 
-    fn insert_goto_check_customer<'a>(&mut self, ctx: &mut SuzuContext<'a>, t: Clock) {
-        let customer = character_factory::create_character(
-            character_factory::CharacterFactoryOrder::CustomerSample,
-            ctx,
-            &self.camera.borrow(),
-            numeric::Point2f::new(1430.0, 1246.0),
-        );
+    ///
+    /// 本を借りている客の中から、返しに来る客を選ぶ
+    ///
+    fn select_returning_visitor<'a>(&self, ctx: &mut SuzuContext<'a>) -> Option<String> {
+        if rand::random::<f32>() >= RETURNING_VISITOR_RATE {
+            return None;
+        }
 
-        let customer = CustomerCharacter::new(
-            ctx.resource,
-            customer,
-            CustomerDestPoint::new(vec![
-                numeric::Vector2u::new(10, 4),
-                numeric::Vector2u::new(6, 4),
-            ]),
-        );
+        let names = ctx
+            .take_save_data()
+            .record_book_data
+            .waiting_borrower_names();
+        if names.is_empty() {
+            None
+        } else {
+            Some(names[rand::random::<usize>() % names.len()].to_string())
+        }
+    }
+
+    ///
+    /// 外出先で知り合った客の中から、店に来る客を選ぶ
+    ///
+    fn select_acquaintance_visitor<'a>(
+        &self,
+        ctx: &mut SuzuContext<'a>,
+    ) -> Option<CharacterDefinition> {
+        if rand::random::<f32>() >= ACQUAINTANCE_VISIT_RATE {
+            return None;
+        }
+
+        ctx.take_save_data()
+            .event_progress
+            .random_unlocked_customer()
+            .and_then(|id| ctx.resource.get_character_definitions().get(id).cloned())
+    }
+
+    ///
+    /// 定義された客のキャラクターから1人選んで、入口に生成する
+    ///
+    fn create_customer<'a>(&mut self, ctx: &mut SuzuContext<'a>) -> CustomerCharacter {
+        let visitor_name = self.select_returning_visitor(ctx);
+
+        // 名簿に載っている客は、前回と同じ見た目で来る
+        let known_definition = visitor_name
+            .as_ref()
+            .and_then(|name| ctx.take_save_data().customer_registry.get(name))
+            .and_then(|customer| customer.definition_id.clone())
+            .and_then(|id| ctx.resource.get_character_definitions().get(&id).cloned())
+            .or_else(|| {
+                if visitor_name.is_none() {
+                    self.select_acquaintance_visitor(ctx)
+                } else {
+                    None
+                }
+            });
+
+        let (character, definition) = match known_definition {
+            Some(definition) => (
+                character_factory::create_character_from_definition(
+                    ctx,
+                    &definition,
+                    &self.camera.get_view(),
+                    self.map.customer_spawn_point(),
+                ),
+                Some(definition),
+            ),
+            None => character_factory::create_random_customer(
+                ctx,
+                &self.camera.get_view(),
+                self.map.customer_spawn_point(),
+            ),
+        };
 
+        let mut customer =
+            CustomerCharacter::new(ctx.resource, character, self.map.customer_dest_point());
+
+        if let Some(definition) = definition.as_ref() {
+            if let Some(name) = definition.random_name() {
+                customer.set_customer_name(name);
+            }
+            customer.set_personality(definition.generate_personality());
+            customer.set_definition_id(&definition.id);
+        }
+
+        if let Some(name) = visitor_name {
+            customer.set_customer_name(&name);
+        }
+
+        customer
+    }
+
+    fn insert_goto_check_customer<'a>(&mut self, ctx: &mut SuzuContext<'a>, t: Clock) {
+        let customer = self.create_customer(ctx);
         self.character_group.add(customer);
 
         if let Some(customer) = self.character_group.pickup_goto_check_customer() {
@@ -1777,22 +2087,8 @@ impl ShopScene {
 	}
 
         if rand::random::<usize>() % self.random_customer_add_timing as usize == 0 {
-            let character = character_factory::create_character(
-                character_factory::CharacterFactoryOrder::CustomerSample,
-                ctx,
-                &self.camera.borrow(),
-                numeric::Point2f::new(1430.0, 1246.0),
-            );
-
-            self.character_group.add(CustomerCharacter::new(
-                ctx.resource,
-                character,
-                CustomerDestPoint::new(vec![
-                    numeric::Vector2u::new(10, 4),
-                    numeric::Vector2u::new(6, 4),
-                    //numeric::Vector2u::new(5, 14),
-                ]),
-            ));
+            let customer = self.create_customer(ctx);
+            self.character_group.add(customer);
         }
     }
 
@@ -1814,6 +2110,151 @@ impl ShopScene {
 	);
     }
 
+    ///
+    /// 返却期限を過ぎた貸出があれば、開店時に知らせる
+    ///
+    fn notify_overdue_loans<'a>(&mut self, ctx: &mut SuzuContext<'a>, t: Clock) {
+        let today = ctx.take_save_data().date;
+        let overdue_count = ctx
+            .take_save_data()
+            .record_book_data
+            .overdue_loan_count(&today);
+
+        if overdue_count == 0 {
+            return;
+        }
+
+        self.notification_area.insert_new_contents_generic(
+            ctx,
+            NotificationContentsData::new(
+                "おしらせ".to_string(),
+                format!(
+                    "延滞中ノ貸出ガ{}件アリマス",
+                    number_to_jk(overdue_count as u64)
+                ),
+                NotificationType::OverdueReminder,
+            ),
+            t,
+        );
+    }
+
+    ///
+    /// 前の開店から紛失扱いになった貸出があれば、誰のどの本かを知らせる
+    ///
+    fn notify_lost_loans<'a>(&mut self, ctx: &mut SuzuContext<'a>, t: Clock) {
+        let lost_loans = ctx
+            .take_save_data_mut()
+            .overdue_record
+            .take_unnotified_lost_loans();
+
+        if lost_loans.is_empty() {
+            return;
+        }
+
+        // 通知に収まるように、紛失した貸出が多いときは件数だけを添える
+        let mut lines: Vec<String> = lost_loans
+            .iter()
+            .take(LOST_LOAN_NOTIFICATION_LINES)
+            .map(|info| {
+                let titles = info
+                    .returning
+                    .iter()
+                    .map(|book| format!("「{}」", book.name))
+                    .collect::<Vec<String>>()
+                    .join("");
+                format!("{}サンノ{}ガ紛失シマシタ", info.borrower, titles)
+            })
+            .collect();
+        if lost_loans.len() > LOST_LOAN_NOTIFICATION_LINES {
+            lines.push(format!(
+                "他{}件",
+                number_to_jk((lost_loans.len() - LOST_LOAN_NOTIFICATION_LINES) as u64)
+            ));
+        }
+        let main_text = lines.join("\n");
+
+        self.notification_area.insert_new_contents_generic(
+            ctx,
+            NotificationContentsData::new(
+                "紛失".to_string(),
+                main_text,
+                NotificationType::LostLoan,
+            ),
+            t,
+        );
+    }
+
+    ///
+    /// 修繕候補の本を修繕に出し、その結果を通知する
+    ///
+    fn repair_books<'a>(&mut self, ctx: &mut SuzuContext<'a>, t: Clock) {
+        let repair_count = ctx
+            .take_save_data_mut()
+            .send_books_to_repair(self.player.get_shelving_book());
+
+        let main_text = if repair_count > 0 {
+            format!("{}冊ヲ修繕ニ出シマシタ", number_to_jk(repair_count as u64))
+        } else {
+            "修繕ニ出セル本ハアリマセン".to_string()
+        };
+
+        self.notification_area.insert_new_contents_generic(
+            ctx,
+            NotificationContentsData::new("修繕".to_string(), main_text, NotificationType::Repair),
+            t,
+        );
+    }
+
+    ///
+    /// 我慢できる時間の半分も待たせずに応対できれば、評判が上がる
+    ///
+    fn apply_served_reputation<'a>(
+        &mut self,
+        ctx: &mut SuzuContext<'a>,
+        customer: &CustomerCharacter,
+        waiting_time: Clock,
+    ) {
+        let save_data = ctx.take_save_data_mut();
+        let patience = customer
+            .get_personality()
+            .patience_with_reputation(save_data.suzunaan_status.get_current_reputation());
+
+        if waiting_time < patience / 2 {
+            let district = save_data
+                .customer_registry
+                .district_of(&customer.get_customer_info().name);
+            save_data.apply_reputation_event(
+                ReputationEvent::ServedQuickly,
+                SERVED_CUSTOMER_REPUTATION_BONUS,
+                district.as_deref(),
+            );
+        }
+    }
+
+    ///
+    /// customer_nameから写本の依頼を受け、それを通知する
+    ///
+    fn accept_copy_request<'a>(
+        &mut self,
+        ctx: &mut SuzuContext<'a>,
+        customer_name: &str,
+        t: Clock,
+    ) {
+        let book = ctx.resource.book_random_select().clone();
+        ctx.take_save_data_mut()
+            .add_copy_request(customer_name, &book);
+
+        self.notification_area.insert_new_contents_generic(
+            ctx,
+            NotificationContentsData::new(
+                "写本".to_string(),
+                format!("「{}」ノ写本ヲ頼マレマシタ", book.name),
+                NotificationType::CopyRequest,
+            ),
+            t,
+        );
+    }
+
     fn transition_to_title_scene<'a>(&mut self, ctx: &mut SuzuContext<'a>, t: Clock) {
         self.event_list.add_event(
             Box::new(|slf: &mut Self, ctx, _| {
@@ -1866,9 +2307,15 @@ impl ShopScene {
             return;
         }
 
-        let giveup_customers = self.customer_queue.drain_giveup_customers(now);
+        let reputation = ctx
+            .take_save_data()
+            .suzunaan_status
+            .get_current_reputation();
+        let giveup_customers = self.customer_queue.drain_giveup_customers(now, reputation);
 
         if giveup_customers.len() > 0 {
+            // 怒って帰る客がいたら、カメラを少し揺らして知らせる
+            self.camera.shake(4.0, 20, now);
             self.goto_check_customers.reset_each_customers_goal(
                 ctx,
                 &self.map.tile_map,
@@ -1878,6 +2325,19 @@ impl ShopScene {
         }
 
         for mut customer in giveup_customers {
+            self.result_report.add_angry_customer();
+
+            let save_data = ctx.take_save_data_mut();
+            let district = save_data
+                .customer_registry
+                .district_of(&customer.get_customer_info().name);
+            save_data.apply_reputation_event(
+                ReputationEvent::AngryCustomer,
+                -ANGRY_CUSTOMER_REPUTATION_PENALTY
+                    * customer.get_personality().reputation_sensitivity,
+                district.as_deref(),
+            );
+
             customer.get_out_shop(
                 ctx.context,
                 &self.map.tile_map,
@@ -1974,7 +2434,7 @@ impl ShopScene {
             );
 
             if self.shop_menu.detail_menu_is_open() {
-                self.shop_menu.toggle_detail_menu(self.get_current_clock());
+                self.shop_menu.close_detail_menu(self.get_current_clock());
             }
         }
     }
@@ -2151,7 +2611,7 @@ impl SceneManager for ShopScene {
             {
                 if left_pressed {
                     if !self.shop_command_palette.contains_buttons(point) {
-                        self.start_mouse_move(ctx.context, point);
+                        self.start_mouse_move(ctx, point);
                     }
                 }
             }
@@ -2190,7 +2650,7 @@ impl SceneManager for ShopScene {
                         && !self.shop_special_object.is_enable_now()
                         && self.map.scenario_event.is_none()
                     {
-                        self.start_mouse_move(ctx.context, point);
+                        self.start_mouse_move(ctx, point);
                     }
 
                     if !self.shop_menu.contains_some_menu(ctx, point) {
@@ -2206,7 +2666,7 @@ impl SceneManager for ShopScene {
                         }
 
                         if self.shop_menu.detail_menu_is_open() {
-                            self.shop_menu.toggle_detail_menu(t);
+                            self.shop_menu.close_detail_menu(t);
                         }
                     }
 
@@ -2349,11 +2809,13 @@ impl SceneManager for ShopScene {
         if !self.now_paused() && self.map.scenario_event.is_none() {
             self.random_add_customer(ctx);
             self.move_playable_character(ctx.context, t);
+            self.update_camera(t);
             self.check_event_panel_onmap(ctx, EventTrigger::Touch);
+            self.update_trigger_zones(ctx, t);
 
             self.character_group.move_and_collision_check(
                 ctx.context,
-                &self.camera.borrow(),
+                &self.camera.get_view(),
                 &self.map.tile_map,
                 t,
             );
@@ -2364,14 +2826,41 @@ impl SceneManager for ShopScene {
             let reset_goto_check_customers_goal_flag = !rising_customers.is_empty();
 
             // 新しく客が列に並んだら、通知をする
-            if !rising_customers.is_empty() {
+            // カメラで映すのは、誰も並んでいなかった列に最初の客が並んだ時だけ
+            if let Some(customer) = rising_customers.first() {
+                let customer_pos = customer.get_map_position();
                 self.notify_customer_calling(ctx, t);
+                if self.customer_queue.is_empty() && !self.camera.is_panning() {
+                    self.pan_camera_to(customer_pos, t);
+                }
             }
 
             for customer in &mut rising_customers {
                 if let Some(request) =
                     customer.check_rise_hand(ctx, self.tutorial_list.gen_tutorial_customer_order())
                 {
+                    let today = ctx.take_save_data().date;
+                    let info = customer.get_customer_info();
+                    let district = info.definition_id.as_ref().and_then(|id| {
+                        ctx.resource
+                            .get_character_definitions()
+                            .get(id)
+                            .and_then(|definition| definition.district.clone())
+                    });
+                    ctx.take_save_data_mut().customer_registry.record_visit(
+                        &info.name,
+                        info.definition_id.as_deref(),
+                        district.as_deref(),
+                        info.personality.carelessness,
+                        today,
+                    );
+
+                    if !ctx.take_save_data().run_tutorial
+                        && rand::random::<f32>() < COPY_REQUEST_RATE
+                    {
+                        self.accept_copy_request(ctx, &info.name, t);
+                    }
+
                     self.tutorial_list.inc_checking_customer_count();
                     self.customer_request_queue.push_back(request);
                 }
@@ -2395,10 +2884,13 @@ impl SceneManager for ShopScene {
 
             self.check_waiting_customer_giveup(ctx, t);
 
+            self.update_customer_obstacles(ctx.context);
+
             for customer in self.character_group.iter_mut() {
                 customer.try_update_move_effect(
                     ctx,
                     &self.map.tile_map,
+                    &mut self.tile_reservation,
                     numeric::Vector2u::new(5, 14),
                     numeric::Vector2u::new(15, 14),
                     t,
@@ -2406,8 +2898,13 @@ impl SceneManager for ShopScene {
                 customer.get_mut_character_object().update_texture(t);
             }
 
-            self.goto_check_customers
-                .go_moving(ctx, &self.camera.borrow(), &self.map.tile_map, t);
+            self.goto_check_customers.go_moving(
+                ctx,
+                &self.camera.get_view(),
+                &self.map.tile_map,
+                &mut self.tile_reservation,
+                t,
+            );
 
             self.result_report
                 .add_customers_waiting_time(self.customer_queue.len() as Clock);
@@ -2415,13 +2912,14 @@ impl SceneManager for ShopScene {
                 Self::customer_move_and_collision_check(
                     ctx.context,
                     customer,
-                    &self.camera.borrow(),
+                    &self.camera.get_view(),
                     &self.map.tile_map,
                     t,
                 );
                 customer.try_update_move_effect(
                     ctx,
                     &self.map.tile_map,
+                    &mut self.tile_reservation,
                     numeric::Vector2u::new(5, 14),
                     numeric::Vector2u::new(15, 14),
                     t,
@@ -2474,8 +2972,17 @@ impl SceneManager for ShopScene {
     fn drawing_process(&mut self, ctx: &mut ggez::Context) {
         //println!("{}", perf_measure!({
 
+        // マップとキャラクターは、カメラの拡大率で拡大して表示する
+        let zoom = self.camera.get_zoom();
+        self.map_view.set_scale(numeric::Vector2f::new(zoom, zoom));
+        sub_screen::stack_screen(ctx, &self.map_view);
+
         self.map.tile_map.draw(ctx).unwrap();
 
+        // カメラの視界に入っているキャラクターだけ描画する
+        let view_size = self.camera.view_size();
+        let visible_area = numeric::Rect::new(0.0, 0.0, view_size.x, view_size.y);
+
         let mut map_obj_drawer = MapObjectDrawer::new();
 
         map_obj_drawer.add(&mut self.player);
@@ -2485,7 +2992,7 @@ impl SceneManager for ShopScene {
                 .get_character_object()
                 .obj()
                 .get_drawing_area(ctx)
-                .overlaps(&numeric::Rect::new(0.0, 0.0, 1366.0, 768.0))
+                .overlaps(&visible_area)
             {
                 map_obj_drawer.add(customer);
             }
@@ -2496,7 +3003,7 @@ impl SceneManager for ShopScene {
                 .get_character_object()
                 .obj()
                 .get_drawing_area(ctx)
-                .overlaps(&numeric::Rect::new(0.0, 0.0, 1366.0, 768.0))
+                .overlaps(&visible_area)
             {
                 map_obj_drawer.add(customer);
             }
@@ -2507,7 +3014,7 @@ impl SceneManager for ShopScene {
                 .get_character_object()
                 .obj()
                 .get_drawing_area(ctx)
-                .overlaps(&numeric::Rect::new(0.0, 0.0, 1366.0, 768.0))
+                .overlaps(&visible_area)
             {
                 map_obj_drawer.add(customer);
             }
@@ -2516,6 +3023,9 @@ impl SceneManager for ShopScene {
         map_obj_drawer.sort(ctx);
         map_obj_drawer.draw(ctx);
 
+        sub_screen::pop_screen(ctx);
+        self.map_view.draw(ctx).unwrap();
+
         self.shop_map.draw(ctx).unwrap();
 
         self.drawable_shop_clock.draw(ctx).unwrap();
@@ -2556,6 +3066,16 @@ impl SceneManager for ShopScene {
         }
     }
 
+    fn layout_event<'a>(&mut self, ctx: &mut SuzuContext<'a>, area: numeric::Rect) {
+        let t = self.get_current_clock();
+
+        self.notification_area.relayout(area);
+        self.drawable_shop_clock.relayout(area);
+        self.shop_command_palette.relayout(area, t);
+
+        ctx.process_utility.redraw();
+    }
+
     fn transition(&self) -> SceneID {
         self.transition_scene
     }
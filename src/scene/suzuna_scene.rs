@@ -155,4 +155,8 @@ impl SceneManager for SuzunaScene {
     fn unfocus_event<'a>(&mut self, ctx: &mut SuzuContext<'a>) {
         self.sub_scene.unfocus_event(ctx);
     }
+
+    fn layout_event<'a>(&mut self, ctx: &mut SuzuContext<'a>, area: numeric::Rect) {
+        self.sub_scene.layout_event(ctx, area);
+    }
 }
@@ -6,6 +6,7 @@ use crate::libsuzu::core::*;
 use crate::libsuzu::device::VirtualKey;
 use crate::libsuzu::numeric;
 
+use crate::core::book_ledger::BookLocation;
 use crate::core::book_management::*;
 use crate::core::*;
 use crate::scene::*;
@@ -67,7 +68,14 @@ impl SuzunaSubScene {
         let date = ctx.take_save_data().date.clone();
 
 	let todays_new_books = match ctx.take_save_data().game_mode {
-	    GameMode::Story => DayNewBooks::random(ctx.resource, 7, 2),
+	    GameMode::Story => {
+		// 発注した本が届く。初日だけは、開店の品揃えとして無作為に届く
+		let mut delivered = ctx.take_save_data_mut().receive_purchased_books();
+		if ctx.take_save_data().date.first_day() {
+		    delivered.extend(DayNewBooks::random(ctx.resource, 7, 2).get_new_books());
+		}
+		DayNewBooks::from_books(delivered)
+	    }
 	    GameMode::TimeAttack(_) => DayNewBooks::random(ctx.resource, 8, 2),
 	};
 
@@ -127,23 +135,56 @@ impl SuzunaSubScene {
                 // 今回のTaskSceneで扱われるCustomerRequestを構築
                 let customer_request = match customer_request_hint.as_ref().unwrap() {
                     CustomerRequest::Borrowing(raw_info) => {
+                        // 目録はGameResource、本はセーブデータにあるので、別々に借用する
+                        let ad_effects = ctx.todays_ad_effects();
+                        let catalogue = ctx.resource.get_book_catalogue();
                         let borrowing_info = ctx
-                            .take_save_data_mut()
+                            .savable_data
+                            .as_mut()
+                            .expect("save data not found")
                             .suzuna_book_pool
                             .generate_borrowing_request(
                                 &raw_info.borrower,
                                 raw_info.borrow_date,
                                 raw_info.rental_limit.clone(),
+                                catalogue,
+                                &ad_effects,
                             );
+                        if let Err(e) = ctx
+                            .take_save_data_mut()
+                            .book_ledger
+                            .place_all(borrowing_info.borrowing.iter(), BookLocation::OnDesk)
+                        {
+                            eprintln!("Failed to put borrowing books on the desk -> {}", e);
+                        }
 
                         CustomerRequest::Borrowing(borrowing_info)
                     }
-                    CustomerRequest::Returning(_) => {
-                        let request = ctx
-                            .take_save_data()
+                    CustomerRequest::Returning(raw_info) => {
+                        // 返しに来た本人の貸出だけを返却する
+                        let save_data = ctx.take_save_data();
+                        let request = match save_data
+                            .record_book_data
+                            .pick_returning_request_of(&raw_info.borrower)
+                        {
+                            Some(request) => request,
+                            None => {
+                                eprintln!(
+                                    "Returning customer has no loan -> {}",
+                                    raw_info.borrower
+                                );
+                                return ();
+                            }
+                        };
+
+                        // 貸出中に傷んだ本は、傷んだ状態で返ってくる
+                        // 傷みは、返却を受け付けた時に蔵書台帳へ記録する
+                        let carelessness = save_data
+                            .customer_registry
+                            .carelessness_of(&request.borrower);
+                        let request = save_data
                             .record_book_data
-                            .pick_returning_request_up()
-                            .unwrap();
+                            .worn_returning_request(&request, carelessness);
                         println!("returning count: {}", request.returning.len());
                         CustomerRequest::Returning(request)
                     }
@@ -457,4 +498,11 @@ impl SceneManager for SuzunaSubScene {
             }
         }
     }
+
+    fn layout_event<'a>(&mut self, ctx: &mut SuzuContext<'a>, area: numeric::Rect) {
+        // 机仕事中も店のUIは保持されているため、店シーンには常に伝える
+        if let Some(shop_scene) = self.shop_scene.as_mut() {
+            shop_scene.layout_event(ctx, area);
+        }
+    }
 }
@@ -27,6 +27,7 @@ pub struct TaskResultScene {
     event_list: DelayEventList<Self>,
     drawable_task_result: DrawableTaskResult,
     ok_button: util_object::FramedButton,
+    ledger_button: util_object::FramedButton,
     scene_transition_status: SceneTransition,
     transition_scene: SceneID,
     scene_transition_effect: Option<effect_object::ScreenTileEffect>,
@@ -68,6 +69,13 @@ impl TaskResultScene {
 
 	ok_button.hide();
 
+        let ledger_button = util_object::FramedButton::create_design1(
+            ctx,
+            numeric::Point2f::new(60.0, 430.0),
+            "帳簿",
+            numeric::Vector2f::new(28.0, 28.0),
+        );
+
         let scene_transition = Some(effect_object::ScreenTileEffect::new(
             ctx,
             TileBatchTextureID::Shoji,
@@ -111,6 +119,7 @@ impl TaskResultScene {
                 0,
             ),
             ok_button: ok_button,
+            ledger_button: ledger_button,
             scene_transition_status: SceneTransition::Keep,
             transition_scene: SceneID::DayResult,
             scene_transition_effect: scene_transition,
@@ -148,6 +157,7 @@ impl SceneManager for TaskResultScene {
         _: numeric::Vector2f,
     ) {
         self.ok_button.mouse_motion_handler(ctx, point);
+        self.ledger_button.mouse_motion_handler(ctx, point);
         if self.mouse_info.is_dragging(MouseButton::Left) {
             self.mouse_info
                 .set_last_dragged(MouseButton::Left, point, self.get_current_clock());
@@ -164,6 +174,10 @@ impl SceneManager for TaskResultScene {
             self.ok_button.make_this_pressed_status(ctx);
         }
 
+        if self.ledger_button.contains(point) {
+            self.ledger_button.make_this_pressed_status(ctx);
+        }
+
         self.mouse_info
             .set_last_clicked(button, point, self.get_current_clock());
         self.mouse_info
@@ -186,7 +200,15 @@ impl SceneManager for TaskResultScene {
             self.ready_to_finish_scene(ctx, t);
         }
 
-        self.drawable_task_result.click_handler(ctx, t);
+        if self.ledger_button.contains(point) {
+            self.ledger_button.make_this_hovered_status(ctx);
+            self.drawable_task_result.toggle_accounting_report(ctx);
+        } else if self.drawable_task_result.accounting_report_is_visible() {
+            self.drawable_task_result
+                .accounting_report_click_handler(ctx, point);
+        } else {
+            self.drawable_task_result.click_handler(ctx, t);
+        }
 
         self.mouse_info.update_dragging(button, false);
         self.mouse_info
@@ -217,6 +239,7 @@ impl SceneManager for TaskResultScene {
         //println!("ResultScene::drawing_process {}", perf_measure!({
         self.drawable_task_result.draw(ctx).unwrap();
         self.ok_button.draw(ctx).unwrap();
+        self.ledger_button.draw(ctx).unwrap();
 
         if let Some(effect) = self.scene_transition_effect.as_mut() {
             effect.draw(ctx).unwrap();
@@ -8,7 +8,14 @@ use crate::libsuzu::numeric;
 use super::super::*;
 use crate::{core::SoundID, object::{scenario::ScenarioEvent, Clickable, DarkEffectPanel}};
 
-use crate::core::{MouseActionRecord, MouseInformation, TileBatchTextureID};
+use crate::core::book_ledger::BookLocation;
+use crate::core::fatigue;
+use crate::core::financial_ledger::TransactionCategory;
+use crate::core::reputation::{ReputationEvent, OVERDUE_HANDLED_REPUTATION_BONUS};
+use crate::core::{
+    BookCondition, MouseActionRecord, MouseInformation, TileBatchTextureID,
+    BAD_CONDITION_REPUTATION_PENALTY,
+};
 use crate::object::effect_object;
 use crate::object::task_object::*;
 use crate::object::util_object::*;
@@ -78,6 +85,13 @@ impl TaskScene {
             animation_time + 1,
         );
 
+        if customer_request.is_some() {
+            // 受付の仕事をこなすと、意欲が減る
+            ctx.take_save_data_mut()
+                .suzunaan_status
+                .add_kosuzu_hp(-fatigue::DESK_TASK_HP_COST);
+        }
+
         if let Some(customer_request) = customer_request.as_ref() {
             match customer_request {
                 CustomerRequest::Borrowing(_) => {
@@ -174,26 +188,95 @@ impl TaskScene {
     }
 
     fn check_done_today_work<'a>(&mut self, ctx: &mut SuzuContext<'a>, request: CustomerRequest) {
-        let task_result = &mut ctx.take_save_data_mut().task_result;
+        let save_data = ctx.take_save_data_mut();
+        let today = save_data.date;
+
+        // 客の名簿に貸出・返却の履歴を残し、蔵書台帳の所在を更新する
+        match &request {
+            CustomerRequest::Borrowing(request_information) => {
+                save_data
+                    .customer_registry
+                    .record_borrowing(request_information);
+                if let Err(e) = save_data.book_ledger.place_all(
+                    request_information.borrowing.iter(),
+                    BookLocation::Lent {
+                        borrower: request_information.borrower.clone(),
+                        return_date: request_information.return_date,
+                    },
+                ) {
+                    eprintln!("Failed to record lent books in the ledger -> {}", e);
+                }
+
+                // 傷んだ本を貸すと、客の機嫌を損ねる
+                let bad_count = request_information
+                    .borrowing
+                    .iter()
+                    .filter(|book| book.condition == BookCondition::Bad)
+                    .count();
+                let district = save_data
+                    .customer_registry
+                    .district_of(&request_information.borrower);
+                save_data.apply_reputation_event(
+                    ReputationEvent::BadConditionLent,
+                    -BAD_CONDITION_REPUTATION_PENALTY * bad_count as f32,
+                    district.as_deref(),
+                );
+
+                save_data.record_transaction(
+                    TransactionCategory::RentalFee,
+                    request_information.calc_fee(),
+                    &request_information.borrower,
+                );
+            }
+            CustomerRequest::Returning(request_information) => {
+                save_data
+                    .customer_registry
+                    .record_returning(request_information, today);
+                // 貸出中に傷んだ本は、ここで傷んだ状態のまま返却箱に入る
+                if let Err(e) = save_data.book_ledger.place_all(
+                    request_information.returning.iter(),
+                    BookLocation::ReturnBox,
+                ) {
+                    eprintln!("Failed to record returned books in the ledger -> {}", e);
+                }
+            }
+        }
+
+        let task_result = &mut save_data.task_result;
         match request {
             CustomerRequest::Borrowing(request_information) => {
                 // 貸出本を記録
                 task_result.done_works += 1;
-                task_result.total_money += request_information.calc_fee();
                 task_result
                     .borrowing_books
                     .extend(request_information.borrowing);
             }
-            CustomerRequest::Returning(request_information) => {
-                // 貸出本を記録
+            CustomerRequest::Returning(_) => {
+                // 返却された本は、蔵書台帳で返却箱に入れた
                 task_result.done_works += 1;
-                task_result
-                    .not_shelved_books
-                    .extend(request_information.returning);
             }
         }
     }
 
+    ///
+    /// 受け付けた返却の延滞料金を、帳簿と延滞の記録に付ける
+    ///
+    fn record_late_fee<'a>(&mut self, ctx: &mut SuzuContext<'a>, late_fee: i32) {
+        let borrower = match self.task_table.get_current_customer_request() {
+            Some(CustomerRequest::Returning(info)) => info.borrower.clone(),
+            _ => String::new(),
+        };
+
+        let save_data = ctx.take_save_data_mut();
+        save_data.record_transaction(TransactionCategory::LateFee, late_fee, &borrower);
+        save_data.overdue_record.add_late_fee(late_fee);
+        save_data.apply_reputation_event(
+            ReputationEvent::OverdueHandled,
+            OVERDUE_HANDLED_REPUTATION_BONUS,
+            None,
+        );
+    }
+
     pub fn get_task_status(&self) -> TaskSceneStatus {
         self.status
     }
@@ -209,10 +292,6 @@ impl TaskScene {
         );
 
         self.scene_transition_close_effect(ctx, t);
-
-        // ctx.take_save_data_mut()
-        //     .suzunaan_status
-        //     .eval_reputation(ReputationEvent::DoneDeskTask);
     }
 
     pub fn export_borrowing_record_book_data(&self) -> BorrowingRecordBookData {
@@ -342,6 +421,10 @@ impl TaskScene {
     }
 
     fn after_task_done_process<'a>(&mut self, ctx: &mut SuzuContext<'a>, t: Clock) {
+        if let Some(late_fee) = self.task_table.take_late_fee() {
+            self.record_late_fee(ctx, late_fee);
+        }
+
         self.event_list.add_event(
             Box::new(|scene: &mut TaskScene, _, _| {
                 scene